@@ -8,7 +8,7 @@ fi
 
 # Build the WebAssembly module
 echo "Building WebAssembly module..."
-wasm-pack build --target web --out-dir pkg
+wasm-pack build wasm --target web --out-dir ../pkg --out-name regexgen_rs
 
 # Create a simple Python server script for development
 cat > serve.py << 'EOF'
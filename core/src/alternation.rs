@@ -0,0 +1,319 @@
+use std::collections::BTreeMap;
+
+use crate::dialect::{DialectError, RegexDialect};
+use crate::options::OptionFlags;
+use crate::word_match::edge_boundaries;
+
+/// Controls when and how `OneOf` options are compacted into a trie-shaped
+/// alternation instead of a flat `a|b|c` list.
+#[derive(Clone, Debug)]
+pub struct AlternationConfig {
+    /// Option lists shorter than this are emitted as a plain alternation.
+    pub min_options: usize,
+    /// Merge branches that end in identical suffixes (`[ai]ble`).
+    pub factor_suffixes: bool,
+    /// Collapse runs of consecutive characters into ranges (`[0-9]`).
+    pub use_ranges: bool,
+}
+
+impl Default for AlternationConfig {
+    fn default() -> Self {
+        AlternationConfig {
+            min_options: 8,
+            factor_suffixes: true,
+            use_ranges: true,
+        }
+    }
+}
+
+#[derive(Default)]
+struct TrieNode {
+    children: BTreeMap<char, TrieNode>,
+    terminal: bool,
+}
+
+impl TrieNode {
+    fn insert(&mut self, word: &str) {
+        let mut node = self;
+        for c in word.chars() {
+            node = node.children.entry(c).or_default();
+        }
+        node.terminal = true;
+    }
+}
+
+/// Builds the body of a non-capturing alternation matching exactly the given
+/// literal options. The result is not wrapped in a group.
+pub fn build_alternation(options: &[String], config: &AlternationConfig, dialect: RegexDialect) -> String {
+    if options.len() < config.min_options {
+        let escaped: Vec<String> = options.iter().map(|opt| dialect.escape(opt)).collect();
+        return escaped.join("|");
+    }
+
+    let mut root = TrieNode::default();
+    for option in options {
+        root.insert(option);
+    }
+    let (body, atomic) = emit_node(&root, config, dialect);
+    if root.terminal && !root.children.is_empty() {
+        make_optional(&body, atomic, dialect)
+    } else {
+        body
+    }
+}
+
+/// A single word (`\w+`) that is none of `words`, without lookahead:
+/// following a trie of the excluded words, a word may leave it at any
+/// point by a character off the trie or by ending before an excluded word
+/// does. `None` for POSIX, which can't subtract from a bracket class.
+pub fn build_word_except(words: &[String], dialect: RegexDialect) -> Option<String> {
+    if dialect == RegexDialect::PosixEre {
+        return None;
+    }
+    let mut root = TrieNode::default();
+    for word in words {
+        root.insert(word);
+    }
+    Some(word_except(&root, false, dialect))
+}
+
+fn word_except(node: &TrieNode, may_end: bool, dialect: RegexDialect) -> String {
+    let word_char = dialect.word_char();
+    let mut branches = Vec::new();
+    if node.children.is_empty() {
+        branches.push(format!("{}+", word_char));
+    } else {
+        let off_trie: String = node
+            .children
+            .keys()
+            .filter_map(|c| dialect.escape_class_char(*c))
+            .collect();
+        branches.push(format!("[^{}{}]{}*", dialect.non_word_char(), off_trie, word_char));
+        for (c, child) in &node.children {
+            branches.push(format!("{}{}", dialect.escape(&c.to_string()), word_except(child, true, dialect)));
+        }
+    }
+    let body = dialect.group(&branches.join("|"));
+    if may_end && !node.terminal {
+        format!("{}?", body)
+    } else {
+        body
+    }
+}
+
+/// Emits a complete `OneOf` element. Options without flags share one
+/// (possibly compacted) alternation between word boundaries; options with
+/// their own flags become separate branches with scoped inline groups.
+pub fn build_one_of(
+    options: &[String],
+    flags: &BTreeMap<String, OptionFlags>,
+    config: &AlternationConfig,
+    dialect: RegexDialect,
+) -> Result<String, DialectError> {
+    let (flagged, plain): (Vec<String>, Vec<String>) = options
+        .iter()
+        .cloned()
+        .partition(|opt| flags.get(opt).is_some_and(|f| *f != OptionFlags::default()));
+
+    // Options are bounded by the edges they share, so "malloc(" among
+    // the options doesn't cost "free" its end boundary
+    let mut by_edges: Vec<((&str, &str), Vec<String>)> = Vec::new();
+    for option in plain {
+        let edges = edge_boundaries(&option, dialect);
+        match by_edges.iter_mut().find(|(other, _)| *other == edges) {
+            Some((_, options)) => options.push(option),
+            None => by_edges.push((edges, vec![option])),
+        }
+    }
+    let mut branches: Vec<String> = by_edges
+        .iter()
+        .map(|((start, end), options)| format!("{}{}{}", start, dialect.group(&build_alternation(options, config, dialect)), end))
+        .collect();
+    if flagged.is_empty() {
+        return Ok(match branches.len() {
+            0 => format!("{}{}{}", dialect.word_start(), dialect.group(""), dialect.word_end()),
+            1 => branches.remove(0),
+            _ => dialect.group(&branches.join("|")),
+        });
+    }
+
+    for option in &flagged {
+        let option_flags = &flags[option];
+        let mut branch = dialect.escape(option);
+        if option_flags.case_insensitive {
+            if dialect == RegexDialect::PosixEre {
+                return Err(dialect.unsupported("case-insensitive options"));
+            }
+            branch = format!("(?i:{})", branch);
+        }
+        if option_flags.whole_word {
+            let (start, end) = edge_boundaries(option, dialect);
+            branch = format!("{}{}{}", start, branch, end);
+        }
+        branches.push(branch);
+    }
+    Ok(dialect.group(&branches.join("|")))
+}
+
+/// Emits the regex for everything below `node`, returning the fragment and
+/// whether it is a single atom that can take a quantifier without grouping.
+fn emit_node(node: &TrieNode, config: &AlternationConfig, dialect: RegexDialect) -> (String, bool) {
+    if node.children.is_empty() {
+        return (String::new(), true);
+    }
+
+    // Group children that share an identical remainder so they can be
+    // emitted as a single character class followed by the shared suffix.
+    let mut groups: Vec<(String, Vec<char>)> = Vec::new();
+    for (c, child) in &node.children {
+        let (mut suffix, atomic) = emit_node(child, config, dialect);
+        if child.terminal && !child.children.is_empty() {
+            suffix = make_optional(&suffix, atomic, dialect);
+        }
+        match groups
+            .iter_mut()
+            .find(|(s, _)| config.factor_suffixes && *s == suffix)
+        {
+            Some((_, chars)) => chars.push(*c),
+            None => groups.push((suffix, vec![*c])),
+        }
+    }
+
+    if let [(suffix, chars)] = groups.as_slice() {
+        return (format!("{}{}", char_class(chars, config, dialect), suffix), suffix.is_empty());
+    }
+
+    let branches: Vec<String> = groups
+        .iter()
+        .map(|(suffix, chars)| format!("{}{}", char_class(chars, config, dialect), suffix))
+        .collect();
+    (dialect.group(&branches.join("|")), true)
+}
+
+fn make_optional(body: &str, atomic: bool, dialect: RegexDialect) -> String {
+    if atomic {
+        format!("{}?", body)
+    } else {
+        format!("{}?", dialect.group(body))
+    }
+}
+
+fn char_class(chars: &[char], config: &AlternationConfig, dialect: RegexDialect) -> String {
+    if chars.len() == 1 {
+        return dialect.escape(&chars[0].to_string());
+    }
+    if chars.iter().any(|c| dialect.escape_class_char(*c).is_none()) {
+        let escaped: Vec<String> = chars.iter().map(|c| dialect.escape(&c.to_string())).collect();
+        return dialect.group(&escaped.join("|"));
+    }
+
+    let mut sorted = chars.to_vec();
+    sorted.sort_unstable();
+    let mut class = String::from("[");
+    let mut i = 0;
+    while i < sorted.len() {
+        let mut j = i;
+        while config.use_ranges
+            && j + 1 < sorted.len()
+            && (sorted[j] as u32) + 1 == sorted[j + 1] as u32
+        {
+            j += 1;
+        }
+        let escape = |c: char| dialect.escape_class_char(c).unwrap_or_default();
+        if j - i >= 2 {
+            class.push_str(&escape(sorted[i]));
+            class.push('-');
+            class.push_str(&escape(sorted[j]));
+        } else {
+            for c in &sorted[i..=j] {
+                class.push_str(&escape(*c));
+            }
+        }
+        i = j + 1;
+    }
+    class.push(']');
+    class
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{build_alternation, build_one_of, build_word_except, AlternationConfig};
+    use crate::dialect::RegexDialect;
+
+    fn compacted() -> AlternationConfig {
+        AlternationConfig { min_options: 0, ..AlternationConfig::default() }
+    }
+
+    fn options(options: &[&str]) -> Vec<String> {
+        options.iter().map(|option| option.to_string()).collect()
+    }
+
+    fn whole(body: &str) -> regex::Regex {
+        regex::Regex::new(&format!("^(?:{})$", body)).unwrap()
+    }
+
+    #[test]
+    fn compacted_alternations_match_exactly_their_options() {
+        let options = options(&["a-", "a]", "a^", r"a\", "ab", "a", "b-c", "b]c", "b^c", r"b\c", "bc"]);
+        let re = whole(&build_alternation(&options, &compacted(), RegexDialect::Rust));
+        for option in &options {
+            assert!(re.is_match(option), "{} should match {}", re, option);
+        }
+        for other in ["", "a-]", "ac", "b", "b-", "bd", "bcc", "-", "]", "^", r"\", "a[", "b[c"] {
+            assert!(!re.is_match(other), "{} should not match {}", re, other);
+        }
+    }
+
+    #[test]
+    fn short_lists_stay_plain() {
+        let config = AlternationConfig::default();
+        assert_eq!(build_alternation(&options(&["a.b", "c"]), &config, RegexDialect::Rust), r"a\.b|c");
+    }
+
+    #[test]
+    fn shared_suffixes_become_one_class() {
+        assert_eq!(build_alternation(&options(&["able", "ible"]), &compacted(), RegexDialect::Rust), "[ai]ble");
+        let unfactored = AlternationConfig { factor_suffixes: false, ..compacted() };
+        assert_eq!(build_alternation(&options(&["able", "ible"]), &unfactored, RegexDialect::Rust), "(?:able|ible)");
+    }
+
+    #[test]
+    fn shared_prefixes_are_not_repeated() {
+        let body = build_alternation(&options(&["cat", "cats", "car"]), &compacted(), RegexDialect::Rust);
+        assert_eq!(body, "ca(?:r|ts?)");
+    }
+
+    #[test]
+    fn consecutive_characters_become_ranges() {
+        let digits: Vec<String> = (0..10).map(|digit| digit.to_string()).collect();
+        assert_eq!(build_alternation(&digits, &compacted(), RegexDialect::Rust), "[0-9]");
+        let no_ranges = AlternationConfig { use_ranges: false, ..compacted() };
+        assert_eq!(build_alternation(&digits, &no_ranges, RegexDialect::Rust), "[0123456789]");
+        // Two in a row are shorter without the dash
+        assert_eq!(build_alternation(&options(&["a", "b", "x"]), &compacted(), RegexDialect::Rust), "[abx]");
+    }
+
+    #[test]
+    fn words_except_the_excluded_ones() {
+        let re = whole(&build_word_except(&options(&["cat", "car"]), RegexDialect::Rust).unwrap());
+        for word in ["c", "ca", "cab", "cats", "cart", "dog", "scat"] {
+            assert!(re.is_match(word), "{} should match {}", re, word);
+        }
+        for excluded in ["cat", "car", "", "two words"] {
+            assert!(!re.is_match(excluded), "{} should not match {}", re, excluded);
+        }
+        assert!(build_word_except(&options(&["cat"]), RegexDialect::PosixEre).is_none());
+    }
+
+    #[test]
+    fn options_are_bounded_by_the_edges_they_have() {
+        let body = build_one_of(&options(&["free", "malloc("]), &BTreeMap::new(), &AlternationConfig::default(), RegexDialect::Rust).unwrap();
+        assert_eq!(body, r"(?:\b(?:free)\b|\b(?:malloc\())");
+        let re = regex::Regex::new(&body).unwrap();
+        assert_eq!(re.find("p = malloc(size)").map(|m| m.as_str()), Some("malloc("));
+        assert_eq!(re.find("free(p)").map(|m| m.as_str()), Some("free"));
+        assert!(!re.is_match("freedom"));
+    }
+}
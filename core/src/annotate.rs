@@ -0,0 +1,135 @@
+use serde::{Serialize, Deserialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use crate::{CompositeOperator, OffsetEncoding, Pattern, PatternElement, Severity};
+
+/// Which pattern keeps a stretch of text that several patterns match.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationPriority {
+    /// The pattern that comes first in the list annotated with.
+    #[default]
+    PatternOrder,
+    /// The longest match, then the earliest pattern.
+    LongestMatch,
+    /// The pattern with the highest `priority`, then the earliest.
+    Priority,
+    /// The pattern displayed with the highest severity (error, then
+    /// warning, then info, then none), then the earliest.
+    Severity,
+    /// The pattern that pins down the most literal text, then the
+    /// earliest; see `specificity`.
+    Specificity,
+}
+
+impl FromStr for AnnotationPriority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "_").as_str() {
+            "pattern_order" | "order" => Ok(AnnotationPriority::PatternOrder),
+            "longest_match" | "longest" => Ok(AnnotationPriority::LongestMatch),
+            "priority" => Ok(AnnotationPriority::Priority),
+            "severity" => Ok(AnnotationPriority::Severity),
+            "specificity" | "specific" => Ok(AnnotationPriority::Specificity),
+            _ => Err(format!("Unknown annotation priority: {}", s)),
+        }
+    }
+}
+
+/// A pattern and the byte ranges of its matches.
+pub type PatternMatches<'a> = (&'a Pattern, Vec<(usize, usize)>);
+
+/// A stretch of text labelled with the pattern that matched it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Annotation {
+    pub start: usize,
+    pub end: usize,
+    pub pattern_id: String,
+    pub pattern_name: String,
+}
+
+/// Labels `text` from the matches (byte ranges) of each pattern, given in
+/// pattern order. Where matches overlap only the one `priority` prefers
+/// is kept, so no two annotations overlap; empty matches are dropped.
+/// Annotations come in text order, with offsets in `encoding`.
+pub fn annotate(text: &str, matches: &[PatternMatches], priority: AnnotationPriority, encoding: OffsetEncoding) -> Vec<Annotation> {
+    resolve(matches, priority)
+        .into_iter()
+        .map(|(start, end, rank)| Annotation {
+            start: encoding.offset(text, start),
+            end: encoding.offset(text, end),
+            pattern_id: matches[rank].0.get_id().to_string(),
+            pattern_name: matches[rank].0.get_name().to_string(),
+        })
+        .collect()
+}
+
+/// The matches `annotate` keeps, as byte ranges in text order with the
+/// position in `matches` of the pattern that owns each.
+pub fn resolve(matches: &[PatternMatches], priority: AnnotationPriority) -> Vec<(usize, usize, usize)> {
+    let mut candidates: Vec<(usize, usize, usize)> = matches
+        .iter()
+        .enumerate()
+        .flat_map(|(rank, (_, spans))| spans.iter().filter(|(start, end)| start < end).map(move |&(start, end)| (start, end, rank)))
+        .collect();
+    match priority {
+        AnnotationPriority::PatternOrder => candidates.sort_by_key(|&(start, _, rank)| (rank, start)),
+        AnnotationPriority::LongestMatch => candidates.sort_by_key(|&(start, end, rank)| (Reverse(end - start), start, rank)),
+        AnnotationPriority::Priority => candidates.sort_by_key(|&(start, _, rank)| (Reverse(matches[rank].0.meta().priority), rank, start)),
+        AnnotationPriority::Severity => candidates.sort_by_key(|&(start, _, rank)| (severity_rank(matches[rank].0), rank, start)),
+        AnnotationPriority::Specificity => {
+            let specificities: Vec<usize> = matches.iter().map(|(pattern, _)| specificity(pattern)).collect();
+            candidates.sort_by_key(|&(start, _, rank)| (Reverse(specificities[rank]), rank, start))
+        }
+    }
+
+    // Kept spans by start; they never overlap, so only the one starting
+    // last before a candidate ends can overlap it
+    let mut kept: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
+    for (start, end, rank) in candidates {
+        let overlaps = kept.range(..end).next_back().is_some_and(|(_, &(kept_end, _))| kept_end > start);
+        if !overlaps {
+            kept.insert(start, (end, rank));
+        }
+    }
+    kept.into_iter().map(|(start, (end, rank))| (start, end, rank)).collect()
+}
+
+/// 0 for the most severe.
+pub(crate) fn severity_rank(pattern: &Pattern) -> u8 {
+    match pattern.meta().display.as_ref().and_then(|display| display.severity) {
+        Some(Severity::Error) => 0,
+        Some(Severity::Warning) => 1,
+        Some(Severity::Info) => 2,
+        None => 3,
+    }
+}
+
+/// How much literal text a pattern insists on: the characters of its
+/// required words, and of the shortest option of its required option
+/// lists, with a token class or regex fragment counting as one. Gaps,
+/// optional elements and references count nothing.
+pub fn specificity(pattern: &Pattern) -> usize {
+    match pattern {
+        Pattern::Sequence { elements, .. } => elements.iter().map(element_specificity).sum(),
+        Pattern::Template { .. } => pattern.template_elements().iter().map(element_specificity).sum(),
+        Pattern::Composite { operator, patterns, .. } => match operator {
+            CompositeOperator::And => patterns.iter().map(specificity).sum(),
+            CompositeOperator::Or => patterns.iter().map(specificity).min().unwrap_or(0),
+            CompositeOperator::Not => patterns.first().map(specificity).unwrap_or(0),
+        },
+    }
+}
+
+fn element_specificity(element: &PatternElement) -> usize {
+    match element {
+        PatternElement::Word { text, .. } => text.chars().count(),
+        PatternElement::OneOf { options, .. } => options.iter().map(|o| o.chars().count()).min().unwrap_or(0),
+        PatternElement::SynonymSet { .. } | PatternElement::TokenClass { .. } | PatternElement::Raw { .. } => 1,
+        PatternElement::Modified { element, modifier } if !modifier.optional && modifier.min_repeat > 0 => element_specificity(element),
+        _ => 0,
+    }
+}
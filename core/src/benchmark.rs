@@ -0,0 +1,69 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{matching, Pattern, RegexContext, RegexGenError};
+
+/// Most passes over the text one benchmark makes.
+pub const MAX_ITERATIONS: u32 = 100_000;
+
+/// How long a pattern takes to compile and to run over a sample text.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Benchmark {
+    pub regex: String,
+    pub iterations: u32,
+    pub compile_ms: f64,
+    /// Time for all passes together.
+    pub total_match_ms: f64,
+    /// Matches found in one pass over the text.
+    pub matches: usize,
+    /// Absent when the passes were too quick for the clock to measure.
+    pub matches_per_sec: Option<f64>,
+}
+
+/// Compiles `pattern` and finds its matches in `text` `iterations` times,
+/// timing both with `clock`, which returns milliseconds from any fixed
+/// point.
+pub fn benchmark_with_clock(
+    pattern: &Pattern,
+    context: &RegexContext,
+    text: &str,
+    iterations: u32,
+    clock: &dyn Fn() -> f64,
+) -> Result<Benchmark, RegexGenError> {
+    if iterations == 0 || iterations > MAX_ITERATIONS {
+        return Err(RegexGenError::InvalidArgument(format!(
+            "Iterations must be between 1 and {}, got {}",
+            MAX_ITERATIONS, iterations
+        )));
+    }
+    let regex = pattern.to_regex_with(context)?;
+    let scope = pattern.meta().options.scope;
+
+    let started = clock();
+    let compiled = regex::Regex::new(&regex)?;
+    let compile_ms = clock() - started;
+
+    let started = clock();
+    let mut matches = 0;
+    for _ in 0..iterations {
+        matches = matching::find_scoped(&compiled, text, scope).len();
+    }
+    let total_match_ms = clock() - started;
+
+    let matches_per_sec = (total_match_ms > 0.0).then(|| (matches as f64 * iterations as f64) / (total_match_ms / 1000.0));
+    Ok(Benchmark {
+        regex,
+        iterations,
+        compile_ms,
+        total_match_ms,
+        matches,
+        matches_per_sec,
+    })
+}
+
+/// `benchmark_with_clock` timed with `std::time::Instant`, which isn't
+/// available in the browser.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn benchmark(pattern: &Pattern, context: &RegexContext, text: &str, iterations: u32) -> Result<Benchmark, RegexGenError> {
+    let origin = std::time::Instant::now();
+    benchmark_with_clock(pattern, context, text, iterations, &|| origin.elapsed().as_secs_f64() * 1000.0)
+}
@@ -0,0 +1,25 @@
+use serde::{Serialize, Deserialize};
+
+/// One entry of the taxonomy patterns are classified by, e.g. id
+/// "security", name "Security", icon "🔒".
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Category {
+    pub id: String,
+    pub name: String,
+    /// An emoji or the name of an icon in the host's icon set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+}
+
+/// Ids must be non-blank and unique, since patterns refer to them.
+pub fn validate_taxonomy(categories: &[Category]) -> Result<(), String> {
+    for (i, category) in categories.iter().enumerate() {
+        if category.id.trim().is_empty() {
+            return Err(format!("Category '{}' has no id", category.name));
+        }
+        if categories[..i].iter().any(|c| c.id == category.id) {
+            return Err(format!("Category id '{}' is used twice", category.id));
+        }
+    }
+    Ok(())
+}
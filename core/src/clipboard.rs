@@ -0,0 +1,63 @@
+use serde::{Serialize, Deserialize};
+
+use crate::library_file::SCHEMA_VERSION;
+use crate::{Pattern, PatternMetadata, RegexContext, RegexGenError};
+
+/// Marks clipboard text as a pattern copied from a pattern builder.
+pub const CLIPBOARD_FORMAT: &str = "regexgen-pattern";
+
+/// Name given to a pattern pasted as a bare regex.
+pub const PASTED_NAME: &str = "Pasted pattern";
+
+/// A pattern as copied to the clipboard. The regex goes along so that
+/// pasting into anything else still gives something usable.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClipboardPayload {
+    pub format: String,
+    /// The library schema version `pattern` is written in.
+    pub version: u32,
+    pub pattern: Pattern,
+    pub regex: String,
+}
+
+/// `pattern` as clipboard text, with its id, history and metadata left
+/// out.
+pub fn clipboard_text(pattern: &Pattern, context: &RegexContext) -> Result<String, RegexGenError> {
+    let mut copy = pattern.clone();
+    copy.set_id(String::new());
+    copy.meta_mut().history.clear();
+    copy.meta_mut().metadata = PatternMetadata::default();
+    Ok(serde_json::to_string_pretty(&ClipboardPayload {
+        format: CLIPBOARD_FORMAT.to_string(),
+        version: SCHEMA_VERSION,
+        regex: copy.to_regex_with(context)?,
+        pattern: copy,
+    })?)
+}
+
+/// Reads clipboard text back into a pattern with an empty id: a payload
+/// written by `clipboard_text`, or else any text taken as a regex and
+/// rebuilt as `from_regex` does.
+pub fn pattern_from_clipboard(text: &str) -> Result<Pattern, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(String::from("The clipboard is empty"));
+    }
+    let value: Option<serde_json::Value> = serde_json::from_str(text).ok();
+    match value {
+        Some(value) if value.get("format").and_then(|f| f.as_str()) == Some(CLIPBOARD_FORMAT) => {
+            let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0);
+            if version > SCHEMA_VERSION as u64 {
+                return Err(format!(
+                    "The copied pattern uses schema version {}, newer than the supported version {}",
+                    version, SCHEMA_VERSION
+                ));
+            }
+            let payload: ClipboardPayload = serde_json::from_value(value).map_err(|e| format!("Invalid copied pattern: {}", e))?;
+            let mut pattern = payload.pattern;
+            pattern.set_id(String::new());
+            Ok(pattern)
+        }
+        _ => Pattern::from_regex(PASTED_NAME, text),
+    }
+}
@@ -0,0 +1,144 @@
+const MIN_MATCH: usize = 4;
+/// Matches are found at most this far back; offsets are stored in 16 bits.
+const WINDOW: usize = u16::MAX as usize;
+const HASH_BITS: u32 = 14;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn hash(bytes: &[u8]) -> usize {
+    let word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    (word.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+/// Lengths of 15 or more continue in following bytes, 255 at a time.
+fn write_length(out: &mut Vec<u8>, mut length: usize) {
+    while length >= 255 {
+        out.push(255);
+        length -= 255;
+    }
+    out.push(length as u8);
+}
+
+/// One sequence: literals copied as they are, then a match `(offset,
+/// length)` into what came before, missing only at the end.
+fn write_sequence(out: &mut Vec<u8>, literals: &[u8], back_reference: Option<(usize, usize)>) {
+    let match_length = back_reference.map_or(0, |(_, length)| length - MIN_MATCH);
+    out.push(((literals.len().min(15) as u8) << 4) | match_length.min(15) as u8);
+    if literals.len() >= 15 {
+        write_length(out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+    if let Some((offset, _)) = back_reference {
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+        if match_length >= 15 {
+            write_length(out, match_length - 15);
+        }
+    }
+}
+
+/// LZ77 in the LZ4 block layout. Pattern libraries are repetitive JSON
+/// and shrink several times over; speed matters more than ratio.
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len() / 2);
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let (mut anchor, mut i) = (0, 0);
+    while i + MIN_MATCH <= input.len() {
+        let slot = hash(&input[i..]);
+        let candidate = table[slot];
+        table[slot] = i;
+        if candidate != usize::MAX && i - candidate <= WINDOW && input[candidate..candidate + MIN_MATCH] == input[i..i + MIN_MATCH] {
+            let mut length = MIN_MATCH;
+            while i + length < input.len() && input[candidate + length] == input[i + length] {
+                length += 1;
+            }
+            write_sequence(&mut out, &input[anchor..i], Some((i - candidate, length)));
+            i += length;
+            anchor = i;
+        } else {
+            i += 1;
+        }
+    }
+    write_sequence(&mut out, &input[anchor..], None);
+    out
+}
+
+fn read_length(nibble: u8, data: &[u8], i: &mut usize) -> Result<usize, String> {
+    let mut length = nibble as usize;
+    if nibble == 15 {
+        loop {
+            let byte = *data.get(*i).ok_or("Compressed data ends inside a length")?;
+            *i += 1;
+            length += byte as usize;
+            if byte != 255 {
+                break;
+            }
+        }
+    }
+    Ok(length)
+}
+
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(data.len() * 4);
+    let mut i = 0;
+    while i < data.len() {
+        let token = data[i];
+        i += 1;
+        let literals = read_length(token >> 4, data, &mut i)?;
+        out.extend_from_slice(data.get(i..i + literals).ok_or("Compressed data ends inside literals")?);
+        i += literals;
+        if i == data.len() {
+            break;
+        }
+        let offset = data
+            .get(i..i + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+            .ok_or("Compressed data ends inside an offset")?;
+        i += 2;
+        if offset == 0 || offset > out.len() {
+            return Err(String::from("Compressed data refers back past its start"));
+        }
+        let length = read_length(token & 15, data, &mut i)? + MIN_MATCH;
+        let start = out.len() - offset;
+        // Byte by byte: the match may overlap what it is copying
+        for k in 0..length {
+            out.push(out[start + k]);
+        }
+    }
+    Ok(out)
+}
+
+/// Standard base64, to keep compressed data storable as a string.
+pub fn encode_base64(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for k in 0..4 {
+            if k <= chunk.len() {
+                out.push(BASE64[(bits >> (18 - 6 * k) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+pub fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let (mut bits, mut count) = (0u32, 0);
+    for c in text.bytes().filter(|&c| c != b'=') {
+        let value = BASE64
+            .iter()
+            .position(|&b| b == c)
+            .ok_or_else(|| format!("Invalid base64 character: {}", c as char))?;
+        bits = bits << 6 | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+            bits &= (1 << count) - 1;
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,196 @@
+use std::str::FromStr;
+
+use crate::glob::glob_parts;
+use crate::import::{LineError, RegexListEntry};
+
+/// Rule files made of path globs that `parse_config` reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigDialect {
+    /// `.gitignore`: one glob per line, `#` comments.
+    Gitignore,
+    /// `.gitattributes`: a glob followed by attributes.
+    Gitattributes,
+    /// `CODEOWNERS`: a glob followed by owners.
+    Codeowners,
+    /// `.editorconfig`: globs as `[section]` headers, with `{a,b}`
+    /// alternatives.
+    Editorconfig,
+}
+
+impl FromStr for ConfigDialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_start_matches('.').to_ascii_lowercase().as_str() {
+            "gitignore" => Ok(ConfigDialect::Gitignore),
+            "gitattributes" => Ok(ConfigDialect::Gitattributes),
+            "codeowners" => Ok(ConfigDialect::Codeowners),
+            "editorconfig" => Ok(ConfigDialect::Editorconfig),
+            _ => Err(format!("Unknown config file format: {}", s)),
+        }
+    }
+}
+
+impl ConfigDialect {
+    /// Lowercase name, e.g. "codeowners", used to tag imported patterns.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConfigDialect::Gitignore => "gitignore",
+            ConfigDialect::Gitattributes => "gitattributes",
+            ConfigDialect::Codeowners => "codeowners",
+            ConfigDialect::Editorconfig => "editorconfig",
+        }
+    }
+}
+
+/// Reads the globs of a rule file as regexes over slash-separated paths
+/// relative to the repository root, one path per line. Each entry is
+/// named after the rule it came from, e.g. "*.rs @rust-team".
+pub fn parse_config(text: &str, dialect: ConfigDialect) -> (Vec<RegexListEntry>, Vec<LineError>) {
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+
+    for (i, raw_line) in text.lines().enumerate() {
+        let line = i + 1;
+        let trimmed = raw_line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || (dialect == ConfigDialect::Editorconfig && trimmed.starts_with(';')) {
+            continue;
+        }
+        let rule = match dialect {
+            ConfigDialect::Editorconfig => match trimmed.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+                Some(glob) => Rule { glob: glob.to_string(), name: trimmed.to_string() },
+                // Properties of the section above
+                None => continue,
+            },
+            ConfigDialect::Gitignore => Rule {
+                // A backslash keeps a leading `#` or `!` literal
+                glob: trimmed.to_string(),
+                name: trimmed.to_string(),
+            },
+            ConfigDialect::Gitattributes | ConfigDialect::Codeowners => Rule {
+                glob: first_field(trimmed),
+                name: trimmed.split_whitespace().collect::<Vec<_>>().join(" "),
+            },
+        };
+        if rule.glob.starts_with('!') {
+            errors.push(LineError {
+                line,
+                message: String::from("Negated rules (!) only make sense next to the rules they exclude from"),
+            });
+            continue;
+        }
+        match config_glob_regex(&rule.glob, dialect) {
+            Ok(regex) => entries.push(RegexListEntry { line, name: rule.name, regex }),
+            Err(message) => errors.push(LineError { line, message }),
+        }
+    }
+
+    (entries, errors)
+}
+
+struct Rule {
+    glob: String,
+    name: String,
+}
+
+/// The glob at the start of a line, in which `\ ` is a space.
+fn first_field(line: &str) -> String {
+    let mut glob = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                glob.push(c);
+                if let Some(escaped) = chars.next() {
+                    glob.push(escaped);
+                }
+            }
+            c if c.is_whitespace() => break,
+            c => glob.push(c),
+        }
+    }
+    glob
+}
+
+/// The regex for a glob under `dialect`'s rules. A glob with a slash
+/// other than a trailing one is relative to the root; any other matches
+/// at every depth. A trailing slash, in the git formats, matches only
+/// directories, so only paths inside one.
+pub fn config_glob_regex(glob: &str, dialect: ConfigDialect) -> Result<String, String> {
+    let directory_only = dialect != ConfigDialect::Editorconfig && glob.len() > 1 && glob.ends_with('/');
+    let glob = glob.strip_suffix('/').filter(|_| directory_only).unwrap_or(glob);
+    let anchored = glob.trim_start_matches("**/").contains('/');
+    let glob = glob.strip_prefix('/').unwrap_or(glob);
+    if glob.is_empty() {
+        return Err(String::from("Empty glob"));
+    }
+
+    let mut regex = String::from(if anchored { "^" } else { "(?:^|/)" });
+    for part in glob_parts(glob, dialect == ConfigDialect::Editorconfig)? {
+        regex.push_str(&part.regex);
+    }
+    // A path also matches when it is inside a matched directory
+    regex.push_str(if directory_only { "/" } else { "(?:/|$)" });
+    regex::Regex::new(&regex).map_err(|e| format!("Invalid glob: {}", e))?;
+    Ok(regex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{config_glob_regex, parse_config, ConfigDialect};
+
+    fn matcher(glob: &str, dialect: ConfigDialect) -> regex::Regex {
+        regex::Regex::new(&config_glob_regex(glob, dialect).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn dialects_parse_from_file_names() {
+        assert_eq!(".gitignore".parse(), Ok(ConfigDialect::Gitignore));
+        assert_eq!("CODEOWNERS".parse(), Ok(ConfigDialect::Codeowners));
+        assert!("package.json".parse::<ConfigDialect>().is_err());
+    }
+
+    #[test]
+    fn globs_with_a_slash_are_anchored_to_the_root() {
+        let anywhere = matcher("*.log", ConfigDialect::Gitignore);
+        assert!(anywhere.is_match("debug.log"));
+        assert!(anywhere.is_match("logs/debug.log"));
+        let rooted = matcher("/build", ConfigDialect::Gitignore);
+        assert!(rooted.is_match("build/out.o"));
+        assert!(!rooted.is_match("src/build/out.o"));
+        let nested = matcher("docs/*.md", ConfigDialect::Codeowners);
+        assert!(nested.is_match("docs/intro.md"));
+        assert!(!nested.is_match("src/docs/intro.md"));
+    }
+
+    #[test]
+    fn trailing_slashes_match_only_directories() {
+        let directory = matcher("target/", ConfigDialect::Gitignore);
+        assert!(directory.is_match("target/debug/app"));
+        assert!(directory.is_match("crates/target/x"));
+        assert!(!directory.is_match("target"));
+    }
+
+    #[test]
+    fn lines_become_named_entries_or_errors() {
+        let text = "# build output\n\n*.rs   @rust-team @alice\n!keep.rs @bob\ndocs/\\ notes.md @docs\n";
+        let (entries, errors) = parse_config(text, ConfigDialect::Codeowners);
+        let names: Vec<(usize, &str)> = entries.iter().map(|e| (e.line, e.name.as_str())).collect();
+        assert_eq!(names, [(3, "*.rs @rust-team @alice"), (5, "docs/\\ notes.md @docs")]);
+        assert!(regex::Regex::new(&entries[1].regex).unwrap().is_match("docs/ notes.md"));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 4);
+    }
+
+    #[test]
+    fn editorconfig_sections_are_globs_with_alternatives() {
+        let text = "root = true\n; comment\n[*.{js,ts}]\nindent_size = 2\n[Makefile]\n";
+        let (entries, errors) = parse_config(text, ConfigDialect::Editorconfig);
+        assert!(errors.is_empty());
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["[*.{js,ts}]", "[Makefile]"]);
+        let re = regex::Regex::new(&entries[0].regex).unwrap();
+        assert!(re.is_match("src/app.ts"));
+        assert!(!re.is_match("src/app.rs"));
+    }
+}
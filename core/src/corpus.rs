@@ -0,0 +1,33 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DocumentStats {
+    /// Position of the document in the corpus.
+    pub index: usize,
+    pub match_count: usize,
+}
+
+/// Match counts of one pattern over a corpus of documents.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct CorpusReport {
+    pub documents: Vec<DocumentStats>,
+    pub total_matches: usize,
+    /// Number of documents with at least one match.
+    pub matched_documents: usize,
+    pub unmatched_documents: usize,
+}
+
+impl CorpusReport {
+    pub fn add(&mut self, match_count: usize) {
+        self.documents.push(DocumentStats {
+            index: self.documents.len(),
+            match_count,
+        });
+        self.total_matches += match_count;
+        if match_count > 0 {
+            self.matched_documents += 1;
+        } else {
+            self.unmatched_documents += 1;
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use regex_syntax::ast::{self, Ast};
+
+use crate::{CompositeOperator, MatchOptions, Pattern, PatternElement, PatternMeta, RegexDialect, TokenClass};
+
+/// Option lists larger than this are kept as raw regex rather than spelled
+/// out as a `OneOf`.
+const MAX_EXPANDED_OPTIONS: usize = 256;
+
+impl Pattern {
+    /// Rebuilds a pattern from an existing regex so it can be edited
+    /// element by element. Everything `to_regex` emits for words, phrases,
+    /// gaps, option lists and token classes is recognised; any other part
+    /// of the regex is kept as a `Raw` element, so the result always
+    /// matches what the original did. The returned pattern has an empty id.
+    pub fn from_regex(name: &str, regex: &str) -> Result<Pattern, String> {
+        regex::Regex::new(regex).map_err(|e| e.to_string())?;
+        let ast = ast::parse::Parser::new().parse(regex).map_err(|e| e.to_string())?;
+
+        if let Ast::Alternation(alternation) = &ast {
+            let mut patterns = Vec::new();
+            for (i, branch) in alternation.asts.iter().enumerate() {
+                let branch = match branch {
+                    Ast::Group(group) if group.capture_index().is_some() => &group.ast,
+                    other => other,
+                };
+                let source = &regex[branch.span().start.offset..branch.span().end.offset];
+                patterns.push(Pattern::from_regex(&format!("{} {}", name, i + 1), source)?);
+            }
+            return Ok(Pattern::composite(name, CompositeOperator::Or, patterns));
+        }
+
+        let mut items: &[Ast] = match &ast {
+            Ast::Concat(concat) => &concat.asts,
+            other => std::slice::from_ref(other),
+        };
+        let mut options = MatchOptions::default();
+        if let Some((Ast::Flags(set), rest)) = items.split_first() {
+            if apply_flags(&set.flags, &mut options) {
+                items = rest;
+            }
+        }
+        if let Some((Ast::Assertion(a), rest)) = items.split_first() {
+            if a.kind == ast::AssertionKind::StartLine {
+                options.anchor_start = true;
+                items = rest;
+            }
+        }
+        if let Some((Ast::Assertion(a), rest)) = items.split_last() {
+            if a.kind == ast::AssertionKind::EndLine {
+                options.anchor_end = true;
+                items = rest;
+            }
+        }
+        // `to_regex` groups an anchored body; rebuild what is inside it
+        if let [Ast::Group(group)] = items {
+            if (options.anchor_start || options.anchor_end) && group.flags().is_some_and(|flags| flags.items.is_empty()) {
+                let span = group.ast.span();
+                let mut pattern = Pattern::from_regex(name, &regex[span.start.offset..span.end.offset])?;
+                if pattern.meta().options == MatchOptions::default() {
+                    pattern.meta_mut().options = options;
+                    return Ok(pattern);
+                }
+            }
+        }
+
+        Ok(Pattern::Sequence {
+            meta: PatternMeta { options, ..PatternMeta::new(name) },
+            elements: decompile_sequence(items, regex),
+        })
+    }
+}
+
+/// Copies `i`, `m` and `s` into `options`. Returns false, leaving the
+/// flags to a raw element, if they include anything else.
+fn apply_flags(flags: &ast::Flags, options: &mut MatchOptions) -> bool {
+    let mut recognised = MatchOptions::default();
+    for item in &flags.items {
+        match item.kind {
+            ast::FlagsItemKind::Flag(ast::Flag::CaseInsensitive) => recognised.case_insensitive = true,
+            ast::FlagsItemKind::Flag(ast::Flag::MultiLine) => recognised.multi_line = true,
+            ast::FlagsItemKind::Flag(ast::Flag::DotMatchesNewLine) => recognised.dot_all = true,
+            _ => return false,
+        }
+    }
+    *options = recognised;
+    true
+}
+
+fn decompile_sequence(items: &[Ast], source: &str) -> Vec<PatternElement> {
+    let mut elements = Vec::new();
+    // Byte range of consecutive items nothing else recognised
+    let mut raw: Option<(usize, usize)> = None;
+    let mut i = 0;
+
+    while i < items.len() {
+        match recognise(&items[i..], source) {
+            Some((element, consumed)) => {
+                if let Some((start, end)) = raw.take() {
+                    elements.push(raw_element(&source[start..end]));
+                }
+                elements.push(element);
+                i += consumed;
+            }
+            None => {
+                let span = items[i].span();
+                raw = Some(match raw {
+                    Some((start, _)) => (start, span.end.offset),
+                    None => (span.start.offset, span.end.offset),
+                });
+                i += 1;
+            }
+        }
+    }
+    if let Some((start, end)) = raw {
+        elements.push(raw_element(&source[start..end]));
+    }
+    elements
+}
+
+/// `to_regex` wraps raw fragments in `(?:...)`; unwrap them again.
+fn raw_element(fragment: &str) -> PatternElement {
+    let unwrapped = fragment
+        .strip_prefix("(?:")
+        .and_then(|rest| rest.strip_suffix(')'))
+        .filter(|inner| regex_syntax::ast::parse::Parser::new().parse(inner).is_ok());
+    PatternElement::Raw {
+        regex: unwrapped.unwrap_or(fragment).to_string(),
+    }
+}
+
+/// Tries to read one element from the start of `items`, returning it and
+/// how many items it spans.
+fn recognise(items: &[Ast], source: &str) -> Option<(PatternElement, usize)> {
+    recognise_token_class(items, source)
+        .or_else(|| recognise_bounded(items))
+        .or_else(|| recognise_gap(items, source))
+}
+
+/// Built-in token classes are matched on their exact source text.
+fn recognise_token_class(items: &[Ast], source: &str) -> Option<(PatternElement, usize)> {
+    let start = items[0].span().start.offset;
+    let rest = &source[start..];
+    let classes = [TokenClass::Number, TokenClass::Date, TokenClass::Email, TokenClass::Url, TokenClass::IpAddress];
+    for class in classes {
+        let Ok(class_regex) = class.to_regex(RegexDialect::Rust) else {
+            continue;
+        };
+        if !rest.starts_with(&class_regex) {
+            continue;
+        }
+        let end = start + class_regex.len();
+        if let Some(last) = items.iter().position(|item| item.span().end.offset == end) {
+            return Some((PatternElement::TokenClass { class }, last + 1));
+        }
+    }
+    None
+}
+
+/// `\b<literal>\b` is a word or phrase, `\b(?:<alternatives>)\b` a list of
+/// options.
+fn recognise_bounded(items: &[Ast]) -> Option<(PatternElement, usize)> {
+    if !is_word_boundary(items.first()?) {
+        return None;
+    }
+
+    let literal: String = items[1..]
+        .iter()
+        .map_while(|item| match item {
+            Ast::Literal(literal) => Some(literal.c),
+            _ => None,
+        })
+        .collect();
+    let literal_len = literal.chars().count();
+    if literal_len > 0 && items.get(literal_len + 1).is_some_and(is_word_boundary) {
+        return Some((PatternElement::word(literal), literal_len + 2));
+    }
+
+    if let (Some(Ast::Group(group)), Some(end)) = (items.get(1), items.get(2)) {
+        if is_word_boundary(end) && group.flags().is_some_and(|flags| flags.items.is_empty()) {
+            let options = expand(&group.ast)?;
+            if options.iter().any(|option| option.is_empty()) {
+                return None;
+            }
+            let element = if options.len() == 1 {
+                PatternElement::word(options[0].clone())
+            } else {
+                PatternElement::one_of(options)
+            };
+            return Some((element, 3));
+        }
+    }
+    None
+}
+
+/// `.*?` (or `.*`) is an open gap, `\W+` the separator between adjacent
+/// elements, and `\W+(?:\w+\W+){m,n}` a gap of m to n words.
+fn recognise_gap(items: &[Ast], source: &str) -> Option<(PatternElement, usize)> {
+    let Ast::Repetition(repetition) = items.first()? else {
+        return None;
+    };
+    match (&repetition.op.kind, repetition.ast.as_ref()) {
+        (ast::RepetitionKind::ZeroOrMore, Ast::Dot(_)) => Some((
+            PatternElement::gap(0, None),
+            1,
+        )),
+        (ast::RepetitionKind::OneOrMore, Ast::ClassPerl(class))
+            if repetition.greedy && class.negated && class.kind == ast::ClassPerlKind::Word =>
+        {
+            if let Some(Ast::Repetition(words)) = items.get(1) {
+                let inner = words.ast.span();
+                let is_word_group = matches!(words.ast.as_ref(), Ast::Group(_))
+                    && &source[inner.start.offset..inner.end.offset] == r"(?:\w+\W+)";
+                if let (true, ast::RepetitionKind::Range(range)) = (is_word_group && words.greedy, &words.op.kind) {
+                    let (min_words, max_words) = match *range {
+                        ast::RepetitionRange::Exactly(n) => (n, Some(n)),
+                        ast::RepetitionRange::AtLeast(n) => (n, None),
+                        ast::RepetitionRange::Bounded(m, n) => (m, Some(n)),
+                    };
+                    return Some((PatternElement::gap(min_words, max_words), 2));
+                }
+            }
+            Some((PatternElement::gap(0, Some(0)), 1))
+        }
+        _ => None,
+    }
+}
+
+fn is_word_boundary(ast: &Ast) -> bool {
+    matches!(ast, Ast::Assertion(a) if a.kind == ast::AssertionKind::WordBoundary)
+}
+
+/// Every string a finite, flag-free regex can match, or `None` if there
+/// are too many or the regex isn't finite.
+fn expand(ast: &Ast) -> Option<Vec<String>> {
+    let strings = match ast {
+        Ast::Empty(_) => vec![String::new()],
+        Ast::Literal(literal) => vec![literal.c.to_string()],
+        Ast::Group(group) if group.flags().is_none_or(|flags| flags.items.is_empty()) => expand(&group.ast)?,
+        Ast::Alternation(alternation) => {
+            let mut all = Vec::new();
+            for branch in &alternation.asts {
+                all.extend(expand(branch)?);
+            }
+            all
+        }
+        Ast::Concat(concat) => {
+            let mut all = vec![String::new()];
+            for part in &concat.asts {
+                let suffixes = expand(part)?;
+                all = all
+                    .iter()
+                    .flat_map(|prefix| suffixes.iter().map(move |suffix| format!("{}{}", prefix, suffix)))
+                    .collect();
+                if all.len() > MAX_EXPANDED_OPTIONS {
+                    return None;
+                }
+            }
+            all
+        }
+        Ast::Repetition(repetition) if repetition.op.kind == ast::RepetitionKind::ZeroOrOne => {
+            let mut all = vec![String::new()];
+            all.extend(expand(&repetition.ast)?);
+            all
+        }
+        Ast::ClassBracketed(class) if !class.negated => expand_class(&class.kind)?,
+        _ => return None,
+    };
+    (strings.len() <= MAX_EXPANDED_OPTIONS).then_some(strings)
+}
+
+fn expand_class(set: &ast::ClassSet) -> Option<Vec<String>> {
+    let ast::ClassSet::Item(item) = set else {
+        return None;
+    };
+    let items = match item {
+        ast::ClassSetItem::Union(union) => union.items.iter().collect(),
+        single => vec![single],
+    };
+    let mut chars = Vec::new();
+    for item in items {
+        match item {
+            ast::ClassSetItem::Literal(literal) => chars.push(literal.c.to_string()),
+            ast::ClassSetItem::Range(range) => {
+                chars.extend((range.start.c..=range.end.c).map(|c| c.to_string()));
+                if chars.len() > MAX_EXPANDED_OPTIONS {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(chars)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompositeOperator, Pattern, PatternElement, TokenClass};
+
+    fn sample() -> Pattern {
+        Pattern::sequence(
+            "sample",
+            vec![
+                PatternElement::word("error".to_string()),
+                PatternElement::gap(1, Some(3)),
+                PatternElement::one_of(vec!["disk".to_string(), "network".to_string()]),
+                PatternElement::TokenClass { class: TokenClass::Number },
+                PatternElement::gap(0, None),
+                PatternElement::word("failed".to_string()),
+            ],
+        )
+    }
+
+    fn elements(pattern: &Pattern) -> &[PatternElement] {
+        match pattern {
+            Pattern::Sequence { elements, .. } => elements,
+            _ => panic!("not a sequence"),
+        }
+    }
+
+    /// `to_regex` of what `from_regex` rebuilt from `pattern`'s regex.
+    fn round_trip(pattern: &Pattern) -> (Pattern, String) {
+        let regex = pattern.to_regex().unwrap();
+        let rebuilt = Pattern::from_regex("rebuilt", &regex).unwrap();
+        assert_eq!(rebuilt.to_regex().unwrap(), regex);
+        (rebuilt, regex)
+    }
+
+    #[test]
+    fn sequences_round_trip_element_by_element() {
+        let pattern = sample();
+        let (rebuilt, _) = round_trip(&pattern);
+        assert_eq!(serde_json::to_value(elements(&rebuilt)).unwrap(), serde_json::to_value(elements(&pattern)).unwrap());
+    }
+
+    #[test]
+    fn anchors_and_flags_round_trip() {
+        let mut pattern = sample();
+        pattern.meta_mut().options.anchor_start = true;
+        pattern.meta_mut().options.anchor_end = true;
+        pattern.meta_mut().options.case_insensitive = true;
+        let (rebuilt, _) = round_trip(&pattern);
+        assert_eq!(rebuilt.meta().options, pattern.meta().options);
+        assert!(elements(&rebuilt).iter().all(|e| !matches!(e, PatternElement::Raw { .. })));
+    }
+
+    #[test]
+    fn alternations_become_or_composites() {
+        let either = Pattern::composite(
+            "either",
+            CompositeOperator::Or,
+            vec![Pattern::sequence("a", vec![PatternElement::word("alpha".to_string())]), sample()],
+        );
+        let (rebuilt, _) = round_trip(&either);
+        let Pattern::Composite { operator: CompositeOperator::Or, patterns, .. } = &rebuilt else { panic!("not an OR") };
+        assert_eq!(patterns.len(), 2);
+
+        let mut anchored = either.clone();
+        anchored.meta_mut().options.anchor_start = true;
+        anchored.meta_mut().options.anchor_end = true;
+        let (rebuilt, _) = round_trip(&anchored);
+        assert!(matches!(&rebuilt, Pattern::Composite { operator: CompositeOperator::Or, .. }));
+        assert!(rebuilt.meta().options.anchor_start && rebuilt.meta().options.anchor_end);
+    }
+
+    #[test]
+    fn unrecognised_parts_stay_raw_and_match_the_same() {
+        let regex = r"\berror\b\W+(?:[A-Z]{2}\d+|x)";
+        let rebuilt = Pattern::from_regex("raw", regex).unwrap();
+        assert!(elements(&rebuilt).iter().any(|e| matches!(e, PatternElement::Raw { .. })));
+        let original = regex::Regex::new(regex).unwrap();
+        let decompiled = regex::Regex::new(&rebuilt.to_regex().unwrap()).unwrap();
+        for text in ["error AB12", "error x", "error ab12", "warning AB12"] {
+            assert_eq!(decompiled.is_match(text), original.is_match(text), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use serde::{Serialize, Deserialize};
+
+/// Drafts matching fewer times than this are never flagged, so a short
+/// sample doesn't make every pattern look degenerate.
+const MIN_MATCHES: usize = 3;
+
+/// How often a draft pattern matches the text it was built from.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DensityReport {
+    pub regex: String,
+    pub match_count: usize,
+    pub word_count: usize,
+    /// Matches per word of the text.
+    pub density: f64,
+    /// The density above which a draft counts as degenerate.
+    pub max_density: f64,
+    /// The draft matches so much of the text that it tells little apart,
+    /// as a lone stopword does.
+    pub degenerate: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+impl DensityReport {
+    pub fn new(regex: String, match_count: usize, word_count: usize, max_density: f64) -> DensityReport {
+        let density = match_count as f64 / word_count.max(1) as f64;
+        let degenerate = match_count >= MIN_MATCHES && density > max_density;
+        let warning = degenerate.then(|| {
+            format!(
+                "The pattern matches {} times in {} words; select more words or narrow its gaps before saving",
+                match_count, word_count
+            )
+        });
+        DensityReport {
+            regex,
+            match_count,
+            word_count,
+            density,
+            max_density,
+            degenerate,
+            warning,
+        }
+    }
+}
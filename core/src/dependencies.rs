@@ -0,0 +1,61 @@
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeSet, VecDeque};
+
+use crate::Pattern;
+
+/// A pattern reached through `Reference` elements, and the pattern it
+/// was reached from.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Dependency {
+    pub id: String,
+    /// `None` if no saved pattern has the id: a broken reference.
+    pub name: Option<String>,
+    /// For a dependency, the pattern that references it; for a
+    /// dependent, the pattern it references.
+    pub via: String,
+}
+
+/// Every pattern `id` references, directly or through others, nearest
+/// first.
+pub fn dependencies(patterns: &[Pattern], id: &str) -> Vec<Dependency> {
+    walk(patterns, id, |from| {
+        patterns
+            .iter()
+            .find(|p| p.get_id() == from)
+            .map(|p| p.referenced_patterns().into_iter().map(String::from).collect())
+            .unwrap_or_default()
+    })
+}
+
+/// Every pattern that references `id`, directly or through others,
+/// nearest first. These break if `id` is deleted.
+pub fn dependents(patterns: &[Pattern], id: &str) -> Vec<Dependency> {
+    walk(patterns, id, |to| {
+        patterns
+            .iter()
+            .filter(|p| p.referenced_patterns().contains(&to))
+            .map(|p| p.get_id().to_string())
+            .collect()
+    })
+}
+
+/// Breadth-first from `start` along `next`, visiting each pattern once so
+/// reference cycles end.
+fn walk(patterns: &[Pattern], start: &str, next: impl Fn(&str) -> Vec<String>) -> Vec<Dependency> {
+    let mut seen = BTreeSet::from([start.to_string()]);
+    let mut queue = VecDeque::from([start.to_string()]);
+    let mut found = Vec::new();
+    while let Some(from) = queue.pop_front() {
+        for id in next(&from) {
+            if seen.insert(id.clone()) {
+                found.push(Dependency {
+                    name: patterns.iter().find(|p| p.get_id() == id).map(|p| p.get_name().to_string()),
+                    id: id.clone(),
+                    via: from.clone(),
+                });
+                queue.push_back(id);
+            }
+        }
+    }
+    found
+}
@@ -0,0 +1,141 @@
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Regex engines that patterns can be exported for.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegexDialect {
+    Rust,
+    JavaScript,
+    Pcre,
+    Re2,
+    PosixEre,
+}
+
+/// A construct the pattern needs that the target dialect cannot express.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DialectError {
+    pub dialect: RegexDialect,
+    pub construct: String,
+}
+
+impl fmt::Display for DialectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is not supported by the {} dialect", self.construct, self.dialect.name())
+    }
+}
+
+impl std::error::Error for DialectError {}
+
+impl FromStr for RegexDialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "rust" => Ok(RegexDialect::Rust),
+            "js" | "javascript" | "ecmascript" => Ok(RegexDialect::JavaScript),
+            "pcre" => Ok(RegexDialect::Pcre),
+            "re2" | "go" => Ok(RegexDialect::Re2),
+            "posix" | "ere" | "posix-ere" | "posixere" => Ok(RegexDialect::PosixEre),
+            _ => Err(format!("Unknown regex dialect: {}", s)),
+        }
+    }
+}
+
+impl RegexDialect {
+    pub const ALL: [RegexDialect; 5] = [
+        RegexDialect::Rust,
+        RegexDialect::JavaScript,
+        RegexDialect::Pcre,
+        RegexDialect::Re2,
+        RegexDialect::PosixEre,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RegexDialect::Rust => "Rust",
+            RegexDialect::JavaScript => "JavaScript",
+            RegexDialect::Pcre => "PCRE",
+            RegexDialect::Re2 => "RE2",
+            RegexDialect::PosixEre => "POSIX ERE",
+        }
+    }
+
+    pub fn supports_lookaround(self) -> bool {
+        matches!(self, RegexDialect::JavaScript | RegexDialect::Pcre)
+    }
+
+    pub fn supports_lazy(self) -> bool {
+        self != RegexDialect::PosixEre
+    }
+
+    pub(crate) fn unsupported(self, construct: &str) -> DialectError {
+        DialectError {
+            dialect: self,
+            construct: construct.to_string(),
+        }
+    }
+
+    /// Escapes a literal so it matches itself. The Rust dialect escapes
+    /// everything `regex` treats as meta; the others stick to the common
+    /// set, since e.g. `\#` is a syntax error in JS unicode mode.
+    pub fn escape(self, literal: &str) -> String {
+        if self == RegexDialect::Rust {
+            return regex::escape(literal);
+        }
+        let mut escaped = String::with_capacity(literal.len());
+        for c in literal.chars() {
+            if matches!(c, '\\' | '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$')
+                || (c == '/' && self == RegexDialect::JavaScript)
+            {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    /// Escapes a character for use inside a bracket expression, or `None`
+    /// when the dialect has no way to do so (POSIX brackets treat `\` as a
+    /// literal, so `]`, `^`, `-` and `\` need positional tricks instead).
+    pub fn escape_class_char(self, c: char) -> Option<String> {
+        match (self, c) {
+            (RegexDialect::PosixEre, '\\' | ']' | '[' | '^' | '-') => None,
+            (RegexDialect::Rust, '\\' | ']' | '[' | '^' | '-' | '&' | '~') => Some(format!("\\{}", c)),
+            (_, '\\' | ']' | '[' | '^' | '-') => Some(format!("\\{}", c)),
+            _ => Some(c.to_string()),
+        }
+    }
+
+    /// Wraps `body` in a group that does not capture where the dialect allows.
+    pub fn group(self, body: &str) -> String {
+        if self == RegexDialect::PosixEre {
+            format!("({})", body)
+        } else {
+            format!("(?:{})", body)
+        }
+    }
+
+    /// POSIX ERE has no word boundary; the GNU `\<`/`\>` extension is
+    /// understood by grep, sed and awk, which is where ERE output ends up.
+    pub fn word_start(self) -> &'static str {
+        if self == RegexDialect::PosixEre { r"\<" } else { r"\b" }
+    }
+
+    pub fn word_end(self) -> &'static str {
+        if self == RegexDialect::PosixEre { r"\>" } else { r"\b" }
+    }
+
+    pub fn word_char(self) -> &'static str {
+        if self == RegexDialect::PosixEre { "[[:alnum:]_]" } else { r"\w" }
+    }
+
+    pub fn non_word_char(self) -> &'static str {
+        if self == RegexDialect::PosixEre { "[^[:alnum:]_]" } else { r"\W" }
+    }
+
+    /// Matches any run of characters, as short as possible where supported.
+    pub fn lazy_any(self) -> &'static str {
+        if self.supports_lazy() { ".*?" } else { ".*" }
+    }
+}
@@ -0,0 +1,80 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{Pattern, Severity};
+
+/// Default colors for the palette indices, in order. Hosts may map the
+/// indices to their own palette of the same size.
+pub const PALETTE: [&str; 10] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f",
+    "#edc948", "#b07aa1", "#ff9da7", "#9c755f", "#bab0ac",
+];
+
+/// How a pattern is shown wherever its matches are highlighted. Stored
+/// with the pattern so every view agrees.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DisplayMeta {
+    /// Index into `PALETTE`.
+    pub color_index: usize,
+    /// Short text shown instead of the name, e.g. in a narrow legend.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Host-defined icon name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// How serious its matches are; the more severe pattern owns text
+    /// several highlight, with the "severity" precedence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LegendEntry {
+    pub id: String,
+    pub name: String,
+    pub color_index: usize,
+    pub color: String,
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+}
+
+impl LegendEntry {
+    pub fn new(pattern: &Pattern) -> LegendEntry {
+        let display = pattern.meta().display.clone().unwrap_or(DisplayMeta {
+            color_index: 0,
+            label: None,
+            icon: None,
+            severity: None,
+        });
+        LegendEntry {
+            id: pattern.get_id().to_string(),
+            name: pattern.get_name().to_string(),
+            color_index: display.color_index,
+            color: PALETTE[display.color_index % PALETTE.len()].to_string(),
+            label: display.label.unwrap_or_else(|| pattern.get_name().to_string()),
+            icon: display.icon,
+            severity: display.severity,
+        }
+    }
+}
+
+/// Gives every pattern without display metadata the color used least so
+/// far, lowest index first, so colors only repeat once all are taken.
+pub fn assign_missing(patterns: &mut [Pattern]) {
+    let mut uses = [0usize; PALETTE.len()];
+    for display in patterns.iter().filter_map(|p| p.meta().display.as_ref()) {
+        uses[display.color_index % PALETTE.len()] += 1;
+    }
+    for pattern in patterns.iter_mut().filter(|p| p.meta().display.is_none()) {
+        let color_index = (0..PALETTE.len()).min_by_key(|&i| uses[i]).unwrap_or(0);
+        uses[color_index] += 1;
+        pattern.meta_mut().display = Some(DisplayMeta {
+            color_index,
+            label: None,
+            icon: None,
+            severity: None,
+        });
+    }
+}
@@ -0,0 +1,132 @@
+use std::str::FromStr;
+
+use crate::RegexDialect;
+
+/// Every dialect a rule catalog lists a regex for.
+pub const DOC_DIALECTS: [RegexDialect; 5] = RegexDialect::ALL;
+
+/// Matches from the corpus listed per pattern.
+pub const MAX_DOC_EXAMPLES: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocsFormat {
+    Markdown,
+    Html,
+}
+
+impl FromStr for DocsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Ok(DocsFormat::Markdown),
+            "html" => Ok(DocsFormat::Html),
+            _ => Err(format!("Unknown documentation format: {}", s)),
+        }
+    }
+}
+
+/// Everything documented about one pattern.
+pub struct PatternDoc {
+    pub name: String,
+    pub description: String,
+    pub explanation: String,
+    /// Notes on elements, with the explanation of the element each is on.
+    pub notes: Vec<(String, String)>,
+    /// The regex per dialect, or why the dialect can't express it.
+    pub regexes: Vec<(RegexDialect, Result<String, String>)>,
+    /// Matches found in the workspace's documents.
+    pub examples: Vec<String>,
+}
+
+pub fn render(docs: &[PatternDoc], format: DocsFormat) -> String {
+    match format {
+        DocsFormat::Markdown => render_markdown(docs),
+        DocsFormat::Html => render_html(docs),
+    }
+}
+
+/// Inline code that survives backticks inside the text.
+pub(crate) fn markdown_code(text: &str) -> String {
+    if text.contains('`') {
+        format!("`` {} ``", text)
+    } else {
+        format!("`{}`", text)
+    }
+}
+
+fn render_markdown(docs: &[PatternDoc]) -> String {
+    let mut out = String::from("# Pattern catalog\n");
+    for doc in docs {
+        out.push_str(&format!("\n## {}\n\n", doc.name));
+        if !doc.description.is_empty() {
+            out.push_str(&format!("{}\n\n", doc.description));
+        }
+        out.push_str(&format!("Matches {}.\n\n", doc.explanation));
+        if !doc.notes.is_empty() {
+            out.push_str("Notes:\n\n");
+            for (element, note) in &doc.notes {
+                out.push_str(&format!("- {}: {}\n", element, note));
+            }
+            out.push('\n');
+        }
+        out.push_str("| Dialect | Regex |\n| --- | --- |\n");
+        for (dialect, regex) in &doc.regexes {
+            let cell = match regex {
+                Ok(regex) => markdown_code(&regex.replace('|', "\\|")),
+                Err(reason) => format!("_{}_", reason),
+            };
+            out.push_str(&format!("| {} | {} |\n", dialect.name(), cell));
+        }
+        if !doc.examples.is_empty() {
+            out.push_str("\nExample matches:\n\n");
+            for example in &doc.examples {
+                out.push_str(&format!("- {}\n", markdown_code(example)));
+            }
+        }
+    }
+    out
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_html(docs: &[PatternDoc]) -> String {
+    let mut out = String::from("<h1>Pattern catalog</h1>\n");
+    for doc in docs {
+        out.push_str(&format!("<section>\n<h2>{}</h2>\n", escape_html(&doc.name)));
+        if !doc.description.is_empty() {
+            out.push_str(&format!("<p>{}</p>\n", escape_html(&doc.description)));
+        }
+        out.push_str(&format!("<p>Matches {}.</p>\n", escape_html(&doc.explanation)));
+        if !doc.notes.is_empty() {
+            out.push_str("<p>Notes:</p>\n<ul>\n");
+            for (element, note) in &doc.notes {
+                out.push_str(&format!("<li>{}: {}</li>\n", escape_html(element), escape_html(note)));
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("<table>\n<tr><th>Dialect</th><th>Regex</th></tr>\n");
+        for (dialect, regex) in &doc.regexes {
+            let cell = match regex {
+                Ok(regex) => format!("<code>{}</code>", escape_html(regex)),
+                Err(reason) => format!("<em>{}</em>", escape_html(reason)),
+            };
+            out.push_str(&format!("<tr><td>{}</td><td>{}</td></tr>\n", dialect.name(), cell));
+        }
+        out.push_str("</table>\n");
+        if !doc.examples.is_empty() {
+            out.push_str("<p>Example matches:</p>\n<ul>\n");
+            for example in &doc.examples {
+                out.push_str(&format!("<li><code>{}</code></li>\n", escape_html(example)));
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</section>\n");
+    }
+    out
+}
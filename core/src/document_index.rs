@@ -0,0 +1,154 @@
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::tokenizer::takes_word_boundary;
+use crate::{CompositeOperator, Pattern, PatternElement, Scope, WordMatch};
+
+/// An inverted index of the words in a set of documents, by line, to find
+/// where a pattern could match without running it over every line.
+pub struct DocumentIndex {
+    names: Vec<String>,
+    /// Byte offset of the start of each line, per document.
+    line_starts: Vec<Vec<usize>>,
+    /// Lowercased word to the (document, line) pairs it occurs on, sorted.
+    postings: HashMap<String, Vec<(usize, usize)>>,
+}
+
+/// The matches of a pattern in one saved document.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DocumentMatches {
+    pub document: String,
+    pub matches: Vec<(usize, usize)>,
+}
+
+/// Where in the indexed documents a pattern has to be run.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Candidates {
+    /// Everywhere: the pattern needs no word the index knows of.
+    All,
+    /// Only these whole documents, by position in `names`.
+    Documents(BTreeSet<usize>),
+    /// Only these lines, as (document, line).
+    Lines(BTreeSet<(usize, usize)>),
+}
+
+/// Words a pattern can't match without, as the index looks them up.
+#[derive(Clone, Debug)]
+enum Requirement {
+    Any,
+    Word(String),
+    All(Vec<Requirement>),
+    OneOf(Vec<Requirement>),
+}
+
+impl DocumentIndex {
+    pub fn new(documents: &BTreeMap<String, String>) -> DocumentIndex {
+        let mut index = DocumentIndex { names: Vec::new(), line_starts: Vec::new(), postings: HashMap::new() };
+        for (document, (name, text)) in documents.iter().enumerate() {
+            let mut starts = vec![0];
+            starts.extend(text.match_indices('\n').map(|(i, _)| i + 1).filter(|&i| i < text.len()));
+            for (line, &start) in starts.iter().enumerate() {
+                let end = starts.get(line + 1).copied().unwrap_or(text.len());
+                for word in words(&text[start..end]) {
+                    let lines = index.postings.entry(word).or_default();
+                    if lines.last() != Some(&(document, line)) {
+                        lines.push((document, line));
+                    }
+                }
+            }
+            index.names.push(name.clone());
+            index.line_starts.push(starts);
+        }
+        index
+    }
+
+    pub fn document_name(&self, document: usize) -> &str {
+        &self.names[document]
+    }
+
+    pub fn document_count(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Byte range of a line of a document, with its line break.
+    pub fn line_range(&self, document: usize, line: usize, text: &str) -> (usize, usize) {
+        let starts = &self.line_starts[document];
+        (starts[line], starts.get(line + 1).copied().unwrap_or(text.len()))
+    }
+
+    /// Where `pattern` could match. A pattern kept within lines by its
+    /// scope narrows down to lines; otherwise to the documents with every
+    /// word it needs.
+    pub fn candidates(&self, pattern: &Pattern) -> Candidates {
+        let by_line = pattern.meta().options.scope != Scope::Document;
+        match self.lookup(&requirement(pattern), by_line) {
+            None => Candidates::All,
+            Some(lines) if by_line => Candidates::Lines(lines),
+            Some(lines) => Candidates::Documents(lines.into_iter().map(|(document, _)| document).collect()),
+        }
+    }
+
+    /// The (document, line) pairs meeting `requirement`, with line 0 for
+    /// every line when not `by_line`; None if it rules nothing out.
+    fn lookup(&self, requirement: &Requirement, by_line: bool) -> Option<BTreeSet<(usize, usize)>> {
+        match requirement {
+            Requirement::Any => None,
+            Requirement::Word(word) => Some(
+                self.postings
+                    .get(word)
+                    .into_iter()
+                    .flatten()
+                    .map(|&(document, line)| (document, if by_line { line } else { 0 }))
+                    .collect(),
+            ),
+            Requirement::All(parts) => parts.iter().filter_map(|part| self.lookup(part, by_line)).reduce(|a, b| &a & &b),
+            Requirement::OneOf(parts) => {
+                let mut all = BTreeSet::new();
+                for part in parts {
+                    all.extend(self.lookup(part, by_line)?);
+                }
+                Some(all)
+            }
+        }
+    }
+}
+
+/// The lowercased runs of word characters in `text`, the same way on
+/// both sides so that any word a pattern matches whole is one of them.
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !takes_word_boundary(c)).filter(|w| !w.is_empty()).map(str::to_lowercase)
+}
+
+fn requirement(pattern: &Pattern) -> Requirement {
+    match pattern {
+        Pattern::Sequence { elements, .. } => Requirement::All(elements.iter().map(element_requirement).collect()),
+        Pattern::Template { .. } => Requirement::All(pattern.template_elements().iter().map(element_requirement).collect()),
+        Pattern::Composite { operator, patterns, .. } => match operator {
+            CompositeOperator::Or => Requirement::OneOf(patterns.iter().map(requirement).collect()),
+            CompositeOperator::And => Requirement::All(patterns.iter().map(requirement).collect()),
+            // Only the first operand has to be present, and only with others
+            CompositeOperator::Not if patterns.len() > 1 => requirement(&patterns[0]),
+            CompositeOperator::Not => Requirement::Any,
+        },
+    }
+}
+
+/// Words only count when the regex bounds them by word boundaries (or
+/// non-word characters of the text itself); a word matched inside a
+/// longer one isn't in the index.
+fn element_requirement(element: &PatternElement) -> Requirement {
+    match element {
+        PatternElement::Modified { element, modifier } if !modifier.optional && modifier.min_repeat > 0 => element_requirement(element),
+        PatternElement::Word { text, mode: WordMatch::Exact | WordMatch::CaseInsensitive } => {
+            Requirement::All(words(text).map(Requirement::Word).collect())
+        }
+        PatternElement::OneOf { options, list: None, option_flags, .. }
+            if !options.is_empty()
+                && option_flags.values().all(|flags| flags.whole_word)
+                && options.iter().all(|o| o.starts_with(takes_word_boundary) && o.ends_with(takes_word_boundary)) =>
+        {
+            Requirement::OneOf(options.iter().map(|o| Requirement::All(words(o).map(Requirement::Word).collect())).collect())
+        }
+        _ => Requirement::Any,
+    }
+}
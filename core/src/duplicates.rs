@@ -0,0 +1,140 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{optimize, Pattern, RegexContext};
+
+/// Least score `find_similar` reports a saved pattern at.
+pub const SIMILARITY_THRESHOLD: f64 = 0.6;
+
+/// Fields that describe a pattern rather than what it matches.
+const METADATA_FIELDS: [&str; 10] = ["id", "name", "description", "display", "tags", "folder", "priority", "history", "metadata", "test_cases"];
+
+/// What happened to a pattern the builder was asked to save.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SaveOutcome {
+    Created { id: String, regex: String },
+    /// Nothing was saved; an identical pattern already is.
+    Duplicate { existing_id: String, existing_name: String },
+}
+
+/// The pattern as JSON without its metadata, at every level, so that
+/// patterns matching the same way compare equal whatever they are called.
+fn structure(pattern: &Pattern) -> serde_json::Value {
+    let mut value = serde_json::to_value(pattern).unwrap_or_default();
+    strip_metadata(&mut value);
+    value
+}
+
+fn strip_metadata(value: &mut serde_json::Value) {
+    let Some(fields) = value.as_object_mut() else { return };
+    if matches!(fields.get("type").and_then(|t| t.as_str()), Some("Sequence" | "Composite" | "Template")) {
+        for field in METADATA_FIELDS {
+            fields.remove(field);
+        }
+    }
+    if let Some(serde_json::Value::Array(patterns)) = fields.get_mut("patterns") {
+        patterns.iter_mut().for_each(strip_metadata);
+    }
+}
+
+/// The first of `saved` that has the same elements, operands and options
+/// as `pattern`.
+pub fn find_duplicate<'a>(saved: &'a [Pattern], pattern: &Pattern) -> Option<&'a Pattern> {
+    let structure = structure(pattern);
+    saved.iter().find(|p| self::structure(p) == structure)
+}
+
+/// Why a saved pattern was found similar to another.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Similarity {
+    /// Same elements, operands and options, as `find_duplicate` checks.
+    Identical,
+    /// Built differently but generating the same regex once optimized.
+    SameRegex,
+    /// Sharing most of its elements, in the same order.
+    SimilarElements,
+}
+
+/// A saved pattern that may be worth merging with a new one.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SimilarPattern {
+    pub id: String,
+    pub name: String,
+    /// From `SIMILARITY_THRESHOLD` to 1, where 1 is identical.
+    pub score: f64,
+    pub similarity: Similarity,
+}
+
+/// The patterns of `saved` that are identical or equivalent to `pattern`
+/// or share most of its elements, most similar first. A pattern with the
+/// same id as `pattern` is skipped.
+pub fn find_similar(saved: &[Pattern], pattern: &Pattern, context: &RegexContext) -> Vec<SimilarPattern> {
+    let structure = structure(pattern);
+    let regex = normalized_regex(pattern, context);
+    let tokens = tokens(pattern, context);
+    let mut similar: Vec<SimilarPattern> = saved
+        .iter()
+        .filter(|p| pattern.get_id().is_empty() || p.get_id() != pattern.get_id())
+        .filter_map(|p| {
+            let (score, similarity) = if self::structure(p) == structure {
+                (1.0, Similarity::Identical)
+            } else if regex.is_some() && normalized_regex(p, context) == regex {
+                // Options like case-insensitivity are part of the regex
+                (0.95, Similarity::SameRegex)
+            } else {
+                (overlap(&tokens, &self::tokens(p, context)), Similarity::SimilarElements)
+            };
+            (score >= SIMILARITY_THRESHOLD).then(|| SimilarPattern {
+                id: p.get_id().to_string(),
+                name: p.get_name().to_string(),
+                score,
+                similarity,
+            })
+        })
+        .collect();
+    similar.sort_by(|a, b| b.score.total_cmp(&a.score));
+    similar
+}
+
+/// `None` for a pattern without a regex, which is like no other.
+fn normalized_regex(pattern: &Pattern, context: &RegexContext) -> Option<String> {
+    let regex = pattern.to_regex_with(context).ok()?;
+    Some(optimize::optimize(&regex).unwrap_or(regex))
+}
+
+/// What element-wise similarity compares: each element as JSON, or for
+/// composites the operator followed by each operand's regex.
+fn tokens(pattern: &Pattern, context: &RegexContext) -> Vec<String> {
+    let elements = match pattern {
+        Pattern::Sequence { elements, .. } => elements.clone(),
+        Pattern::Template { .. } => pattern.template_elements(),
+        Pattern::Composite { operator, patterns, .. } => {
+            return std::iter::once(format!("{:?}", operator))
+                .chain(patterns.iter().map(|p| normalized_regex(p, context).unwrap_or_default()))
+                .collect();
+        }
+    };
+    elements
+        .iter()
+        .map(|e| serde_json::to_string(e).unwrap_or_default())
+        .collect()
+}
+
+/// Twice the longest common subsequence of `a` and `b` over their total
+/// length: 1 when they are equal, 0 when they share nothing.
+fn overlap(a: &[String], b: &[String]) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 0.0;
+    }
+    let mut row = vec![0usize; b.len() + 1];
+    for x in a {
+        let mut diagonal = 0;
+        for (j, y) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if x == y { diagonal + 1 } else { above.max(row[j]) };
+            diagonal = above;
+        }
+    }
+    2.0 * row[b.len()] as f64 / (a.len() + b.len()) as f64
+}
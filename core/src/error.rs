@@ -0,0 +1,196 @@
+use std::fmt;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::JsValue;
+
+use crate::dialect::DialectError;
+use crate::limits::LimitExceeded;
+use crate::storage::StorageError;
+
+/// Every error the library returns, and that the wasm API throws.
+/// JavaScript receives an object with a stable `code` to branch on and a
+/// `message` to show, plus any fields particular to the error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegexGenError {
+    /// A pattern index past the end of the library.
+    InvalidIndex { index: usize, len: usize },
+    /// No pattern has this id.
+    PatternNotFound(String),
+    /// No document, workspace, test case, list or the like by this name.
+    NotFound(String),
+    /// A pattern's regex, or one given to the builder, doesn't compile.
+    CompileError(String),
+    /// The pattern uses a construct the target dialect lacks.
+    Unsupported(DialectError),
+    /// An argument the call can't use: an unknown option, a malformed
+    /// element, an empty name.
+    InvalidArgument(String),
+    /// The call doesn't make sense in the builder's current state, such
+    /// as building with no selections or switching workspaces mid
+    /// transaction.
+    InvalidState(String),
+    /// The stored library was changed by another builder since this one
+    /// loaded it.
+    Conflict { stored: u64, expected: u64 },
+    /// An identical pattern is already saved.
+    Duplicate { existing_id: String, existing_name: String },
+    Storage(StorageError),
+    LimitExceeded(LimitExceeded),
+    Serialization(String),
+    /// The host's heartbeat callback asked a long call to stop.
+    Cancelled { operation: String },
+}
+
+impl RegexGenError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RegexGenError::InvalidIndex { .. } => "invalid_index",
+            RegexGenError::PatternNotFound(_) => "pattern_not_found",
+            RegexGenError::NotFound(_) => "not_found",
+            RegexGenError::CompileError(_) => "compile_error",
+            RegexGenError::Unsupported(_) => "unsupported_in_dialect",
+            RegexGenError::InvalidArgument(_) => "invalid_argument",
+            RegexGenError::InvalidState(_) => "invalid_state",
+            RegexGenError::Conflict { .. } => "conflict",
+            RegexGenError::Duplicate { .. } => "duplicate",
+            RegexGenError::Storage(error) => error.code(),
+            RegexGenError::LimitExceeded(_) => "limit_exceeded",
+            RegexGenError::Serialization(_) => "serialization_error",
+            RegexGenError::Cancelled { .. } => "cancelled",
+        }
+    }
+
+    /// The fields beyond `code` and `message` the error carries to
+    /// JavaScript.
+    #[cfg(feature = "wasm")]
+    fn details(&self) -> Vec<(&'static str, JsValue)> {
+        match self {
+            RegexGenError::InvalidIndex { index, len } => {
+                vec![("index", (*index as f64).into()), ("len", (*len as f64).into())]
+            }
+            RegexGenError::PatternNotFound(id) => vec![("id", id.into())],
+            RegexGenError::Unsupported(error) => {
+                vec![("dialect", error.dialect.name().into()), ("construct", error.construct.as_str().into())]
+            }
+            RegexGenError::Conflict { stored, expected } => {
+                vec![("stored", (*stored as f64).into()), ("expected", (*expected as f64).into())]
+            }
+            RegexGenError::Duplicate { existing_id, existing_name } => {
+                vec![("existing_id", existing_id.into()), ("existing_name", existing_name.into())]
+            }
+            RegexGenError::Storage(StorageError::QuotaExceeded { key, bytes }) => {
+                vec![("key", key.into()), ("bytes", (*bytes as f64).into())]
+            }
+            RegexGenError::Cancelled { operation } => vec![("operation", operation.into())],
+            RegexGenError::LimitExceeded(error) => vec![
+                ("limit", error.limit.into()),
+                ("max", (error.max as f64).into()),
+                ("actual", (error.actual as f64).into()),
+            ],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for RegexGenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegexGenError::InvalidIndex { index, len } => {
+                write!(f, "Pattern index {} is out of range; the library has {} patterns", index, len)
+            }
+            RegexGenError::PatternNotFound(id) => write!(f, "Pattern not found: {}", id),
+            RegexGenError::NotFound(message)
+            | RegexGenError::InvalidArgument(message)
+            | RegexGenError::InvalidState(message) => f.write_str(message),
+            RegexGenError::CompileError(message) => write!(f, "Invalid regex: {}", message),
+            RegexGenError::Unsupported(error) => error.fmt(f),
+            RegexGenError::Conflict { stored, expected } => write!(
+                f,
+                "Conflict: library is at revision {} but this builder expected {}; reload before editing",
+                stored, expected
+            ),
+            RegexGenError::Duplicate { existing_id, existing_name } => {
+                write!(f, "An identical pattern is already saved as \"{}\" ({})", existing_name, existing_id)
+            }
+            RegexGenError::Storage(error) => error.fmt(f),
+            RegexGenError::LimitExceeded(error) => error.fmt(f),
+            RegexGenError::Serialization(message) => write!(f, "Could not serialize: {}", message),
+            RegexGenError::Cancelled { operation } => write!(f, "{} was cancelled", operation),
+        }
+    }
+}
+
+impl std::error::Error for RegexGenError {}
+
+impl From<StorageError> for RegexGenError {
+    fn from(error: StorageError) -> Self {
+        RegexGenError::Storage(error)
+    }
+}
+
+impl From<LimitExceeded> for RegexGenError {
+    fn from(error: LimitExceeded) -> Self {
+        RegexGenError::LimitExceeded(error)
+    }
+}
+
+impl From<DialectError> for RegexGenError {
+    fn from(error: DialectError) -> Self {
+        RegexGenError::Unsupported(error)
+    }
+}
+
+impl From<regex::Error> for RegexGenError {
+    fn from(error: regex::Error) -> Self {
+        RegexGenError::CompileError(error.to_string())
+    }
+}
+
+/// Values from JavaScript that don't have the shape a call expects.
+#[cfg(feature = "wasm")]
+impl From<serde_wasm_bindgen::Error> for RegexGenError {
+    fn from(error: serde_wasm_bindgen::Error) -> Self {
+        RegexGenError::InvalidArgument(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for RegexGenError {
+    fn from(error: serde_json::Error) -> Self {
+        RegexGenError::Serialization(error.to_string())
+    }
+}
+
+/// Reaches JavaScript as `{ code, message, ...details }`.
+#[cfg(feature = "wasm")]
+impl From<RegexGenError> for JsValue {
+    fn from(error: RegexGenError) -> JsValue {
+        let object = js_sys::Object::new();
+        let fields = [("code", error.code().into()), ("message", error.to_string().into())]
+            .into_iter()
+            .chain(error.details());
+        for (name, value) in fields {
+            let _ = js_sys::Reflect::set(&object, &name.into(), &value);
+        }
+        object.into()
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl From<StorageError> for JsValue {
+    fn from(error: StorageError) -> JsValue {
+        RegexGenError::from(error).into()
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl From<LimitExceeded> for JsValue {
+    fn from(error: LimitExceeded) -> JsValue {
+        RegexGenError::from(error).into()
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl From<DialectError> for JsValue {
+    fn from(error: DialectError) -> JsValue {
+        RegexGenError::from(error).into()
+    }
+}
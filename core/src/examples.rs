@@ -0,0 +1,129 @@
+use crate::rng::SeededRng;
+use crate::{CompositeOperator, Pattern, PatternElement, RegexContext, TokenClass};
+
+/// Filler used for gaps and unresolved references.
+const PLACEHOLDER_WORDS: &[&str] = &["lorem", "ipsum", "dolor", "sit", "amet", "consectetur"];
+
+/// Extra words an open-ended gap may receive beyond its minimum.
+const MAX_EXTRA_GAP_WORDS: u32 = 3;
+
+/// Candidates tried per requested example before giving up.
+const ATTEMPTS_PER_EXAMPLE: usize = 4;
+
+impl Pattern {
+    /// Up to `count` distinct strings that match this pattern, using a
+    /// fixed seed so the same pattern always yields the same examples.
+    pub fn generate_examples(&self, count: usize) -> Vec<String> {
+        self.generate_examples_seeded(count, 0x5eed, &RegexContext::default())
+    }
+
+    /// Like `generate_examples` with an explicit seed. Candidates are
+    /// checked against the compiled regex, so every returned string is a
+    /// real match; fewer than `count` come back if the pattern admits few.
+    pub fn generate_examples_seeded(&self, count: usize, seed: u64, context: &RegexContext) -> Vec<String> {
+        let Some(re) = self.to_regex_with(context).ok().and_then(|source| regex::Regex::new(&source).ok()) else {
+            return Vec::new();
+        };
+        let mut rng = SeededRng::new(seed);
+        let mut examples: Vec<String> = Vec::new();
+        for _ in 0..count * ATTEMPTS_PER_EXAMPLE {
+            if examples.len() >= count {
+                break;
+            }
+            let candidate = self.example_text(&mut rng, context);
+            if re.is_match(&candidate) && !examples.contains(&candidate) {
+                examples.push(candidate);
+            }
+        }
+        examples
+    }
+
+    fn example_text(&self, rng: &mut SeededRng, context: &RegexContext) -> String {
+        match self {
+            Pattern::Sequence { elements, .. } => {
+                let mut words: Vec<String> = Vec::new();
+                for element in elements {
+                    push_element_words(element, rng, context, &mut words);
+                }
+                words.join(" ")
+            }
+            Pattern::Template { .. } => {
+                let mut words: Vec<String> = Vec::new();
+                for element in &self.template_elements() {
+                    push_element_words(element, rng, context, &mut words);
+                }
+                words.join(" ")
+            }
+            Pattern::Composite { operator, patterns, .. } => match operator {
+                CompositeOperator::Or if !patterns.is_empty() => {
+                    patterns[rng.below(patterns.len())].example_text(rng, context)
+                }
+                CompositeOperator::Not if patterns.len() > 1 => patterns[0].example_text(rng, context),
+                CompositeOperator::And => patterns
+                    .iter()
+                    .filter(|p| !matches!(p, Pattern::Composite { operator: CompositeOperator::Not, .. }))
+                    .map(|p| p.example_text(rng, context))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                _ => placeholder(rng),
+            },
+        }
+    }
+}
+
+fn placeholder(rng: &mut SeededRng) -> String {
+    PLACEHOLDER_WORDS[rng.below(PLACEHOLDER_WORDS.len())].to_string()
+}
+
+fn token_sample(class: &TokenClass, rng: &mut SeededRng) -> String {
+    let samples: &[&str] = match class {
+        TokenClass::Number => &["42", "7", "1024", "3.14"],
+        TokenClass::Date => &["2024-01-15", "12/31/2023", "2023-7-4"],
+        TokenClass::Email => &["user@example.com", "ops.team@example.org"],
+        TokenClass::Url => &["https://example.com/status", "http://localhost:8080/"],
+        TokenClass::IpAddress => &["192.168.0.1", "10.0.0.254"],
+        TokenClass::Custom { .. } => return placeholder(rng),
+    };
+    samples[rng.below(samples.len())].to_string()
+}
+
+fn push_element_words(element: &PatternElement, rng: &mut SeededRng, context: &RegexContext, words: &mut Vec<String>) {
+    match element {
+        PatternElement::Word { text, .. } => words.push(text.clone()),
+        // The space between the surrounding words already fits any char limit
+        PatternElement::Gap { max_chars: Some(_), .. } => {}
+        PatternElement::Gap { min_words, max_words, .. } => {
+            let max = max_words.unwrap_or(min_words + MAX_EXTRA_GAP_WORDS);
+            let n = *min_words as usize + rng.below((max - min_words) as usize + 1);
+            for _ in 0..n {
+                words.push(placeholder(rng));
+            }
+        }
+        PatternElement::OneOf { options, list, .. } => {
+            let all_options = context.resolve_options(options, list.as_deref());
+            if !all_options.is_empty() {
+                words.push(all_options[rng.below(all_options.len())].clone());
+            }
+        }
+        PatternElement::SynonymSet { set_id } => {
+            let synonyms = context.resolve_synonyms(set_id);
+            if !synonyms.is_empty() {
+                words.push(synonyms[rng.below(synonyms.len())].clone());
+            }
+        }
+        PatternElement::TokenClass { class } => words.push(token_sample(class, rng)),
+        PatternElement::Reference { .. } | PatternElement::Raw { .. } => {
+            words.push(placeholder(rng))
+        }
+        PatternElement::Modified { element, modifier } => {
+            if modifier.optional && rng.below(2) == 0 {
+                return;
+            }
+            let max = modifier.max_repeat.unwrap_or(modifier.min_repeat + MAX_EXTRA_GAP_WORDS);
+            let n = modifier.min_repeat as usize + rng.below((max - modifier.min_repeat) as usize + 1);
+            for _ in 0..n {
+                push_element_words(element, rng, context, words);
+            }
+        }
+    }
+}
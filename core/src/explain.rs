@@ -0,0 +1,227 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+
+use crate::locale::MessageCatalog;
+use crate::{CompositeOperator, Pattern, PatternElement, TokenClass, WordMatch};
+
+/// One step of an explanation. `key` and `params` identify the message so
+/// hosts can translate it; `text` is the rendering in the requested locale.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ExplanationPart {
+    pub key: String,
+    pub params: BTreeMap<String, String>,
+    pub text: String,
+    /// Explanations of sub-patterns, for composites.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Explanation>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Explanation {
+    pub text: String,
+    pub parts: Vec<ExplanationPart>,
+}
+
+impl ExplanationPart {
+    fn new(key: &str, params: &[(&str, String)], text: String) -> Self {
+        ExplanationPart {
+            key: key.to_string(),
+            params: params
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+            text,
+            children: Vec::new(),
+        }
+    }
+
+    /// A part whose text is the catalog's message for `key`.
+    fn rendered(catalog: &MessageCatalog, key: &str, params: &[(&str, String)]) -> Self {
+        ExplanationPart::new(key, params, catalog.render(key, params))
+    }
+
+    /// Wraps the text in a decoration message such as "optionally {part}".
+    fn decorate(&mut self, catalog: &MessageCatalog, key: &str, params: &[(&str, String)]) {
+        let mut vars = params.to_vec();
+        vars.push(("part", self.text.clone()));
+        self.text = catalog.render(key, &vars);
+    }
+}
+
+impl Pattern {
+    /// Describes what the pattern matches in plain language, e.g. "the word
+    /// 'error', then 1–5 words, then one of ('timeout', 'refused')".
+    pub fn explain(&self) -> Explanation {
+        self.explain_in(&MessageCatalog::english())
+    }
+
+    /// `explain` with the text rendered from `catalog`.
+    pub fn explain_in(&self, catalog: &MessageCatalog) -> Explanation {
+        let parts = match self {
+            Pattern::Sequence { elements, .. } => explain_elements(elements, catalog),
+            Pattern::Template { .. } => explain_elements(&self.template_elements(), catalog),
+            Pattern::Composite { operator, patterns, .. } => {
+                let children: Vec<Explanation> = patterns.iter().map(|p| p.explain_in(catalog)).collect();
+                let listed: Vec<String> = children
+                    .iter()
+                    .map(|c| catalog.render("sub_pattern", &[("text", c.text.clone())]))
+                    .collect();
+                let (key, text) = match operator {
+                    CompositeOperator::Not if listed.len() > 1 => {
+                        let excluded = listed[1..].join(&catalog.render("none_of_separator", &[]));
+                        let params = [("required", listed[0].clone()), ("patterns", excluded)];
+                        ("but_none_of", catalog.render("but_none_of", &params))
+                    }
+                    _ => {
+                        let key = match operator {
+                            CompositeOperator::And => "all_of",
+                            CompositeOperator::Or => "any_of",
+                            CompositeOperator::Not => "none_of",
+                        };
+                        let separator = catalog.render(&format!("{}_separator", key), &[]);
+                        (key, catalog.render(key, &[("patterns", listed.join(&separator))]))
+                    }
+                };
+                let mut part = ExplanationPart::new(key, &[("count", patterns.len().to_string())], text);
+                part.children = children;
+                vec![part]
+            }
+        };
+        let texts: Vec<&str> = parts.iter().map(|p: &ExplanationPart| p.text.as_str()).collect();
+        Explanation {
+            text: texts.join(&catalog.render("sequence_separator", &[])),
+            parts,
+        }
+    }
+}
+
+fn quote_list(options: &[String], catalog: &MessageCatalog) -> String {
+    let quoted: Vec<String> = options
+        .iter()
+        .map(|o| catalog.render("quoted", &[("text", o.clone())]))
+        .collect();
+    quoted.join(&catalog.render("list_separator", &[]))
+}
+
+/// Adds "not containing ..." to a gap's explanation.
+fn exclude_words(mut part: ExplanationPart, excluded: &[String], catalog: &MessageCatalog) -> ExplanationPart {
+    if !excluded.is_empty() {
+        part.decorate(catalog, "gap_excluded", &[("excluded", quote_list(excluded, catalog))]);
+        part.params.insert(String::from("excluded"), excluded.join("|"));
+    }
+    part
+}
+
+fn explain_elements(elements: &[PatternElement], catalog: &MessageCatalog) -> Vec<ExplanationPart> {
+    elements
+        .iter()
+        // Zero-word gaps only separate adjacent elements, unless
+        // they carry a note or field name
+        .filter(|e| !e.is_separator() || matches!(e, PatternElement::Modified { .. }))
+        .map(|e| explain_element(e, catalog))
+        .collect()
+}
+
+fn explain_element(element: &PatternElement, catalog: &MessageCatalog) -> ExplanationPart {
+    match element {
+        PatternElement::Word { text, mode } => {
+            let key = if text.contains(' ') { "phrase" } else { "word" };
+            let mut part = ExplanationPart::rendered(catalog, key, &[("text", text.clone())]);
+            match mode {
+                WordMatch::Exact => {}
+                WordMatch::CaseInsensitive => part.decorate(catalog, "word_case_insensitive", &[]),
+                WordMatch::Prefix => part.decorate(catalog, "word_prefix", &[]),
+                WordMatch::Approximate => part.decorate(catalog, "word_approximate", &[]),
+                WordMatch::Substring => part.decorate(catalog, "word_substring", &[]),
+            }
+            part
+        }
+        PatternElement::Gap { max_chars: Some(max), excluded, .. } => exclude_words(
+            ExplanationPart::rendered(catalog, "gap_chars", &[("max_chars", max.to_string())]),
+            excluded,
+            catalog,
+        ),
+        PatternElement::Gap { min_words: 0, max_words: None, excluded, .. } => {
+            exclude_words(ExplanationPart::rendered(catalog, "gap_any", &[]), excluded, catalog)
+        }
+        PatternElement::Gap { min_words, max_words: None, excluded, .. } => exclude_words(
+            ExplanationPart::rendered(catalog, "gap_at_least", &[("min", min_words.to_string())]),
+            excluded,
+            catalog,
+        ),
+        PatternElement::Gap { min_words, max_words: Some(max), excluded, .. } if min_words == max => exclude_words(
+            ExplanationPart::rendered(catalog, "gap_exact", &[("count", max.to_string())]),
+            excluded,
+            catalog,
+        ),
+        PatternElement::Gap { min_words, max_words: Some(max), excluded, .. } => exclude_words(
+            ExplanationPart::rendered(catalog, "gap_range", &[("min", min_words.to_string()), ("max", max.to_string())]),
+            excluded,
+            catalog,
+        ),
+        PatternElement::OneOf { options, list, .. } => {
+            let quoted = quote_list(options, catalog);
+            let mut params = vec![("options", options.join("|"))];
+            let text = match list {
+                None => catalog.render("one_of", &[("options", quoted)]),
+                Some(list) => {
+                    params.push(("list", list.clone()));
+                    if options.is_empty() {
+                        catalog.render("one_of_list", &[("list", list.clone())])
+                    } else {
+                        catalog.render("one_of_options_and_list", &[("options", quoted), ("list", list.clone())])
+                    }
+                }
+            };
+            ExplanationPart::new("one_of", &params, text)
+        }
+        PatternElement::SynonymSet { set_id } => {
+            ExplanationPart::rendered(catalog, "synonym_set", &[("set", set_id.clone())])
+        }
+        PatternElement::TokenClass { class } => {
+            let message = match class {
+                TokenClass::Number => "token_class_number",
+                TokenClass::Date => "token_class_date",
+                TokenClass::Email => "token_class_email",
+                TokenClass::Url => "token_class_url",
+                TokenClass::IpAddress => "token_class_ip_address",
+                TokenClass::Custom { .. } => "token_class",
+            };
+            let params = [("class", class.name().to_string())];
+            ExplanationPart::new("token_class", &params, catalog.render(message, &params))
+        }
+        PatternElement::Raw { regex } => ExplanationPart::rendered(catalog, "raw", &[("regex", regex.clone())]),
+        PatternElement::Reference { pattern_id } => {
+            ExplanationPart::rendered(catalog, "reference", &[("pattern_id", pattern_id.clone())])
+        }
+        PatternElement::Modified { element, modifier } => {
+            let mut part = explain_element(element, catalog);
+            if modifier.is_repeated() {
+                let min = modifier.min_repeat.to_string();
+                match modifier.max_repeat {
+                    Some(max) if max == modifier.min_repeat => {
+                        part.decorate(catalog, "repeat_exact", &[("count", max.to_string())])
+                    }
+                    Some(max) => part.decorate(catalog, "repeat_range", &[("min", min.clone()), ("max", max.to_string())]),
+                    None => part.decorate(catalog, "repeat_at_least", &[("min", min.clone())]),
+                }
+                part.params.insert(String::from("min_repeat"), min);
+                if let Some(max) = modifier.max_repeat {
+                    part.params.insert(String::from("max_repeat"), max.to_string());
+                }
+            }
+            if modifier.optional {
+                part.decorate(catalog, "optional", &[]);
+                part.params.insert(String::from("optional"), String::from("true"));
+            }
+            if let Some(name) = &modifier.capture {
+                part.decorate(catalog, "captured", &[("name", name.clone())]);
+                part.params.insert(String::from("capture"), name.clone());
+            }
+            if let Some(note) = &modifier.note {
+                part.params.insert(String::from("note"), note.clone());
+            }
+            part
+        }
+    }
+}
@@ -0,0 +1,435 @@
+use serde::{Serialize, Deserialize};
+use std::str::FromStr;
+
+use crate::portability::{portability_warnings, ExportWarning};
+use crate::{DialectError, Pattern, RegexContext, RegexDialect, RegexGenError, TestCase};
+
+/// Settings for VS Code's search box: the query plus the state of its
+/// regex, match-case and whole-word toggles.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VsCodeSearch {
+    pub pattern: String,
+    pub is_regex: bool,
+    pub match_case: bool,
+    pub whole_word: bool,
+}
+
+/// VS Code has a toggle for case sensitivity, so it is taken out of the
+/// regex. Word boundaries stay inline since they apply per element.
+pub fn vscode_search(pattern: &Pattern, context: &RegexContext) -> Result<VsCodeSearch, DialectError> {
+    let mut stripped = pattern.clone();
+    let case_insensitive = stripped.meta().options.case_insensitive;
+    stripped.meta_mut().options.case_insensitive = false;
+    Ok(VsCodeSearch {
+        pattern: stripped.to_regex_for_with(RegexDialect::JavaScript, context)?,
+        is_regex: true,
+        match_case: !case_insensitive,
+        whole_word: false,
+    })
+}
+
+/// Lowercase identifier made of `[a-z0-9_]`, for config keys and rule ids.
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.ends_with('_') {
+            slug.push('_');
+        }
+    }
+    let slug = slug.trim_matches('_').to_string();
+    if slug.is_empty() || slug.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("pattern_{}", slug)
+    } else {
+        slug
+    }
+}
+
+/// Vector config with two transforms: a `route` with one output per
+/// pattern, and a `remap` that tags each event with the names of the
+/// patterns it matches. Vector evaluates VRL regexes with the Rust engine.
+pub fn vector_transform(patterns: &[&Pattern], context: &RegexContext) -> Result<String, DialectError> {
+    let mut rules = Vec::new();
+    for pattern in patterns {
+        let regex = pattern.to_regex_for_with(RegexDialect::Rust, context)?;
+        // VRL raw regex literals are single-quoted
+        rules.push((slugify(pattern.get_name()), pattern.get_name(), regex.replace('\'', "\\'")));
+    }
+
+    let mut out = String::new();
+    out.push_str("# Generated by regexgen. Replace INPUT with your source or transform id.\n");
+    out.push_str("[transforms.regexgen_route]\n");
+    out.push_str("type = \"route\"\n");
+    out.push_str("inputs = [\"INPUT\"]\n\n");
+    out.push_str("[transforms.regexgen_route.route]\n");
+    for (slug, name, regex) in &rules {
+        out.push_str(&format!("# {}\n", name));
+        out.push_str(&format!("{} = '''match(string!(.message), r'{}')'''\n", slug, regex));
+    }
+
+    out.push_str("\n[transforms.regexgen_tag]\n");
+    out.push_str("type = \"remap\"\n");
+    out.push_str("inputs = [\"INPUT\"]\n");
+    out.push_str("source = '''\n");
+    out.push_str(".regexgen_matches = []\n");
+    for (slug, _, regex) in &rules {
+        out.push_str(&format!(
+            "if match(string!(.message), r'{}') {{ .regexgen_matches = push(.regexgen_matches, \"{}\") }}\n",
+            regex, slug
+        ));
+    }
+    out.push_str("'''\n");
+    Ok(out)
+}
+
+/// Tools `tool_snippet` writes a ready-to-paste query or command for.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolTarget {
+    /// A `grep -E` command line.
+    Grep,
+    /// A Splunk search filtering with `regex`, plus `rex` for fields.
+    Splunk,
+    /// An Elasticsearch `regexp` query.
+    Elasticsearch,
+    /// A Grafana Loki LogQL line filter, plus a `regexp` parser for fields.
+    Loki,
+}
+
+impl FromStr for ToolTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "grep" | "egrep" => Ok(ToolTarget::Grep),
+            "splunk" | "spl" => Ok(ToolTarget::Splunk),
+            "elasticsearch" | "elastic" | "es" => Ok(ToolTarget::Elasticsearch),
+            "loki" | "logql" | "grafana" => Ok(ToolTarget::Loki),
+            _ => Err(format!("Unknown export target: {}", s)),
+        }
+    }
+}
+
+impl ToolTarget {
+    /// The engine the tool runs the regex with. Elasticsearch's Lucene
+    /// syntax is derived from the RE2 output.
+    pub fn dialect(self) -> RegexDialect {
+        match self {
+            ToolTarget::Grep => RegexDialect::PosixEre,
+            ToolTarget::Splunk => RegexDialect::Pcre,
+            ToolTarget::Elasticsearch | ToolTarget::Loki => RegexDialect::Re2,
+        }
+    }
+}
+
+/// A snippet for one tool, with the regex inside it and anything that
+/// behaves differently there than in the builder.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ToolExport {
+    pub target: ToolTarget,
+    pub regex: String,
+    pub snippet: String,
+    pub warnings: Vec<ExportWarning>,
+}
+
+/// Writes `pattern` as a query or command for `target`. Where the tool
+/// has its own case-insensitivity switch it is used instead of an inline
+/// flag. Splunk and Loki also extract the pattern's fields.
+pub fn tool_snippet(pattern: &Pattern, context: &RegexContext, target: ToolTarget) -> Result<ToolExport, RegexGenError> {
+    let dialect = target.dialect();
+    let mut warnings = portability_warnings(pattern, dialect);
+    let case_switch = matches!(target, ToolTarget::Grep | ToolTarget::Elasticsearch);
+    let mut stripped = pattern.clone();
+    let case_insensitive = case_switch && stripped.meta().options.case_insensitive;
+    if case_switch {
+        stripped.meta_mut().options.case_insensitive = false;
+    }
+    let regex = stripped.to_regex_for_with(dialect, context)?;
+    let has_fields = !pattern.fields().is_empty();
+    let field_regex = || {
+        let capture_context = RegexContext {
+            capture_fields: true,
+            ..context.clone()
+        };
+        pattern.to_regex_for_with(dialect, &capture_context)
+    };
+
+    let (regex, snippet) = match target {
+        ToolTarget::Grep => {
+            let flags = if case_insensitive { "-E -i" } else { "-E" };
+            let snippet = format!("grep {} -e {} FILE\n", flags, shell_quoted(&regex));
+            (regex, snippet)
+        }
+        ToolTarget::Splunk => {
+            let mut snippet = format!("| regex _raw=\"{}\"\n", splunk_escaped(&regex));
+            if has_fields {
+                snippet.push_str(&format!("| rex field=_raw \"{}\"\n", splunk_escaped(&field_regex()?)));
+            }
+            (regex, snippet)
+        }
+        ToolTarget::Elasticsearch => {
+            let lucene = lucene_regexp(&regex)?;
+            if lucene.dropped_boundaries {
+                warnings.push(ExportWarning {
+                    code: String::from("no_word_boundaries"),
+                    message: String::from(
+                        "Lucene regexps have no word boundaries, so words also match inside longer words",
+                    ),
+                });
+            }
+            let query = serde_json::json!({
+                "query": {
+                    "regexp": {
+                        "message": {
+                            "value": lucene.regex,
+                            "flags": "ALL",
+                            "case_insensitive": case_insensitive,
+                        }
+                    }
+                }
+            });
+            let snippet = format!("{}\n", serde_json::to_string_pretty(&query)?);
+            (lucene.regex, snippet)
+        }
+        ToolTarget::Loki => {
+            let mut snippet = format!("{{job=\"JOB\"}} |~ {}", logql_string(&regex));
+            if has_fields {
+                snippet.push_str(&format!(" | regexp {}", logql_string(&field_regex()?)));
+            }
+            snippet.push('\n');
+            (regex, snippet)
+        }
+    };
+    Ok(ToolExport {
+        target,
+        regex,
+        snippet,
+        warnings,
+    })
+}
+
+/// A single-quoted shell word; quotes inside are closed, escaped and
+/// reopened.
+fn shell_quoted(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Splunk unescapes quoted strings once before the regex engine sees
+/// them, so quotes and literal backslashes need another backslash.
+fn splunk_escaped(regex: &str) -> String {
+    let mut escaped = String::with_capacity(regex.len());
+    let mut chars = regex.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('\\') => escaped.push_str(r"\\\\"),
+                Some(next) => {
+                    escaped.push('\\');
+                    escaped.push(next);
+                }
+                None => escaped.push('\\'),
+            },
+            '"' => escaped.push_str("\\\""),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A LogQL string: backquoted, which needs no escaping, unless the regex
+/// has a backquote itself.
+fn logql_string(regex: &str) -> String {
+    if regex.contains('`') {
+        serde_json::to_string(regex).unwrap_or_default()
+    } else {
+        format!("`{}`", regex)
+    }
+}
+
+struct LuceneRegexp {
+    regex: String,
+    dropped_boundaries: bool,
+}
+
+/// Rewrites an RE2 regex in Lucene's syntax. Lucene matches the whole
+/// field value, so unanchored ends get `.*`; it has no non-capturing
+/// groups, lazy quantifiers or word boundaries, and `@&~<>#"` are
+/// operators there.
+fn lucene_regexp(regex: &str) -> Result<LuceneRegexp, RegexGenError> {
+    let unsupported = |construct: &str| {
+        RegexGenError::InvalidArgument(format!("{} can't be expressed in an Elasticsearch regexp query", construct))
+    };
+    let chars: Vec<char> = regex.chars().collect();
+    let mut body = String::new();
+    let mut dropped_boundaries = false;
+    let mut in_class = false;
+    let mut after_quantifier = false;
+    let (mut anchored_start, mut anchored_end) = (false, false);
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let quantifier = !in_class && matches!(c, '*' | '+' | '?' | '}');
+        match c {
+            '\\' => {
+                let next = *chars.get(i + 1).ok_or_else(|| unsupported("A trailing backslash"))?;
+                i += 1;
+                match next {
+                    'b' if !in_class => dropped_boundaries = true,
+                    'd' | 'w' | 's' | 'D' | 'W' | 'S' => body.push_str(&format!("\\{}", next)),
+                    next if next.is_alphanumeric() => return Err(unsupported(&format!("The escape \\{}", next))),
+                    next => body.push_str(&format!("\\{}", next)),
+                }
+            }
+            '[' if !in_class => {
+                in_class = true;
+                body.push(c);
+                // A leading `]` (after an optional `^`) is a literal
+                if chars.get(i + 1) == Some(&'^') {
+                    body.push('^');
+                    i += 1;
+                }
+                if chars.get(i + 1) == Some(&']') {
+                    body.push_str("\\]");
+                    i += 1;
+                }
+            }
+            '[' if chars.get(i + 1) == Some(&':') => return Err(unsupported("A POSIX character class")),
+            ']' if in_class => {
+                in_class = false;
+                body.push(c);
+            }
+            '(' if !in_class && chars.get(i + 1) == Some(&'?') => {
+                if chars.get(i + 2) != Some(&':') {
+                    return Err(unsupported("An inline flag or lookaround"));
+                }
+                body.push('(');
+                i += 2;
+            }
+            '?' if after_quantifier => {}
+            '^' if !in_class && i == 0 => anchored_start = true,
+            '$' if !in_class && i == chars.len() - 1 => anchored_end = true,
+            '^' | '$' if !in_class => return Err(unsupported("An anchor inside the pattern")),
+            '@' | '&' | '~' | '<' | '>' | '#' | '"' => {
+                body.push('\\');
+                body.push(c);
+            }
+            c => body.push(c),
+        }
+        after_quantifier = quantifier && !after_quantifier;
+        i += 1;
+    }
+    let start = if anchored_start { "" } else { ".*" };
+    let end = if anchored_end { "" } else { ".*" };
+    Ok(LuceneRegexp {
+        regex: format!("{}{}{}", start, body, end),
+        dropped_boundaries,
+    })
+}
+
+/// How a pattern's test cases travel with its exported regex.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FixtureFormat {
+    /// The regex followed by `#` comments listing the cases.
+    Comments,
+    /// A pytest module using Python's `re`.
+    Pytest,
+    /// A Jest test file.
+    Jest,
+}
+
+impl FromStr for FixtureFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "comments" | "comment" => Ok(FixtureFormat::Comments),
+            "pytest" | "python" => Ok(FixtureFormat::Pytest),
+            "jest" | "js" => Ok(FixtureFormat::Jest),
+            _ => Err(format!("Unknown fixture format: {}", s)),
+        }
+    }
+}
+
+impl FixtureFormat {
+    /// The dialect whose regex the test harness runs.
+    pub fn natural_dialect(self) -> Option<RegexDialect> {
+        match self {
+            FixtureFormat::Comments => None,
+            FixtureFormat::Pytest => Some(RegexDialect::Pcre),
+            FixtureFormat::Jest => Some(RegexDialect::JavaScript),
+        }
+    }
+}
+
+/// A double-quoted string literal valid in Python and JavaScript alike.
+fn quoted(text: &str) -> String {
+    serde_json::to_string(text).unwrap_or_default()
+}
+
+fn quoted_list(cases: &[&TestCase], indent: &str) -> String {
+    cases
+        .iter()
+        .map(|case| format!("{}{},\n", indent, quoted(&case.text)))
+        .collect()
+}
+
+/// The exported `regex` of `pattern` together with its test cases.
+pub fn with_test_fixture(pattern: &Pattern, regex: &str, format: FixtureFormat) -> String {
+    let name = pattern.get_name();
+    let (positive, negative): (Vec<&TestCase>, Vec<&TestCase>) =
+        pattern.meta().test_cases.iter().partition(|case| case.should_match);
+
+    match format {
+        FixtureFormat::Comments => {
+            let mut out = format!("# {}\n{}\n", name, regex);
+            for case in &positive {
+                out.push_str(&format!("# should match: {}\n", quoted(&case.text)));
+            }
+            for case in &negative {
+                out.push_str(&format!("# should not match: {}\n", quoted(&case.text)));
+            }
+            out
+        }
+        FixtureFormat::Pytest => {
+            let slug = slugify(name);
+            let mut out = format!(
+                "# Tests for the pattern {}\nimport re\n\nimport pytest\n\nPATTERN = re.compile({})\n",
+                quoted(name),
+                quoted(regex)
+            );
+            if !positive.is_empty() {
+                out.push_str(&format!(
+                    "\n\n@pytest.mark.parametrize(\"text\", [\n{}])\ndef test_{}_matches(text):\n    assert PATTERN.search(text)\n",
+                    quoted_list(&positive, "    "),
+                    slug
+                ));
+            }
+            if !negative.is_empty() {
+                out.push_str(&format!(
+                    "\n\n@pytest.mark.parametrize(\"text\", [\n{}])\ndef test_{}_does_not_match(text):\n    assert not PATTERN.search(text)\n",
+                    quoted_list(&negative, "    "),
+                    slug
+                ));
+            }
+            out
+        }
+        FixtureFormat::Jest => {
+            let mut out = format!("const PATTERN = new RegExp({});\n\ndescribe({}, () => {{\n", quoted(regex), quoted(name));
+            if !positive.is_empty() {
+                out.push_str(&format!(
+                    "  test.each([\n{}  ])('matches %s', (text) => {{\n    expect(PATTERN.test(text)).toBe(true);\n  }});\n",
+                    quoted_list(&positive, "    ")
+                ));
+            }
+            if !negative.is_empty() {
+                out.push_str(&format!(
+                    "  test.each([\n{}  ])('does not match %s', (text) => {{\n    expect(PATTERN.test(text)).toBe(false);\n  }});\n",
+                    quoted_list(&negative, "    ")
+                ));
+            }
+            out.push_str("});\n");
+            out
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::alternation::{self, AlternationConfig};
+use crate::dialect::{DialectError, RegexDialect};
+use crate::options::OptionFlags;
+
+/// Option-list alternations already built while compiling a library, so
+/// patterns sharing a `OneOf` list or synonym set build its trie once.
+/// Lives for one pass over the library, through `RegexContext::fragments`.
+#[derive(Debug, Default)]
+pub struct FragmentCache {
+    alternations: RefCell<HashMap<String, String>>,
+    hits: Cell<usize>,
+}
+
+impl FragmentCache {
+    /// `alternation::build_one_of`, from the cache when the same options,
+    /// flags and settings were built before.
+    pub fn one_of(
+        &self,
+        options: &[String],
+        flags: &BTreeMap<String, OptionFlags>,
+        config: &AlternationConfig,
+        dialect: RegexDialect,
+    ) -> Result<String, DialectError> {
+        let key = format!("{:?}{:?}{:?}{:?}", dialect, config, flags, options);
+        if let Some(regex) = self.alternations.borrow().get(&key) {
+            self.hits.set(self.hits.get() + 1);
+            return Ok(regex.clone());
+        }
+        let regex = alternation::build_one_of(options, flags, config, dialect)?;
+        self.alternations.borrow_mut().insert(key, regex.clone());
+        Ok(regex)
+    }
+
+    /// How many alternations were reused rather than built.
+    pub fn hits(&self) -> usize {
+        self.hits.get()
+    }
+}
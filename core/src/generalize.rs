@@ -0,0 +1,218 @@
+use serde::{Serialize, Deserialize};
+
+use crate::infer::{tokens, Token};
+use crate::{Pattern, PatternElement, RegexContext, RegexDialect, TokenClass, TokenizerConfig};
+
+/// Examples with more tokens than this are refused; the search is cubic
+/// in their length.
+const MAX_EXAMPLE_TOKENS: usize = 400;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    WidenGap,
+    AddOption,
+    GeneralizeToClass,
+}
+
+/// One relaxed element, before and after.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Change {
+    pub element_index: usize,
+    pub kind: ChangeKind,
+    pub before: PatternElement,
+    pub after: PatternElement,
+}
+
+/// A proposed relaxation of a saved pattern, for the user to approve.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Generalization {
+    pub pattern: Pattern,
+    pub regex: String,
+    /// Empty when the pattern already matched the example.
+    pub changes: Vec<Change>,
+}
+
+/// How an element can take on the tokens at one position: how many it
+/// consumes, and the relaxed element if it had to change.
+struct Step {
+    consumed: usize,
+    relaxed: Option<(ChangeKind, PatternElement)>,
+    /// Turning an anchor word into a choice loosens the pattern most, so
+    /// it costs more than the other changes.
+    cost: usize,
+}
+
+struct Matcher<'a> {
+    tokens: &'a [Token],
+    tokenizer: &'a TokenizerConfig,
+    case_insensitive: bool,
+}
+
+impl Matcher<'_> {
+    fn same(&self, a: &str, b: &str) -> bool {
+        if self.case_insensitive {
+            a.to_lowercase() == b.to_lowercase()
+        } else {
+            a == b
+        }
+    }
+
+    /// Whether `text`'s words are the tokens from `at` on, and how many.
+    fn literal(&self, text: &str, at: usize) -> Option<usize> {
+        let words = self.tokenizer.tokenize(text);
+        let found = self.tokens.get(at..at + words.len())?;
+        let equal = words.iter().zip(found).all(|(w, t)| self.same(&w.text, &t.text));
+        (equal && !words.is_empty()).then_some(words.len())
+    }
+
+    fn words(&self, from: usize, to: usize) -> u32 {
+        self.tokens[from..to].iter().map(|t| t.words).sum()
+    }
+
+    /// Every way `element` can match starting at token `at`.
+    fn steps(&self, element: &PatternElement, at: usize) -> Vec<Step> {
+        let rest = self.tokens.len() - at;
+        let exact = |consumed| Step { consumed, relaxed: None, cost: 0 };
+        match element {
+            PatternElement::Word { text, .. } => {
+                if let Some(n) = self.literal(text, at) {
+                    return vec![exact(n)];
+                }
+                match (self.tokenizer.tokenize(text).len(), self.tokens.get(at)) {
+                    (1, Some(token)) => {
+                        let (kind, after) = widen_word(text, token);
+                        let cost = if kind == ChangeKind::AddOption { 2 } else { 1 };
+                        vec![Step { consumed: 1, relaxed: Some((kind, after)), cost }]
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            PatternElement::OneOf { options, list, weights, option_flags } => {
+                let mut steps: Vec<Step> = options.iter().filter_map(|o| self.literal(o, at)).map(exact).collect();
+                if steps.is_empty() && list.is_none() {
+                    if let Some(token) = self.tokens.get(at) {
+                        let mut options = options.clone();
+                        options.push(token.text.clone());
+                        let after = PatternElement::OneOf {
+                            options,
+                            list: None,
+                            weights: weights.clone(),
+                            option_flags: option_flags.clone(),
+                        };
+                        steps.push(Step { consumed: 1, relaxed: Some((ChangeKind::AddOption, after)), cost: 1 });
+                    }
+                }
+                steps
+            }
+            PatternElement::TokenClass { class } => match self.tokens.get(at) {
+                Some(token) if token.class.as_ref() == Some(class) || full_match(class, &token.text) => vec![exact(1)],
+                _ => Vec::new(),
+            },
+            PatternElement::Gap { min_words, max_words, max_chars: None, excluded } if excluded.is_empty() => (0..=rest)
+                .map(|consumed| {
+                    let words = self.words(at, at + consumed);
+                    let (min, max) = (*min_words, *max_words);
+                    if words >= min && max.is_none_or(|max| words <= max) {
+                        return exact(consumed);
+                    }
+                    let after = PatternElement::gap(min.min(words), max.map(|max| max.max(words)));
+                    Step { consumed, relaxed: Some((ChangeKind::WidenGap, after)), cost: 1 }
+                })
+                .collect(),
+            PatternElement::Modified { element, modifier } if modifier.optional && !modifier.is_repeated() => {
+                // Optional elements are only skipped or matched as they are
+                let mut steps = vec![exact(0)];
+                steps.extend(self.steps(element, at).into_iter().filter(|s| s.relaxed.is_none()));
+                steps
+            }
+            // Constrained gaps, raw regexes, references and repeats are left as they are
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// A single word relaxed to take `token` too: its class if both are of
+/// the same one, otherwise a choice between the two.
+fn widen_word(word: &str, token: &Token) -> (ChangeKind, PatternElement) {
+    match &token.class {
+        Some(class) if full_match(class, word) => (ChangeKind::GeneralizeToClass, PatternElement::TokenClass { class: class.clone() }),
+        _ => (ChangeKind::AddOption, PatternElement::one_of(vec![word.to_string(), token.text.clone()])),
+    }
+}
+
+fn full_match(class: &TokenClass, text: &str) -> bool {
+    class
+        .to_regex(RegexDialect::Rust)
+        .ok()
+        .and_then(|r| regex::Regex::new(&format!("^(?:{})$", r)).ok())
+        .is_some_and(|re| re.is_match(text))
+}
+
+/// The cheapest set of element changes that makes the sequence `pattern`
+/// match somewhere in `example`.
+pub fn generalize(
+    pattern: &Pattern,
+    example: &str,
+    tokenizer: &TokenizerConfig,
+    context: &RegexContext,
+) -> Result<Generalization, String> {
+    let Pattern::Sequence { meta, elements } = pattern else {
+        return Err(String::from("Only sequence patterns can be generalized"));
+    };
+    let regex = pattern.to_regex_with(context).map_err(|e| e.to_string())?;
+    if regex::Regex::new(&regex).is_ok_and(|re| re.is_match(example)) {
+        return Ok(Generalization { pattern: pattern.clone(), regex, changes: Vec::new() });
+    }
+
+    let tokens = tokens(example, tokenizer);
+    if tokens.len() > MAX_EXAMPLE_TOKENS {
+        return Err(format!("The example is too long to align (more than {} words)", MAX_EXAMPLE_TOKENS));
+    }
+    let matcher = Matcher { tokens: &tokens, tokenizer, case_insensitive: meta.options.case_insensitive };
+
+    // best[i][j]: cheapest changes for elements[i..] starting at token j
+    let (n, m) = (elements.len(), tokens.len());
+    let mut best: Vec<Vec<Option<(usize, Step)>>> = (0..=n).map(|_| (0..=m).map(|_| None).collect()).collect();
+    for slot in best[n].iter_mut() {
+        *slot = Some((0, Step { consumed: 0, relaxed: None, cost: 0 }));
+    }
+    for i in (0..n).rev() {
+        for j in 0..=m {
+            best[i][j] = matcher
+                .steps(&elements[i], j)
+                .into_iter()
+                .filter_map(|step| {
+                    let (cost, _) = best[i + 1][j + step.consumed].as_ref()?;
+                    Some((cost + step.cost, step))
+                })
+                .min_by_key(|(cost, _)| *cost);
+        }
+    }
+    let start = (0..=m)
+        .filter(|&j| best[0][j].is_some())
+        .min_by_key(|&j| best[0][j].as_ref().map(|(cost, _)| *cost))
+        .ok_or_else(|| String::from("No combination of widened gaps, added options and token classes makes the pattern match the example"))?;
+
+    let mut relaxed = elements.clone();
+    let mut changes = Vec::new();
+    let mut j = start;
+    for (i, element) in elements.iter().enumerate() {
+        let Some((_, step)) = &best[i][j] else { break };
+        if let Some((kind, after)) = &step.relaxed {
+            relaxed[i] = after.clone();
+            changes.push(Change { element_index: i, kind: *kind, before: element.clone(), after: after.clone() });
+        }
+        j += step.consumed;
+    }
+
+    let mut pattern = pattern.clone();
+    if let Pattern::Sequence { elements, .. } = &mut pattern {
+        *elements = relaxed;
+    }
+    let regex = pattern.to_regex_with(context).map_err(|e| e.to_string())?;
+    if !regex::Regex::new(&regex).is_ok_and(|re| re.is_match(example)) {
+        return Err(String::from("The relaxed pattern still doesn't match the example; edit it by hand"));
+    }
+    Ok(Generalization { pattern, regex, changes })
+}
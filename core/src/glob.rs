@@ -0,0 +1,299 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{MatchOptions, Pattern, PatternElement};
+
+/// A path segment's worth of anything: what `*` matches. Not across lines
+/// either, as paths are listed one per line.
+const STAR: &str = "[^/\\n]*";
+const QUESTION: &str = "[^/\\n]";
+const GLOBSTAR: &str = ".*";
+const GLOBSTAR_DIR: &str = "(?:.*/)?";
+
+/// A glob written back from a pattern by `to_glob`, with what got lost on
+/// the way when the pattern says more than a glob can.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GlobConversion {
+    pub glob: String,
+    /// Whether the glob matches exactly the paths the pattern does.
+    pub exact: bool,
+    /// One line per approximation, e.g. "gap element written as *", and
+    /// per wildcard added for an unanchored pattern.
+    pub notes: Vec<String>,
+}
+
+/// One token of a glob and the regex it stands for.
+pub(crate) struct GlobPart {
+    pub regex: String,
+    /// A plain character rather than a wildcard.
+    pub literal: bool,
+}
+
+/// Splits a shell-style glob into its tokens: `*` within a path segment,
+/// `**` across them, `?`, `[...]` classes (`[!...]` negated), backslash
+/// escapes, and with `braces` `{a,b}` alternatives.
+pub(crate) fn glob_parts(glob: &str, braces: bool) -> Result<Vec<GlobPart>, String> {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut parts = Vec::new();
+    let wildcard = |regex: &str| GlobPart { regex: regex.to_string(), literal: false };
+    let literal = |c: char| GlobPart { regex: regex::escape(&c.to_string()), literal: true };
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                if chars.get(i) == Some(&'/') {
+                    parts.push(wildcard(GLOBSTAR_DIR));
+                    i += 1;
+                } else {
+                    parts.push(wildcard(GLOBSTAR));
+                }
+                continue;
+            }
+            '*' => parts.push(wildcard(STAR)),
+            '?' => parts.push(wildcard(QUESTION)),
+            '[' => match chars[i + 1..].iter().skip(1).position(|&c| c == ']') {
+                Some(offset) => {
+                    let close = i + 2 + offset;
+                    let mut class: String = chars[i + 1..close].iter().collect();
+                    if let Some(rest) = class.strip_prefix('!') {
+                        class = format!("^{}", rest);
+                    }
+                    parts.push(wildcard(&format!("[{}]", class.replace('\\', "\\\\").replace('[', "\\["))));
+                    i = close;
+                }
+                None => parts.push(literal('[')),
+            },
+            '{' if braces => {
+                let close = chars[i..]
+                    .iter()
+                    .position(|&c| c == '}')
+                    .ok_or_else(|| format!("Unclosed {{ in {}", glob))?;
+                let inner: String = chars[i + 1..i + close].iter().collect();
+                if inner.contains("..") {
+                    return Err(format!("Numeric ranges like {{{}}} aren't supported", inner));
+                }
+                let options: Vec<String> = inner
+                    .split(',')
+                    .map(|option| Ok(glob_parts(option, false)?.into_iter().map(|part| part.regex).collect()))
+                    .collect::<Result<_, String>>()?;
+                parts.push(wildcard(&format!("(?:{})", options.join("|"))));
+                i += close;
+            }
+            '\\' if i + 1 < chars.len() => {
+                i += 1;
+                parts.push(literal(chars[i]));
+            }
+            c => parts.push(literal(c)),
+        }
+        i += 1;
+    }
+    Ok(parts)
+}
+
+impl Pattern {
+    /// A pattern matching whole paths, one per line, that `glob` matches.
+    /// Each wildcard becomes a Raw element of its own and each run of
+    /// plain characters another, so the pattern reads like the glob and
+    /// `to_glob` gives it back. `{a,b}` alternatives are supported. The
+    /// returned pattern has an empty id.
+    pub fn from_glob(name: &str, glob: &str) -> Result<Pattern, String> {
+        if glob.is_empty() {
+            return Err(String::from("Empty glob"));
+        }
+        let mut elements = Vec::new();
+        let mut literal = String::new();
+        for part in glob_parts(glob, true)? {
+            if part.literal {
+                literal.push_str(&part.regex);
+                continue;
+            }
+            if !literal.is_empty() {
+                elements.push(PatternElement::Raw { regex: std::mem::take(&mut literal) });
+            }
+            elements.push(PatternElement::Raw { regex: part.regex });
+        }
+        if !literal.is_empty() {
+            elements.push(PatternElement::Raw { regex: literal });
+        }
+        let mut pattern = Pattern::sequence(name, elements);
+        pattern.meta_mut().options = MatchOptions { anchor_start: true, anchor_end: true, multi_line: true, ..MatchOptions::default() };
+        Ok(pattern)
+    }
+
+    /// The glob closest to this pattern. Raw fragments written by
+    /// `from_glob` (or made only of escaped text and the same wildcards)
+    /// convert exactly; words, option lists, gaps, token classes and
+    /// modifiers are approximated and listed in the notes. Composite
+    /// patterns have no glob form.
+    pub fn to_glob(&self) -> Result<GlobConversion, String> {
+        let elements = match self {
+            Pattern::Sequence { elements, .. } => elements.clone(),
+            Pattern::Template { .. } => self.template_elements(),
+            Pattern::Composite { .. } => return Err(String::from("Composite patterns can't be written as a glob")),
+        };
+        let mut conversion = GlobConversion { glob: String::new(), exact: true, notes: Vec::new() };
+        let options = &self.meta().options;
+        if !options.anchor_start && !elements.first().is_some_and(is_leading_wildcard) {
+            conversion.glob.push_str("**");
+            conversion.notes.push(String::from("Matches anywhere in the path, so the glob starts with **"));
+        }
+        for element in &elements {
+            element_glob(element, &mut conversion);
+        }
+        if !options.anchor_end && !elements.last().is_some_and(|e| matches!(e, PatternElement::Raw { regex } if regex == GLOBSTAR)) {
+            conversion.glob.push_str("**");
+            conversion.notes.push(String::from("Matches anywhere in the path, so the glob ends with **"));
+        }
+        if options.case_insensitive {
+            conversion.exact = false;
+            conversion.notes.push(String::from("Globs are case-sensitive; case-insensitive matching is lost"));
+        }
+        Ok(conversion)
+    }
+}
+
+impl GlobConversion {
+    fn approximate(&mut self, note: String, glob: &str) {
+        self.exact = false;
+        self.notes.push(note);
+        self.glob.push_str(glob);
+    }
+}
+
+fn is_leading_wildcard(element: &PatternElement) -> bool {
+    matches!(element, PatternElement::Raw { regex } if regex == GLOBSTAR || regex == GLOBSTAR_DIR)
+}
+
+fn element_glob(element: &PatternElement, conversion: &mut GlobConversion) {
+    match element {
+        PatternElement::Raw { regex } => match raw_glob(regex) {
+            Some(glob) => conversion.glob.push_str(&glob),
+            None => conversion.approximate(format!("Regex fragment \"{}\" written as *", regex), "*"),
+        },
+        PatternElement::Word { text, .. } => {
+            conversion.glob.push_str(&escape(text));
+            conversion.exact = false;
+            conversion.notes.push(format!("Word \"{}\" written without its word boundaries", text));
+        }
+        PatternElement::OneOf { options, list: None, .. } if !options.is_empty() => {
+            let options: Vec<String> = options.iter().map(|option| escape(option)).collect();
+            conversion.glob.push_str(&format!("{{{}}}", options.join(",")));
+            conversion.exact = false;
+            conversion.notes.push(String::from("Option list written as {...} alternatives without word boundaries"));
+        }
+        PatternElement::Modified { element, .. } => {
+            let mut inner = GlobConversion { glob: String::new(), exact: true, notes: Vec::new() };
+            element_glob(element, &mut inner);
+            conversion.approximate(format!("Optional or repeated {} written as *", inner.glob), "*");
+        }
+        other => conversion.approximate(format!("{} element written as *", other.kind().replace('_', " ")), "*"),
+    }
+}
+
+/// The glob for a Raw fragment made only of the regexes `glob_parts`
+/// writes, or None for any other regex.
+fn raw_glob(regex: &str) -> Option<String> {
+    let mut glob = String::new();
+    let mut rest = regex;
+    while !rest.is_empty() {
+        if let Some((wildcard, after)) = [(GLOBSTAR_DIR, "**/"), (GLOBSTAR, "**"), (STAR, "*"), (QUESTION, "?")]
+            .iter()
+            .find_map(|(pattern, glob)| rest.strip_prefix(pattern).map(|after| (*glob, after)))
+        {
+            glob.push_str(wildcard);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("(?:") {
+            let close = after.find(')')?;
+            let options: Vec<String> = after[..close].split('|').map(raw_glob).collect::<Option<_>>()?;
+            glob.push_str(&format!("{{{}}}", options.join(",")));
+            rest = &after[close + 1..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let close = after.find(']')?;
+            let class = &after[..close];
+            let class = class.strip_prefix('^').map(|negated| format!("!{}", negated)).unwrap_or_else(|| class.to_string());
+            glob.push_str(&format!("[{}]", class.replace("\\[", "[").replace("\\\\", "\\")));
+            rest = &after[close + 1..];
+        } else {
+            let mut chars = rest.chars();
+            let c = match chars.next()? {
+                '\\' => chars.next().filter(|c| !c.is_alphanumeric())?,
+                c if regex_syntax::is_meta_character(c) => return None,
+                c => c,
+            };
+            glob.push_str(&escape(&c.to_string()));
+            rest = chars.as_str();
+        }
+    }
+    Some(glob)
+}
+
+/// `text` with glob wildcards backslash-escaped.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '{' | '}' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Pattern, PatternElement, WordMatch};
+
+    fn matcher(glob: &str) -> regex::Regex {
+        regex::Regex::new(&Pattern::from_glob("glob", glob).unwrap().to_regex().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn regex_metacharacters_in_globs_are_literal() {
+        let re = matcher("a.b+c(d)|e^$.txt");
+        assert!(re.is_match("a.b+c(d)|e^$.txt"));
+        assert!(!re.is_match("aXb+c(d)|e^$.txt"));
+        assert!(!re.is_match("a.bbc(d)|e^$.txt"));
+        assert!(!re.is_match("e^$.txt"));
+    }
+
+    #[test]
+    fn escaped_wildcards_are_literal() {
+        let re = matcher(r"notes\*\?\[1\].md");
+        assert!(re.is_match("notes*?[1].md"));
+        assert!(!re.is_match("notesXY1.md"));
+        assert!(!re.is_match("notes*?1.md"));
+    }
+
+    #[test]
+    fn wildcards_stay_within_or_cross_segments() {
+        let star = matcher("src/*.rs");
+        assert!(star.is_match("src/lib.rs"));
+        assert!(!star.is_match("src/nested/lib.rs"));
+        let globstar = matcher("src/**/*.rs");
+        assert!(globstar.is_match("src/lib.rs"));
+        assert!(globstar.is_match("src/a/b/lib.rs"));
+        let class = matcher("file[!a-c]?.{json,toml}");
+        assert!(class.is_match("filed1.json"));
+        assert!(!class.is_match("filea1.json"));
+        assert!(!class.is_match("filed1.yaml"));
+    }
+
+    #[test]
+    fn globs_round_trip_with_their_escapes() {
+        for glob in ["src/**/*.rs", "file[!a-c]?.{json,toml}", r"a\*b\?c\[d\]\{e\}", "a.b+c(d)|e^$", r"back\\slash"] {
+            let conversion = Pattern::from_glob("glob", glob).unwrap().to_glob().unwrap();
+            assert_eq!(conversion.glob, glob);
+            assert!(conversion.exact, "{}", glob);
+        }
+    }
+
+    #[test]
+    fn words_with_wildcard_characters_are_escaped() {
+        let mut pattern = Pattern::sequence("word", vec![PatternElement::Word { text: "a*b?[c]{d}".to_string(), mode: WordMatch::Exact }]);
+        pattern.meta_mut().options.anchor_start = true;
+        pattern.meta_mut().options.anchor_end = true;
+        let conversion = pattern.to_glob().unwrap();
+        assert_eq!(conversion.glob, r"a\*b\?\[c\]\{d\}");
+        assert!(!conversion.exact);
+    }
+}
@@ -0,0 +1,101 @@
+use serde::{Serialize, Deserialize};
+
+use crate::display::LegendEntry;
+use crate::docs::escape_html;
+use crate::{OffsetEncoding, Pattern};
+
+/// A run of the input that is either all matched or all unmatched.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Segment {
+    pub text: String,
+    pub matched: bool,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Splits `text` at the edges of `matches` (byte ranges, overlapping ones
+/// merged), reporting offsets in `encoding`. Together the segments
+/// spell out `text` exactly.
+pub fn segments(text: &str, matches: &[(usize, usize)], encoding: OffsetEncoding) -> Vec<Segment> {
+    let mut sorted = matches.to_vec();
+    sorted.sort_unstable();
+    let mut segments = Vec::new();
+    let mut push = |start: usize, end: usize, matched: bool| {
+        if start < end {
+            segments.push(Segment {
+                text: text[start..end].to_string(),
+                matched,
+                start: encoding.offset(text, start),
+                end: encoding.offset(text, end),
+            });
+        }
+    };
+    let mut position = 0;
+    let mut i = 0;
+    while i < sorted.len() {
+        let (start, mut end) = sorted[i];
+        i += 1;
+        while i < sorted.len() && sorted[i].0 < end {
+            end = end.max(sorted[i].1);
+            i += 1;
+        }
+        push(position, start, false);
+        push(start, end, true);
+        position = end;
+    }
+    push(position, text.len(), false);
+    segments
+}
+
+/// A run of the input owned by one pattern, or by none.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OwnedSegment {
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+    /// How to show the pattern owning the run; absent for unmatched text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<LegendEntry>,
+}
+
+/// Splits `text` at the edges of `spans`, byte ranges in text order that
+/// don't overlap, each with the pattern owning it, reporting offsets in
+/// `encoding`. Together the segments spell out `text` exactly.
+pub fn owned_segments(text: &str, spans: &[(usize, usize, &Pattern)], encoding: OffsetEncoding) -> Vec<OwnedSegment> {
+    let mut segments = Vec::new();
+    let mut push = |start: usize, end: usize, pattern: Option<&Pattern>| {
+        if start < end {
+            segments.push(OwnedSegment {
+                text: text[start..end].to_string(),
+                start: encoding.offset(text, start),
+                end: encoding.offset(text, end),
+                pattern: pattern.map(LegendEntry::new),
+            });
+        }
+    };
+    let mut position = 0;
+    for &(start, end, pattern) in spans {
+        push(position, start, None);
+        push(start, end, Some(pattern));
+        position = end;
+    }
+    push(position, text.len(), None);
+    segments
+}
+
+/// The segments as HTML, all text escaped and matches wrapped in
+/// `<span class="css_class">`.
+pub fn to_html(segments: &[Segment], css_class: &str) -> String {
+    let open = format!("<span class=\"{}\">", escape_html(css_class));
+    let mut html = String::new();
+    for segment in segments {
+        if segment.matched {
+            html.push_str(&open);
+            html.push_str(&escape_html(&segment.text));
+            html.push_str("</span>");
+        } else {
+            html.push_str(&escape_html(&segment.text));
+        }
+    }
+    html
+}
@@ -0,0 +1,31 @@
+use serde::{Serialize, Deserialize};
+
+/// Oldest versions are dropped beyond this many.
+pub const MAX_HISTORY_ENTRIES: usize = 50;
+
+/// One saved version of a pattern.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryEntry {
+    /// 1 for the version the pattern was created as, counting up.
+    pub version: u32,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: f64,
+    pub regex: String,
+    /// Why the change was made, e.g. "loosened gap for the new log format".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Appends the next version to `history`, saved at `timestamp`.
+pub fn push(history: &mut Vec<HistoryEntry>, regex: String, note: Option<String>, timestamp: f64) {
+    let version = history.last().map_or(1, |entry| entry.version + 1);
+    history.push(HistoryEntry {
+        version,
+        timestamp,
+        regex,
+        note: note.filter(|n| !n.trim().is_empty()),
+    });
+    if history.len() > MAX_HISTORY_ENTRIES {
+        history.drain(..history.len() - MAX_HISTORY_ENTRIES);
+    }
+}
@@ -0,0 +1,94 @@
+use serde::{Serialize, Deserialize};
+
+/// A problem with one line of an imported file. Lines are 1-based.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LineError {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ImportedPattern {
+    pub line: usize,
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ImportReport {
+    pub imported: Vec<ImportedPattern>,
+    pub errors: Vec<LineError>,
+}
+
+/// A named regex read from a cheat-sheet list.
+pub struct RegexListEntry {
+    pub line: usize,
+    pub name: String,
+    pub regex: String,
+}
+
+/// Parses a regex list. Each non-blank, non-comment line is either
+/// `name<TAB>regex` or a bare regex; a bare regex takes its name from the
+/// `#` or `//` comment directly above it, or gets a numbered default.
+pub fn parse_regex_list(text: &str) -> (Vec<RegexListEntry>, Vec<LineError>) {
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    let mut pending_name: Option<String> = None;
+
+    for (i, raw_line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let line = raw_line.trim_end_matches('\r');
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() {
+            pending_name = None;
+            continue;
+        }
+        if let Some(comment) = trimmed.strip_prefix('#').or_else(|| trimmed.strip_prefix("//")) {
+            let comment = comment.trim();
+            pending_name = (!comment.is_empty()).then(|| comment.to_string());
+            continue;
+        }
+
+        let (name, regex) = match line.split_once('\t') {
+            Some((name, regex)) => (name.trim().to_string(), regex.trim().to_string()),
+            None => (
+                pending_name
+                    .take()
+                    .unwrap_or_else(|| format!("Imported pattern {}", entries.len() + 1)),
+                trimmed.to_string(),
+            ),
+        };
+        pending_name = None;
+
+        if name.is_empty() {
+            errors.push(LineError {
+                line: line_number,
+                message: String::from("Missing pattern name before the tab"),
+            });
+            continue;
+        }
+        if regex.is_empty() {
+            errors.push(LineError {
+                line: line_number,
+                message: String::from("Missing regex after the tab"),
+            });
+            continue;
+        }
+        if let Err(e) = regex::Regex::new(&regex) {
+            errors.push(LineError {
+                line: line_number,
+                message: format!("Invalid regex: {}", e),
+            });
+            continue;
+        }
+
+        entries.push(RegexListEntry {
+            line: line_number,
+            name,
+            regex,
+        });
+    }
+
+    (entries, errors)
+}
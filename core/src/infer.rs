@@ -0,0 +1,324 @@
+use serde::{Serialize, Deserialize};
+
+use crate::test_cases::Evaluation;
+use crate::{Pattern, PatternElement, RegexDialect, TokenClass, TokenizerConfig};
+
+/// Alignments beyond this many token pairs are refused rather than
+/// allocating a huge table.
+const MAX_ALIGNMENT_CELLS: usize = 4_000_000;
+
+/// Classes recognised as one token even though they span several words,
+/// in the order they are looked for. Numbers are single words.
+const MULTI_WORD_CLASSES: [TokenClass; 4] = [TokenClass::Email, TokenClass::Url, TokenClass::IpAddress, TokenClass::Date];
+
+/// A pattern inferred from examples, not yet saved.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Draft {
+    pub pattern: Pattern,
+    pub regex: String,
+    /// How the draft does on the examples it was inferred from, if they
+    /// were labeled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evaluation: Option<Evaluation>,
+}
+
+/// A word of an example, or a run of words recognised as a token class.
+pub(crate) struct Token {
+    pub text: String,
+    pub class: Option<TokenClass>,
+    pub words: u32,
+    pub start: usize,
+    pub end: usize,
+}
+
+pub(crate) fn tokens(text: &str, tokenizer: &TokenizerConfig) -> Vec<Token> {
+    let mut found: Vec<Token> = Vec::new();
+    for class in MULTI_WORD_CLASSES {
+        let Some(regex) = class.to_regex(RegexDialect::Rust).ok().and_then(|r| regex::Regex::new(&r).ok()) else {
+            continue;
+        };
+        for m in regex.find_iter(text) {
+            if found.iter().all(|t| m.end() <= t.start || m.start() >= t.end) {
+                found.push(Token {
+                    text: m.as_str().to_string(),
+                    class: Some(class.clone()),
+                    words: tokenizer.tokenize(m.as_str()).len() as u32,
+                    start: m.start(),
+                    end: m.end(),
+                });
+            }
+        }
+    }
+    for word in tokenizer.tokenize(text) {
+        if found.iter().all(|t| word.end_index <= t.start || word.start_index >= t.end) {
+            let number = word.text.chars().all(|c| c.is_ascii_digit());
+            found.push(Token {
+                class: number.then_some(TokenClass::Number),
+                text: word.text,
+                words: 1,
+                start: word.start_index,
+                end: word.end_index,
+            });
+        }
+    }
+    found.sort_by_key(|t| t.start);
+    found
+}
+
+/// A position every example so far has something at: one or more
+/// literal values, or any token of a class they all belong to.
+struct Slot {
+    values: Vec<String>,
+    class: Option<TokenClass>,
+    words: (u32, u32),
+    /// List the values even though they share a class.
+    specific: bool,
+}
+
+impl Slot {
+    /// Whether the slot stands for its class rather than its values.
+    fn generalized(&self) -> bool {
+        self.class.is_some() && self.values.len() > 1
+    }
+
+    fn accepts(&self, token: &Token) -> bool {
+        self.values.contains(&token.text) || (self.generalized() && self.class == token.class)
+    }
+
+    fn widen(&mut self, token: &Token) {
+        if !self.values.contains(&token.text) {
+            self.values.push(token.text.clone());
+        }
+        if self.class != token.class {
+            self.class = None;
+        }
+        self.words = (self.words.0.min(token.words), self.words.1.max(token.words));
+    }
+
+    fn element(&self) -> PatternElement {
+        match (&self.class, self.values.as_slice()) {
+            (_, [value]) => PatternElement::word(value.clone()),
+            (Some(class), _) if !self.specific => PatternElement::TokenClass { class: class.clone() },
+            (_, values) => PatternElement::one_of(values.to_vec()),
+        }
+    }
+}
+
+/// Words between two neighbouring slots, as seen across the examples.
+#[derive(Clone, Copy)]
+struct Span {
+    min: u32,
+    max: u32,
+    /// Whether the slots were separated by exactly one space everywhere.
+    spaced: bool,
+}
+
+/// What the examples aligned so far have in common: slots, in order,
+/// and the spans between them. `spans[i]` lies between slots `i` and `i + 1`.
+pub struct Template {
+    slots: Vec<Slot>,
+    spans: Vec<Span>,
+}
+
+impl Template {
+    /// A template matching exactly the words of `text`.
+    pub fn new(text: &str, tokenizer: &TokenizerConfig) -> Template {
+        let tokens = tokens(text, tokenizer);
+        let spans = tokens
+            .windows(2)
+            .map(|pair| Span {
+                min: 0,
+                max: 0,
+                spaced: &text[pair[0].end..pair[1].start] == " ",
+            })
+            .collect();
+        let slots = tokens
+            .into_iter()
+            .map(|t| Slot {
+                values: vec![t.text],
+                class: t.class,
+                words: (t.words, t.words),
+                specific: false,
+            })
+            .collect();
+        Template { slots, spans }
+    }
+
+    /// Narrows the template to what it shares with `text`. Slots keep
+    /// their place where `text` has one of their values. Where a single
+    /// slot and a single differing token sit between two such anchors (or
+    /// an anchor and the end), the token is folded into the slot if both
+    /// are of the same class, or with `one_of` always. Everything else
+    /// becomes a gap sized to fit every example.
+    pub fn align(&mut self, text: &str, tokenizer: &TokenizerConfig, one_of: bool) -> Result<(), String> {
+        let tokens = tokens(text, tokenizer);
+        let anchors = self.anchors(&tokens)?;
+        if anchors.is_empty() {
+            return Err(String::from("The examples have no words in common"));
+        }
+
+        let mut kept = anchors.clone();
+        let ends = std::iter::once(None)
+            .chain(anchors.iter().copied().map(Some))
+            .zip(anchors.iter().copied().map(Some).chain(std::iter::once(None)));
+        for (before, after) in ends {
+            let (k, j) = before.map_or((0, 0), |(k, j)| (k + 1, j + 1));
+            let (end_k, end_j) = after.unwrap_or((self.slots.len(), tokens.len()));
+            if end_k == k + 1 && end_j == j + 1 {
+                let slot = &self.slots[k];
+                if one_of || (slot.class.is_some() && slot.class == tokens[j].class) {
+                    kept.push((k, j));
+                }
+            }
+        }
+        kept.sort_unstable();
+
+        let old_slots = std::mem::take(&mut self.slots);
+        let old_spans = std::mem::take(&mut self.spans);
+        for pair in kept.windows(2) {
+            let ((prev_k, prev_j), (k, j)) = (pair[0], pair[1]);
+            let skipped = &old_slots[prev_k + 1..k];
+            let old = Span {
+                min: old_spans[prev_k..k].iter().map(|s| s.min).sum::<u32>()
+                    + skipped.iter().map(|s| s.words.0).sum::<u32>(),
+                max: old_spans[prev_k..k].iter().map(|s| s.max).sum::<u32>()
+                    + skipped.iter().map(|s| s.words.1).sum::<u32>(),
+                spaced: k == prev_k + 1 && old_spans[prev_k].spaced,
+            };
+            let words = tokens[prev_j + 1..j].iter().map(|t| t.words).sum();
+            let new = Span {
+                min: words,
+                max: words,
+                spaced: j == prev_j + 1 && &text[tokens[prev_j].end..tokens[j].start] == " ",
+            };
+            self.spans.push(widen_span(old, new));
+        }
+        // Kept slots are in increasing order, so one pass picks them out
+        let mut old_slots = old_slots.into_iter().enumerate();
+        for &(k, j) in &kept {
+            if let Some((_, mut slot)) = old_slots.find(|&(i, _)| i == k) {
+                slot.widen(&tokens[j]);
+                self.slots.push(slot);
+            }
+        }
+        Ok(())
+    }
+
+    /// Longest common subsequence of slots and tokens, as index pairs.
+    fn anchors(&self, tokens: &[Token]) -> Result<Vec<(usize, usize)>, String> {
+        let (n, m) = (self.slots.len(), tokens.len());
+        if (n + 1).saturating_mul(m + 1) > MAX_ALIGNMENT_CELLS {
+            return Err(String::from("The examples are too long to align"));
+        }
+        // lengths[i][j]: LCS of slots[i..] and tokens[j..]
+        let mut lengths = vec![vec![0u32; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lengths[i][j] = if self.slots[i].accepts(&tokens[j]) {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        let mut pairs = Vec::new();
+        while i < n && j < m {
+            if self.slots[i].accepts(&tokens[j]) {
+                pairs.push((i, j));
+                i += 1;
+                j += 1;
+            } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        Ok(pairs)
+    }
+
+    /// Sequence elements for the template: adjacent literal slots that
+    /// were always one space apart become a phrase, other slots are
+    /// joined by gaps of the observed size.
+    pub fn elements(&self) -> Vec<PatternElement> {
+        let mut elements: Vec<PatternElement> = Vec::new();
+        for (i, slot) in self.slots.iter().enumerate() {
+            let element = slot.element();
+            if i > 0 {
+                let span = self.spans[i - 1];
+                if let (true, Some(PatternElement::Word { text, .. }), PatternElement::Word { text: next, .. }) =
+                    (span.max == 0 && span.spaced, elements.last_mut(), &element)
+                {
+                    text.push(' ');
+                    text.push_str(next);
+                    continue;
+                }
+                elements.push(PatternElement::gap(span.min, Some(span.max)));
+            }
+            elements.push(element);
+        }
+        elements
+    }
+
+    /// The suggested pattern once every positive example is aligned, with
+    /// classes that let a negative example match narrowed back to the
+    /// values seen where that helps.
+    pub fn suggest(mut self, negatives: &[String]) -> Pattern {
+        let pattern = |template: &Template| Pattern::sequence("Suggested pattern", template.elements());
+        let false_positives = |template: &Template| match pattern(template).to_regex().ok().and_then(|source| regex::Regex::new(&source).ok()) {
+            Some(regex) => negatives.iter().filter(|n| regex.is_match(n)).count(),
+            None => usize::MAX,
+        };
+
+        let mut matched = false_positives(&self);
+        for i in 0..self.slots.len() {
+            if matched == 0 {
+                break;
+            }
+            if self.slots[i].generalized() {
+                self.slots[i].specific = true;
+                let narrowed = false_positives(&self);
+                if narrowed < matched {
+                    matched = narrowed;
+                } else {
+                    self.slots[i].specific = false;
+                }
+            }
+        }
+        pattern(&self)
+    }
+}
+
+fn widen_span(a: Span, b: Span) -> Span {
+    Span {
+        min: a.min.min(b.min),
+        max: a.max.max(b.max),
+        spaced: a.spaced && b.spaced,
+    }
+}
+
+/// A sequence of what `a` and `b` share: common words stay, words of the
+/// same class that differ become that class, anything else a gap.
+pub fn infer_pair(a: &str, b: &str, tokenizer: &TokenizerConfig) -> Result<Pattern, String> {
+    let mut template = Template::new(a, tokenizer);
+    template.align(b, tokenizer, false)?;
+    Ok(Pattern::sequence("Inferred pattern", template.elements()))
+}
+
+/// A sequence matching every positive example, for hosts that would
+/// rather give examples than select words: words all positives share are
+/// kept, single varying tokens become their class or a `OneOf` of the
+/// values seen, and the rest becomes gaps. Classes that let a negative
+/// example match are narrowed back to the values seen where that helps.
+/// Callers that spread the work out can do the same one example at a
+/// time with `Template::new`, `align` and `suggest`.
+pub fn induce(positives: &[String], negatives: &[String], tokenizer: &TokenizerConfig) -> Result<Pattern, String> {
+    let (first, rest) = positives
+        .split_first()
+        .ok_or_else(|| String::from("At least one example that should match is needed"))?;
+    let mut template = Template::new(first, tokenizer);
+    for positive in rest {
+        template.align(positive, tokenizer, true)?;
+    }
+    Ok(template.suggest(negatives))
+}
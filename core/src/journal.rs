@@ -0,0 +1,274 @@
+use serde::{Serialize, Deserialize};
+
+use std::collections::HashSet;
+
+use crate::Pattern;
+
+/// Oldest entries are dropped beyond this many.
+pub const MAX_JOURNAL_ENTRIES: usize = 100;
+
+/// One pattern created, edited or deleted by a library change.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PatternChange {
+    /// Position of the pattern in the library it was in: before the
+    /// change for a deletion, after it otherwise.
+    pub index: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<Pattern>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<Pattern>,
+    /// Position before the change of a pattern that was moved, which may
+    /// also have been edited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub moved_from: Option<usize>,
+}
+
+impl PatternChange {
+    /// Whether the pattern only changed position.
+    pub fn is_move(&self) -> bool {
+        self.moved_from.is_some() && matches!((&self.before, &self.after), (Some(old), Some(new)) if same(old, new))
+    }
+}
+
+/// Everything one save changed, undone and redone as a unit.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JournalEntry {
+    pub changes: Vec<PatternChange>,
+}
+
+impl JournalEntry {
+    /// Short description for an undo menu, e.g. "Delete refused".
+    pub fn label(&self) -> String {
+        let names: Vec<&str> = self
+            .changes
+            .iter()
+            .filter_map(|c| c.after.as_ref().or(c.before.as_ref()))
+            .map(|p| p.get_name())
+            .collect();
+        let verb = match self.changes.as_slice() {
+            [PatternChange { before: None, .. }] => "Create",
+            [PatternChange { after: None, .. }] => "Delete",
+            [change] if change.is_move() => "Move",
+            [_] => "Edit",
+            _ => "Change",
+        };
+        match names.as_slice() {
+            [name] => format!("{} {}", verb, name),
+            _ => format!("{} {} patterns", verb, names.len()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Journal {
+    pub undo: Vec<JournalEntry>,
+    pub redo: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// Records a new change. Anything undone before it can no longer be
+    /// redone.
+    pub fn push(&mut self, entry: JournalEntry) {
+        self.undo.push(entry);
+        self.redo.clear();
+        if self.undo.len() > MAX_JOURNAL_ENTRIES {
+            self.undo.drain(..self.undo.len() - MAX_JOURNAL_ENTRIES);
+        }
+    }
+}
+
+/// The changes that turn `before` into `after`, matching patterns by id.
+/// Of the patterns in both, as few as possible are taken to have moved.
+pub fn diff(before: &[Pattern], after: &[Pattern]) -> Vec<PatternChange> {
+    let mut changes = Vec::new();
+    for (index, old) in before.iter().enumerate() {
+        if !after.iter().any(|p| p.get_id() == old.get_id()) {
+            changes.push(PatternChange { index, before: Some(old.clone()), after: None, moved_from: None });
+        }
+    }
+    let moved = moved_ids(before, after);
+    for (index, new) in after.iter().enumerate() {
+        let Some(old_index) = before.iter().position(|p| p.get_id() == new.get_id()) else {
+            changes.push(PatternChange { index, before: None, after: Some(new.clone()), moved_from: None });
+            continue;
+        };
+        let old = &before[old_index];
+        let moved_from = moved.contains(new.get_id()).then_some(old_index);
+        if moved_from.is_some() || !same(old, new) {
+            changes.push(PatternChange { index, before: Some(old.clone()), after: Some(new.clone()), moved_from });
+        }
+    }
+    changes
+}
+
+fn same(a: &Pattern, b: &Pattern) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+/// Ids of the patterns in both lists that are out of their old order:
+/// those outside the longest run of them kept in order.
+fn moved_ids<'a>(before: &[Pattern], after: &'a [Pattern]) -> HashSet<&'a str> {
+    let old: Vec<&str> = before
+        .iter()
+        .map(|p| p.get_id())
+        .filter(|id| after.iter().any(|p| p.get_id() == *id))
+        .collect();
+    let new: Vec<&'a str> = after
+        .iter()
+        .map(|p| p.get_id())
+        .filter(|id| old.contains(id))
+        .collect();
+    if old == new {
+        return HashSet::new();
+    }
+    // Longest common subsequence, by dynamic programming from the ends
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut kept = HashSet::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            kept.insert(new[j]);
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    new.into_iter().filter(|id| !kept.contains(id)).collect()
+}
+
+/// The pattern a change replaces and the one it leaves behind, in the
+/// direction it is being applied.
+fn sides(change: &PatternChange, undo: bool) -> (Option<&Pattern>, Option<&Pattern>) {
+    if undo {
+        (change.after.as_ref(), change.before.as_ref())
+    } else {
+        (change.before.as_ref(), change.after.as_ref())
+    }
+}
+
+/// Where a change leaves its pattern, in the direction it is being
+/// applied: moved patterns are taken out and put back there.
+fn target_index(change: &PatternChange, undo: bool) -> usize {
+    match (undo, change.moved_from) {
+        (true, Some(from)) => from,
+        _ => change.index,
+    }
+}
+
+/// Applies `changes` to `patterns`, backwards to undo them. Patterns that
+/// have since been removed are skipped rather than failing.
+pub fn apply(patterns: &mut Vec<Pattern>, changes: &[PatternChange], undo: bool) {
+    let mut inserted: Vec<(usize, &Pattern)> = Vec::new();
+    for change in changes {
+        match sides(change, undo) {
+            (Some(old), Some(new)) if change.moved_from.is_some() => {
+                let before = patterns.len();
+                patterns.retain(|p| p.get_id() != old.get_id());
+                if patterns.len() < before {
+                    inserted.push((target_index(change, undo), new));
+                }
+            }
+            (Some(old), Some(new)) => {
+                if let Some(pattern) = patterns.iter_mut().find(|p| p.get_id() == old.get_id()) {
+                    *pattern = new.clone();
+                }
+            }
+            (Some(old), None) => patterns.retain(|p| p.get_id() != old.get_id()),
+            (None, Some(new)) => inserted.push((target_index(change, undo), new)),
+            (None, None) => {}
+        }
+    }
+    // Insert in order of position so each lands where it was
+    inserted.sort_by_key(|(index, _)| *index);
+    for (index, pattern) in inserted {
+        patterns.insert(index.min(patterns.len()), pattern.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply, diff, Journal, JournalEntry, MAX_JOURNAL_ENTRIES};
+    use crate::{Pattern, PatternElement};
+
+    fn pattern(id: &str, word: &str) -> Pattern {
+        let mut pattern = Pattern::sequence(id, vec![PatternElement::word(word.to_string())]);
+        pattern.set_id(id.to_string());
+        pattern
+    }
+
+    fn ids(patterns: &[Pattern]) -> Vec<&str> {
+        patterns.iter().map(|p| p.get_id()).collect()
+    }
+
+    fn assert_round_trip(before: &[Pattern], after: &[Pattern]) {
+        let changes = diff(before, after);
+        let mut patterns = before.to_vec();
+        apply(&mut patterns, &changes, false);
+        assert_eq!(serde_json::to_value(&patterns).unwrap(), serde_json::to_value(after).unwrap());
+        apply(&mut patterns, &changes, true);
+        assert_eq!(serde_json::to_value(&patterns).unwrap(), serde_json::to_value(before).unwrap());
+    }
+
+    #[test]
+    fn single_changes_are_labelled_by_what_they_did() {
+        let before = vec![pattern("a", "one"), pattern("b", "two")];
+        let label = |after: &[Pattern]| JournalEntry { changes: diff(&before, after) }.label();
+        assert_eq!(label(&[pattern("a", "one"), pattern("b", "two"), pattern("c", "three")]), "Create c");
+        assert_eq!(label(&[pattern("b", "two")]), "Delete a");
+        assert_eq!(label(&[pattern("a", "uno"), pattern("b", "two")]), "Edit a");
+        assert_eq!(label(&[pattern("b", "two"), pattern("a", "one")]), "Move a");
+        assert_eq!(label(&[pattern("c", "three")]), "Change 3 patterns");
+        assert!(diff(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn only_patterns_out_of_order_are_moved() {
+        let before = vec![pattern("a", "1"), pattern("b", "2"), pattern("c", "3"), pattern("d", "4")];
+        let after = vec![pattern("d", "4"), pattern("a", "1"), pattern("b", "2"), pattern("c", "3")];
+        let changes = diff(&before, &after);
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].index, changes[0].moved_from), (0, Some(3)));
+        assert!(changes[0].is_move());
+    }
+
+    #[test]
+    fn changes_undo_and_redo() {
+        let before = vec![pattern("a", "1"), pattern("b", "2"), pattern("c", "3"), pattern("d", "4")];
+        assert_round_trip(&before, &[pattern("c", "3"), pattern("a", "uno"), pattern("e", "5"), pattern("d", "4")]);
+        assert_round_trip(&before, &[pattern("d", "four"), pattern("c", "3"), pattern("b", "2"), pattern("a", "1")]);
+        assert_round_trip(&before, &[]);
+        assert_round_trip(&[], &before);
+    }
+
+    #[test]
+    fn patterns_removed_since_are_skipped() {
+        let before = vec![pattern("a", "1"), pattern("b", "2")];
+        let changes = diff(&before, &[pattern("a", "uno"), pattern("b", "2")]);
+        let mut patterns = vec![pattern("b", "2")];
+        apply(&mut patterns, &changes, true);
+        assert_eq!(ids(&patterns), ["b"]);
+    }
+
+    #[test]
+    fn new_changes_clear_redo_and_old_ones_are_dropped() {
+        let mut journal = Journal::default();
+        journal.redo.push(JournalEntry { changes: Vec::new() });
+        for _ in 0..MAX_JOURNAL_ENTRIES + 5 {
+            journal.push(JournalEntry { changes: Vec::new() });
+        }
+        assert!(journal.redo.is_empty());
+        assert_eq!(journal.undo.len(), MAX_JOURNAL_ENTRIES);
+    }
+}
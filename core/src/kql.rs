@@ -0,0 +1,395 @@
+#[cfg(feature = "export-flavors")]
+use serde::{Serialize, Deserialize};
+
+#[cfg(feature = "export-flavors")]
+use crate::portability::{portability_warnings, ExportWarning};
+#[cfg(feature = "export-flavors")]
+use crate::{RegexContext, RegexDialect, RegexGenError, WordMatch};
+use crate::template::literal_elements;
+use crate::{ElementModifier, Pattern, PatternElement, TokenClass};
+
+/// Column exported queries read the text from; Sentinel's custom log
+/// tables keep it there.
+pub const TEXT_COLUMN: &str = "RawData";
+
+/// A pattern as KQL for Microsoft Sentinel and other Kusto-backed tools.
+#[cfg(feature = "export-flavors")]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct KqlExport {
+    /// The regex in `query`, in the RE2 syntax Kusto uses.
+    pub regex: String,
+    /// Filters `TABLE` with `matches regex` and extracts the pattern's
+    /// fields into columns of their own.
+    pub query: String,
+    /// A `parse` operator doing the same without a regex, for
+    /// case-sensitive patterns made of exact words, gaps, token classes
+    /// and fields. It doesn't check word boundaries or gap lengths.
+    pub parse: Option<String>,
+    pub warnings: Vec<ExportWarning>,
+}
+
+/// Writes `pattern` as a KQL query. Replace `TABLE` with the table to
+/// search.
+#[cfg(feature = "export-flavors")]
+pub fn kql_export(pattern: &Pattern, context: &RegexContext) -> Result<KqlExport, RegexGenError> {
+    let dialect = RegexDialect::Re2;
+    let warnings = portability_warnings(pattern, dialect);
+    let regex = pattern.to_regex_for_with(dialect, context)?;
+    let mut query = format!("TABLE\n| where {} matches regex {}\n", TEXT_COLUMN, verbatim(&regex));
+    let fields = pattern.fields();
+    if !fields.is_empty() {
+        let capture_context = RegexContext {
+            capture_fields: true,
+            ..context.clone()
+        };
+        let field_regex = verbatim(&pattern.to_regex_for_with(dialect, &capture_context)?);
+        for (group, (_, name)) in fields.iter().enumerate() {
+            query.push_str(&format!("| extend {} = extract({}, {}, {})\n", name, field_regex, group + 1, TEXT_COLUMN));
+        }
+    }
+    Ok(KqlExport {
+        regex,
+        query,
+        parse: parse_operator(pattern),
+        warnings,
+    })
+}
+
+/// A KQL verbatim string, in which only the quote needs escaping.
+#[cfg(feature = "export-flavors")]
+fn verbatim(text: &str) -> String {
+    format!("@\"{}\"", text.replace('"', "\"\""))
+}
+
+/// What a `parse` operator is made of.
+#[derive(Clone, Debug, PartialEq)]
+enum ParseItem {
+    Literal(String),
+    /// `*`, any text.
+    Any,
+    /// `name:type`, text extracted into a column.
+    Column { name: String, kind: String },
+}
+
+#[cfg(feature = "export-flavors")]
+fn parse_operator(pattern: &Pattern) -> Option<String> {
+    let Pattern::Sequence { meta, elements } = pattern else {
+        return None;
+    };
+    if meta.options.case_insensitive {
+        return None;
+    }
+    let mut items = vec![ParseItem::Any];
+    for element in elements {
+        let item = match element {
+            PatternElement::Word { text, mode: WordMatch::Exact } => ParseItem::Literal(text.clone()),
+            separator if separator.is_separator() => ParseItem::Literal(String::from(" ")),
+            PatternElement::Gap { .. } | PatternElement::TokenClass { .. } => ParseItem::Any,
+            PatternElement::Modified { element, modifier } => {
+                let name = modifier.capture.clone()?;
+                if modifier.optional || modifier.min_repeat != 1 || modifier.max_repeat != Some(1) {
+                    return None;
+                }
+                let kind = match element.as_ref() {
+                    PatternElement::TokenClass { class: TokenClass::Date } => "datetime",
+                    _ => "string",
+                };
+                ParseItem::Column { name, kind: kind.to_string() }
+            }
+            _ => return None,
+        };
+        match (items.last_mut(), item) {
+            (Some(ParseItem::Literal(text)), ParseItem::Literal(more)) => text.push_str(&more),
+            (Some(ParseItem::Any), ParseItem::Any) => {}
+            (_, item) => items.push(item),
+        }
+    }
+    if items.last() != Some(&ParseItem::Any) {
+        items.push(ParseItem::Any);
+    }
+
+    let parts: Vec<String> = items
+        .iter()
+        .map(|item| match item {
+            ParseItem::Literal(text) => serde_json::to_string(text).unwrap_or_default(),
+            ParseItem::Any => String::from("*"),
+            ParseItem::Column { name, kind } => format!("{}:{}", name, kind),
+        })
+        .collect();
+    Some(format!("| parse {} with {}\n", TEXT_COLUMN, parts.join(" ")))
+}
+
+/// Reads a pattern back from KQL: a `parse` operator (simple, relaxed or
+/// regex kind), or an expression using `matches regex`, `extract` or
+/// `extract_all`, whose regex is rebuilt as `from_regex` does. Columns of
+/// a `parse` become fields. The returned pattern has an empty id.
+pub fn pattern_from_kql(name: &str, expression: &str) -> Result<Pattern, String> {
+    let tokens = tokenize(expression)?;
+    let keyword = |i: usize, word: &str| matches!(tokens.get(i), Some(Token::Word(w)) if w.eq_ignore_ascii_case(word));
+
+    for i in 0..tokens.len() {
+        if keyword(i, "parse") {
+            return parse_pattern(name, &tokens[i + 1..]);
+        }
+        if keyword(i, "matches") && keyword(i + 1, "regex") {
+            if let Some(Token::Str(regex)) = tokens.get(i + 2) {
+                return Pattern::from_regex(name, regex);
+            }
+        }
+        if (keyword(i, "extract") || keyword(i, "extract_all")) && tokens.get(i + 1) == Some(&Token::Other('(')) {
+            if let Some(Token::Str(regex)) = tokens.get(i + 2) {
+                return Pattern::from_regex(name, regex);
+            }
+        }
+    }
+    Err(String::from("Expected a parse operator or a matches regex, extract or extract_all expression"))
+}
+
+/// The elements of a `parse` operator, from the tokens after `parse`.
+fn parse_pattern(name: &str, tokens: &[Token]) -> Result<Pattern, String> {
+    let mut regex_kind = false;
+    let mut rest = tokens;
+    // parse [kind=...] [flags=...] Column with ...
+    while let [Token::Word(option), Token::Other('='), Token::Word(value), tail @ ..] = rest {
+        if option.eq_ignore_ascii_case("kind") {
+            regex_kind = value.eq_ignore_ascii_case("regex");
+        }
+        rest = tail;
+    }
+    let with = rest
+        .iter()
+        .position(|t| matches!(t, Token::Word(w) if w.eq_ignore_ascii_case("with")))
+        .ok_or_else(|| String::from("Expected 'with' in the parse operator"))?;
+
+    let mut items = Vec::new();
+    let mut tokens = rest[with + 1..].iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Other('|') => break,
+            Token::Other('*') => items.push(ParseItem::Any),
+            Token::Str(text) => items.push(ParseItem::Literal(text.clone())),
+            Token::Word(column) => {
+                let mut kind = String::from("string");
+                if tokens.peek() == Some(&&Token::Other(':')) {
+                    tokens.next();
+                    match tokens.next() {
+                        Some(Token::Word(k)) => kind = k.to_ascii_lowercase(),
+                        _ => return Err(format!("Expected a type after '{}:'", column)),
+                    }
+                }
+                ElementModifier::validate_capture(column)?;
+                items.push(ParseItem::Column { name: column.clone(), kind });
+            }
+            Token::Other(c) => return Err(format!("Unexpected '{}' in the parse operator", c)),
+        }
+    }
+
+    // Leading and trailing wildcards are implied; patterns match anywhere
+    while items.first() == Some(&ParseItem::Any) {
+        items.remove(0);
+    }
+    while items.last() == Some(&ParseItem::Any) {
+        items.pop();
+    }
+    if items.is_empty() {
+        return Err(String::from("The parse operator has nothing to match"));
+    }
+
+    let last = items.len() - 1;
+    let mut elements = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        match item {
+            ParseItem::Any => elements.push(PatternElement::gap(0, None)),
+            ParseItem::Literal(text) if regex_kind => elements.push(PatternElement::Raw { regex: text.clone() }),
+            ParseItem::Literal(text) => literal_elements(text, i > 0, i < last, &mut elements),
+            ParseItem::Column { name, kind } => {
+                let element = match kind.as_str() {
+                    "datetime" | "date" => PatternElement::TokenClass { class: TokenClass::Date },
+                    "int" | "long" | "real" | "double" | "decimal" => PatternElement::TokenClass { class: TokenClass::Number },
+                    // A column takes everything up to the next literal
+                    _ if i == last => PatternElement::Raw { regex: String::from(".+") },
+                    _ => PatternElement::Raw { regex: String::from(".+?") },
+                };
+                elements.push(PatternElement::Modified {
+                    element: Box::new(element),
+                    modifier: ElementModifier {
+                        capture: Some(name.clone()),
+                        ..ElementModifier::default()
+                    },
+                });
+            }
+        }
+    }
+    Ok(Pattern::sequence(name, elements))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Other(char),
+}
+
+/// Splits KQL into names, string literals (plain, single-quoted and `@`
+/// verbatim) and single punctuation characters.
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect()));
+        } else if c == '"' || c == '\'' || (c == '@' && matches!(chars.get(i + 1), Some('"' | '\''))) {
+            let verbatim = c == '@';
+            if verbatim {
+                i += 1;
+            }
+            let quote = chars[i];
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(String::from("Unterminated string literal")),
+                    Some(&q) if q == quote && verbatim && chars.get(i + 1) == Some(&quote) => {
+                        value.push(quote);
+                        i += 2;
+                    }
+                    Some(&q) if q == quote => {
+                        i += 1;
+                        break;
+                    }
+                    Some('\\') if !verbatim => {
+                        let escaped = chars.get(i + 1).ok_or_else(|| String::from("Unterminated string literal"))?;
+                        value.push(match escaped {
+                            'n' => '\n',
+                            't' => '\t',
+                            'r' => '\r',
+                            other => *other,
+                        });
+                        i += 2;
+                    }
+                    Some(&other) => {
+                        value.push(other);
+                        i += 1;
+                    }
+                }
+            }
+            tokens.push(Token::Str(value));
+        } else {
+            tokens.push(Token::Other(c));
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pattern_from_kql;
+    use crate::{Pattern, PatternElement, RegexContext};
+    #[cfg(feature = "export-flavors")]
+    use crate::{ElementModifier, TokenClass};
+
+    #[cfg(feature = "export-flavors")]
+    fn field(name: &str, element: PatternElement) -> PatternElement {
+        PatternElement::Modified {
+            element: Box::new(element),
+            modifier: ElementModifier { capture: Some(name.to_string()), ..ElementModifier::default() },
+        }
+    }
+
+    #[cfg(feature = "export-flavors")]
+    fn login_failed() -> Pattern {
+        Pattern::sequence(
+            "login",
+            vec![
+                PatternElement::word("login".to_string()),
+                PatternElement::gap(0, Some(0)),
+                PatternElement::word("failed".to_string()),
+                PatternElement::gap(0, Some(3)),
+                field("user", PatternElement::Raw { regex: String::from(r"\w+") }),
+                PatternElement::gap(0, Some(0)),
+                field("at", PatternElement::TokenClass { class: TokenClass::Date }),
+            ],
+        )
+    }
+
+    #[cfg(feature = "export-flavors")]
+    #[test]
+    fn exports_where_extend_and_parse_operators() {
+        use super::kql_export;
+
+        let export = kql_export(&login_failed(), &RegexContext::default()).unwrap();
+        assert!(export.query.starts_with("TABLE\n| where RawData matches regex @\""));
+        assert!(export.query.contains("| extend user = extract(@\""));
+        assert!(export.query.contains(", 1, RawData)\n| extend at = extract(@\""));
+        assert!(export.query.contains(", 2, RawData)\n"));
+        assert_eq!(export.parse.as_deref(), Some("| parse RawData with * \"login failed\" * user:string \" \" at:datetime *\n"));
+    }
+
+    #[cfg(feature = "export-flavors")]
+    #[test]
+    fn quotes_are_doubled_and_case_insensitive_patterns_have_no_parse() {
+        use super::kql_export;
+
+        let quoted = Pattern::sequence("quoted", vec![PatternElement::Raw { regex: String::from("say \"hi\"") }]);
+        let export = kql_export(&quoted, &RegexContext::default()).unwrap();
+        assert!(export.query.contains(r#"matches regex @"(?:say ""hi"")""#), "{}", export.query);
+        assert!(export.parse.is_none());
+
+        let word = Pattern::sequence("word", vec![PatternElement::word("login".to_string())]);
+        let mut insensitive = word.clone();
+        insensitive.meta_mut().options.case_insensitive = true;
+        assert!(kql_export(&word, &RegexContext::default()).unwrap().parse.is_some());
+        assert!(kql_export(&insensitive, &RegexContext::default()).unwrap().parse.is_none());
+    }
+
+    #[test]
+    fn parse_operators_become_elements_with_fields() {
+        let pattern = pattern_from_kql("imported", "Syslog | parse SyslogMessage with * \"login failed for \" user \" at \" at:datetime *").unwrap();
+        assert_eq!(pattern.fields().iter().map(|(_, name)| *name).collect::<Vec<_>>(), vec!["user", "at"]);
+        let context = RegexContext { capture_fields: true, ..RegexContext::default() };
+        let re = regex::Regex::new(&pattern.to_regex_with(&context).unwrap()).unwrap();
+        let caps = re.captures("sshd: login failed for root at 2024-01-02 rest").unwrap();
+        assert_eq!(&caps["user"], "root");
+        assert_eq!(&caps["at"], "2024-01-02");
+    }
+
+    #[test]
+    fn regex_kind_parse_keeps_literals_as_regex() {
+        let pattern = pattern_from_kql("imported", r#"T | parse kind=regex Message with "code=" code:int ";[a-z]+""#).unwrap();
+        let Pattern::Sequence { elements, .. } = &pattern else { panic!("not a sequence") };
+        assert!(elements.iter().any(|e| matches!(e, PatternElement::Raw { regex } if regex == ";[a-z]+")));
+        let re = regex::Regex::new(&pattern.to_regex().unwrap()).unwrap();
+        assert!(re.is_match("code=42;abc"));
+    }
+
+    #[test]
+    fn matches_regex_and_extract_are_decompiled() {
+        for expression in [
+            r#"T | where RawData matches regex @"\berror\b\W+\bdisk\b""#,
+            r#"T | extend x = extract("\\berror\\b\\W+\\bdisk\\b", 0, RawData)"#,
+            r#"T | extend x = extract_all('\\berror\\b\\W+\\bdisk\\b', RawData)"#,
+        ] {
+            let pattern = pattern_from_kql("imported", expression).unwrap();
+            assert_eq!(pattern.to_regex().unwrap(), r"\berror\b\W+\bdisk\b", "{}", expression);
+        }
+        let quoted = pattern_from_kql("imported", r#"T | where RawData matches regex @"say ""hi""""#).unwrap();
+        assert!(regex::Regex::new(&quoted.to_regex().unwrap()).unwrap().is_match("say \"hi\""));
+    }
+
+    #[test]
+    fn unsupported_expressions_are_errors() {
+        assert!(pattern_from_kql("x", "T | where RawData has \"error\"").is_err());
+        assert!(pattern_from_kql("x", "T | parse RawData \"no with\"").is_err());
+        assert!(pattern_from_kql("x", "T | parse RawData with * *").is_err());
+        assert!(pattern_from_kql("x", "T | where RawData matches regex \"unterminated").is_err());
+    }
+}
@@ -0,0 +1,1023 @@
+//! The pattern model behind regexgen: patterns built from selected words,
+//! their compilation to regexes in several dialects, and matching,
+//! explaining and exporting them. Nothing here depends on a browser; the
+//! `regexgen-wasm` crate wraps it for JavaScript.
+//!
+//! The items re-exported at the crate root and the public functions of its
+//! modules follow semver: a release that changes or removes one bumps the
+//! major version (the minor version while it is 0.x). The serialized form
+//! of `Pattern` and `Settings` only ever gains optional fields.
+//!
+//! Persistence goes through the `storage::Store` trait, so a server or CLI
+//! can keep a library in memory or any key-value store.
+//! The `wasm` feature adds conversions of `RegexGenError` to JavaScript
+//! values and is off by default. The `corpus`, `export-flavors` and
+//! `inference` features, on by default, can be turned off by embedders
+//! that only compile and match patterns.
+
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+
+pub mod alternation;
+pub mod annotate;
+pub mod benchmark;
+pub mod category;
+pub mod clipboard;
+pub mod compress;
+pub mod config_import;
+#[cfg(feature = "corpus")]
+pub mod corpus;
+pub mod decompile;
+pub mod density;
+pub mod dependencies;
+pub mod dialect;
+pub mod display;
+pub mod duplicates;
+pub mod error;
+pub mod docs;
+pub mod document_index;
+pub mod examples;
+pub mod explain;
+#[cfg(feature = "export-flavors")]
+pub mod export;
+pub mod fragments;
+#[cfg(feature = "inference")]
+pub mod generalize;
+pub mod glob;
+pub mod highlight;
+pub mod history;
+pub mod import;
+pub mod journal;
+pub mod kql;
+#[cfg(feature = "inference")]
+pub mod infer;
+pub mod library_file;
+pub mod limits;
+pub mod locale;
+pub mod matcher;
+pub mod matching;
+pub mod metadata;
+pub mod modifier;
+pub mod naming;
+pub mod option_stats;
+pub mod optimize;
+pub mod options;
+pub mod pack;
+pub mod portability;
+pub mod range_selection;
+pub mod redact;
+#[cfg(feature = "corpus")]
+pub mod report;
+pub mod rng;
+#[cfg(feature = "export-flavors")]
+pub mod sarif;
+pub mod search;
+pub mod settings;
+pub mod snapshot;
+pub mod storage;
+pub mod summary;
+pub mod synonyms;
+pub mod template;
+pub mod telemetry;
+pub mod test_cases;
+pub mod toml;
+pub mod token_class;
+pub mod tokenizer;
+pub mod validation;
+#[cfg(feature = "inference")]
+pub mod variants;
+pub mod word_match;
+pub mod yaml;
+
+pub use alternation::AlternationConfig;
+pub use annotate::{Annotation, AnnotationPriority, PatternMatches};
+pub use benchmark::Benchmark;
+pub use category::Category;
+pub use clipboard::ClipboardPayload;
+#[cfg(feature = "corpus")]
+pub use corpus::{CorpusReport, DocumentStats};
+pub use density::DensityReport;
+pub use dependencies::Dependency;
+pub use dialect::{DialectError, RegexDialect};
+pub use display::{DisplayMeta, LegendEntry};
+pub use document_index::{Candidates, DocumentIndex, DocumentMatches};
+pub use duplicates::{SaveOutcome, SimilarPattern, Similarity};
+pub use error::RegexGenError;
+pub use explain::{Explanation, ExplanationPart};
+#[cfg(feature = "export-flavors")]
+pub use export::{FixtureFormat, ToolExport, ToolTarget, VsCodeSearch};
+#[cfg(feature = "inference")]
+pub use generalize::{Change, ChangeKind, Generalization};
+pub use fragments::FragmentCache;
+pub use glob::GlobConversion;
+pub use highlight::{OwnedSegment, Segment};
+pub use history::HistoryEntry;
+pub use config_import::ConfigDialect;
+pub use import::{ImportReport, ImportedPattern, LineError};
+pub use journal::{JournalEntry, PatternChange};
+#[cfg(feature = "export-flavors")]
+pub use kql::KqlExport;
+#[cfg(feature = "inference")]
+pub use infer::Draft;
+pub use limits::{LimitExceeded, Limits};
+pub use library_file::{LibraryFile, MergeReport, MergeStrategy, SCHEMA_VERSION};
+pub use locale::MessageCatalog;
+pub use matcher::{MatchOrder, MatcherStats, PatternMatcher};
+pub use matching::{ElementMatch, Match, MatchMode, MatchResult};
+pub use metadata::PatternMetadata;
+pub use modifier::ElementModifier;
+pub use option_stats::{OneOfStats, OptionStat};
+pub use pack::{InstallReport, InstalledPack, PackManifest, PackSource, PatternPack};
+pub use options::{MatchOptions, OptionFlags, Scope};
+pub use word_match::WordMatch;
+pub use portability::{ExportResult, ExportWarning};
+pub use range_selection::{RangeKind, RangeSelection};
+pub use redact::{ChangedSpan, Transformed};
+pub use rng::SeededRng;
+pub use settings::{GapStrategy, OffsetEncoding, Settings};
+pub use snapshot::BuilderSnapshot;
+pub use storage::StorageError;
+pub use telemetry::{DurationBucket, UsageEvent};
+pub use template::{ParameterKind, TemplateParameter};
+pub use test_cases::{Evaluation, TestCase, TestCaseResult};
+pub use token_class::TokenClass;
+pub use tokenizer::{SegmentationMode, TokenDetail, TokenizedDocument, TokenizerConfig, WordInfo};
+pub use validation::{Diagnostic, Severity, ValidationReport};
+#[cfg(feature = "inference")]
+pub use variants::{Strictness, Variant};
+use synonyms::SynonymSets;
+
+/// Named groups for element captures are called `e0`, `e1`, ...
+const ELEMENT_GROUP_PREFIX: &str = "e";
+
+/// AND without lookahead expands to n! orderings, so keep n small.
+const MAX_AND_PERMUTATION_OPERANDS: usize = 4;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type")]
+pub enum PatternElement {
+    Word {
+        text: String,
+        #[serde(default, skip_serializing_if = "WordMatch::is_exact")]
+        mode: WordMatch,
+    },
+    Gap {
+        min_words: u32,
+        max_words: Option<u32>,
+        /// Bounds the gap by characters instead of words.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_chars: Option<u32>,
+        /// Whole words the gap must not contain.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        excluded: Vec<String>,
+    },
+    Reference { pattern_id: String },
+    OneOf {
+        options: Vec<String>,
+        /// Name of an externally loaded list whose entries are added to
+        /// `options` when the regex is generated.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        list: Option<String>,
+        /// User-assigned ranking of options; unlisted options weigh 0.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        weights: BTreeMap<String, u32>,
+        /// Per-option case and whole-word overrides.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        option_flags: BTreeMap<String, OptionFlags>,
+    },
+    /// Any word of a synonym set registered on the builder, so editing
+    /// the set updates every pattern using it.
+    SynonymSet { set_id: String },
+    /// A hand-written regex fragment, emitted as-is.
+    Raw { regex: String },
+    /// Any token of a kind such as number, date or email address.
+    TokenClass { class: TokenClass },
+    /// Another element made optional or repeated.
+    Modified {
+        element: Box<PatternElement>,
+        modifier: ElementModifier,
+    },
+}
+
+impl PatternElement {
+    /// Checks user-supplied regex content (Raw fragments and custom token
+    /// classes) compiles on its own.
+    pub fn validate(&self) -> Result<(), String> {
+        let fragment = match self {
+            PatternElement::Raw { regex } => regex,
+            PatternElement::TokenClass { class: TokenClass::Custom { regex, .. } } => regex,
+            PatternElement::Modified { element, modifier } => {
+                modifier.validate()?;
+                return element.validate();
+            }
+            _ => return Ok(()),
+        };
+        regex::Regex::new(fragment)
+            .map(|_| ())
+            .map_err(|e| format!("Invalid regex fragment: {}", e))
+    }
+
+    /// Short name of the element kind, as used in match breakdowns.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PatternElement::Word { .. } => "word",
+            PatternElement::Gap { .. } => "gap",
+            PatternElement::Reference { .. } => "reference",
+            PatternElement::OneOf { .. } => "one_of",
+            PatternElement::SynonymSet { .. } => "synonym_set",
+            PatternElement::Raw { .. } => "raw",
+            PatternElement::TokenClass { .. } => "token_class",
+            PatternElement::Modified { element, .. } => element.kind(),
+        }
+    }
+
+    /// A word or phrase matched exactly.
+    pub fn word(text: String) -> PatternElement {
+        PatternElement::Word { text, mode: WordMatch::Exact }
+    }
+
+    /// A gap of `min_words` to `max_words` words without further constraints.
+    pub fn gap(min_words: u32, max_words: Option<u32>) -> PatternElement {
+        PatternElement::Gap {
+            min_words,
+            max_words,
+            max_chars: None,
+            excluded: Vec::new(),
+        }
+    }
+
+    /// A choice between `options` with no list, weights or flags.
+    pub fn one_of(options: Vec<String>) -> PatternElement {
+        PatternElement::OneOf {
+            options,
+            list: None,
+            weights: BTreeMap::new(),
+            option_flags: BTreeMap::new(),
+        }
+    }
+
+    /// The element itself, without any modifier around it.
+    pub fn base(&self) -> &PatternElement {
+        match self {
+            PatternElement::Modified { element, .. } => element,
+            other => other,
+        }
+    }
+
+    /// The element's modifier; the default for an unmodified element.
+    pub fn modifier(&self) -> ElementModifier {
+        match self {
+            PatternElement::Modified { modifier, .. } => modifier.clone(),
+            _ => ElementModifier::default(),
+        }
+    }
+
+    /// Replaces the element's modifier, unwrapping the element when the
+    /// new one changes nothing.
+    pub fn set_modifier(&mut self, modifier: ElementModifier) {
+        let base = std::mem::replace(self.base_mut(), PatternElement::gap(0, None));
+        *self = if modifier == ElementModifier::default() {
+            base
+        } else {
+            PatternElement::Modified {
+                element: Box::new(base),
+                modifier,
+            }
+        };
+    }
+
+    pub fn base_mut(&mut self) -> &mut PatternElement {
+        match self {
+            PatternElement::Modified { element, .. } => element,
+            other => other,
+        }
+    }
+
+    /// A gap that only stands for the separator between two elements.
+    pub fn is_separator(&self) -> bool {
+        matches!(self.base(), PatternElement::Gap { min_words: 0, max_words: Some(0), max_chars: None, .. })
+    }
+
+    fn emit(&self, dialect: RegexDialect, context: &RegexContext, lookaround: bool) -> Result<String, DialectError> {
+        Ok(match self {
+            PatternElement::Word { text, mode } => mode.to_regex(text, dialect),
+            PatternElement::Gap { max_chars: Some(max_chars), excluded, .. } => {
+                let lazy = if dialect.supports_lazy() { "?" } else { "" };
+                if excluded.is_empty() {
+                    format!("{}{{0,{}}}{}", context.scope.any_char(dialect), max_chars, lazy)
+                } else if lookaround {
+                    // Every character of the gap checks no excluded word starts there
+                    let words: Vec<String> = excluded
+                        .iter()
+                        .map(|w| {
+                            let (start, end) = word_match::edge_boundaries(w, dialect);
+                            format!("{}{}{}", start, dialect.escape(w), end)
+                        })
+                        .collect();
+                    let forbidden = dialect.group(&words.join("|"));
+                    format!("(?:(?!{}){}){{0,{}}}{}", forbidden, context.scope.any_char(dialect), max_chars, lazy)
+                } else {
+                    return Err(dialect.unsupported("excluded words in a character-limited gap"));
+                }
+            }
+            PatternElement::Gap { min_words, max_words, excluded, .. } if !excluded.is_empty() && *max_words != Some(0) => {
+                // Spell the gap out word by word, each word anything but the excluded ones
+                let word = alternation::build_word_except(excluded, dialect)
+                    .ok_or_else(|| dialect.unsupported("excluded words in a gap"))?;
+                let separator = context.scope.separator(dialect);
+                let step = dialect.group(&format!("{}{}", word, separator));
+                match max_words {
+                    None if *min_words == 0 => format!("{}{}*{}", separator, step, if dialect.supports_lazy() { "?" } else { "" }),
+                    Some(max) => format!(r"{}{}{{{},{}}}", separator, step, min_words, max),
+                    None => format!(r"{}{}{{{},}}", separator, step, min_words),
+                }
+            }
+            PatternElement::Gap { min_words, max_words, .. } => {
+                // For AND patterns (open-ended gaps), match anything
+                if *min_words == 0 && max_words.is_none() {
+                    context.scope.lazy_any(dialect)  // Non-greedy match anything
+                } else {
+                    // The gap owns the separators on both sides of its words,
+                    // so the next element can start right after it
+                    let separator = context.scope.separator(dialect);
+                    let word = dialect.group(&format!("{}+{}", dialect.word_char(), separator));
+                    match max_words {
+                        Some(0) => separator,
+                        Some(max) => format!(r"{}{}{{{},{}}}", separator, word, min_words, max),
+                        None => format!(r"{}{}{{{},}}", separator, word, min_words),
+                    }
+                }
+            }
+            PatternElement::OneOf { options, list, option_flags, .. } => {
+                let all_options = context.resolve_options(options, list.as_deref());
+                context.one_of(&all_options, option_flags, dialect)?
+            }
+            PatternElement::SynonymSet { set_id } => {
+                let words = context.resolve_synonyms(set_id);
+                context.one_of(&words, &BTreeMap::new(), dialect)?
+            }
+            PatternElement::TokenClass { class } => class.to_regex(dialect)?,
+            PatternElement::Raw { regex } => dialect.group(regex),
+            PatternElement::Reference { .. } => {
+                // TODO: Implement pattern reference resolution
+                String::from(".*")
+            }
+            PatternElement::Modified { element, modifier } => {
+                let body = modifier.repeat(&element.emit(dialect, context, lookaround)?, &context.scope.separator(dialect), dialect);
+                match &modifier.capture {
+                    Some(name) if context.capture_fields => format!("(?P<{}>{})", name, body),
+                    _ => body,
+                }
+            }
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type")]
+pub enum Pattern {
+    Sequence {
+        #[serde(flatten)]
+        meta: PatternMeta,
+        elements: Vec<PatternElement>,
+    },
+    Composite {
+        #[serde(flatten)]
+        meta: PatternMeta,
+        operator: CompositeOperator,
+        patterns: Vec<Pattern>,
+    },
+    /// Text with «named» placeholders, instantiated into sequences by
+    /// giving each parameter a value. On its own it matches any text in
+    /// place of the parameters.
+    Template {
+        #[serde(flatten)]
+        meta: PatternMeta,
+        /// e.g. "«service» failed with code «code»".
+        body: String,
+        parameters: Vec<TemplateParameter>,
+    },
+}
+
+/// What every kind of pattern carries besides its structure: identity,
+/// match options, and the fields the library is organized by.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PatternMeta {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub options: MatchOptions,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub test_cases: Vec<TestCase>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<DisplayMeta>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Slash-separated path, e.g. "auth/ssh".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder: Option<String>,
+    /// Id of a category from the taxonomy in the settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Ranks it against other patterns matching the same text, highest
+    /// first, when annotating by priority.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub priority: i32,
+    /// Earlier versions, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<HistoryEntry>,
+    #[serde(default, skip_serializing_if = "PatternMetadata::is_unset")]
+    pub metadata: PatternMetadata,
+}
+
+impl PatternMeta {
+    /// An unsaved pattern's (empty id), with default match options.
+    pub fn new(name: &str) -> PatternMeta {
+        PatternMeta { name: name.to_string(), ..PatternMeta::default() }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum CompositeOperator {
+    And,
+    Or,
+    /// The first pattern and none of the others. With a single pattern,
+    /// as topics use inside an AND, only "not this one".
+    Not,
+}
+
+impl std::str::FromStr for CompositeOperator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "AND" => Ok(CompositeOperator::And),
+            "OR" => Ok(CompositeOperator::Or),
+            "NOT" => Ok(CompositeOperator::Not),
+            _ => Err(format!("Unknown operator: {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SelectionSpan {
+    pub text: String,
+    pub start_index: usize,
+    pub end_index: usize,
+    pub word_index: usize,
+    /// Set when the selection stands for any token of a class rather than
+    /// its literal text.
+    #[serde(default)]
+    pub token_class: Option<TokenClass>,
+    /// Set when the selection is a hand-written regex fragment.
+    #[serde(default)]
+    pub raw: Option<String>,
+    /// Set for character-range selections: how their exact text matches.
+    #[serde(default)]
+    pub word_match: Option<WordMatch>,
+    /// The last word a character-range selection overlaps, when it runs
+    /// past the word at `word_index`.
+    #[serde(default)]
+    pub last_word_index: Option<usize>,
+    /// Other words that may stand in the selection's place, e.g. "refused"
+    /// and "reset" for "timeout". Builds a `OneOf`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternatives: Vec<String>,
+}
+
+impl SelectionSpan {
+    /// Whether the selection is a word chosen in the text, rather than a
+    /// token class, raw fragment or character range.
+    pub fn is_word(&self) -> bool {
+        self.token_class.is_none() && self.raw.is_none() && self.word_match.is_none()
+    }
+
+    /// Whether the selection stands for its own text and can join a phrase.
+    pub fn is_literal(&self) -> bool {
+        self.is_word() && self.alternatives.is_empty()
+    }
+
+    pub fn end_word_index(&self) -> usize {
+        self.last_word_index.unwrap_or(self.word_index)
+    }
+
+    /// Order in the text; several selections can fall in one word.
+    pub fn position(&self) -> (usize, usize) {
+        (self.word_index, self.start_index)
+    }
+}
+
+/// Everything outside a pattern itself that affects how it compiles.
+#[derive(Clone, Debug, Default)]
+pub struct RegexContext<'a> {
+    pub alternation: AlternationConfig,
+    /// Named lists that `OneOf` elements can pull their options from.
+    /// Entries of lists missing here are left out of the regex.
+    pub lists: Option<&'a HashMap<String, Vec<String>>>,
+    /// Synonym sets `SynonymSet` elements expand to. A set missing here
+    /// leaves its elements without words.
+    pub synonyms: Option<&'a SynonymSets>,
+    /// Wrap each element of a sequence in a named group (`e0`, `e1`, ...)
+    /// so matches can be broken down per element.
+    pub capture_elements: bool,
+    /// Wrap elements named as fields in a group of that name.
+    pub capture_fields: bool,
+    /// How far gaps and AND may reach. A pattern whose own options name a
+    /// narrower scope than `Document` overrides it for itself and its
+    /// sub-patterns.
+    pub scope: Scope,
+    /// Alternations shared with the other patterns compiled in the same
+    /// pass; without it every `OneOf` builds its own.
+    pub fragments: Option<&'a FragmentCache>,
+}
+
+impl RegexContext<'_> {
+    /// A `OneOf`'s inline options followed by the entries of its list, if
+    /// that list is available.
+    pub fn resolve_options(&self, options: &[String], list: Option<&str>) -> Vec<String> {
+        let mut all_options = options.to_vec();
+        if let Some(entries) = list.and_then(|name| self.lists.and_then(|lists| lists.get(name))) {
+            all_options.extend(entries.iter().cloned());
+        }
+        all_options
+    }
+
+    /// `alternation::build_one_of` with the context's alternation settings,
+    /// through `fragments` if there is one.
+    fn one_of(&self, options: &[String], flags: &BTreeMap<String, OptionFlags>, dialect: RegexDialect) -> Result<String, DialectError> {
+        match self.fragments {
+            Some(fragments) => fragments.one_of(options, flags, &self.alternation, dialect),
+            None => alternation::build_one_of(options, flags, &self.alternation, dialect),
+        }
+    }
+
+    /// The words of a synonym set, if it is available.
+    pub fn resolve_synonyms(&self, set_id: &str) -> Vec<String> {
+        self.synonyms
+            .and_then(|sets| sets.get(set_id))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl Pattern {
+    /// An unsaved sequence (empty id) with default match options.
+    pub fn sequence(name: &str, elements: Vec<PatternElement>) -> Pattern {
+        Pattern::Sequence { meta: PatternMeta::new(name), elements }
+    }
+
+    /// An unsaved composite (empty id) with default match options.
+    pub fn composite(name: &str, operator: CompositeOperator, patterns: Vec<Pattern>) -> Pattern {
+        Pattern::Composite { meta: PatternMeta::new(name), operator, patterns }
+    }
+
+    /// An unsaved template (empty id) with default match options. See
+    /// `template::validate` for the checks a saved one passes.
+    pub fn template(name: &str, body: &str, parameters: Vec<TemplateParameter>) -> Pattern {
+        Pattern::Template { meta: PatternMeta::new(name), body: body.to_string(), parameters }
+    }
+
+    /// The pattern as a Rust-syntax regex, writing AND and NOT with
+    /// lookarounds. Fails for what has no regex at all, such as a NOT
+    /// without operands or inverted gap bounds; see `to_regex_checked` for
+    /// one the `regex` crate compiles.
+    pub fn to_regex(&self) -> Result<String, DialectError> {
+        self.to_regex_with(&RegexContext::default())
+    }
+
+    /// Like `to_regex`, but resolving external lists and alternation
+    /// settings from `context`.
+    pub fn to_regex_with(&self, context: &RegexContext) -> Result<String, DialectError> {
+        self.emit_regex(RegexDialect::Rust, context, true)
+    }
+
+    /// Produces a regex for a specific engine. Unlike `to_regex`, this never
+    /// emits syntax the target cannot parse: AND is rewritten as ordered
+    /// alternatives where lookahead is missing, and NOT is an error there.
+    pub fn to_regex_for(&self, dialect: RegexDialect) -> Result<String, DialectError> {
+        self.to_regex_for_with(dialect, &RegexContext::default())
+    }
+
+    pub fn to_regex_for_with(&self, dialect: RegexDialect, context: &RegexContext) -> Result<String, DialectError> {
+        self.emit_regex(dialect, context, dialect.supports_lookaround())
+    }
+
+    /// A regex the `regex` crate compiles, or an error saying why there is
+    /// none. `to_regex` writes AND and NOT with lookarounds the crate
+    /// lacks; here AND is spelled out as ordered alternatives instead and
+    /// NOT is `Unsupported`, as is AND of more than 4 patterns. Composites
+    /// refused here can still be tested with `matching::find_composite`.
+    pub fn to_regex_checked(&self) -> Result<String, RegexGenError> {
+        self.to_regex_checked_with(&RegexContext::default())
+    }
+
+    pub fn to_regex_checked_with(&self, context: &RegexContext) -> Result<String, RegexGenError> {
+        let source = self.to_regex_for_with(RegexDialect::Rust, context)?;
+        regex::Regex::new(&source)?;
+        Ok(source)
+    }
+
+    fn emit_regex(&self, dialect: RegexDialect, context: &RegexContext, lookaround: bool) -> Result<String, DialectError> {
+        let scoped;
+        let context = match self.meta().options.scope {
+            Scope::Document => context,
+            scope => {
+                scoped = RegexContext { scope, ..context.clone() };
+                &scoped
+            }
+        };
+        let body = self.emit_body(dialect, context, lookaround)?;
+        self.meta().options.apply(&body, dialect)
+    }
+
+    fn emit_body(&self, dialect: RegexDialect, context: &RegexContext, lookaround: bool) -> Result<String, DialectError> {
+        match self {
+            Pattern::Sequence { elements, .. } => emit_elements(elements, dialect, context, lookaround),
+            Pattern::Template { .. } => emit_elements(&self.template_elements(), dialect, context, lookaround),
+            Pattern::Composite { operator, patterns, .. } => {
+                // Element and field captures would repeat group names across operands
+                let sub_context = RegexContext {
+                    capture_elements: false,
+                    capture_fields: false,
+                    ..context.clone()
+                };
+                let mut sub_regexes = Vec::new();
+                for pattern in patterns {
+                    sub_regexes.push(pattern.emit_regex(dialect, &sub_context, lookaround)?);
+                }
+                match operator {
+                    CompositeOperator::Or => {
+                        let sub_patterns: Vec<String> = sub_regexes
+                            .iter()
+                            .map(|r| format!("({})", r))
+                            .collect();
+                        Ok(sub_patterns.join("|"))
+                    }
+                    CompositeOperator::And if lookaround => {
+                        // For AND, all patterns must match somewhere in the text
+                        // We'll use positive lookahead from the start to ensure all patterns exist
+                        let any = context.scope.any_char(dialect);
+                        let lookaheads: Vec<String> = sub_regexes
+                            .iter()
+                            .map(|r| format!("(?={}*{})", any, r))
+                            .collect();
+                        // After all lookaheads, match the entire string
+                        Ok(format!("^{}{}*$", lookaheads.join(""), any))
+                    }
+                    CompositeOperator::And => {
+                        // Without lookahead, spell out every order the parts may appear in
+                        if sub_regexes.len() > MAX_AND_PERMUTATION_OPERANDS {
+                            return Err(dialect.unsupported("AND of more than 4 patterns"));
+                        }
+                        let groups: Vec<String> = sub_regexes.iter().map(|r| dialect.group(r)).collect();
+                        let orders: Vec<String> = permutations(&groups)
+                            .iter()
+                            .map(|order| order.join(&context.scope.lazy_any(dialect)))
+                            .collect();
+                        Ok(dialect.group(&orders.join("|")))
+                    }
+                    CompositeOperator::Not if lookaround => {
+                        // The first pattern must occur (unless it is the only
+                        // one), none of the others may, then match the whole text
+                        let any = context.scope.any_char(dialect);
+                        let (required, excluded) = match sub_regexes.as_slice() {
+                            [only] => (None, std::slice::from_ref(only)),
+                            [first, rest @ ..] => (Some(first), rest),
+                            [] => return Err(dialect.unsupported("NOT without patterns")),
+                        };
+                        let mut assertions = String::new();
+                        if let Some(r) = required {
+                            assertions.push_str(&format!("(?={}*{})", any, r));
+                        }
+                        for r in excluded {
+                            assertions.push_str(&format!("(?!{}*{})", any, r));
+                        }
+                        Ok(format!("^{}{}*$", assertions, any))
+                    }
+                    CompositeOperator::Not => Err(dialect.unsupported("NOT (negative lookahead)")),
+                }
+            }
+        }
+    }
+
+    /// The elements named as fields, as (element index, field name).
+    /// A template's placeholders are fields named after their parameters.
+    /// Composites have none.
+    pub fn fields(&self) -> Vec<(usize, &str)> {
+        match self {
+            Pattern::Sequence { elements, .. } => elements
+                .iter()
+                .enumerate()
+                .filter_map(|(index, element)| match element {
+                    PatternElement::Modified { modifier, .. } => modifier.capture.as_deref().map(|name| (index, name)),
+                    _ => None,
+                })
+                .collect(),
+            Pattern::Template { parameters, .. } => self
+                .template_elements()
+                .iter()
+                .enumerate()
+                .filter_map(|(index, element)| match element {
+                    PatternElement::Modified { modifier, .. } => {
+                        let name = modifier.capture.as_deref()?;
+                        parameters.iter().find(|p| p.name == name).map(|p| (index, p.name.as_str()))
+                    }
+                    _ => None,
+                })
+                .collect(),
+            Pattern::Composite { .. } => Vec::new(),
+        }
+    }
+
+    /// Ids of the patterns this pattern's `Reference` elements point to.
+    pub fn referenced_patterns(&self) -> Vec<&str> {
+        match self {
+            Pattern::Sequence { elements, .. } => elements
+                .iter()
+                .filter_map(|element| match element.base() {
+                    PatternElement::Reference { pattern_id } => Some(pattern_id.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            Pattern::Template { .. } => Vec::new(),
+            Pattern::Composite { patterns, .. } => patterns
+                .iter()
+                .flat_map(|p| p.referenced_patterns())
+                .collect(),
+        }
+    }
+
+    /// Ids of the synonym sets this pattern's elements use.
+    pub fn referenced_synonym_sets(&self) -> Vec<&str> {
+        match self {
+            Pattern::Sequence { elements, .. } => elements
+                .iter()
+                .filter_map(|element| match element.base() {
+                    PatternElement::SynonymSet { set_id } => Some(set_id.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            Pattern::Template { .. } => Vec::new(),
+            Pattern::Composite { patterns, .. } => patterns
+                .iter()
+                .flat_map(|p| p.referenced_synonym_sets())
+                .collect(),
+        }
+    }
+
+    /// Names of the external lists this pattern's `OneOf` elements use.
+    pub fn referenced_lists(&self) -> Vec<&str> {
+        match self {
+            Pattern::Sequence { elements, .. } => elements
+                .iter()
+                .filter_map(|element| match element.base() {
+                    PatternElement::OneOf { list: Some(name), .. } => Some(name.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            Pattern::Template { .. } => Vec::new(),
+            Pattern::Composite { patterns, .. } => patterns
+                .iter()
+                .flat_map(|p| p.referenced_lists())
+                .collect(),
+        }
+    }
+
+    /// The fields every kind of pattern has.
+    pub fn meta(&self) -> &PatternMeta {
+        match self {
+            Pattern::Sequence { meta, .. } => meta,
+            Pattern::Composite { meta, .. } => meta,
+            Pattern::Template { meta, .. } => meta,
+        }
+    }
+
+    pub fn meta_mut(&mut self) -> &mut PatternMeta {
+        match self {
+            Pattern::Sequence { meta, .. } => meta,
+            Pattern::Composite { meta, .. } => meta,
+            Pattern::Template { meta, .. } => meta,
+        }
+    }
+
+    pub fn get_id(&self) -> &str {
+        &self.meta().id
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.meta().name
+    }
+
+    pub fn set_id(&mut self, new_id: String) {
+        self.meta_mut().id = new_id;
+    }
+
+    pub fn set_name(&mut self, new_name: String) {
+        self.meta_mut().name = new_name;
+    }
+}
+
+/// Leaves a default `priority` out of the serialized pattern.
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+/// The regex for a sequence of elements, each emitted in turn; the gaps
+/// between them supply the spacing.
+fn emit_elements(elements: &[PatternElement], dialect: RegexDialect, context: &RegexContext, lookaround: bool) -> Result<String, DialectError> {
+    let mut parts: Vec<String> = Vec::new();
+    let mut absorbed_next = false;
+    for (i, element) in elements.iter().enumerate() {
+        if std::mem::take(&mut absorbed_next) {
+            // Already part of the optional element before it
+            parts.push(String::new());
+            continue;
+        }
+        let mut part = element.emit(dialect, context, lookaround)?;
+        if let PatternElement::Modified { modifier, .. } = element {
+            if modifier.optional {
+                // An optional element takes one neighbouring separator
+                // along, so leaving it out doesn't leave two behind
+                let separator = context.scope.separator(dialect);
+                if elements.get(i + 1).is_some_and(PatternElement::is_separator) {
+                    part = format!("{}{}", part, separator);
+                    absorbed_next = true;
+                } else if i > 0 && elements[i - 1].is_separator() && !parts[i - 1].is_empty() {
+                    part = format!("{}{}", separator, part);
+                    parts[i - 1] = String::new();
+                }
+                part = format!("{}?", dialect.group(&part));
+            }
+        }
+        parts.push(part);
+    }
+    if context.capture_elements {
+        for (i, part) in parts.iter_mut().enumerate() {
+            *part = format!("(?P<{}{}>{})", ELEMENT_GROUP_PREFIX, i, part);
+        }
+    }
+    // Don't join with \W+ anymore, let the gaps handle the spacing
+    Ok(parts.join(""))
+}
+
+/// Turns selections sorted by word index into sequence elements: runs of
+/// adjacent words become phrases, token-class selections become their own
+/// elements, and non-adjacent selections are joined by gaps built as
+/// `gap_strategy` says.
+pub fn elements_from_selections(selections: &[SelectionSpan], gap_strategy: GapStrategy) -> Vec<PatternElement> {
+    sourced_elements(selections, gap_strategy).into_iter().map(|(element, _)| element).collect()
+}
+
+/// `elements_from_selections`, with the selections each element came
+/// from: those it was built from, or the two a gap lies between.
+pub fn sourced_elements(selections: &[SelectionSpan], gap_strategy: GapStrategy) -> Vec<(PatternElement, Range<usize>)> {
+    let mut elements = Vec::new();
+    let mut i = 0;
+
+    while i < selections.len() {
+        let start_selection = &selections[i];
+        let mut j = i + 1;
+
+        if let Some(class) = &start_selection.token_class {
+            elements.push((PatternElement::TokenClass { class: class.clone() }, i..j));
+        } else if let Some(regex) = &start_selection.raw {
+            elements.push((PatternElement::Raw { regex: regex.clone() }, i..j));
+        } else if let Some(mode) = start_selection.word_match {
+            // A character range keeps its exact text, separators included
+            let text = start_selection.text.clone();
+            elements.push((PatternElement::Word { text, mode }, i..j));
+        } else if let Some(options) = alternatives(selections, &mut j) {
+            elements.push((PatternElement::one_of(options), i..j));
+        } else {
+            let mut phrase_words = vec![start_selection.text.clone()];
+
+            // Collect adjacent words into a phrase
+            while j < selections.len() {
+                let current = &selections[j - 1];
+                let next = &selections[j];
+
+                // Check if words are adjacent (consecutive word indices)
+                if next.word_index == current.end_word_index() + 1 && next.is_literal() {
+                    phrase_words.push(next.text.clone());
+                    j += 1;
+                } else {
+                    break;
+                }
+            }
+
+            // Join adjacent words with spaces to create a phrase
+            elements.push((PatternElement::word(phrase_words.join(" ")), i..j));
+        }
+
+        // If there's a next selection, determine if we need a gap
+        if j < selections.len() {
+            let (previous, next) = (&selections[j - 1], &selections[j]);
+            if next.word_index <= previous.end_word_index() {
+                // Both in one word: at most the characters that lay between them
+                let max_chars = next.start_index.saturating_sub(previous.end_index) as u32;
+                let gap = PatternElement::Gap { min_words: 0, max_words: None, max_chars: Some(max_chars), excluded: Vec::new() };
+                elements.push((gap, j - 1..j + 1));
+            } else if next.word_index == previous.end_word_index() + 1 {
+                // Adjacent, but not mergeable into one phrase: only the separator
+                elements.push((PatternElement::gap(0, Some(0)), j - 1..j + 1));
+            } else {
+                let skipped = next.word_index.saturating_sub(previous.end_word_index() + 1) as u32;
+                let gap = match gap_strategy {
+                    // For non-adjacent selections, we use an open-ended gap
+                    // This creates an AND pattern - both parts must exist but with anything in between
+                    GapStrategy::Open => PatternElement::gap(0, None), // No upper limit - matches any amount of text
+                    GapStrategy::Exact => PatternElement::gap(skipped, Some(skipped)),
+                    GapStrategy::UpTo => PatternElement::gap(0, Some(skipped)),
+                    GapStrategy::Loose => PatternElement::gap(0, Some(2 * skipped)),
+                };
+                elements.push((gap, j - 1..j + 1));
+            }
+        }
+
+        i = j;
+    }
+
+    elements
+}
+
+/// The words that may stand at the position of the word selection at
+/// `*next - 1`: its own and alternatives, plus those of the word
+/// selections after it at the same position, which `*next` moves past.
+/// `None` if there is only the one word.
+fn alternatives(selections: &[SelectionSpan], next: &mut usize) -> Option<Vec<String>> {
+    let first = &selections[*next - 1];
+    let mut options = vec![first.text.clone()];
+    let mut add = |selection: &SelectionSpan| {
+        for option in std::iter::once(&selection.text).chain(&selection.alternatives) {
+            if !options.contains(option) {
+                options.push(option.clone());
+            }
+        }
+    };
+    add(first);
+    while *next < selections.len() && selections[*next].is_word() && selections[*next].word_index == first.word_index {
+        add(&selections[*next]);
+        *next += 1;
+    }
+    (options.len() > 1).then_some(options)
+}
+
+fn permutations(items: &[String]) -> Vec<Vec<String>> {
+    if items.len() <= 1 {
+        return vec![items.to_vec()];
+    }
+    let mut result = Vec::new();
+    for i in 0..items.len() {
+        let mut rest = items.to_vec();
+        let first = rest.remove(i);
+        for mut tail in permutations(&rest) {
+            tail.insert(0, first.clone());
+            result.push(tail);
+        }
+    }
+    result
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompositeOperator, Pattern, PatternElement, WordMatch};
+
+    #[test]
+    fn pattern_meta_serializes_flat() {
+        let mut word = Pattern::sequence("alpha", vec![PatternElement::Word { text: "alpha".to_string(), mode: WordMatch::Exact }]);
+        word.set_id("word".to_string());
+        word.meta_mut().tags = vec!["auth".to_string()];
+        word.meta_mut().priority = 2;
+        let mut either = Pattern::composite("either", CompositeOperator::Or, vec![word]);
+        either.set_id("either".to_string());
+        either.meta_mut().options.case_insensitive = true;
+
+        let json: serde_json::Value = serde_json::to_value(&either).unwrap();
+        assert_eq!(json["type"], "Composite");
+        assert_eq!(json["id"], "either");
+        assert_eq!(json["options"]["case_insensitive"], true);
+        assert!(json.get("meta").is_none());
+        assert!(json.get("description").is_none());
+        assert_eq!(json["patterns"][0]["tags"][0], "auth");
+        assert_eq!(json["patterns"][0]["priority"], 2);
+
+        let back: Pattern = serde_json::from_value(json).unwrap();
+        assert_eq!(back.get_id(), "either");
+        assert!(back.meta().options.case_insensitive);
+        let Pattern::Composite { patterns, .. } = &back else { panic!("not a composite") };
+        assert_eq!(patterns[0].meta().tags, vec!["auth"]);
+        assert_eq!(patterns[0].meta().priority, 2);
+    }
+
+    #[test]
+    fn patterns_saved_without_meta_fields_still_load() {
+        let pattern: Pattern = serde_json::from_str(r#"{"type":"Sequence","id":"p1","name":"old","elements":[]}"#).unwrap();
+        assert_eq!(pattern.get_name(), "old");
+        assert!(pattern.meta().tags.is_empty());
+        assert_eq!(pattern.meta().priority, 0);
+    }
+}
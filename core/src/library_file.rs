@@ -0,0 +1,168 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value};
+
+use crate::{toml, yaml, Pattern};
+
+/// Schema version written by `export_patterns`. Bump it whenever the
+/// pattern model changes in a way old readers can't ignore, and add a step
+/// to `migrate`.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A pattern library as written to disk.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LibraryFile {
+    pub version: u32,
+    pub patterns: Vec<Pattern>,
+    /// Sample documents saved alongside the patterns, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub documents: BTreeMap<String, String>,
+}
+
+/// What to do when an imported pattern's id is already in the library.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeStrategy {
+    Skip,
+    Overwrite,
+    Duplicate,
+}
+
+impl std::str::FromStr for MergeStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "skip" => Ok(MergeStrategy::Skip),
+            "overwrite" => Ok(MergeStrategy::Overwrite),
+            "duplicate" => Ok(MergeStrategy::Duplicate),
+            _ => Err(format!("Unknown merge strategy: {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MergeReport {
+    pub added: usize,
+    pub skipped: usize,
+    pub overwritten: usize,
+    pub duplicated: usize,
+}
+
+pub fn export_library(patterns: &[Pattern], documents: &BTreeMap<String, String>) -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(&LibraryFile {
+        version: SCHEMA_VERSION,
+        patterns: patterns.to_vec(),
+        documents: documents.clone(),
+    })
+}
+
+/// Reads a library file of any known schema version. Version 0 is a bare
+/// array of patterns, i.e. a raw dump of the localStorage value.
+pub fn parse_library(json: &str) -> Result<Vec<Pattern>, String> {
+    let value: serde_json::Value = serde_json::from_str(json).map_err(|e| format!("Invalid JSON: {}", e))?;
+    parse_library_value(value)
+}
+
+/// The library as YAML, for keeping in version control: keys in a fixed
+/// order, and each pattern under a `# name` comment so diffs show which
+/// pattern changed.
+pub fn export_library_yaml(patterns: &[Pattern], documents: &BTreeMap<String, String>) -> Result<String, serde_json::Error> {
+    let value = library_value(patterns, documents)?;
+    let mut out = String::new();
+    let Value::Object(map) = &value else { return Ok(out) };
+    for (key, value) in ordered_entries(map) {
+        match value {
+            Value::Array(items) if key == "patterns" && !items.is_empty() => {
+                out.push_str("patterns:\n");
+                for item in items {
+                    out.push_str(&format!("  # {}\n", pattern_comment(item)));
+                    for line in yaml::to_yaml(&Value::Array(vec![item.clone()])).lines() {
+                        out.push_str(&format!("  {}\n", line));
+                    }
+                }
+            }
+            value => {
+                let mut entry = Map::new();
+                entry.insert(key.clone(), value.clone());
+                out.push_str(&yaml::to_yaml(&Value::Object(entry)));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// The library as TOML, with each pattern a `[[patterns]]` table under a
+/// `# name` comment. TOML can't hold nulls, so it fails for the few
+/// patterns that have one in a list.
+pub fn export_library_toml(patterns: &[Pattern], documents: &BTreeMap<String, String>) -> Result<String, String> {
+    let value = library_value(patterns, documents).map_err(|e| e.to_string())?;
+    toml::to_toml(&value, &|item| Some(pattern_comment(item)))
+}
+
+/// `parse_library` for a file written by `export_library_yaml`.
+pub fn parse_library_yaml(text: &str) -> Result<Vec<Pattern>, String> {
+    parse_library_value(yaml::from_yaml(text).map_err(|e| format!("Invalid YAML: {}", e))?)
+}
+
+/// `parse_library` for a file written by `export_library_toml`.
+pub fn parse_library_toml(text: &str) -> Result<Vec<Pattern>, String> {
+    parse_library_value(toml::from_toml(text).map_err(|e| format!("Invalid TOML: {}", e))?)
+}
+
+fn library_value(patterns: &[Pattern], documents: &BTreeMap<String, String>) -> Result<Value, serde_json::Error> {
+    serde_json::to_value(LibraryFile {
+        version: SCHEMA_VERSION,
+        patterns: patterns.to_vec(),
+        documents: documents.clone(),
+    })
+}
+
+/// The comment above a pattern in YAML and TOML files: its name and id.
+fn pattern_comment(pattern: &Value) -> String {
+    let field = |key: &str| pattern.get(key).and_then(Value::as_str).unwrap_or_default().replace('\n', " ");
+    format!("{} ({})", field("name"), field("id"))
+}
+
+/// Object fields in the order YAML and TOML files list them, so that
+/// re-exporting an unchanged library gives an identical file: the
+/// identifying keys first, then the rest alphabetically.
+pub(crate) fn ordered_entries(map: &Map<String, Value>) -> Vec<(&String, &Value)> {
+    const LEADING: [&str; 4] = ["version", "type", "id", "name"];
+    let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+    entries.sort_by_key(|(key, _)| (LEADING.iter().position(|k| k == key).unwrap_or(LEADING.len()), key.as_str()));
+    entries
+}
+
+fn parse_library_value(value: Value) -> Result<Vec<Pattern>, String> {
+    let version = match &value {
+        serde_json::Value::Array(_) => 0,
+        serde_json::Value::Object(map) => map
+            .get("version")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| String::from("Missing schema version"))? as u32,
+        _ => return Err(String::from("Expected a pattern library object")),
+    };
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "File uses schema version {}, newer than the supported version {}",
+            version, SCHEMA_VERSION
+        ));
+    }
+    let migrated = migrate(value, version);
+    let file: LibraryFile = serde_json::from_value(migrated).map_err(|e| format!("Invalid pattern library: {}", e))?;
+    Ok(file.patterns)
+}
+
+/// Upgrades a library document one version at a time to `SCHEMA_VERSION`.
+fn migrate(mut value: serde_json::Value, from: u32) -> serde_json::Value {
+    let mut version = from;
+    while version < SCHEMA_VERSION {
+        value = match version {
+            0 => serde_json::json!({ "version": 1, "patterns": value }),
+            _ => value,
+        };
+        version += 1;
+    }
+    value
+}
@@ -0,0 +1,66 @@
+use serde::{Serialize, Deserialize};
+use std::fmt;
+
+/// Sizes past which calls are refused rather than risk running the wasm
+/// instance out of memory partway through.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct Limits {
+    /// Largest text any call takes, in bytes: documents, text to test,
+    /// examples.
+    pub max_document_bytes: usize,
+    /// Most selections a pattern can be built from.
+    pub max_selections: usize,
+    /// Most patterns the library can hold.
+    pub max_patterns: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_document_bytes: 16 * 1024 * 1024,
+            max_selections: 1_000,
+            max_patterns: 10_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitExceeded {
+    /// Name of the `Limits` field that was exceeded.
+    pub limit: &'static str,
+    pub max: usize,
+    pub actual: usize,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.limit {
+            "max_document_bytes" => write!(f, "The text is {} bytes; the limit is {} bytes", self.actual, self.max),
+            "max_selections" => write!(f, "{} selections is more than the limit of {}", self.actual, self.max),
+            _ => write!(f, "The library would hold {} patterns; the limit is {}", self.actual, self.max),
+        }
+    }
+}
+
+fn check(limit: &'static str, max: usize, actual: usize) -> Result<(), LimitExceeded> {
+    if actual > max {
+        Err(LimitExceeded { limit, max, actual })
+    } else {
+        Ok(())
+    }
+}
+
+impl Limits {
+    pub fn check_text(&self, text: &str) -> Result<(), LimitExceeded> {
+        check("max_document_bytes", self.max_document_bytes, text.len())
+    }
+
+    pub fn check_selections(&self, count: usize) -> Result<(), LimitExceeded> {
+        check("max_selections", self.max_selections, count)
+    }
+
+    pub fn check_patterns(&self, count: usize) -> Result<(), LimitExceeded> {
+        check("max_patterns", self.max_patterns, count)
+    }
+}
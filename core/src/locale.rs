@@ -0,0 +1,144 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+
+/// Locale whose messages are built in.
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// English templates for every message explanations and match summaries
+/// use. `{name}` is replaced by the parameter of that name.
+const ENGLISH: &[(&str, &str)] = &[
+    ("sequence_separator", ", then "),
+    ("list_separator", ", "),
+    ("quoted", "'{text}'"),
+    ("sub_pattern", "({text})"),
+    ("all_of", "all of {patterns}"),
+    ("all_of_separator", " and "),
+    ("any_of", "any of {patterns}"),
+    ("any_of_separator", " or "),
+    ("none_of", "none of {patterns}"),
+    ("none_of_separator", " or "),
+    ("but_none_of", "{required} but none of {patterns}"),
+    ("phrase", "the phrase '{text}'"),
+    ("word", "the word '{text}'"),
+    ("word_case_insensitive", "{part} in any case"),
+    ("word_prefix", "{part} or a word starting with it"),
+    ("word_approximate", "{part} or a spelling one letter off"),
+    ("word_substring", "{part}, even inside a longer word"),
+    ("gap_any", "anything"),
+    ("gap_at_least", "at least {min} words"),
+    ("gap_exact", "exactly {count} words"),
+    ("gap_range", "{min}–{max} words"),
+    ("gap_chars", "up to {max_chars} characters"),
+    ("gap_excluded", "{part} not containing {excluded}"),
+    ("one_of", "one of ({options})"),
+    ("one_of_list", "one of the entries in list '{list}'"),
+    ("one_of_options_and_list", "one of ({options}) or the entries in list '{list}'"),
+    ("synonym_set", "a synonym from set '{set}'"),
+    ("token_class", "a {class}"),
+    ("token_class_number", "a number"),
+    ("token_class_date", "a date"),
+    ("token_class_email", "an email address"),
+    ("token_class_url", "a URL"),
+    ("token_class_ip_address", "an IP address"),
+    ("raw", "text matching the regex `{regex}`"),
+    ("reference", "whatever pattern {pattern_id} matches"),
+    ("repeat_exact", "{part} {count} times"),
+    ("repeat_range", "{part} {min}–{max} times"),
+    ("repeat_at_least", "{part} at least {min} times"),
+    ("optional", "optionally {part}"),
+    ("captured", "{part} (the field '{name}')"),
+    ("summary_no_match", "Pattern '{name}' did not match."),
+    ("summary_matched_once", "Pattern '{name}' matched once on line {line}."),
+    ("summary_matched_one_line", "Pattern '{name}' matched {count} times on line {line}."),
+    ("summary_matched", "Pattern '{name}' matched {count} times on lines {lines}."),
+    ("summary_more_lines", "{lines} and {more} more"),
+    ("summary_invalid", "Pattern '{name}' could not be tested: {error}."),
+];
+
+/// Message templates for one locale. Messages the catalog doesn't define
+/// fall back to English, so a partial translation is still usable.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MessageCatalog {
+    pub locale: String,
+    pub messages: BTreeMap<String, String>,
+}
+
+impl MessageCatalog {
+    /// The built-in catalog; every message comes from the English templates.
+    pub fn english() -> MessageCatalog {
+        MessageCatalog {
+            locale: DEFAULT_LOCALE.to_string(),
+            messages: BTreeMap::new(),
+        }
+    }
+
+    /// A catalog of host-supplied templates. Fails on keys no message
+    /// uses, which are almost always typos.
+    pub fn from_templates(locale: &str, messages: BTreeMap<String, String>) -> Result<MessageCatalog, String> {
+        if let Some(key) = messages.keys().find(|key| english_template(key).is_none()) {
+            return Err(format!("Unknown message key: {}", key));
+        }
+        Ok(MessageCatalog {
+            locale: locale.to_string(),
+            messages,
+        })
+    }
+
+    pub fn render(&self, key: &str, params: &[(&str, String)]) -> String {
+        let template = self
+            .messages
+            .get(key)
+            .map(String::as_str)
+            .or_else(|| english_template(key))
+            .unwrap_or(key);
+        fill(template, params)
+    }
+}
+
+/// Every message key with its English template, for translators.
+pub fn english_templates() -> BTreeMap<String, String> {
+    ENGLISH
+        .iter()
+        .map(|(key, template)| (key.to_string(), template.to_string()))
+        .collect()
+}
+
+fn english_template(key: &str) -> Option<&'static str> {
+    ENGLISH
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, template)| *template)
+}
+
+/// Replaces each `{name}` in `template` with its parameter; placeholders
+/// without a parameter are left as they are.
+fn fill(template: &str, params: &[(&str, String)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        match after.find('}').map(|close| (close, &after[..close])) {
+            Some((close, name)) => {
+                match params.iter().find(|(k, _)| *k == name) {
+                    Some((_, value)) => out.push_str(value),
+                    None => out.push_str(&rest[open..open + close + 2]),
+                }
+                rest = &after[close + 1..];
+            }
+            None => {
+                out.push_str(&rest[open..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The registered catalog for `locale`, trying the bare language
+/// ("pt-BR" falls back to "pt") before English.
+pub fn resolve<'a>(catalogs: &'a BTreeMap<String, MessageCatalog>, locale: &str) -> Option<&'a MessageCatalog> {
+    let language = locale.split(['-', '_']).next().unwrap_or(locale);
+    catalogs.get(locale).or_else(|| catalogs.get(language))
+}
@@ -0,0 +1,357 @@
+use serde::{Serialize, Deserialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::annotate::severity_rank;
+use crate::matching::{find_scoped, CompiledComposite, MatchMode};
+use crate::{FragmentCache, Pattern, RegexContext, RegexDialect, RegexGenError, Scope};
+
+/// All patterns of a library compiled once: a `RegexSet` to find which
+/// patterns match a text in a single pass, plus each pattern's own regex
+/// for locating matches.
+pub struct PatternMatcher {
+    /// Library position and ID of the patterns compiling to each regex of
+    /// the set.
+    ids: Vec<Vec<(usize, String)>>,
+    /// What `match_all` sorts and counts each pattern by, by library
+    /// position.
+    sort_keys: Vec<SortKey>,
+    set: regex::RegexSet,
+    regexes: HashMap<String, regex::Regex>,
+    /// Library position and ID of the composites without a regex, NOT
+    /// above all, searched operand by operand instead.
+    composites: Vec<((usize, String), CompiledComposite)>,
+    invalid: Vec<(String, String)>,
+    stats: MatcherStats,
+}
+
+struct SortKey {
+    /// The name, lowercased.
+    name: String,
+    /// The scope matches are counted in, as `test_pattern` finds them.
+    scope: Scope,
+    /// 0 for the most severe.
+    severity: u8,
+}
+
+/// The order `match_all` lists matching patterns in. Every order falls
+/// back to library order, so results only move when what they are sorted
+/// by changes.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchOrder {
+    /// The order of the library.
+    #[default]
+    PatternOrder,
+    /// By name, ignoring case.
+    Name,
+    /// Most matches in the text first.
+    MatchCount,
+    /// The pattern displayed with the highest severity (error, then
+    /// warning, then info, then none) first.
+    Severity,
+}
+
+impl FromStr for MatchOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "_").as_str() {
+            "pattern_order" | "order" => Ok(MatchOrder::PatternOrder),
+            "name" => Ok(MatchOrder::Name),
+            "match_count" | "count" => Ok(MatchOrder::MatchCount),
+            "severity" => Ok(MatchOrder::Severity),
+            _ => Err(format!("Unknown match order: {}", s)),
+        }
+    }
+}
+
+/// How much compiling a library shared between its patterns.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MatcherStats {
+    pub patterns: usize,
+    /// Regexes actually compiled; patterns with the same source share one.
+    pub distinct_regexes: usize,
+    /// `OneOf` and synonym alternations reused from another pattern
+    /// rather than built again.
+    pub shared_fragments: usize,
+}
+
+impl PatternMatcher {
+    /// Compiles every pattern. Alternations the patterns have in common
+    /// are built once, and patterns with the same regex share one compiled
+    /// copy, in the set as well. Regexes are those `test_pattern` compiles;
+    /// composites that have none are searched as `find_composite` searches
+    /// them. Patterns the engine still rejects are left out and reported
+    /// by `invalid`.
+    pub fn new(patterns: &[Pattern], context: &RegexContext) -> PatternMatcher {
+        let fragments = FragmentCache::default();
+        let context = RegexContext { fragments: Some(&fragments), ..context.clone() };
+        let mut ids: Vec<Vec<(usize, String)>> = Vec::new();
+        let mut sources: Vec<String> = Vec::new();
+        let mut compiled: HashMap<String, (usize, regex::Regex)> = HashMap::new();
+        let mut regexes = HashMap::new();
+        let mut composites = Vec::new();
+        let mut invalid = Vec::new();
+
+        for (position, pattern) in patterns.iter().enumerate() {
+            let id = pattern.get_id().to_string();
+            let source = match pattern.to_regex_for_with(RegexDialect::Rust, &context) {
+                Ok(source) => source,
+                Err(_) if matches!(pattern, Pattern::Composite { .. }) => {
+                    match CompiledComposite::new(pattern, &context) {
+                        Ok(composite) => composites.push(((position, id), composite)),
+                        Err(e) => invalid.push((id, e.to_string())),
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    invalid.push((id, e.to_string()));
+                    continue;
+                }
+            };
+            if let Some((index, re)) = compiled.get(&source) {
+                ids[*index].push((position, id.clone()));
+                regexes.insert(id, re.clone());
+                continue;
+            }
+            match regex::Regex::new(&source) {
+                Ok(re) => {
+                    compiled.insert(source.clone(), (sources.len(), re.clone()));
+                    ids.push(vec![(position, id.clone())]);
+                    sources.push(source);
+                    regexes.insert(id, re);
+                }
+                Err(e) => invalid.push((id, e.to_string())),
+            }
+        }
+
+        // Every source compiled individually, so the set compiles too
+        let set = regex::RegexSet::new(&sources).unwrap_or_else(|_| regex::RegexSet::empty());
+        let sort_keys = patterns
+            .iter()
+            .map(|pattern| SortKey {
+                name: pattern.get_name().to_lowercase(),
+                scope: pattern.meta().options.scope,
+                severity: severity_rank(pattern),
+            })
+            .collect();
+        let stats = MatcherStats { patterns: patterns.len(), distinct_regexes: sources.len(), shared_fragments: fragments.hits() };
+        PatternMatcher {
+            ids,
+            sort_keys,
+            set,
+            regexes,
+            composites,
+            invalid,
+            stats,
+        }
+    }
+
+    /// IDs of every pattern with at least one match in `text`, in `order`.
+    pub fn match_all(&self, text: &str, order: MatchOrder) -> Vec<&str> {
+        let mut matched: Vec<&(usize, String)> = self.set.matches(text).into_iter().flat_map(|i| &self.ids[i]).collect();
+        matched.extend(
+            self.composites
+                .iter()
+                .filter(|(_, composite)| !composite.find(text, MatchMode::Contains).is_empty())
+                .map(|(entry, _)| entry),
+        );
+        matched.sort_unstable_by_key(|(position, _)| *position);
+        match order {
+            MatchOrder::PatternOrder => {}
+            MatchOrder::Name => matched.sort_by(|(a, _), (b, _)| self.sort_keys[*a].name.cmp(&self.sort_keys[*b].name)),
+            MatchOrder::MatchCount => matched.sort_by_cached_key(|(position, id)| Reverse(self.match_count(*position, id, text))),
+            MatchOrder::Severity => matched.sort_by_key(|(position, _)| self.sort_keys[*position].severity),
+        }
+        matched.into_iter().map(|(_, id)| id.as_str()).collect()
+    }
+
+    /// Matches of the pattern at `position` in `text`, as `test_pattern`
+    /// finds them.
+    fn match_count(&self, position: usize, id: &str, text: &str) -> usize {
+        match self.regexes.get(id) {
+            Some(re) => find_scoped(re, text, self.sort_keys[position].scope).len(),
+            None => self
+                .composites
+                .iter()
+                .find(|((at, _), _)| *at == position)
+                .map_or(0, |(_, composite)| composite.find(text, MatchMode::Contains).len()),
+        }
+    }
+
+    pub fn stats(&self) -> &MatcherStats {
+        &self.stats
+    }
+
+    /// The compiled regex of a pattern; composites searched operand by
+    /// operand have none.
+    pub fn regex(&self, pattern_id: &str) -> Option<&regex::Regex> {
+        self.regexes.get(pattern_id)
+    }
+
+    /// Patterns that could not be compiled, with the compiler's message.
+    pub fn invalid(&self) -> &[(String, String)] {
+        &self.invalid
+    }
+}
+
+/// Compiled regexes of single patterns, keyed by pattern ID. Each entry
+/// remembers the source it was compiled from, so an edited pattern or a
+/// changed compile setting simply misses and recompiles, and patterns
+/// with the same source share one compiled regex.
+#[derive(Clone, Default)]
+pub struct RegexCache {
+    entries: HashMap<String, (String, regex::Regex)>,
+}
+
+impl RegexCache {
+    pub fn get_or_compile(&mut self, pattern: &Pattern, context: &RegexContext) -> Result<regex::Regex, RegexGenError> {
+        // `to_regex_checked_with`, compiling only on a miss
+        let source = pattern.to_regex_for_with(RegexDialect::Rust, context)?;
+        if let Some((cached, re)) = self.entries.get(pattern.get_id()) {
+            if *cached == source {
+                return Ok(re.clone());
+            }
+        }
+        // Another pattern with the same regex shares its compiled copy
+        let shared = self.entries.values().find(|(other, _)| *other == source).map(|(_, re)| re.clone());
+        let re = match shared {
+            Some(re) => re,
+            None => regex::Regex::new(&source)?,
+        };
+        self.entries
+            .insert(pattern.get_id().to_string(), (source, re.clone()));
+        Ok(re)
+    }
+
+    /// Drops the entry of an edited or deleted pattern.
+    pub fn remove(&mut self, pattern_id: &str) {
+        self.entries.remove(pattern_id);
+    }
+
+    /// Drops entries of patterns that are no longer in the library.
+    pub fn retain_patterns(&mut self, patterns: &[Pattern]) {
+        self.entries
+            .retain(|id, _| patterns.iter().any(|p| p.get_id() == id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MatchOrder, PatternMatcher};
+    use crate::{CompositeOperator, DisplayMeta, Pattern, PatternElement, RegexContext, Scope, Severity};
+
+    fn with_severity(mut pattern: Pattern, severity: Severity) -> Pattern {
+        pattern.meta_mut().display = Some(DisplayMeta { color_index: 0, label: None, icon: None, severity: Some(severity) });
+        pattern
+    }
+
+    fn word(id: &str, name: &str, text: &str) -> Pattern {
+        let mut pattern = Pattern::sequence(name, vec![PatternElement::word(text.to_string())]);
+        pattern.set_id(id.to_string());
+        pattern
+    }
+
+    fn matcher() -> PatternMatcher {
+        let cat = word("cat", "Cat", "cat");
+        let dog = word("dog", "dog", "dog");
+        let bird = word("bird", "Bird", "bird");
+        let also_dog = word("also_dog", "also dog", "dog");
+        PatternMatcher::new(&[cat, dog, bird, also_dog], &RegexContext::default())
+    }
+
+    const TEXT: &str = "bird dog bird cat bird dog";
+
+    #[test]
+    fn patterns_the_engine_rejects_are_left_out() {
+        let word = word("word", "alpha", "alpha");
+        let mut broken = Pattern::sequence("broken", vec![PatternElement::Raw { regex: "(".to_string() }]);
+        broken.set_id("broken".to_string());
+
+        let matcher = PatternMatcher::new(&[word.clone(), broken.clone()], &RegexContext::default());
+        assert_eq!(matcher.invalid().len(), 1);
+        assert_eq!(matcher.invalid()[0].0, broken.get_id());
+        assert!(matcher.regex(broken.get_id()).is_none());
+        assert_eq!(matcher.match_all("alpha and more", MatchOrder::PatternOrder), vec![word.get_id()]);
+        assert!(matcher.match_all("nothing here", MatchOrder::PatternOrder).is_empty());
+    }
+
+    #[test]
+    fn and_and_not_composites_are_matched() {
+        let both = Pattern::composite("both", CompositeOperator::And, vec![word("", "cat", "cat"), word("", "dog", "dog")]);
+        let cat_alone = Pattern::composite("cat alone", CompositeOperator::Not, vec![word("", "cat", "cat"), word("", "dog", "dog")]);
+        let no_bird = Pattern::composite("no bird", CompositeOperator::Not, vec![word("", "bird", "bird")]);
+        let patterns: Vec<Pattern> = [("both", both), ("cat_alone", cat_alone), ("no_bird", no_bird)]
+            .into_iter()
+            .map(|(id, mut pattern)| {
+                pattern.set_id(id.to_string());
+                pattern
+            })
+            .collect();
+        let matcher = PatternMatcher::new(&patterns, &RegexContext::default());
+
+        assert!(matcher.invalid().is_empty());
+        assert!(matcher.regex("both").is_some());
+        assert_eq!(matcher.match_all("dog and cat", MatchOrder::PatternOrder), vec!["both", "no_bird"]);
+        assert_eq!(matcher.match_all("a cat", MatchOrder::PatternOrder), vec!["cat_alone", "no_bird"]);
+        assert_eq!(matcher.match_all("a cat and a bird", MatchOrder::PatternOrder), vec!["cat_alone"]);
+        assert!(matcher.match_all("a bird", MatchOrder::PatternOrder).is_empty());
+    }
+
+    #[test]
+    fn composites_are_counted_per_matching_segment() {
+        let mut not_bird = Pattern::composite("not bird", CompositeOperator::Not, vec![word("", "cat", "cat"), word("", "bird", "bird")]);
+        not_bird.set_id("not_bird".to_string());
+        not_bird.meta_mut().options.scope = Scope::Line;
+        let dog = word("dog", "dog", "dog");
+        let matcher = PatternMatcher::new(&[dog, not_bird], &RegexContext::default());
+
+        // Three lines with a cat and no bird, against two dogs
+        let text = "cat\ncat dog\ncat bird\ncat dog";
+        assert_eq!(matcher.match_all(text, MatchOrder::MatchCount), vec!["not_bird", "dog"]);
+    }
+
+    #[test]
+    fn pattern_order_is_library_order() {
+        assert_eq!(matcher().match_all(TEXT, MatchOrder::PatternOrder), vec!["cat", "dog", "bird", "also_dog"]);
+        assert_eq!(matcher().match_all("dog", MatchOrder::PatternOrder), vec!["dog", "also_dog"]);
+        assert!(matcher().match_all("fish", MatchOrder::PatternOrder).is_empty());
+    }
+
+    #[test]
+    fn name_order_ignores_case() {
+        assert_eq!(matcher().match_all(TEXT, MatchOrder::Name), vec!["also_dog", "bird", "cat", "dog"]);
+    }
+
+    #[test]
+    fn match_count_order_keeps_library_order_for_ties() {
+        assert_eq!(matcher().match_all(TEXT, MatchOrder::MatchCount), vec!["bird", "dog", "also_dog", "cat"]);
+    }
+
+    #[test]
+    fn severity_order_puts_the_most_severe_first() {
+        let cat = with_severity(word("cat", "Cat", "cat"), Severity::Info);
+        let dog = with_severity(word("dog", "dog", "dog"), Severity::Error);
+        let bird = word("bird", "Bird", "bird");
+        let also_dog = with_severity(word("also_dog", "also dog", "dog"), Severity::Warning);
+        let matcher = PatternMatcher::new(&[cat, dog, bird, also_dog], &RegexContext::default());
+
+        assert_eq!(matcher.match_all(TEXT, MatchOrder::Severity), vec!["dog", "also_dog", "cat", "bird"]);
+        assert_eq!("severity".parse(), Ok(MatchOrder::Severity));
+    }
+
+    #[test]
+    fn match_count_counts_within_the_pattern_scope() {
+        // Sentences end at each '.', so "e.g" is never found inside one
+        let mut abbreviation = word("abbreviation", "abbreviation", "e.g");
+        abbreviation.meta_mut().options.scope = Scope::Sentence;
+        let x = word("x", "x", "x");
+        let matcher = PatternMatcher::new(&[abbreviation, x], &RegexContext::default());
+
+        assert_eq!(matcher.match_all("e.g e.g x", MatchOrder::MatchCount), vec!["x", "abbreviation"]);
+        assert_eq!("match-count".parse(), Ok(MatchOrder::MatchCount));
+        assert!("loudness".parse::<MatchOrder>().is_err());
+    }
+}
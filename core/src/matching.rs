@@ -0,0 +1,594 @@
+use serde::{Serialize, Deserialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use crate::{CompositeOperator, OffsetEncoding, Pattern, PatternElement, RegexContext, RegexDialect, RegexGenError, Scope, ELEMENT_GROUP_PREFIX};
+
+/// The part of a match contributed by one pattern element.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ElementMatch {
+    pub element_index: usize,
+    pub kind: String,
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+    /// The note on the element, if it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// One match of a pattern, with a per-element breakdown for sequences.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MatchResult {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+    pub elements: Vec<ElementMatch>,
+}
+
+/// Byte ranges of the matches of `regex` in `text`, searching each segment
+/// of `scope` on its own so no match crosses a line or sentence end.
+pub fn find_scoped(regex: &regex::Regex, text: &str, scope: Scope) -> Vec<(usize, usize)> {
+    scope
+        .segments(text)
+        .into_iter()
+        .flat_map(|(offset, segment)| regex.find_iter(segment).map(move |m| (offset + m.start(), offset + m.end())))
+        .collect()
+}
+
+/// What counts as the pattern matching a text, after how the regex will
+/// be used.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    /// Every match in the text, none overlapping, as a search finds them.
+    #[default]
+    Contains,
+    /// Only the whole text, as a validator checks a field.
+    FullMatch,
+    /// Each line with a match in it, as a line filter such as grep keeps.
+    PerLine,
+    /// A match from every position one starts at, including inside
+    /// another match.
+    Overlapping,
+}
+
+impl FromStr for MatchMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "_").as_str() {
+            "contains" | "search" => Ok(MatchMode::Contains),
+            "full_match" | "full" | "whole" => Ok(MatchMode::FullMatch),
+            "per_line" | "line" | "lines" => Ok(MatchMode::PerLine),
+            "overlapping" => Ok(MatchMode::Overlapping),
+            _ => Err(format!("Unknown match mode: {}", s)),
+        }
+    }
+}
+
+/// Byte ranges where `regex` matches `text` in `mode`: the matches
+/// themselves, the whole text if it matches entirely, or each matching
+/// line without its line break.
+pub fn find_in_mode(regex: &regex::Regex, text: &str, scope: Scope, mode: MatchMode) -> Result<Vec<(usize, usize)>, regex::Error> {
+    Ok(match mode {
+        MatchMode::Contains => find_scoped(regex, text, scope),
+        MatchMode::FullMatch => {
+            let anchored = regex::Regex::new(&format!(r"\A(?:{})\z", regex.as_str()))?;
+            if anchored.is_match(text) { vec![(0, text.len())] } else { Vec::new() }
+        }
+        MatchMode::PerLine => Scope::Line
+            .segments(text)
+            .into_iter()
+            .map(|(offset, line)| (offset, line.trim_end_matches(['\n', '\r'])))
+            .filter(|&(_, line)| !find_scoped(regex, line, scope).is_empty())
+            .map(|(offset, line)| (offset, offset + line.len()))
+            .collect(),
+        MatchMode::Overlapping => scope
+            .segments(text)
+            .into_iter()
+            .flat_map(|(offset, segment)| find_overlapping(regex, segment).into_iter().map(move |(start, end)| (offset + start, offset + end)))
+            .collect(),
+    })
+}
+
+/// The leftmost match starting at or after each position, moving one
+/// character past the start of the last.
+fn find_overlapping(regex: &regex::Regex, text: &str) -> Vec<(usize, usize)> {
+    let mut matches = Vec::new();
+    let mut at = 0;
+    while let Some(m) = regex.find_at(text, at) {
+        matches.push((m.start(), m.end()));
+        match text[m.start()..].chars().next() {
+            Some(c) => at = m.start() + c.len_utf8(),
+            None => break,
+        }
+    }
+    matches
+}
+
+/// Matches of an AND or NOT composite that `to_regex_checked` refuses,
+/// found by running its operands one at a time. Each segment of the
+/// composite's scope matches, as a whole, when every operand is found in
+/// it (AND), or the first is and no other (NOT), as the lookaround regex
+/// `to_regex` writes would match it.
+pub fn find_composite(pattern: &Pattern, text: &str, context: &RegexContext, mode: MatchMode) -> Result<Vec<(usize, usize)>, RegexGenError> {
+    Ok(CompiledComposite::new(pattern, context)?.find(text, mode))
+}
+
+/// A composite searched as `find_composite` searches it, with the regexes
+/// of its operands compiled once for many texts.
+pub struct CompiledComposite {
+    scope: Scope,
+    root: Operand,
+}
+
+enum Operand {
+    Regex(regex::Regex),
+    /// An operand with no regex of its own, run operand by operand too.
+    Composite { operator: CompositeOperator, operands: Vec<Operand> },
+}
+
+impl CompiledComposite {
+    pub fn new(pattern: &Pattern, context: &RegexContext) -> Result<CompiledComposite, RegexGenError> {
+        Ok(CompiledComposite { scope: pattern.meta().options.scope, root: Operand::new(pattern, context)? })
+    }
+
+    pub fn find(&self, text: &str, mode: MatchMode) -> Vec<(usize, usize)> {
+        let mut spans = Vec::new();
+        for (offset, segment) in self.scope.segments(text) {
+            let segment = segment.trim_end_matches(['\n', '\r']);
+            if self.root.is_match(segment) {
+                spans.push((offset, offset + segment.len()));
+            }
+        }
+        match mode {
+            MatchMode::Contains | MatchMode::Overlapping => spans,
+            MatchMode::FullMatch => spans.into_iter().filter(|&span| span == (0, text.len())).collect(),
+            MatchMode::PerLine => Scope::Line
+                .segments(text)
+                .into_iter()
+                .map(|(offset, line)| (offset, offset + line.trim_end_matches(['\n', '\r']).len()))
+                .filter(|&(start, end)| spans.iter().any(|&(s, e)| s <= end && e >= start))
+                .collect(),
+        }
+    }
+}
+
+impl Operand {
+    fn new(pattern: &Pattern, context: &RegexContext) -> Result<Operand, RegexGenError> {
+        let Pattern::Composite { operator, patterns, .. } = pattern else {
+            return Ok(Operand::Regex(regex::Regex::new(&pattern.to_regex_checked_with(context)?)?));
+        };
+        let flags = pattern.meta().options.flag_letters();
+        let operand_context = RegexContext { capture_elements: false, capture_fields: false, ..context.clone() };
+        let operands = patterns
+            .iter()
+            .map(|operand| match operand.to_regex_checked_with(&operand_context) {
+                // The composite's own flags hold for its operands too
+                Ok(source) if flags.is_empty() => Ok(Operand::Regex(regex::Regex::new(&source)?)),
+                Ok(source) => Ok(Operand::Regex(regex::Regex::new(&format!("(?{}:{})", flags, source))?)),
+                Err(RegexGenError::Unsupported(_)) if matches!(operand, Pattern::Composite { .. }) => {
+                    Operand::new(operand, &operand_context)
+                }
+                Err(e) => Err(e),
+            })
+            .collect::<Result<Vec<_>, RegexGenError>>()?;
+        Ok(Operand::Composite { operator: operator.clone(), operands })
+    }
+
+    /// Whether the operand is found in `segment`.
+    fn is_match(&self, segment: &str) -> bool {
+        let (operator, operands) = match self {
+            Operand::Regex(re) => return re.is_match(segment),
+            Operand::Composite { operator, operands } => (operator, operands),
+        };
+        match operator {
+            CompositeOperator::Or => operands.iter().any(|operand| operand.is_match(segment)),
+            CompositeOperator::And => !operands.is_empty() && operands.iter().all(|operand| operand.is_match(segment)),
+            CompositeOperator::Not => match operands.as_slice() {
+                [only] => !only.is_match(segment),
+                [required, excluded @ ..] => required.is_match(segment) && !excluded.iter().any(|operand| operand.is_match(segment)),
+                [] => false,
+            },
+        }
+    }
+}
+
+/// Finds all matches of `pattern` in `text`. Sequence patterns are compiled
+/// with a named group per element; composites only report whole matches,
+/// found with `find_composite` where they have no regex.
+pub fn find_detailed(pattern: &Pattern, context: &RegexContext, text: &str) -> Result<Vec<MatchResult>, RegexGenError> {
+    let capture_context = RegexContext {
+        capture_elements: true,
+        ..context.clone()
+    };
+    let source = match pattern.to_regex_for_with(RegexDialect::Rust, &capture_context) {
+        Ok(source) => source,
+        Err(_) if matches!(pattern, Pattern::Composite { .. }) => {
+            return Ok(find_composite(pattern, text, context, MatchMode::Contains)?
+                .into_iter()
+                .map(|(start, end)| MatchResult { start, end, text: text[start..end].to_string(), elements: Vec::new() })
+                .collect());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let re = regex::Regex::new(&source)?;
+    let template_elements = pattern.template_elements();
+    let kinds: Vec<(&str, Option<String>)> = match pattern {
+        Pattern::Sequence { elements, .. } => elements.iter().map(|e| (e.kind(), e.modifier().note)).collect(),
+        Pattern::Template { .. } => template_elements.iter().map(|e| (e.kind(), e.modifier().note)).collect(),
+        Pattern::Composite { .. } => Vec::new(),
+    };
+
+    let results = pattern
+        .meta().options
+        .scope
+        .segments(text)
+        .into_iter()
+        .flat_map(|(offset, segment)| re.captures_iter(segment).map(move |caps| (offset, caps)))
+        .filter_map(|(offset, caps)| {
+            let whole = caps.get(0)?;
+            let elements = kinds
+                .iter()
+                .enumerate()
+                .filter_map(|(i, (kind, note))| {
+                    let group = caps.name(&format!("{}{}", ELEMENT_GROUP_PREFIX, i))?;
+                    Some(ElementMatch {
+                        element_index: i,
+                        kind: kind.to_string(),
+                        start: offset + group.start(),
+                        end: offset + group.end(),
+                        text: group.as_str().to_string(),
+                        note: note.clone(),
+                    })
+                })
+                .collect();
+            Some(MatchResult {
+                start: offset + whole.start(),
+                end: offset + whole.end(),
+                text: whole.as_str().to_string(),
+                elements,
+            })
+        })
+        .collect();
+    Ok(results)
+}
+
+/// The fields of each match of `pattern` in `text`, by field name. Gaps
+/// own the separators around their words, which are trimmed off.
+pub fn extract_fields(pattern: &Pattern, context: &RegexContext, text: &str) -> Result<Vec<BTreeMap<String, String>>, RegexGenError> {
+    let capture_context = RegexContext {
+        capture_fields: true,
+        ..context.clone()
+    };
+    let re = regex::Regex::new(&pattern.to_regex_with(&capture_context)?)?;
+    let fields: Vec<(&str, bool)> = match pattern {
+        Pattern::Sequence { elements, .. } => pattern
+            .fields()
+            .into_iter()
+            .map(|(index, name)| (name, matches!(elements[index].base(), PatternElement::Gap { .. })))
+            .collect(),
+        Pattern::Template { .. } => pattern.fields().into_iter().map(|(_, name)| (name, false)).collect(),
+        Pattern::Composite { .. } => Vec::new(),
+    };
+
+    let records = pattern
+        .meta().options
+        .scope
+        .segments(text)
+        .into_iter()
+        .flat_map(|(_, segment)| re.captures_iter(segment))
+        .map(|caps| {
+            fields
+                .iter()
+                .filter_map(|&(name, is_gap)| {
+                    let value = caps.name(name)?.as_str();
+                    let value = if is_gap { value.trim_matches(|c: char| !c.is_alphanumeric() && c != '_') } else { value };
+                    Some((name.to_string(), value.to_string()))
+                })
+                .collect()
+        })
+        .collect();
+    Ok(records)
+}
+
+/// Rewrites the byte offsets of `find_detailed` results into `encoding`.
+pub fn encode_offsets(results: &mut [MatchResult], text: &str, encoding: OffsetEncoding) {
+    for result in results {
+        result.start = encoding.offset(text, result.start);
+        result.end = encoding.offset(text, result.end);
+        for element in &mut result.elements {
+            element.start = encoding.offset(text, element.start);
+            element.end = encoding.offset(text, element.end);
+        }
+    }
+}
+
+/// One match of `Pattern::matches`, borrowing from the searched text.
+/// Offsets are bytes into the whole text.
+#[derive(Clone, Debug)]
+pub struct Match<'t> {
+    pub start: usize,
+    pub end: usize,
+    pub text: &'t str,
+    /// The text of each field of the pattern that took part in the match,
+    /// by field name, trimmed as `extract_fields` trims it.
+    pub captures: BTreeMap<String, &'t str>,
+    /// What each element of a sequence or template contributed; empty for
+    /// composites.
+    pub elements: Vec<ElementMatch>,
+}
+
+impl Match<'_> {
+    pub fn span(&self) -> (usize, usize) {
+        (self.start, self.end)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.text
+    }
+}
+
+/// Entries `COMPILED` holds before it starts over.
+const MAX_COMPILED: usize = 256;
+
+thread_local! {
+    /// Regexes compiled for `Pattern::matches`, by source, so searching
+    /// with the same pattern again doesn't compile it again.
+    static COMPILED: RefCell<HashMap<String, regex::Regex>> = RefCell::new(HashMap::new());
+}
+
+fn compiled(source: &str) -> Result<regex::Regex, regex::Error> {
+    COMPILED.with(|compiled| {
+        if let Some(re) = compiled.borrow().get(source) {
+            return Ok(re.clone());
+        }
+        let re = regex::Regex::new(source)?;
+        let mut compiled = compiled.borrow_mut();
+        if compiled.len() >= MAX_COMPILED {
+            compiled.clear();
+        }
+        compiled.insert(source.to_string(), re.clone());
+        Ok(re)
+    })
+}
+
+impl Pattern {
+    /// The matches of the pattern in `text`, found as they are iterated,
+    /// with the fields and per-element breakdown of each. Lists and
+    /// synonym sets resolve to nothing; see `matches_with`. A pattern
+    /// that can't be searched for, such as one with an invalid regex
+    /// fragment, has no matches.
+    pub fn matches<'t>(&self, text: &'t str) -> impl Iterator<Item = Match<'t>> {
+        self.matches_with(text, &RegexContext::default()).into_iter().flatten()
+    }
+
+    /// `matches`, compiling the pattern in `context`, or the reason it
+    /// can't be searched for. Composites the `regex` crate can't express
+    /// are run as `find_composite` runs them, all at once, and report
+    /// only whole matches.
+    pub fn matches_with<'t>(&self, text: &'t str, context: &RegexContext) -> Result<impl Iterator<Item = Match<'t>>, RegexGenError> {
+        let capture_context = RegexContext {
+            capture_elements: true,
+            capture_fields: true,
+            ..context.clone()
+        };
+        let search = match self.to_regex_for_with(RegexDialect::Rust, &capture_context) {
+            Ok(source) => Search::Regex {
+                regex: compiled(&source)?,
+                segments: self.meta().options.scope.segments(text).into_iter(),
+                segment: None,
+                at: 0,
+                last_end: None,
+            },
+            Err(_) if matches!(self, Pattern::Composite { .. }) => {
+                Search::Spans(find_composite(self, text, context, MatchMode::Contains)?.into_iter())
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let template_elements = self.template_elements();
+        let kinds = match self {
+            Pattern::Sequence { elements, .. } => elements.iter().map(|e| (e.kind(), e.modifier().note)).collect(),
+            Pattern::Template { .. } => template_elements.iter().map(|e| (e.kind(), e.modifier().note)).collect(),
+            Pattern::Composite { .. } => Vec::new(),
+        };
+        let fields = match self {
+            Pattern::Sequence { elements, .. } => self
+                .fields()
+                .into_iter()
+                .map(|(index, name)| (name.to_string(), matches!(elements[index].base(), PatternElement::Gap { .. })))
+                .collect(),
+            Pattern::Template { .. } => self.fields().into_iter().map(|(_, name)| (name.to_string(), false)).collect(),
+            Pattern::Composite { .. } => Vec::new(),
+        };
+        Ok(Matches {
+            text,
+            search,
+            kinds: kinds.into_iter().map(|(kind, note)| (kind.to_string(), note)).collect(),
+            fields,
+        })
+    }
+}
+
+/// Where the next match of `Matches` comes from.
+enum Search<'t> {
+    /// Searching each segment of the pattern's scope in turn, from `at`
+    /// in the current one.
+    Regex {
+        regex: regex::Regex,
+        segments: std::vec::IntoIter<(usize, &'t str)>,
+        segment: Option<(usize, &'t str)>,
+        at: usize,
+        /// End of the last match in the current segment; an empty match
+        /// there is skipped, as `Regex::find_iter` skips it.
+        last_end: Option<usize>,
+    },
+    /// Whole matches found up front.
+    Spans(std::vec::IntoIter<(usize, usize)>),
+}
+
+struct Matches<'t> {
+    text: &'t str,
+    search: Search<'t>,
+    /// Kind and note of each element, in element order.
+    kinds: Vec<(String, Option<String>)>,
+    /// Name of each field, and whether it is a gap to trim.
+    fields: Vec<(String, bool)>,
+}
+
+impl<'t> Matches<'t> {
+    fn to_match(&self, offset: usize, caps: &regex::Captures<'t>) -> Option<Match<'t>> {
+        let whole = caps.get(0)?;
+        let elements = self
+            .kinds
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (kind, note))| {
+                let group = caps.name(&format!("{}{}", ELEMENT_GROUP_PREFIX, i))?;
+                Some(ElementMatch {
+                    element_index: i,
+                    kind: kind.clone(),
+                    start: offset + group.start(),
+                    end: offset + group.end(),
+                    text: group.as_str().to_string(),
+                    note: note.clone(),
+                })
+            })
+            .collect();
+        let captures = self
+            .fields
+            .iter()
+            .filter_map(|(name, is_gap)| {
+                let value = caps.name(name)?.as_str();
+                let value = if *is_gap { value.trim_matches(|c: char| !c.is_alphanumeric() && c != '_') } else { value };
+                Some((name.clone(), value))
+            })
+            .collect();
+        Some(Match {
+            start: offset + whole.start(),
+            end: offset + whole.end(),
+            text: whole.as_str(),
+            captures,
+            elements,
+        })
+    }
+}
+
+impl<'t> Iterator for Matches<'t> {
+    type Item = Match<'t>;
+
+    fn next(&mut self) -> Option<Match<'t>> {
+        let text = self.text;
+        let (regex, segments, segment, at, last_end) = match &mut self.search {
+            Search::Spans(spans) => {
+                let (start, end) = spans.next()?;
+                return Some(Match { start, end, text: &text[start..end], captures: BTreeMap::new(), elements: Vec::new() });
+            }
+            Search::Regex { regex, segments, segment, at, last_end } => (regex, segments, segment, at, last_end),
+        };
+        loop {
+            let (offset, current) = match *segment {
+                Some(current) if *at <= current.1.len() => current,
+                _ => {
+                    *segment = Some(segments.next()?);
+                    *at = 0;
+                    *last_end = None;
+                    continue;
+                }
+            };
+            let Some(caps) = regex.captures_at(current, *at) else {
+                *at = current.len() + 1;
+                continue;
+            };
+            let whole = caps.get(0)?;
+            if whole.is_empty() && Some(whole.end()) == *last_end {
+                *at = current[whole.end()..].chars().next().map_or(current.len() + 1, |c| whole.end() + c.len_utf8());
+                continue;
+            }
+            *at = whole.end();
+            *last_end = Some(whole.end());
+            return self.to_match(offset, &caps);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compiled, find_detailed, CompiledComposite, MatchMode, COMPILED, MAX_COMPILED};
+    use crate::{CompositeOperator, Pattern, PatternElement, RegexContext, Scope};
+
+    /// Tests get a thread each, but don't count on it.
+    fn start_empty() {
+        COMPILED.with(|compiled| compiled.borrow_mut().clear());
+    }
+
+    fn cached() -> usize {
+        COMPILED.with(|compiled| compiled.borrow().len())
+    }
+
+    fn is_cached(source: &str) -> bool {
+        COMPILED.with(|compiled| compiled.borrow().contains_key(source))
+    }
+
+    #[test]
+    fn searching_again_reuses_the_compiled_regex() {
+        start_empty();
+        let pattern = Pattern::sequence("word", vec![PatternElement::word("alpha".to_string())]);
+        assert_eq!(pattern.matches("alpha beta alpha").count(), 2);
+        assert_eq!(cached(), 1);
+        assert_eq!(pattern.matches("alpha").count(), 1);
+        assert_eq!(cached(), 1);
+    }
+
+    #[test]
+    fn the_cache_starts_over_past_its_bound() {
+        start_empty();
+        for i in 0..MAX_COMPILED {
+            compiled(&format!("a{}", i)).unwrap();
+        }
+        assert_eq!(cached(), MAX_COMPILED);
+        // Hits don't grow it
+        compiled("a0").unwrap();
+        assert_eq!(cached(), MAX_COMPILED);
+
+        compiled("overflow").unwrap();
+        assert_eq!(cached(), 1);
+        assert!(is_cached("overflow"));
+        assert!(!is_cached("a0"));
+    }
+
+    #[test]
+    fn invalid_sources_are_not_cached() {
+        start_empty();
+        assert!(compiled("(unclosed").is_err());
+        assert_eq!(cached(), 0);
+    }
+
+    fn cat_without_dog() -> Pattern {
+        let cat = Pattern::sequence("cat", vec![PatternElement::word("cat".to_string())]);
+        let dog = Pattern::sequence("dog", vec![PatternElement::word("dog".to_string())]);
+        let mut pattern = Pattern::composite("cat without dog", CompositeOperator::Not, vec![cat, dog]);
+        pattern.meta_mut().options.scope = Scope::Line;
+        pattern
+    }
+
+    #[test]
+    fn compiled_composites_match_each_segment_as_a_whole() {
+        let composite = CompiledComposite::new(&cat_without_dog(), &RegexContext::default()).unwrap();
+        let text = "a cat\na cat and a dog\nno cat here";
+        assert_eq!(composite.find(text, MatchMode::Contains), vec![(0, 5), (22, 33)]);
+        assert_eq!(composite.find(text, MatchMode::PerLine), vec![(0, 5), (22, 33)]);
+        assert!(composite.find(text, MatchMode::FullMatch).is_empty());
+        assert_eq!(composite.find("a cat", MatchMode::FullMatch), vec![(0, 5)]);
+    }
+
+    #[test]
+    fn detailed_matches_of_composites_without_a_regex_are_whole() {
+        let results = find_detailed(&cat_without_dog(), &RegexContext::default(), "a cat\na dog and a cat").unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!((results[0].start, results[0].end, results[0].text.as_str()), (0, 5, "a cat"));
+        assert!(results[0].elements.is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{Serialize, Deserialize};
+
+use crate::pack::PackSource;
+use crate::Pattern;
+
+/// Who made a pattern, when, and how many times it has been edited since.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PatternMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Milliseconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<f64>,
+    /// Milliseconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<f64>,
+    /// 1 once saved, counting up with every saved edit; 0 for patterns
+    /// from before metadata was kept.
+    #[serde(default)]
+    pub revision: u32,
+    /// The pattern pack it was installed from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack: Option<PackSource>,
+}
+
+impl PatternMetadata {
+    pub fn is_unset(&self) -> bool {
+        *self == PatternMetadata::default()
+    }
+}
+
+/// Brings the metadata of `patterns` up to date with the library as it
+/// was at the last save, `base`. A pattern new to the library is created
+/// now by `author`, unless it comes with metadata of its own (imported
+/// from a shared library, say). One whose content differs from its
+/// `base` version gets the next revision; one whose metadata alone was
+/// replaced keeps its creation time and revision from `base`, so
+/// revisions never go back.
+///
+/// Stamping again before `base` moves on gives the same revisions, so a
+/// transaction saving the same pattern several times counts one edit.
+pub fn stamp(base: &[Pattern], patterns: &mut [Pattern], author: Option<&str>, now: f64) {
+    for pattern in patterns {
+        let previous = base.iter().find(|p| p.get_id() == pattern.get_id());
+        let changed = previous.is_none_or(|previous| content(previous) != content(pattern));
+        let metadata = &mut pattern.meta_mut().metadata;
+        match previous.map(|previous| &previous.meta().metadata) {
+            None if metadata.revision == 0 => {
+                metadata.author = metadata.author.take().or_else(|| author.map(String::from));
+                metadata.created_at = Some(now);
+                metadata.updated_at = Some(now);
+                metadata.revision = 1;
+            }
+            None => {}
+            Some(previous) => {
+                metadata.created_at = metadata.created_at.or(previous.created_at);
+                if changed {
+                    metadata.updated_at = Some(now);
+                    metadata.revision = previous.revision + 1;
+                } else {
+                    metadata.updated_at = metadata.updated_at.or(previous.updated_at);
+                    metadata.revision = metadata.revision.max(previous.revision);
+                }
+            }
+        }
+    }
+}
+
+/// The pattern as JSON without its metadata, to tell edits apart from
+/// restamps.
+fn content(pattern: &Pattern) -> serde_json::Value {
+    let mut value = serde_json::to_value(pattern).unwrap_or_default();
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("metadata");
+    }
+    value
+}
@@ -0,0 +1,104 @@
+use serde::{Serialize, Deserialize};
+
+use crate::dialect::RegexDialect;
+
+/// Longest note an element can carry, in characters.
+pub const MAX_NOTE_CHARS: usize = 280;
+
+fn one() -> u32 {
+    1
+}
+
+fn one_max() -> Option<u32> {
+    Some(1)
+}
+
+/// Makes an element optional and/or repeated, e.g. "please" may be left
+/// out, "very" may appear 1–3 times. Repetitions are separated like
+/// adjacent words. It can also name the element as a field to extract
+/// and carry a note on what the element is for.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ElementModifier {
+    #[serde(default)]
+    pub optional: bool,
+    #[serde(default = "one")]
+    pub min_repeat: u32,
+    /// `None` repeats without upper limit.
+    #[serde(default = "one_max")]
+    pub max_repeat: Option<u32>,
+    /// Field name the element's text is extracted under.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capture: Option<String>,
+    /// e.g. "this gap covers the optional thread-id field".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl Default for ElementModifier {
+    fn default() -> Self {
+        ElementModifier {
+            optional: false,
+            min_repeat: 1,
+            max_repeat: Some(1),
+            capture: None,
+            note: None,
+        }
+    }
+}
+
+impl ElementModifier {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_repeat == 0 {
+            return Err(String::from("min_repeat must be at least 1; use optional for zero occurrences"));
+        }
+        if self.max_repeat.is_some_and(|max| max < self.min_repeat) {
+            return Err(String::from("max_repeat must not be less than min_repeat"));
+        }
+        Ok(())
+    }
+
+    /// Field names become regex group names, so they follow the same rules.
+    pub fn validate_capture(name: &str) -> Result<(), String> {
+        let mut chars = name.chars();
+        let starts_well = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+        if !starts_well || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!(
+                "Invalid field name '{}': use letters, digits and '_', not starting with a digit",
+                name
+            ));
+        }
+        Ok(())
+    }
+
+    /// A note as stored: trimmed, `None` if blank.
+    pub fn normalize_note(note: Option<String>) -> Result<Option<String>, String> {
+        let Some(note) = note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()) else {
+            return Ok(None);
+        };
+        let chars = note.chars().count();
+        if chars > MAX_NOTE_CHARS {
+            return Err(format!("The note is {} characters; keep it under {}", chars, MAX_NOTE_CHARS));
+        }
+        Ok(Some(note))
+    }
+
+    pub fn is_repeated(&self) -> bool {
+        self.min_repeat != 1 || self.max_repeat != Some(1)
+    }
+
+    /// `body` repeated `min_repeat` to `max_repeat` times, separated by
+    /// non-word characters. Optionality is left to the caller, which knows
+    /// the neighbouring separators.
+    pub fn repeat(&self, body: &str, separator: &str, dialect: RegexDialect) -> String {
+        if !self.is_repeated() {
+            return body.to_string();
+        }
+        let again = dialect.group(&format!("{}{}", separator, body));
+        let min = self.min_repeat - 1;
+        match self.max_repeat {
+            Some(max) => format!("{}{}{{{},{}}}", body, again, min, max - 1),
+            None if min == 0 => format!("{}{}*", body, again),
+            None => format!("{}{}{{{},}}", body, again, min),
+        }
+    }
+}
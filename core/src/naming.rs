@@ -0,0 +1,87 @@
+use crate::Pattern;
+
+/// Everything a name template can refer to for one pattern.
+pub struct NameFields<'a> {
+    /// 1-based position among the patterns being renamed.
+    pub index: usize,
+    pub pattern: &'a Pattern,
+}
+
+/// Renders `template` for one pattern. Placeholders are `{index}` (or
+/// `{index:N}`, zero-padded to N digits), `{name}` (the current name),
+/// `{id}`, `{tag}` (the first tag), `{folder}` (the last segment of the
+/// folder) and `{created}` (the date of the pattern's first recorded
+/// version, `YYYY-MM-DD`). Missing values render as empty.
+/// `{{` and `}}` stand for literal braces.
+pub fn render(template: &str, fields: &NameFields) -> Result<String, String> {
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(at) = rest.find(['{', '}']) {
+        name.push_str(&rest[..at]);
+        let brace = &rest[at..at + 1];
+        rest = &rest[at + 1..];
+        if let Some(after) = rest.strip_prefix(brace) {
+            name.push_str(brace);
+            rest = after;
+            continue;
+        }
+        if brace == "}" {
+            return Err(String::from("Unmatched `}` in name template; write `}}` for a literal brace"));
+        }
+        let end = rest
+            .find('}')
+            .ok_or_else(|| String::from("Unclosed `{` in name template; write `{{` for a literal brace"))?;
+        name.push_str(&placeholder(&rest[..end], fields)?);
+        rest = &rest[end + 1..];
+    }
+    name.push_str(rest);
+    Ok(name.trim().to_string())
+}
+
+fn placeholder(spec: &str, fields: &NameFields) -> Result<String, String> {
+    let (key, width) = match spec.split_once(':') {
+        Some((key, width)) => {
+            let width: usize = width
+                .parse()
+                .map_err(|_| format!("`{{{}}}` needs a number of digits after the colon", spec))?;
+            (key, Some(width))
+        }
+        None => (spec, None),
+    };
+    match (key.trim(), width) {
+        ("index", width) => Ok(format!("{:0width$}", fields.index, width = width.unwrap_or(0))),
+        ("name", None) => Ok(fields.pattern.get_name().to_string()),
+        ("id", None) => Ok(fields.pattern.get_id().to_string()),
+        ("tag", None) => Ok(fields.pattern.meta().tags.first().cloned().unwrap_or_default()),
+        ("folder", None) => Ok(fields
+            .pattern
+            .meta().folder.as_deref()
+            .and_then(|folder| folder.rsplit('/').next())
+            .unwrap_or_default()
+            .to_string()),
+        ("created", None) => Ok(fields
+            .pattern
+            .meta().history
+            .first()
+            .map(|entry| date(entry.timestamp))
+            .unwrap_or_default()),
+        (key @ ("name" | "id" | "tag" | "folder" | "created"), Some(_)) => Err(format!("`{{{}}}` takes no width", key)),
+        (key, _) => Err(format!("Unknown placeholder in name template: {{{}}}", key)),
+    }
+}
+
+/// `YYYY-MM-DD` (UTC) for milliseconds since the Unix epoch.
+fn date(timestamp: f64) -> String {
+    let days = (timestamp / 86_400_000.0).floor() as i64;
+    // Civil-from-days, counting in 400-year eras from 0000-03-01
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
@@ -0,0 +1,269 @@
+use regex_syntax::ast::{self, Ast};
+
+use crate::{DialectError, Pattern, RegexContext};
+
+impl Pattern {
+    /// `to_regex` with the output simplified: redundant non-capturing
+    /// groups removed, repeated word boundaries collapsed and alternatives
+    /// factored by their common prefix. Matches and capture groups are the
+    /// same as the unoptimized regex. Lookaround from AND and NOT cannot be
+    /// parsed here, so a regex using it is returned as is.
+    pub fn to_regex_optimized(&self) -> Result<String, DialectError> {
+        self.to_regex_optimized_with(&RegexContext::default())
+    }
+
+    pub fn to_regex_optimized_with(&self, context: &RegexContext) -> Result<String, DialectError> {
+        let regex = self.to_regex_with(context)?;
+        Ok(optimize(&regex).unwrap_or(regex))
+    }
+}
+
+/// Simplifies a regex in the Rust dialect. Only non-capturing groups
+/// without flags are touched, so group numbers and flag scopes are kept.
+pub fn optimize(regex: &str) -> Result<String, String> {
+    let mut ast = ast::parse::Parser::new().parse(regex).map_err(|e| e.to_string())?;
+    simplify(&mut ast, Place::Free);
+    Ok(print(&ast))
+}
+
+/// Where a node sits, which decides whether a group around it can go.
+#[derive(Clone, Copy, PartialEq)]
+enum Place {
+    /// The whole regex, a group body or an alternative.
+    Free,
+    /// An item of a concatenation.
+    Concat,
+    /// The operand of a repetition.
+    Repeated,
+}
+
+fn simplify(ast: &mut Ast, place: Place) {
+    let span = *ast.span();
+    match ast {
+        Ast::Group(group) => {
+            simplify(&mut group.ast, Place::Free);
+            let plain = matches!(&group.kind, ast::GroupKind::NonCapturing(flags) if flags.items.is_empty());
+            if plain && !sets_flags(&group.ast) && can_unwrap(&group.ast, place) {
+                *ast = std::mem::replace(&mut *group.ast, Ast::empty(span));
+            }
+        }
+        Ast::Repetition(repetition) => simplify(&mut repetition.ast, Place::Repeated),
+        Ast::Concat(concat) => {
+            let mut asts: Vec<Ast> = Vec::with_capacity(concat.asts.len());
+            for mut item in std::mem::take(&mut concat.asts) {
+                simplify(&mut item, Place::Concat);
+                for item in items(item) {
+                    // Zero-width assertions are idempotent, so `\b\b` is `\b`.
+                    let repeated = matches!((asts.last(), &item), (Some(Ast::Assertion(a)), Ast::Assertion(b)) if a.kind == b.kind);
+                    if !repeated {
+                        asts.push(item);
+                    }
+                }
+            }
+            *ast = ast::Concat { span, asts }.into_ast();
+        }
+        Ast::Alternation(alternation) => {
+            let mut branches = Vec::with_capacity(alternation.asts.len());
+            for mut branch in std::mem::take(&mut alternation.asts) {
+                simplify(&mut branch, Place::Free);
+                match &mut branch {
+                    Ast::Alternation(inner) => branches.append(&mut inner.asts),
+                    _ => branches.push(branch),
+                }
+            }
+            // A `(?flags)` in one alternative carries on into the next, so
+            // moving alternatives into a new group would cut its scope short.
+            *ast = if branches.iter().any(sets_flags) {
+                ast::Alternation { span, asts: branches }.into_ast()
+            } else {
+                factor(branches, span)
+            };
+        }
+        _ => {}
+    }
+}
+
+/// Whether the body of a plain group can stand on its own at `place`.
+fn can_unwrap(inner: &Ast, place: Place) -> bool {
+    match place {
+        Place::Free => true,
+        Place::Concat => !matches!(inner, Ast::Alternation(_)),
+        Place::Repeated => matches!(
+            inner,
+            Ast::Literal(_) | Ast::Dot(_) | Ast::ClassUnicode(_) | Ast::ClassPerl(_) | Ast::ClassBracketed(_) | Ast::Group(_)
+        ),
+    }
+}
+
+/// Whether `ast` contains a `(?flags)` directive that would leak out of a
+/// group that was removed around it.
+fn sets_flags(ast: &Ast) -> bool {
+    match ast {
+        Ast::Flags(_) => true,
+        Ast::Concat(concat) => concat.asts.iter().any(sets_flags),
+        Ast::Alternation(alternation) => alternation.asts.iter().any(sets_flags),
+        _ => false,
+    }
+}
+
+/// Merges runs of adjacent alternatives starting with the same items, so
+/// `cat|car|dog` becomes `ca(?:t|r)|dog`. Only single-character items are
+/// shared: they match one way, so trying `ca` once and then each suffix in
+/// order matches exactly what the alternatives did. Runs are never
+/// reordered, which keeps leftmost-first preference intact.
+fn factor(branches: Vec<Ast>, span: ast::Span) -> Ast {
+    let mut sequences: Vec<Vec<Ast>> = branches.into_iter().map(items).collect();
+    let mut result = Vec::new();
+    while !sequences.is_empty() {
+        let first = sequences[0].first().filter(|item| shareable(item)).map(print);
+        let run = match first {
+            Some(first) => sequences
+                .iter()
+                .take_while(|sequence| sequence.first().map(print).as_ref() == Some(&first))
+                .count(),
+            None => 1,
+        };
+        let mut group: Vec<Vec<Ast>> = sequences.drain(..run).collect();
+        if run < 2 {
+            result.push(sequence_ast(group.remove(0), span));
+            continue;
+        }
+
+        let mut shared = 1;
+        while group.iter().all(|sequence| sequence.len() > shared && shareable(&sequence[shared]))
+            && group.iter().all(|sequence| print(&sequence[shared]) == print(&group[0][shared]))
+        {
+            shared += 1;
+        }
+        let mut prefix: Vec<Ast> = group[0].drain(..shared).collect();
+        let suffixes = group
+            .into_iter()
+            .enumerate()
+            .map(|(i, sequence)| {
+                let suffix = if i == 0 { sequence } else { sequence.into_iter().skip(shared).collect() };
+                sequence_ast(suffix, span)
+            })
+            .collect();
+        prefix.push(Ast::group(ast::Group {
+            span,
+            kind: ast::GroupKind::NonCapturing(ast::Flags { span, items: Vec::new() }),
+            ast: Box::new(factor(suffixes, span)),
+        }));
+        result.push(sequence_ast(prefix, span));
+    }
+    ast::Alternation { span, asts: result }.into_ast()
+}
+
+fn items(mut ast: Ast) -> Vec<Ast> {
+    match &mut ast {
+        Ast::Concat(concat) => std::mem::take(&mut concat.asts),
+        Ast::Empty(_) => Vec::new(),
+        _ => vec![ast],
+    }
+}
+
+fn sequence_ast(items: Vec<Ast>, span: ast::Span) -> Ast {
+    if items.is_empty() {
+        return Ast::empty(span);
+    }
+    ast::Concat { span, asts: items }.into_ast()
+}
+
+/// Items that can be pulled out of alternatives: a single character,
+/// class or assertion, never anything holding a capture group.
+fn shareable(ast: &Ast) -> bool {
+    matches!(
+        ast,
+        Ast::Literal(_) | Ast::Dot(_) | Ast::Assertion(_) | Ast::ClassUnicode(_) | Ast::ClassPerl(_) | Ast::ClassBracketed(_)
+    )
+}
+
+fn print(ast: &Ast) -> String {
+    let mut out = String::new();
+    // Writing to a String cannot fail.
+    let _ = ast::print::Printer::new().print(ast, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::optimize;
+    use crate::{CompositeOperator, Pattern, PatternElement, TokenClass};
+
+    const TEXTS: [&str; 8] = [
+        "cat car dog",
+        "the cart is scary",
+        "CAT Car",
+        "error 42 on disk failed",
+        "error: network 7.5 failed later",
+        "abcabc xyz",
+        "",
+        "carcat dogcar",
+    ];
+
+    /// Every match of both regexes in each text, with its capture groups.
+    fn assert_equivalent(original: &str, optimized: &str) {
+        let a = regex::Regex::new(original).unwrap();
+        let b = regex::Regex::new(optimized).unwrap();
+        assert_eq!(a.captures_len(), b.captures_len(), "{} / {}", original, optimized);
+        for text in TEXTS {
+            let spans = |re: &regex::Regex| -> Vec<Vec<Option<(usize, usize)>>> {
+                re.captures_iter(text)
+                    .map(|caps| caps.iter().map(|m| m.map(|m| (m.start(), m.end()))).collect())
+                    .collect()
+            };
+            assert_eq!(spans(&a), spans(&b), "{} / {} on {:?}", original, optimized, text);
+        }
+    }
+
+    #[test]
+    fn simplified_regexes_match_the_same() {
+        let regexes = [
+            r"(?:a)(?:b)c",
+            r"(?:cat|car|dog)",
+            r"\b(?:cat|car)\b\W+(?:dog)",
+            r"\b\b(?:ca(?:t|r))\b\b",
+            r"(?:abc)+ (?:x)?yz",
+            r"(?:a|b)c",
+            r"(car|cat)(?:\s+)(dog)?",
+            r"(?i)(?:cat|car)",
+            r"(?:(?i)ca)t|car",
+            r"(?:[a-c])+",
+            r"cart|car|c",
+        ];
+        for regex in regexes {
+            assert_equivalent(regex, &optimize(regex).unwrap());
+        }
+    }
+
+    #[test]
+    fn simplifies_what_it_can() {
+        assert_eq!(optimize(r"(?:a)(?:b)c").unwrap(), "abc");
+        assert_eq!(optimize(r"cat|car|dog").unwrap(), "ca(?:t|r)|dog");
+        assert_eq!(optimize(r"\b\bcat\b").unwrap(), r"\bcat\b");
+        // Groups with flags or captures, and alternations inside a
+        // concatenation, stay
+        assert_eq!(optimize(r"(?i:a)(b)").unwrap(), r"(?i:a)(b)");
+        assert_eq!(optimize(r"(?:a|b)c").unwrap(), r"(?:a|b)c");
+    }
+
+    #[test]
+    fn optimized_patterns_match_the_same() {
+        let sequence = Pattern::sequence(
+            "sample",
+            vec![
+                PatternElement::word("error".to_string()),
+                PatternElement::gap(0, Some(2)),
+                PatternElement::one_of(vec!["disk".to_string(), "network".to_string()]),
+                PatternElement::gap(0, None),
+                PatternElement::word("failed".to_string()),
+            ],
+        );
+        let number = Pattern::sequence("number", vec![PatternElement::TokenClass { class: TokenClass::Number }]);
+        let mut either = Pattern::composite("either", CompositeOperator::Or, vec![sequence.clone(), number]);
+        either.meta_mut().options.anchor_start = true;
+        for pattern in [sequence, either] {
+            assert_equivalent(&pattern.to_regex().unwrap(), &pattern.to_regex_optimized().unwrap());
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Ranking information for one `OneOf` option.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OptionStat {
+    pub option: String,
+    pub weight: u32,
+    pub hits: u64,
+}
+
+/// All options of a `OneOf` element, highest weight first, then most hits.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OneOfStats {
+    pub element_index: usize,
+    pub options: Vec<OptionStat>,
+}
+
+/// Per-option hit counters, keyed by pattern id and element index. These
+/// live only as long as the builder; weights are what gets persisted.
+#[derive(Clone, Default)]
+pub struct OptionHits {
+    counts: HashMap<(String, usize), BTreeMap<String, u64>>,
+}
+
+impl OptionHits {
+    /// Counts which options of a `OneOf` occur inside the texts of a
+    /// pattern's matches.
+    pub fn record(&mut self, pattern_id: &str, element_index: usize, options: &[String], case_insensitive: bool, matched: &[&str]) {
+        let Some(finder) = option_finder(options, case_insensitive) else {
+            return;
+        };
+        let counts = self
+            .counts
+            .entry((pattern_id.to_string(), element_index))
+            .or_default();
+        for found in matched.iter().flat_map(|text| finder.find_iter(text)) {
+            let found = found.as_str();
+            let option = options.iter().find(|opt| {
+                if case_insensitive {
+                    opt.to_lowercase() == found.to_lowercase()
+                } else {
+                    opt.as_str() == found
+                }
+            });
+            if let Some(option) = option {
+                *counts.entry(option.clone()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    pub fn get(&self, pattern_id: &str, element_index: usize, option: &str) -> u64 {
+        self.counts
+            .get(&(pattern_id.to_string(), element_index))
+            .and_then(|counts| counts.get(option))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn clear_pattern(&mut self, pattern_id: &str) {
+        self.counts.retain(|(id, _), _| id != pattern_id);
+    }
+}
+
+fn option_finder(options: &[String], case_insensitive: bool) -> Option<regex::Regex> {
+    if options.is_empty() {
+        return None;
+    }
+    // Longest first so "foo bar" wins over "foo" at the same position.
+    let mut sorted: Vec<&String> = options.iter().collect();
+    sorted.sort_by_key(|opt| std::cmp::Reverse(opt.len()));
+    let escaped: Vec<String> = sorted.iter().map(|opt| regex::escape(opt)).collect();
+    let flags = if case_insensitive { "(?i)" } else { "" };
+    regex::Regex::new(&format!(r"{}\b(?:{})\b", flags, escaped.join("|"))).ok()
+}
+
+/// Orders options for display as ranked suggestions.
+pub fn rank(mut stats: Vec<OptionStat>) -> Vec<OptionStat> {
+    stats.sort_by(|a, b| b.weight.cmp(&a.weight).then(b.hits.cmp(&a.hits)));
+    stats
+}
@@ -0,0 +1,208 @@
+use serde::{Serialize, Deserialize};
+use std::str::FromStr;
+
+use crate::dialect::{DialectError, RegexDialect};
+
+/// Matching behaviour stored with each pattern and compiled into inline
+/// flags and anchors.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MatchOptions {
+    #[serde(default)]
+    pub case_insensitive: bool,
+    /// `^` and `$` match at line boundaries instead of only at the ends.
+    #[serde(default)]
+    pub multi_line: bool,
+    /// `.` also matches newlines, so gaps can span lines.
+    #[serde(default)]
+    pub dot_all: bool,
+    #[serde(default)]
+    pub anchor_start: bool,
+    #[serde(default)]
+    pub anchor_end: bool,
+    #[serde(default)]
+    pub scope: Scope,
+}
+
+/// How far gaps and the parts of an AND may reach through the text.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// Anywhere in the text, subject to `dot_all`.
+    #[default]
+    Document,
+    /// Within one line.
+    Line,
+    /// Within one sentence, which ends at `.`, `!`, `?` or a line break.
+    Sentence,
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "document" => Ok(Scope::Document),
+            "line" => Ok(Scope::Line),
+            "sentence" => Ok(Scope::Sentence),
+            _ => Err(format!("Unknown scope: {}", s)),
+        }
+    }
+}
+
+impl Scope {
+    /// Characters that end the scope, as bracket expression contents.
+    /// POSIX brackets have no escapes, so they get the characters themselves.
+    fn boundaries(self, dialect: RegexDialect) -> &'static str {
+        match (self, dialect == RegexDialect::PosixEre) {
+            (Scope::Document, _) => "",
+            (Scope::Line, false) => r"\r\n",
+            (Scope::Line, true) => "\r\n",
+            (Scope::Sentence, false) => r".!?\r\n",
+            (Scope::Sentence, true) => ".!?\r\n",
+        }
+    }
+
+    /// Any one character that stays in the scope.
+    pub fn any_char(self, dialect: RegexDialect) -> String {
+        match self {
+            Scope::Document => String::from("."),
+            _ => format!("[^{}]", self.boundaries(dialect)),
+        }
+    }
+
+    /// Any run of characters within the scope, as short as possible where
+    /// supported.
+    pub fn lazy_any(self, dialect: RegexDialect) -> String {
+        match self {
+            Scope::Document => dialect.lazy_any().to_string(),
+            _ => format!("{}*{}", self.any_char(dialect), if dialect.supports_lazy() { "?" } else { "" }),
+        }
+    }
+
+    /// The run of non-word characters between two words in the scope.
+    pub fn separator(self, dialect: RegexDialect) -> String {
+        match (self, dialect == RegexDialect::PosixEre) {
+            (Scope::Document, _) => format!("{}+", dialect.non_word_char()),
+            (_, false) => format!(r"[^\w{}]+", self.boundaries(dialect)),
+            (_, true) => format!("[^[:alnum:]_{}]+", self.boundaries(dialect)),
+        }
+    }
+
+    /// Splits `text` into the pieces a match must stay within, with the
+    /// byte offset of each. Sentence ends stay with their sentence.
+    pub fn segments(self, text: &str) -> Vec<(usize, &str)> {
+        let ends: &[char] = match self {
+            Scope::Document => return vec![(0, text)],
+            Scope::Line => &['\n'],
+            Scope::Sentence => &['.', '!', '?', '\n'],
+        };
+        let mut segments = Vec::new();
+        let mut start = 0;
+        for (i, c) in text.char_indices() {
+            if ends.contains(&c) {
+                segments.push((start, &text[start..i + 1]));
+                start = i + 1;
+            }
+        }
+        segments.push((start, &text[start..]));
+        segments
+    }
+}
+
+impl MatchOptions {
+    /// The flag letters in effect, e.g. `"im"`.
+    pub fn flag_letters(&self) -> String {
+        let mut flags = String::new();
+        if self.case_insensitive {
+            flags.push('i');
+        }
+        if self.multi_line {
+            flags.push('m');
+        }
+        if self.dot_all {
+            flags.push('s');
+        }
+        flags
+    }
+
+    /// Wraps a compiled pattern body with the flags and anchors. JavaScript
+    /// only has scoped modifier groups (`(?i:...)`), POSIX ERE has neither.
+    /// An anchored body is grouped first, so anchors hold for every
+    /// branch of a top-level alternation rather than the first and last.
+    pub fn apply(&self, body: &str, dialect: RegexDialect) -> Result<String, DialectError> {
+        let flags = self.flag_letters();
+        let start = if self.anchor_start { "^" } else { "" };
+        let end = if self.anchor_end { "$" } else { "" };
+        let grouped;
+        let body = if self.anchor_start || self.anchor_end {
+            grouped = dialect.group(body);
+            grouped.as_str()
+        } else {
+            body
+        };
+
+        if flags.is_empty() {
+            return Ok(format!("{}{}{}", start, body, end));
+        }
+        match dialect {
+            RegexDialect::PosixEre => Err(dialect.unsupported(&format!("inline flags ({})", flags))),
+            RegexDialect::JavaScript => Ok(format!("(?{}:{}{}{})", flags, start, body, end)),
+            _ => Ok(format!("(?{}){}{}{}", flags, start, body, end)),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Overrides for a single `OneOf` option, so acronyms can stay
+/// case-sensitive next to options that aren't.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct OptionFlags {
+    #[serde(default)]
+    pub case_insensitive: bool,
+    #[serde(default = "default_true")]
+    pub whole_word: bool,
+}
+
+impl Default for OptionFlags {
+    fn default() -> Self {
+        OptionFlags {
+            case_insensitive: false,
+            whole_word: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompositeOperator, Pattern, PatternElement, WordMatch};
+
+    fn word(text: &str) -> Pattern {
+        Pattern::sequence(text, vec![PatternElement::Word { text: text.to_string(), mode: WordMatch::Exact }])
+    }
+
+    #[test]
+    fn anchors_hold_for_every_branch_of_an_or_composite() {
+        let mut either = Pattern::composite("either", CompositeOperator::Or, vec![word("alpha"), word("beta")]);
+        either.meta_mut().options.anchor_start = true;
+        either.meta_mut().options.anchor_end = true;
+        let regex = regex::Regex::new(&either.to_regex().unwrap()).unwrap();
+        assert!(regex.is_match("alpha"));
+        assert!(regex.is_match("beta"));
+        assert!(!regex.is_match("x alpha x"));
+        assert!(!regex.is_match("alpha x"));
+        assert!(!regex.is_match("x beta"));
+    }
+
+    #[test]
+    fn anchors_stay_inside_scoped_flags() {
+        let mut either = Pattern::composite("either", CompositeOperator::Or, vec![word("alpha"), word("beta")]);
+        either.meta_mut().options.case_insensitive = true;
+        either.meta_mut().options.anchor_start = true;
+        let regex = regex::Regex::new(&either.to_regex().unwrap()).unwrap();
+        assert!(regex.is_match("BETA here"));
+        assert!(!regex.is_match("x Beta"));
+    }
+}
@@ -0,0 +1,380 @@
+use serde::{Serialize, Deserialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::dependencies;
+use crate::library_file::SCHEMA_VERSION;
+use crate::{Pattern, PatternElement};
+
+/// Marks a file as a pattern pack written by `publish`.
+pub const PACK_FORMAT: &str = "regexgen-pack";
+
+/// Describes a pattern pack.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PackManifest {
+    /// Lowercase letters, digits, `-`, `_` and `.`; installed pattern ids
+    /// are prefixed with it.
+    pub name: String,
+    /// Dot-separated numbers, e.g. "1.4.0".
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// e.g. "MIT".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// The library schema version the patterns are written in, which
+    /// installing builders must support. Set by `publish`.
+    #[serde(default)]
+    pub min_schema_version: u32,
+}
+
+/// A pattern pack as published: its manifest and the patterns, with the
+/// ids they have in the publisher's library.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PatternPack {
+    pub format: String,
+    pub manifest: PackManifest,
+    pub patterns: Vec<Pattern>,
+}
+
+/// The pack an installed pattern came from.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PackSource {
+    pub name: String,
+    pub version: String,
+}
+
+/// A pack with patterns in the library.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InstalledPack {
+    pub name: String,
+    pub version: String,
+    pub pattern_ids: Vec<String>,
+}
+
+/// What `install` did to the library.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InstallReport {
+    pub name: String,
+    pub version: String,
+    /// The version upgraded from, if the pack was installed already.
+    pub previous_version: Option<String>,
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    /// Patterns of the previous version the new one no longer has.
+    pub removed: Vec<String>,
+}
+
+impl PackManifest {
+    pub fn validate(&self) -> Result<(), String> {
+        let valid_name = !self.name.is_empty()
+            && self.name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '.'));
+        if !valid_name {
+            return Err(format!("Invalid pack name \"{}\": use lowercase letters, digits, '-', '_' and '.'", self.name));
+        }
+        parse_version(&self.version)?;
+        Ok(())
+    }
+}
+
+fn parse_version(version: &str) -> Result<Vec<u64>, String> {
+    version
+        .split('.')
+        .map(|part| part.parse::<u64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("Invalid pack version \"{}\": expected numbers separated by dots, e.g. 1.2.0", version))
+}
+
+/// Orders two valid versions, with missing trailing parts as 0 so that
+/// "1.2" and "1.2.0" are the same.
+fn compare_versions(a: &str, b: &str) -> Result<Ordering, String> {
+    let (a, b) = (parse_version(a)?, parse_version(b)?);
+    let len = a.len().max(b.len());
+    let part = |v: &[u64], i: usize| v.get(i).copied().unwrap_or(0);
+    Ok((0..len).map(|i| part(&a, i).cmp(&part(&b, i))).find(|o| o.is_ne()).unwrap_or(Ordering::Equal))
+}
+
+/// Packs the patterns `ids` of `library`, along with every pattern they
+/// reference so the pack stands alone, as JSON. History and install
+/// provenance are left out.
+pub fn publish(library: &[Pattern], ids: &[String], mut manifest: PackManifest) -> Result<String, String> {
+    manifest.validate()?;
+    if ids.is_empty() {
+        return Err(String::from("A pack needs at least one pattern"));
+    }
+    let mut included: BTreeSet<String> = BTreeSet::new();
+    for id in ids {
+        if !library.iter().any(|p| p.get_id() == id) {
+            return Err(format!("No pattern with id {}", id));
+        }
+        included.insert(id.clone());
+        for dependency in dependencies::dependencies(library, id) {
+            if dependency.name.is_none() {
+                return Err(format!("Pattern {} references {}, which doesn't exist", dependency.via, dependency.id));
+            }
+            included.insert(dependency.id);
+        }
+    }
+    let patterns = library
+        .iter()
+        .filter(|p| included.contains(p.get_id()))
+        .map(|pattern| {
+            let mut pattern = pattern.clone();
+            pattern.meta_mut().history.clear();
+            pattern.meta_mut().metadata.pack = None;
+            pattern
+        })
+        .collect();
+    manifest.min_schema_version = SCHEMA_VERSION;
+    serde_json::to_string_pretty(&PatternPack { format: PACK_FORMAT.to_string(), manifest, patterns }).map_err(|e| e.to_string())
+}
+
+/// Reads a pack written by `publish`, refusing one that needs a newer
+/// schema than this version supports.
+pub fn parse(json: &str) -> Result<PatternPack, String> {
+    let value: serde_json::Value = serde_json::from_str(json).map_err(|e| format!("Invalid JSON: {}", e))?;
+    if value.get("format").and_then(|f| f.as_str()) != Some(PACK_FORMAT) {
+        return Err(String::from("Not a pattern pack"));
+    }
+    let required = value.pointer("/manifest/min_schema_version").and_then(|v| v.as_u64()).unwrap_or(0);
+    if required > SCHEMA_VERSION as u64 {
+        return Err(format!(
+            "Pack needs schema version {}, newer than the supported version {}",
+            required, SCHEMA_VERSION
+        ));
+    }
+    let pack: PatternPack = serde_json::from_value(value).map_err(|e| format!("Invalid pattern pack: {}", e))?;
+    pack.manifest.validate()?;
+    Ok(pack)
+}
+
+/// Id of pattern `id` of pack `name` once installed.
+pub fn namespaced_id(name: &str, id: &str) -> String {
+    format!("{}/{}", name, id)
+}
+
+/// Packs with patterns in `library`, by name.
+pub fn installed(library: &[Pattern]) -> Vec<InstalledPack> {
+    let mut packs: BTreeMap<&str, InstalledPack> = BTreeMap::new();
+    for pattern in library {
+        if let Some(source) = &pattern.meta().metadata.pack {
+            packs
+                .entry(&source.name)
+                .or_insert_with(|| InstalledPack { name: source.name.clone(), version: source.version.clone(), pattern_ids: Vec::new() })
+                .pattern_ids
+                .push(pattern.get_id().to_string());
+        }
+    }
+    packs.into_values().collect()
+}
+
+/// Installs `pack` into `library`, or upgrades the version installed.
+/// Pattern ids get the pack's name as a namespace, so packs can't clash
+/// with each other or with the library's own patterns, and an upgrade
+/// replaces each pattern in place, keeping references to it working.
+/// Refuses to downgrade, and to drop a pattern the library's own patterns
+/// still reference.
+pub fn install(library: &mut Vec<Pattern>, pack: PatternPack) -> Result<InstallReport, String> {
+    let PatternPack { manifest, patterns, .. } = pack;
+    let previous = installed(library).into_iter().find(|p| p.name == manifest.name);
+    if let Some(previous) = &previous {
+        if compare_versions(&previous.version, &manifest.version)? == Ordering::Greater {
+            return Err(format!(
+                "{} {} is installed, newer than {}",
+                manifest.name, previous.version, manifest.version
+            ));
+        }
+    }
+
+    let source = PackSource { name: manifest.name.clone(), version: manifest.version.clone() };
+    let ids: BTreeSet<String> = patterns.iter().map(|p| p.get_id().to_string()).collect();
+    let mut incoming = Vec::new();
+    for mut pattern in patterns {
+        for id in pattern.referenced_patterns() {
+            if !ids.contains(id) {
+                return Err(format!("Pattern {} references {}, which isn't in the pack", pattern.get_id(), id));
+            }
+        }
+        namespace(&mut pattern, &manifest.name);
+        let clash = library
+            .iter()
+            .find(|p| p.get_id() == pattern.get_id())
+            .is_some_and(|p| p.meta().metadata.pack.as_ref().is_none_or(|s| s.name != manifest.name));
+        if clash {
+            return Err(format!("A pattern outside the pack already has the id {}", pattern.get_id()));
+        }
+        pattern.meta_mut().history.clear();
+        pattern.meta_mut().metadata.pack = Some(source.clone());
+        incoming.push(pattern);
+    }
+
+    let kept: BTreeSet<&str> = incoming.iter().map(|p| p.get_id()).collect();
+    let removed: Vec<String> = previous
+        .iter()
+        .flat_map(|p| p.pattern_ids.iter())
+        .filter(|id| !kept.contains(id.as_str()))
+        .cloned()
+        .collect();
+    for id in &removed {
+        let outside: Vec<String> = dependencies::dependents(library, id)
+            .into_iter()
+            .filter(|d| !removed.contains(&d.id) && !kept.contains(d.id.as_str()))
+            .map(|d| d.name.unwrap_or(d.id))
+            .collect();
+        if !outside.is_empty() {
+            return Err(format!(
+                "{} {} drops pattern {}, which {} still reference(s)",
+                manifest.name, manifest.version, id, outside.join(", ")
+            ));
+        }
+    }
+
+    let mut report = InstallReport {
+        name: manifest.name,
+        version: manifest.version,
+        previous_version: previous.map(|p| p.version),
+        added: Vec::new(),
+        updated: Vec::new(),
+        removed,
+    };
+    library.retain(|p| !report.removed.iter().any(|id| id == p.get_id()));
+    for pattern in incoming {
+        let id = pattern.get_id().to_string();
+        match library.iter().position(|p| p.get_id() == id) {
+            Some(index) => {
+                library[index] = pattern;
+                report.updated.push(id);
+            }
+            None => {
+                library.push(pattern);
+                report.added.push(id);
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Prefixes the ids of `pattern`, its sub-patterns and the patterns it
+/// references with the pack's name.
+fn namespace(pattern: &mut Pattern, name: &str) {
+    pattern.set_id(namespaced_id(name, pattern.get_id()));
+    match pattern {
+        Pattern::Sequence { elements, .. } => {
+            for element in elements {
+                if let PatternElement::Reference { pattern_id } = element.base_mut() {
+                    *pattern_id = namespaced_id(name, pattern_id);
+                }
+            }
+        }
+        Pattern::Composite { patterns, .. } => patterns.iter_mut().for_each(|p| namespace(p, name)),
+        Pattern::Template { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_versions, install, installed, parse, publish, PackManifest};
+    use crate::{Pattern, PatternElement};
+    use std::cmp::Ordering;
+
+    fn library() -> Vec<Pattern> {
+        let mut status = Pattern::sequence("status", vec![PatternElement::word(String::from("failed"))]);
+        status.set_id(String::from("status"));
+        let mut error = Pattern::sequence(
+            "error",
+            vec![PatternElement::word(String::from("error")), PatternElement::Reference { pattern_id: String::from("status") }],
+        );
+        error.set_id(String::from("error"));
+        let mut unrelated = Pattern::sequence("unrelated", vec![PatternElement::word(String::from("other"))]);
+        unrelated.set_id(String::from("unrelated"));
+        vec![status, error, unrelated]
+    }
+
+    fn manifest(version: &str) -> PackManifest {
+        PackManifest {
+            name: String::from("logs"),
+            version: version.to_string(),
+            author: None,
+            license: Some(String::from("MIT")),
+            description: String::new(),
+            min_schema_version: 0,
+        }
+    }
+
+    fn pack(version: &str) -> super::PatternPack {
+        parse(&publish(&library(), &[String::from("error")], manifest(version)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn published_packs_parse_back_with_their_dependencies() {
+        let pack = pack("1.0.0");
+        assert_eq!(pack.manifest.name, "logs");
+        assert_eq!(pack.manifest.license.as_deref(), Some("MIT"));
+        assert!(pack.manifest.min_schema_version > 0);
+        let ids: Vec<&str> = pack.patterns.iter().map(|p| p.get_id()).collect();
+        assert_eq!(ids, ["status", "error"]);
+        assert!(pack.patterns.iter().all(|p| p.meta().history.is_empty()));
+    }
+
+    #[test]
+    fn invalid_packs_are_refused() {
+        assert!(publish(&library(), &[], manifest("1.0")).is_err());
+        assert!(publish(&library(), &[String::from("missing")], manifest("1.0")).is_err());
+        assert!(publish(&library(), &[String::from("error")], manifest("one")).is_err());
+        assert!(parse(r#"{"format": "something-else"}"#).is_err());
+        let json = publish(&library(), &[String::from("error")], manifest("1.0")).unwrap();
+        let newer = json.replacen("\"min_schema_version\": ", "\"min_schema_version\": 1000", 1);
+        assert!(parse(&newer).unwrap_err().contains("schema version"));
+    }
+
+    #[test]
+    fn installed_patterns_are_namespaced() {
+        let mut target = Vec::new();
+        let report = install(&mut target, pack("1.0.0")).unwrap();
+        assert_eq!(report.added, ["logs/status", "logs/error"]);
+        assert_eq!(report.previous_version, None);
+        assert_eq!(target[1].referenced_patterns(), ["logs/status"]);
+        let packs = installed(&target);
+        assert_eq!(packs.len(), 1);
+        assert_eq!(packs[0].version, "1.0.0");
+        assert_eq!(packs[0].pattern_ids, ["logs/status", "logs/error"]);
+    }
+
+    #[test]
+    fn upgrades_replace_in_place_and_downgrades_are_refused() {
+        let mut target = Vec::new();
+        install(&mut target, pack("1.0")).unwrap();
+        let mut own = Pattern::sequence("own", vec![PatternElement::word(String::from("mine"))]);
+        own.set_id(String::from("own"));
+        target.insert(0, own);
+
+        let report = install(&mut target, pack("1.1")).unwrap();
+        assert_eq!(report.previous_version.as_deref(), Some("1.0"));
+        assert_eq!(report.updated, ["logs/status", "logs/error"]);
+        assert!(report.added.is_empty() && report.removed.is_empty());
+        let ids: Vec<&str> = target.iter().map(|p| p.get_id()).collect();
+        assert_eq!(ids, ["own", "logs/status", "logs/error"]);
+
+        assert!(install(&mut target, pack("1.0.9")).unwrap_err().contains("newer"));
+        assert_eq!(installed(&target)[0].version, "1.1");
+    }
+
+    #[test]
+    fn patterns_outside_the_pack_are_not_overwritten() {
+        let mut own = Pattern::sequence("own", vec![PatternElement::word(String::from("mine"))]);
+        own.set_id(String::from("logs/status"));
+        let mut target = vec![own];
+        assert!(install(&mut target, pack("1.0")).unwrap_err().contains("logs/status"));
+        assert_eq!(target.len(), 1);
+    }
+
+    #[test]
+    fn missing_version_parts_count_as_zero() {
+        assert_eq!(compare_versions("1.2", "1.2.0"), Ok(Ordering::Equal));
+        assert_eq!(compare_versions("1.10", "1.9"), Ok(Ordering::Greater));
+        assert_eq!(compare_versions("2", "2.0.1"), Ok(Ordering::Less));
+        assert!(compare_versions("1.x", "1").is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{CompositeOperator, Pattern, PatternElement, RegexDialect};
+
+/// A way the exported regex may behave differently in the target engine
+/// than it does in the builder, which tests with the Rust engine.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ExportWarning {
+    pub code: String,
+    pub message: String,
+}
+
+/// A regex exported for one dialect, with anything to look out for.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ExportResult {
+    pub regex: String,
+    pub dialect: RegexDialect,
+    pub warnings: Vec<ExportWarning>,
+}
+
+/// What the checks need to know about a pattern, gathered over all its
+/// sub-patterns.
+#[derive(Default)]
+struct Usage {
+    words: bool,
+    non_ascii: bool,
+    open_gaps: bool,
+    alternation: bool,
+    unicode_classes: bool,
+    anchored_end: bool,
+    multi_line: bool,
+    and_operands: usize,
+}
+
+impl Usage {
+    fn collect(&mut self, pattern: &Pattern) {
+        let options = &pattern.meta().options;
+        self.anchored_end |= options.anchor_end;
+        self.multi_line |= options.multi_line;
+        match pattern {
+            Pattern::Sequence { elements, .. } => {
+                for element in elements {
+                    self.collect_element(element);
+                }
+            }
+            Pattern::Template { .. } => {
+                for element in &pattern.template_elements() {
+                    self.collect_element(element);
+                }
+            }
+            Pattern::Composite { operator, patterns, .. } => {
+                match operator {
+                    CompositeOperator::And => self.and_operands = self.and_operands.max(patterns.len()),
+                    CompositeOperator::Or => self.alternation = true,
+                    CompositeOperator::Not => {}
+                }
+                for sub_pattern in patterns {
+                    self.collect(sub_pattern);
+                }
+            }
+        }
+    }
+
+    fn collect_element(&mut self, element: &PatternElement) {
+        match element {
+            PatternElement::Word { text, .. } => {
+                self.words = true;
+                self.non_ascii |= !text.is_ascii();
+            }
+            PatternElement::Gap { max_chars: Some(_), .. } => self.open_gaps = true,
+            PatternElement::Gap { min_words: 0, max_words: None, excluded, .. } => {
+                self.open_gaps = true;
+                self.words |= !excluded.is_empty();
+            }
+            PatternElement::Gap { .. } => self.words = true,
+            PatternElement::OneOf { options, .. } => {
+                self.words = true;
+                self.alternation = true;
+                self.non_ascii |= options.iter().any(|o| !o.is_ascii());
+            }
+            PatternElement::Raw { regex } => {
+                self.unicode_classes |= regex.contains(r"\p{") || regex.contains(r"\P{");
+                self.alternation |= regex.contains('|');
+                self.words |= regex.contains(r"\w") || regex.contains(r"\b") || regex.contains(r"\W");
+            }
+            PatternElement::SynonymSet { .. } => {
+                self.words = true;
+                self.alternation = true;
+            }
+            PatternElement::TokenClass { .. } => self.words = true,
+            PatternElement::Reference { .. } => self.open_gaps = true,
+            PatternElement::Modified { element, .. } => self.collect_element(element),
+        }
+    }
+}
+
+fn warning(code: &str, message: String) -> ExportWarning {
+    ExportWarning {
+        code: code.to_string(),
+        message,
+    }
+}
+
+/// Statically checks `pattern` for constructs whose meaning changes when
+/// the regex runs in `dialect` instead of the Rust engine.
+pub fn portability_warnings(pattern: &Pattern, dialect: RegexDialect) -> Vec<ExportWarning> {
+    let mut usage = Usage::default();
+    usage.collect(pattern);
+    let name = dialect.name();
+    let mut warnings = Vec::new();
+
+    if dialect != RegexDialect::Rust && usage.words {
+        let detail = if usage.non_ascii {
+            "words with accented or non-Latin letters will not match as whole words"
+        } else {
+            "accented or non-Latin letters in the text split words"
+        };
+        warnings.push(warning(
+            "ascii_word_chars",
+            format!("Word characters and boundaries are ASCII-only in {}; {}", name, detail),
+        ));
+    }
+    if dialect != RegexDialect::Rust && usage.unicode_classes {
+        let message = match dialect {
+            RegexDialect::JavaScript => String::from("Unicode property classes (\\p{...}) need the `u` flag in JavaScript"),
+            _ => format!("Unicode property classes (\\p{{...}}) may not be supported in {}", name),
+        };
+        warnings.push(warning("unicode_classes", message));
+    }
+    if !dialect.supports_lazy() && usage.open_gaps {
+        warnings.push(warning(
+            "greedy_gaps",
+            format!("{} has no lazy quantifiers, so open gaps match as much text as possible and matches can run together", name),
+        ));
+    }
+    if dialect == RegexDialect::PosixEre && usage.alternation {
+        warnings.push(warning(
+            "leftmost_longest",
+            format!("{} picks the longest alternative rather than the first, so matches can be longer", name),
+        ));
+    }
+    if dialect == RegexDialect::Pcre && usage.anchored_end && !usage.multi_line {
+        warnings.push(warning(
+            "dollar_final_newline",
+            format!("In {} `$` also matches before a trailing newline", name),
+        ));
+    }
+    if !dialect.supports_lookaround() && usage.and_operands > 1 {
+        warnings.push(warning(
+            "and_permutations",
+            format!(
+                "{} has no lookahead, so AND is spelled out as every order of its parts; parts that overlap in the text will not match",
+                name
+            ),
+        ));
+    }
+    warnings
+}
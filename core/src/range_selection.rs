@@ -0,0 +1,48 @@
+use serde::{Serialize, Deserialize};
+
+use crate::tokenizer::WordInfo;
+
+/// What a character-range selection turned out to be.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RangeKind {
+    /// Starts and ends on word edges: one or more whole words, matched
+    /// with word boundaries like any other selected word.
+    Word,
+    /// Part of a word, or cut off mid-word at either end, e.g. "2024-01"
+    /// out of "2024-01-15": matched anywhere, without word boundaries.
+    Substring,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RangeSelection {
+    pub kind: RangeKind,
+    pub text: String,
+    /// The first word the range overlaps, or the word before it when it
+    /// only covers punctuation or spaces.
+    pub word_index: usize,
+    /// The last word the range overlaps.
+    pub last_word_index: usize,
+}
+
+/// Classifies the byte range `start..end` of `text` against its `words`.
+pub fn classify(text: &str, words: &[WordInfo], start: usize, end: usize) -> RangeSelection {
+    let overlapping: Vec<&WordInfo> = words.iter().filter(|w| w.end_index > start && w.start_index < end).collect();
+    let (kind, word_index, last_word_index) = match (overlapping.first(), overlapping.last()) {
+        (Some(first), Some(last)) => {
+            let aligned = first.start_index == start && last.end_index == end;
+            let kind = if aligned { RangeKind::Word } else { RangeKind::Substring };
+            (kind, first.word_index, last.word_index)
+        }
+        _ => {
+            let before = words.iter().rev().find(|w| w.end_index <= start).map_or(0, |w| w.word_index);
+            (RangeKind::Substring, before, before)
+        }
+    };
+    RangeSelection {
+        kind,
+        text: text[start..end].to_string(),
+        word_index,
+        last_word_index,
+    }
+}
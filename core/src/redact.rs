@@ -0,0 +1,75 @@
+use serde::{Serialize, Deserialize};
+
+use crate::OffsetEncoding;
+
+/// A stretch of the input that was replaced.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChangedSpan {
+    /// Offsets in the input text.
+    pub start: usize,
+    pub end: usize,
+    /// Offsets of the replacement in the output text.
+    pub output_start: usize,
+    pub output_end: usize,
+    /// Patterns whose matches the span covers.
+    pub pattern_ids: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Transformed {
+    pub text: String,
+    pub spans: Vec<ChangedSpan>,
+}
+
+/// Replaces each of `matches` (byte ranges with the id of the pattern
+/// that found them) by `replace` of the matched text. Overlapping matches
+/// are merged first, so every character is replaced at most once.
+/// Offsets are reported in `encoding`.
+pub fn transform(
+    text: &str,
+    mut matches: Vec<(usize, usize, String)>,
+    replace: impl Fn(&str) -> String,
+    encoding: OffsetEncoding,
+) -> Transformed {
+    matches.sort_by_key(|&(start, end, _)| (start, end));
+    let mut merged: Vec<(usize, usize, Vec<String>)> = Vec::new();
+    for (start, end, id) in matches {
+        match merged.last_mut() {
+            Some((_, last_end, ids)) if start < *last_end => {
+                *last_end = (*last_end).max(end);
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+            _ => merged.push((start, end, vec![id])),
+        }
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut spans = Vec::new();
+    let mut copied = 0;
+    for (start, end, pattern_ids) in merged {
+        if start == end {
+            continue;
+        }
+        output.push_str(&text[copied..start]);
+        let output_start = output.len();
+        output.push_str(&replace(&text[start..end]));
+        let output_end = output.len();
+        copied = end;
+        spans.push((start, end, output_start, output_end, pattern_ids));
+    }
+    output.push_str(&text[copied..]);
+
+    let spans = spans
+        .into_iter()
+        .map(|(start, end, output_start, output_end, pattern_ids)| ChangedSpan {
+            start: encoding.offset(text, start),
+            end: encoding.offset(text, end),
+            output_start: encoding.offset(&output, output_start),
+            output_end: encoding.offset(&output, output_end),
+            pattern_ids,
+        })
+        .collect();
+    Transformed { text: output, spans }
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use crate::docs::{escape_html, markdown_code, DocsFormat};
+
+/// Lines shown before and after each matching line.
+pub const CONTEXT_LINES: usize = 1;
+
+/// Where one pattern matched the reported text.
+pub struct PatternMatches {
+    pub name: String,
+    pub regex: String,
+    /// One sentence on how often and on which lines it matched.
+    pub summary: String,
+    /// Byte ranges of the matches, in order.
+    pub matches: Vec<(usize, usize)>,
+}
+
+struct Lines<'a> {
+    /// Byte offset each line starts at, and its text without the line break.
+    lines: Vec<(usize, &'a str)>,
+}
+
+impl<'a> Lines<'a> {
+    fn new(text: &'a str) -> Lines<'a> {
+        let mut lines = Vec::new();
+        let mut start = 0;
+        for line in text.split('\n') {
+            lines.push((start, line.strip_suffix('\r').unwrap_or(line)));
+            start += line.len() + 1;
+        }
+        Lines { lines }
+    }
+
+    fn index_of(&self, offset: usize) -> usize {
+        self.lines.partition_point(|&(start, _)| start <= offset) - 1
+    }
+
+    /// The parts of each line the matches cover, as byte ranges within the
+    /// line. A match running over several lines is marked on each.
+    fn marks(&self, matches: &[(usize, usize)]) -> Vec<(usize, Vec<Range<usize>>)> {
+        let mut marks: Vec<(usize, Vec<Range<usize>>)> = Vec::new();
+        for &(start, end) in matches {
+            let first = self.index_of(start);
+            let last = if end > start { self.index_of(end - 1) } else { first };
+            for index in first..=last {
+                let (line_start, line) = self.lines[index];
+                let from = start.saturating_sub(line_start).min(line.len());
+                let to = end.saturating_sub(line_start).min(line.len()).max(from);
+                match marks.last_mut() {
+                    Some((last, ranges)) if *last == index => ranges.push(from..to),
+                    _ => marks.push((index, std::iter::once(from..to).collect())),
+                }
+            }
+        }
+        marks
+    }
+
+    /// Runs of lines shown together: the marked lines with their context,
+    /// merged where the contexts touch.
+    fn hunks(&self, marked: &[usize]) -> Vec<Range<usize>> {
+        let mut hunks: Vec<Range<usize>> = Vec::new();
+        for &index in marked {
+            let lines = index.saturating_sub(CONTEXT_LINES)..(index + CONTEXT_LINES + 1).min(self.lines.len());
+            match hunks.last_mut() {
+                Some(hunk) if hunk.end >= lines.start => hunk.end = lines.end,
+                _ => hunks.push(lines),
+            }
+        }
+        hunks
+    }
+}
+
+/// GitHub's line anchor for a run of 0-based lines: `L12` or `L11-L13`.
+fn line_anchor(lines: &Range<usize>) -> String {
+    if lines.len() == 1 {
+        format!("L{}", lines.start + 1)
+    } else {
+        format!("L{}-L{}", lines.start + 1, lines.end)
+    }
+}
+
+/// Lowercase letters, digits and dashes, as in GitHub heading anchors.
+fn slug(name: &str) -> String {
+    let slug: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    let slug = slug.split('-').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("-");
+    if slug.is_empty() { String::from("pattern") } else { slug }
+}
+
+/// A fence longer than any run of backticks in the text it encloses.
+fn fence(text: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn header(results: &[PatternMatches], line_count: usize) -> String {
+    let total: usize = results.iter().map(|r| r.matches.len()).sum();
+    format!(
+        "{} pattern{}, {} match{} in {} line{}.",
+        results.len(),
+        if results.len() == 1 { "" } else { "s" },
+        total,
+        if total == 1 { "" } else { "es" },
+        line_count,
+        if line_count == 1 { "" } else { "s" }
+    )
+}
+
+pub fn render(text: &str, results: &[PatternMatches], format: DocsFormat) -> String {
+    let lines = Lines::new(text);
+    let mut slugs = BTreeSet::new();
+    let mut out = match format {
+        DocsFormat::Markdown => format!("# Match report\n\n{}\n", header(results, lines.lines.len())),
+        DocsFormat::Html => format!("<h1>Match report</h1>\n<p>{}</p>\n", header(results, lines.lines.len())),
+    };
+    for result in results {
+        let mut id = slug(&result.name);
+        let mut n = 1;
+        while !slugs.insert(id.clone()) {
+            n += 1;
+            id = format!("{}-{}", slug(&result.name), n);
+        }
+        let marks = lines.marks(&result.matches);
+        let marked: Vec<usize> = marks.iter().map(|(index, _)| *index).collect();
+        let hunks = lines.hunks(&marked);
+        match format {
+            DocsFormat::Markdown => render_markdown(&mut out, result, &lines, &marks, &hunks),
+            DocsFormat::Html => render_html(&mut out, &id, result, &lines, &marks, &hunks),
+        }
+    }
+    out
+}
+
+fn render_markdown(out: &mut String, result: &PatternMatches, lines: &Lines, marks: &[(usize, Vec<Range<usize>>)], hunks: &[Range<usize>]) {
+    out.push_str(&format!("\n## {}\n\n", result.name));
+    out.push_str(&format!("Regex: {}\n\n{}\n", markdown_code(&result.regex), result.summary));
+    let width = lines.lines.len().to_string().len();
+    for hunk in hunks {
+        let mut block = String::new();
+        for index in hunk.clone() {
+            let line = lines.lines[index].1;
+            let ranges = marks.iter().find(|(marked, _)| *marked == index).map(|(_, ranges)| ranges);
+            let marker = if ranges.is_some() { '>' } else { ' ' };
+            block.push_str(&format!("{} {:>width$} | {}\n", marker, index + 1, line, width = width));
+            if let Some(ranges) = ranges {
+                // Carets under the matched text, counted in characters
+                let mut underline = String::new();
+                for range in ranges {
+                    let column = line[..range.start].chars().count();
+                    let length = line[range.clone()].chars().count().max(1);
+                    let padding = column.saturating_sub(underline.chars().count());
+                    underline.push_str(&" ".repeat(padding));
+                    underline.push_str(&"^".repeat(length));
+                }
+                block.push_str(&format!("  {:>width$} | {}\n", "", underline, width = width));
+            }
+        }
+        let fence = fence(&block);
+        out.push_str(&format!("\n### {}\n\n{}text\n{}{}\n", line_anchor(hunk), fence, block, fence));
+    }
+}
+
+fn render_html(out: &mut String, id: &str, result: &PatternMatches, lines: &Lines, marks: &[(usize, Vec<Range<usize>>)], hunks: &[Range<usize>]) {
+    out.push_str(&format!("<section id=\"{}\">\n<h2>{}</h2>\n", id, escape_html(&result.name)));
+    out.push_str(&format!(
+        "<p>Regex: <code>{}</code></p>\n<p>{}</p>\n",
+        escape_html(&result.regex),
+        escape_html(&result.summary)
+    ));
+    for hunk in hunks {
+        out.push_str(&format!("<pre id=\"{}-{}\">", id, line_anchor(hunk)));
+        for index in hunk.clone() {
+            let line = lines.lines[index].1;
+            let mut html = String::new();
+            let mut at = 0;
+            if let Some((_, ranges)) = marks.iter().find(|(marked, _)| *marked == index) {
+                for range in ranges {
+                    let start = range.start.max(at);
+                    html.push_str(&escape_html(&line[at..start]));
+                    html.push_str(&format!("<mark>{}</mark>", escape_html(&line[start..range.end.max(start)])));
+                    at = range.end.max(start);
+                }
+            }
+            html.push_str(&escape_html(&line[at..]));
+            out.push_str(&format!("<span class=\"line-number\">{}</span> {}\n", index + 1, html));
+        }
+        out.push_str("</pre>\n");
+    }
+    out.push_str("</section>\n");
+}
@@ -0,0 +1,115 @@
+use serde_json::{json, Value};
+
+use crate::{OffsetEncoding, Pattern, RegexContext, Severity};
+
+/// The SARIF version `sarif_log` writes.
+pub const SARIF_VERSION: &str = "2.1.0";
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// A pattern and the byte ranges of its matches in each document, in
+/// document order.
+pub type RuleMatches<'a> = (&'a Pattern, Vec<Vec<(usize, usize)>>);
+
+/// A SARIF log with one run: each pattern is a rule, each match a result
+/// located by line and column in its document. `documents` are named by
+/// the URI results point to. Columns count UTF-16 code units, SARIF's
+/// default; a pattern's level follows the severity it is displayed with,
+/// "warning" if it has none.
+pub fn sarif_log(documents: &[(String, &str)], rules: &[RuleMatches], context: &RegexContext) -> String {
+    let descriptors: Vec<Value> = rules
+        .iter()
+        .map(|(pattern, _)| {
+            let mut rule = json!({
+                "id": pattern.get_id(),
+                "name": pattern.get_name(),
+                "shortDescription": { "text": pattern.get_name() },
+                "defaultConfiguration": { "level": level(pattern) },
+                "properties": { "regex": pattern.to_regex_with(context).ok() },
+            });
+            if !pattern.meta().description.is_empty() {
+                rule["fullDescription"] = json!({ "text": pattern.meta().description });
+            }
+            if !pattern.meta().tags.is_empty() {
+                rule["properties"]["tags"] = json!(pattern.meta().tags);
+            }
+            rule
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    for (doc_index, (uri, text)) in documents.iter().enumerate() {
+        let mut found: Vec<(usize, usize, usize)> = rules
+            .iter()
+            .enumerate()
+            .flat_map(|(rule_index, (_, matches))| {
+                matches.get(doc_index).into_iter().flatten().map(move |&(start, end)| (start, end, rule_index))
+            })
+            .collect();
+        found.sort();
+        for (start, end, rule_index) in found {
+            let pattern = rules[rule_index].0;
+            let (start_line, start_column) = line_column(text, start);
+            let (end_line, end_column) = line_column(text, end);
+            let char_offset = OffsetEncoding::Utf16.offset(text, start);
+            results.push(json!({
+                "ruleId": pattern.get_id(),
+                "ruleIndex": rule_index,
+                "level": level(pattern),
+                "message": { "text": format!("Matches pattern \"{}\"", pattern.get_name()) },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": uri, "index": doc_index },
+                        "region": {
+                            "startLine": start_line,
+                            "startColumn": start_column,
+                            "endLine": end_line,
+                            "endColumn": end_column,
+                            "charOffset": char_offset,
+                            "charLength": OffsetEncoding::Utf16.offset(text, end) - char_offset,
+                            "snippet": { "text": &text[start..end] },
+                        },
+                    },
+                }],
+            }));
+        }
+    }
+
+    let artifacts: Vec<Value> = documents
+        .iter()
+        .map(|(uri, text)| json!({ "location": { "uri": uri }, "length": text.encode_utf16().count() }))
+        .collect();
+    let log = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": SARIF_VERSION,
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "regexgen",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": descriptors,
+                },
+            },
+            "columnKind": "utf16CodeUnits",
+            "artifacts": artifacts,
+            "results": results,
+        }],
+    });
+    serde_json::to_string_pretty(&log).unwrap_or_default()
+}
+
+fn level(pattern: &Pattern) -> &'static str {
+    match pattern.meta().display.as_ref().and_then(|display| display.severity) {
+        Some(Severity::Error) => "error",
+        Some(Severity::Info) => "note",
+        Some(Severity::Warning) | None => "warning",
+    }
+}
+
+/// The 1-based line and column of a byte offset, the column in UTF-16
+/// code units.
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    (before.matches('\n').count() + 1, before[line_start..].encode_utf16().count() + 1)
+}
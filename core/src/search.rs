@@ -0,0 +1,57 @@
+use crate::Pattern;
+
+/// A tag as stored: trimmed and lowercased, so "Auth" and "auth " are
+/// the same tag.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+/// A folder path as stored: segments trimmed and joined by single
+/// slashes. `None` for a path with no segments, i.e. the top level.
+pub fn normalize_folder(folder: &str) -> Option<String> {
+    let segments: Vec<&str> = folder.split('/').map(str::trim).filter(|s| !s.is_empty()).collect();
+    (!segments.is_empty()).then(|| segments.join("/"))
+}
+
+pub fn in_category(pattern: &Pattern, category: &str) -> bool {
+    pattern.meta().category.as_deref().is_some_and(|id| id.eq_ignore_ascii_case(category))
+}
+
+pub fn has_tag(pattern: &Pattern, tag: &str) -> bool {
+    pattern.meta().tags.contains(&normalize_tag(tag))
+}
+
+/// Whether `pattern` is in `folder` or one of its subfolders.
+pub fn in_folder(pattern: &Pattern, folder: &str) -> bool {
+    match (pattern.meta().folder.as_deref(), normalize_folder(folder)) {
+        (_, None) => true,
+        (Some(path), Some(folder)) => {
+            let (path, folder) = (path.to_lowercase(), folder.to_lowercase());
+            path == folder || path.starts_with(&format!("{}/", folder))
+        }
+        (None, Some(_)) => false,
+    }
+}
+
+/// Whether `pattern` satisfies every whitespace-separated term of
+/// `query`. `tag:x` needs the tag x, `folder:x` the folder x or one
+/// below it, `category:x` the category x; any other term must appear, ignoring case, in the name,
+/// description, id, folder or a tag.
+pub fn matches(pattern: &Pattern, query: &str) -> bool {
+    query.split_whitespace().all(|term| {
+        if let Some(tag) = term.strip_prefix("tag:") {
+            return has_tag(pattern, tag);
+        }
+        if let Some(folder) = term.strip_prefix("folder:") {
+            return in_folder(pattern, folder);
+        }
+        if let Some(category) = term.strip_prefix("category:") {
+            return in_category(pattern, category);
+        }
+        let term = term.to_lowercase();
+        [pattern.get_name(), &pattern.meta().description, pattern.get_id(), pattern.meta().folder.as_deref().unwrap_or_default()]
+            .into_iter()
+            .chain(pattern.meta().tags.iter().map(String::as_str))
+            .any(|field| field.to_lowercase().contains(&term))
+    })
+}
@@ -0,0 +1,152 @@
+use serde::{Serialize, Deserialize};
+use std::str::FromStr;
+
+use crate::alternation::AlternationConfig;
+use crate::category::Category;
+use crate::dialect::RegexDialect;
+use crate::limits::Limits;
+use crate::locale::DEFAULT_LOCALE;
+use crate::options::MatchOptions;
+use crate::tokenizer::TokenizerConfig;
+
+/// How the gap between two selections that aren't next to each other is
+/// built.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GapStrategy {
+    /// Anything in between, however long.
+    Open,
+    /// Exactly as many words as were skipped in the sample text.
+    Exact,
+    /// Up to as many words as were skipped in the sample text.
+    UpTo,
+    /// Up to twice as many words as were skipped in the sample text, so
+    /// slightly wordier texts still match.
+    Loose,
+}
+
+impl FromStr for GapStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "_").as_str() {
+            "unbounded" | "open" => Ok(GapStrategy::Open),
+            "strict" | "exact" => Ok(GapStrategy::Exact),
+            "up_to" | "upto" => Ok(GapStrategy::UpTo),
+            "loose" => Ok(GapStrategy::Loose),
+            _ => Err(format!("Unknown gap mode: {}", s)),
+        }
+    }
+}
+
+/// Unit of the match offsets reported to the host. JavaScript strings are
+/// indexed in UTF-16 code units, Rust strings in bytes.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OffsetEncoding {
+    Utf8,
+    Utf16,
+    CodePoint,
+}
+
+impl FromStr for OffsetEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "utf8" | "utf-8" | "bytes" | "byte" => Ok(OffsetEncoding::Utf8),
+            "utf16" | "utf-16" | "js" => Ok(OffsetEncoding::Utf16),
+            "code_point" | "codepoint" | "chars" | "char" => Ok(OffsetEncoding::CodePoint),
+            _ => Err(format!("Unknown offset unit: {}", s)),
+        }
+    }
+}
+
+impl OffsetEncoding {
+    /// Converts an offset in this encoding back to a byte offset into
+    /// `text`. `None` if it is past the end or splits a character.
+    pub fn byte_offset(self, text: &str, offset: usize) -> Option<usize> {
+        match self {
+            OffsetEncoding::Utf8 => text.is_char_boundary(offset).then_some(offset),
+            OffsetEncoding::Utf16 => {
+                let mut units = 0;
+                for (byte, c) in text.char_indices() {
+                    if units >= offset {
+                        return (units == offset).then_some(byte);
+                    }
+                    units += c.len_utf16();
+                }
+                (units == offset).then_some(text.len())
+            }
+            OffsetEncoding::CodePoint => text
+                .char_indices()
+                .map(|(byte, _)| byte)
+                .chain(std::iter::once(text.len()))
+                .nth(offset),
+        }
+    }
+
+    /// Converts a byte offset into `text` to this encoding.
+    pub fn offset(self, text: &str, byte_offset: usize) -> usize {
+        match self {
+            OffsetEncoding::Utf8 => byte_offset,
+            OffsetEncoding::Utf16 => text[..byte_offset].encode_utf16().count(),
+            OffsetEncoding::CodePoint => text[..byte_offset].chars().count(),
+        }
+    }
+}
+
+/// Defaults applied by the builder, persisted per workspace.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    /// Match options given to newly built patterns.
+    pub match_options: MatchOptions,
+    /// See `AlternationConfig::min_options`.
+    pub alternation_threshold: usize,
+    pub gap_strategy: GapStrategy,
+    pub offset_encoding: OffsetEncoding,
+    /// Dialect used when an export doesn't name one.
+    pub dialect: RegexDialect,
+    pub tokenizer: TokenizerConfig,
+    /// Locale explanations are rendered in unless a call names one.
+    pub locale: String,
+    /// Keep the undo history in local storage, so changes can still be
+    /// undone after a reload.
+    pub persist_undo_history: bool,
+    /// Compress the library, documents and undo history in local storage
+    /// so more fits in the browser's quota.
+    pub compress_storage: bool,
+    pub limits: Limits,
+    /// Matches per word of the source text above which `check_draft`
+    /// reports a draft as degenerate.
+    pub max_match_density: f64,
+    /// The taxonomy pattern categories must come from.
+    pub categories: Vec<Category>,
+    /// Recorded as the author of patterns created in this workspace.
+    pub author: Option<String>,
+    /// Keep a word index of the saved documents, so `search_documents`
+    /// skips the documents and lines without the words a pattern needs.
+    pub index_documents: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            match_options: MatchOptions::default(),
+            alternation_threshold: AlternationConfig::default().min_options,
+            gap_strategy: GapStrategy::Open,
+            offset_encoding: OffsetEncoding::Utf8,
+            dialect: RegexDialect::Rust,
+            tokenizer: TokenizerConfig::default(),
+            locale: DEFAULT_LOCALE.to_string(),
+            persist_undo_history: false,
+            compress_storage: false,
+            limits: Limits::default(),
+            max_match_density: 0.02,
+            categories: Vec::new(),
+            author: None,
+            index_documents: false,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::synonyms::SynonymSets;
+use crate::{Pattern, Settings};
+
+/// Marks a snapshot written by `BuilderSnapshot::to_json`.
+pub const SNAPSHOT_FORMAT: &str = "regexgen-snapshot";
+
+/// Everything a builder works from, without where it is stored: enough to
+/// rebuild it where storage isn't reachable, such as a Web Worker, and to
+/// hand the result back to a builder that can save it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BuilderSnapshot {
+    pub format: String,
+    pub workspace: String,
+    /// The stored revision the library was at when the snapshot was
+    /// taken; saving it back is refused once the store has moved on.
+    pub revision: u64,
+    pub patterns: Vec<Pattern>,
+    #[serde(default)]
+    pub documents: BTreeMap<String, String>,
+    #[serde(default)]
+    pub synonyms: SynonymSets,
+    /// Loaded `OneOf` lists, which aren't stored but which the patterns
+    /// need to compile the same way.
+    #[serde(default)]
+    pub lists: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub settings: Settings,
+}
+
+impl BuilderSnapshot {
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    pub fn parse(json: &str) -> Result<BuilderSnapshot, String> {
+        let value: serde_json::Value = serde_json::from_str(json).map_err(|e| format!("Invalid JSON: {}", e))?;
+        if value.get("format").and_then(|f| f.as_str()) != Some(SNAPSHOT_FORMAT) {
+            return Err(String::from("Not a builder snapshot"));
+        }
+        serde_json::from_value(value).map_err(|e| format!("Invalid builder snapshot: {}", e))
+    }
+}
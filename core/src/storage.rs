@@ -0,0 +1,204 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::compress;
+
+/// The workspace that uses the original, unprefixed storage keys.
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+/// Everything a workspace persists is stored under its own key per item.
+pub const WORKSPACE_ITEMS: &[&str] = &["patterns", "revision", "topics", "documents", "settings", "journal", "synonyms"];
+
+/// Values longer than this many bytes are split over several keys,
+/// as some browsers limit the size of a single item.
+const CHUNK_LENGTH: usize = 512 * 1024;
+
+/// Marks a value stored compressed, followed by base64.
+const COMPRESSED_PREFIX: &str = "lz:";
+
+/// Marks a value split over several keys: `chunks:<count>:<generation>`.
+const CHUNKED_PREFIX: &str = "chunks:";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageError {
+    /// No local storage in this environment, or access to it was denied.
+    Unavailable(String),
+    /// The browser refused to store `bytes` of data under `key`.
+    QuotaExceeded { key: String, bytes: usize },
+    Serialization(String),
+    Other(String),
+}
+
+impl StorageError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            StorageError::Unavailable(_) => "storage_unavailable",
+            StorageError::QuotaExceeded { .. } => "quota_exceeded",
+            StorageError::Serialization(_) => "serialization_error",
+            StorageError::Other(_) => "storage_error",
+        }
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageError::Unavailable(reason) => write!(f, "Local storage is unavailable: {}", reason),
+            StorageError::QuotaExceeded { key, bytes } => write!(
+                f,
+                "Local storage is full: could not save {} bytes under {}. Export the library to keep a copy, then remove documents or patterns or turn on storage compression",
+                bytes, key
+            ),
+            StorageError::Serialization(message) => write!(f, "Could not serialize data for storage: {}", message),
+            StorageError::Other(message) => write!(f, "Could not write to local storage: {}", message),
+        }
+    }
+}
+
+/// A string key-value store a library persists to, such as the browser's
+/// local storage.
+pub trait Store {
+    fn get_item(&self, key: &str) -> Option<String>;
+    fn set_item(&self, key: &str, value: &str) -> Result<(), StorageError>;
+    fn remove_item(&self, key: &str) -> Result<(), StorageError>;
+}
+
+/// A `Store` kept in memory, for hosts without persistent storage.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    items: RefCell<BTreeMap<String, String>>,
+}
+
+impl Store for MemoryStore {
+    fn get_item(&self, key: &str) -> Option<String> {
+        self.items.borrow().get(key).cloned()
+    }
+
+    fn set_item(&self, key: &str, value: &str) -> Result<(), StorageError> {
+        self.items.borrow_mut().insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn remove_item(&self, key: &str) -> Result<(), StorageError> {
+        self.items.borrow_mut().remove(key);
+        Ok(())
+    }
+}
+
+/// Prefix of every key of the store named `store`: `regexgen_` for the
+/// original, unnamed one ("") and `regexgen_store_<name>_` for others.
+pub fn key_prefix(store: &str) -> String {
+    if store.is_empty() {
+        String::from("regexgen_")
+    } else {
+        format!("regexgen_store_{}_", store)
+    }
+}
+
+/// The key `item` of `workspace` in `store` is stored under.
+pub fn storage_key(store: &str, workspace: &str, item: &str) -> String {
+    if workspace == DEFAULT_WORKSPACE {
+        format!("{}{}", key_prefix(store), item)
+    } else {
+        format!("{}ws_{}_{}", key_prefix(store), workspace, item)
+    }
+}
+
+fn chunk_key(key: &str, generation: u8, index: usize) -> String {
+    format!("{}#{}#{}", key, generation, index)
+}
+
+/// Count and generation of a chunked value's manifest.
+fn chunk_manifest(value: &str) -> Option<(usize, u8)> {
+    let (count, generation) = value.strip_prefix(CHUNKED_PREFIX)?.split_once(':')?;
+    Some((count.parse().ok()?, generation.parse().ok()?))
+}
+
+/// The stored text under `key`, with chunks joined and compression undone.
+pub fn load_text(store: &dyn Store, key: &str) -> Option<String> {
+    let mut value = store.get_item(key)?;
+    if let Some((count, generation)) = chunk_manifest(&value) {
+        value = (0..count)
+            .map(|i| store.get_item(&chunk_key(key, generation, i)))
+            .collect::<Option<String>>()?;
+    }
+    match value.strip_prefix(COMPRESSED_PREFIX) {
+        Some(encoded) => {
+            let bytes = compress::decode_base64(encoded).ok()?;
+            String::from_utf8(compress::decompress(&bytes).ok()?).ok()
+        }
+        None => Some(value),
+    }
+}
+
+fn remove_chunks(store: &dyn Store, key: &str, manifest: Option<(usize, u8)>) {
+    if let Some((count, generation)) = manifest {
+        for i in 0..count {
+            let _ = store.remove_item(&chunk_key(key, generation, i));
+        }
+    }
+}
+
+/// Stores `text` under `key`, compressed if `compress` is set and split
+/// into chunks if it is long. New chunks are written under a fresh
+/// generation before the manifest points at them, so a write that runs out
+/// of space leaves the old value intact.
+pub fn save_text(store: &dyn Store, key: &str, text: &str, compress: bool) -> Result<(), StorageError> {
+    let encoded;
+    let text = if compress {
+        encoded = format!("{}{}", COMPRESSED_PREFIX, compress::encode_base64(&compress::compress(text.as_bytes())));
+        encoded.as_str()
+    } else {
+        text
+    };
+    let old = store.get_item(key).and_then(|value| chunk_manifest(&value));
+    // A quota error names the whole value, not the chunk that hit it
+    let write = |item_key: &str, value: &str| {
+        store.set_item(item_key, value).map_err(|e| match e {
+            StorageError::QuotaExceeded { .. } => StorageError::QuotaExceeded { key: key.to_string(), bytes: text.len() },
+            e => e,
+        })
+    };
+
+    if text.len() <= CHUNK_LENGTH {
+        write(key, text)?;
+    } else {
+        let generation = old.map_or(0, |(_, generation)| 1 - generation);
+        let chunks: Vec<&str> = split_at_char_boundaries(text, CHUNK_LENGTH);
+        for (i, chunk) in chunks.iter().enumerate() {
+            if let Err(e) = write(&chunk_key(key, generation, i), chunk) {
+                remove_chunks(store, key, Some((i, generation)));
+                return Err(e);
+            }
+        }
+        if let Err(e) = write(key, &format!("{}{}:{}", CHUNKED_PREFIX, chunks.len(), generation)) {
+            remove_chunks(store, key, Some((chunks.len(), generation)));
+            return Err(e);
+        }
+    }
+    remove_chunks(store, key, old);
+    Ok(())
+}
+
+/// Removes the value under `key` and any chunks it was split into.
+pub fn remove_text(store: &dyn Store, key: &str) -> Result<(), StorageError> {
+    let manifest = store.get_item(key).and_then(|value| chunk_manifest(&value));
+    remove_chunks(store, key, manifest);
+    store.remove_item(key)
+}
+
+fn split_at_char_boundaries(text: &str, length: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut end = length.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
+}
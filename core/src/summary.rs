@@ -0,0 +1,46 @@
+use crate::locale::MessageCatalog;
+
+/// Lines listed in a summary before the rest are only counted.
+pub const MAX_SUMMARY_LINES: usize = 10;
+
+/// 1-based line number of each byte offset, without repeats. `offsets`
+/// must be ascending.
+pub fn line_numbers(text: &str, offsets: &[usize]) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let mut line = 1;
+    let mut counted = 0;
+    for &offset in offsets {
+        line += text[counted..offset].matches('\n').count();
+        counted = offset;
+        if lines.last() != Some(&line) {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+/// One sentence saying how often and where a pattern matched, e.g.
+/// "Pattern 'DB errors' matched 4 times on lines 12, 40, 41, 77."
+pub fn summarize(name: &str, text: &str, match_starts: &[usize], catalog: &MessageCatalog) -> String {
+    let name = name.to_string();
+    let lines = line_numbers(text, match_starts);
+    match (match_starts.len(), lines.as_slice()) {
+        (0, _) => catalog.render("summary_no_match", &[("name", name)]),
+        (1, [line]) => catalog.render("summary_matched_once", &[("name", name), ("line", line.to_string())]),
+        (count, [line]) => catalog.render(
+            "summary_matched_one_line",
+            &[("name", name), ("count", count.to_string()), ("line", line.to_string())],
+        ),
+        (count, _) => {
+            let listed: Vec<String> = lines.iter().take(MAX_SUMMARY_LINES).map(|l| l.to_string()).collect();
+            let mut listed = listed.join(&catalog.render("list_separator", &[]));
+            if lines.len() > MAX_SUMMARY_LINES {
+                listed = catalog.render(
+                    "summary_more_lines",
+                    &[("lines", listed), ("more", (lines.len() - MAX_SUMMARY_LINES).to_string())],
+                );
+            }
+            catalog.render("summary_matched", &[("name", name), ("count", count.to_string()), ("lines", listed)])
+        }
+    }
+}
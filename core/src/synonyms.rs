@@ -0,0 +1,28 @@
+use std::collections::BTreeMap;
+
+/// Named sets of interchangeable words, e.g. "error-words": error,
+/// failure, fault, exception, by set id.
+pub type SynonymSets = BTreeMap<String, Vec<String>>;
+
+/// Set ids go into pattern JSON and messages, so keep them simple.
+pub fn validate_set_id(set_id: &str) -> Result<(), String> {
+    if set_id.is_empty() || !set_id.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid synonym set id '{}': use letters, digits, '-' and '_'", set_id));
+    }
+    Ok(())
+}
+
+/// Trims the words and drops blanks and repeats, keeping the order given.
+pub fn normalize(words: Vec<String>) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for word in words {
+        let word = word.trim();
+        if !word.is_empty() && !normalized.iter().any(|w| w == word) {
+            normalized.push(word.to_string());
+        }
+    }
+    if normalized.is_empty() {
+        return Err(String::from("A synonym set needs at least one word"));
+    }
+    Ok(normalized)
+}
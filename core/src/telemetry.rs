@@ -0,0 +1,60 @@
+use serde::{Serialize, Deserialize};
+use std::fmt::Debug;
+
+/// What a host's telemetry hook is called with. Events name the capability
+/// used and nothing else: no pattern, regex, name or text ever goes in one.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UsageEvent {
+    /// A pattern was saved, by building it from selections or importing it.
+    /// `elements` counts the patterns a composite combines.
+    PatternBuilt { composite: bool, elements: usize },
+    /// A pattern was exported, e.g. as "regex_javascript" or "tool_grep".
+    Exported { flavor: String },
+    /// Patterns were run over text, e.g. by "test_pattern".
+    Evaluated { operation: String, duration: DurationBucket },
+}
+
+impl UsageEvent {
+    /// An `Exported` event whose flavor is `kind` followed by the format
+    /// chosen, like "regex_pcre".
+    pub fn exported(kind: &str, format: impl Debug) -> UsageEvent {
+        UsageEvent::Exported {
+            flavor: format!("{}_{:?}", kind, format).to_lowercase(),
+        }
+    }
+
+    pub fn evaluated(operation: &str, millis: f64) -> UsageEvent {
+        UsageEvent::Evaluated {
+            operation: operation.to_string(),
+            duration: DurationBucket::from_millis(millis),
+        }
+    }
+}
+
+/// How long an evaluation took, coarse enough not to identify the text.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DurationBucket {
+    #[serde(rename = "under_1ms")]
+    Under1Ms,
+    #[serde(rename = "under_10ms")]
+    Under10Ms,
+    #[serde(rename = "under_100ms")]
+    Under100Ms,
+    #[serde(rename = "under_1s")]
+    Under1S,
+    #[serde(rename = "1s_or_more")]
+    AtLeast1S,
+}
+
+impl DurationBucket {
+    pub fn from_millis(millis: f64) -> DurationBucket {
+        match millis {
+            m if m < 1.0 => DurationBucket::Under1Ms,
+            m if m < 10.0 => DurationBucket::Under10Ms,
+            m if m < 100.0 => DurationBucket::Under100Ms,
+            m if m < 1000.0 => DurationBucket::Under1S,
+            _ => DurationBucket::AtLeast1S,
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{ElementModifier, Pattern, PatternElement, PatternMeta};
+
+/// Opens a placeholder in a template body.
+pub const OPEN: char = '«';
+/// Closes a placeholder in a template body.
+pub const CLOSE: char = '»';
+
+/// How the value given for a parameter is matched.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterKind {
+    /// As literal text, the way selected words are.
+    #[default]
+    Text,
+    /// As a regex fragment, e.g. "50[0-9]".
+    Regex,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TemplateParameter {
+    pub name: String,
+    #[serde(default)]
+    pub kind: ParameterKind,
+}
+
+/// A piece of a template body.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Part<'a> {
+    Text(&'a str),
+    Parameter(&'a str),
+}
+
+/// Splits a body like "«service» failed with code «code»" into its text
+/// and placeholders. An unclosed `«` is kept as text.
+pub fn parts(body: &str) -> Vec<Part<'_>> {
+    let mut parts = Vec::new();
+    let mut rest = body;
+    while let Some(open) = rest.find(OPEN) {
+        let after = &rest[open + OPEN.len_utf8()..];
+        let Some(close) = after.find(CLOSE) else { break };
+        if open > 0 {
+            parts.push(Part::Text(&rest[..open]));
+        }
+        parts.push(Part::Parameter(after[..close].trim()));
+        rest = &after[close + CLOSE.len_utf8()..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest));
+    }
+    parts
+}
+
+/// Checks that every placeholder in `body` is a declared parameter and
+/// every parameter is used, with names usable as field names.
+pub fn validate(body: &str, parameters: &[TemplateParameter]) -> Result<(), String> {
+    let mut declared = BTreeSet::new();
+    for parameter in parameters {
+        ElementModifier::validate_capture(&parameter.name)?;
+        if !declared.insert(parameter.name.as_str()) {
+            return Err(format!("Parameter '{}' is declared twice", parameter.name));
+        }
+    }
+    let mut used = BTreeSet::new();
+    for part in parts(body) {
+        match part {
+            Part::Text(text) if text.contains(OPEN) => {
+                return Err(format!("Unclosed placeholder in template: {}", body));
+            }
+            Part::Text(_) => {}
+            Part::Parameter(name) if !declared.contains(name) => {
+                return Err(format!("Placeholder «{}» is not a declared parameter", name));
+            }
+            Part::Parameter(name) => {
+                used.insert(name);
+            }
+        }
+    }
+    if let Some(unused) = declared.difference(&used).next() {
+        return Err(format!("Parameter '{}' does not appear in the template", unused));
+    }
+    if used.is_empty() {
+        return Err(format!("A template needs at least one placeholder, like {}name{}", OPEN, CLOSE));
+    }
+    Ok(())
+}
+
+/// The elements of `body` with each placeholder replaced by what
+/// `value` returns for it. Text between placeholders becomes words
+/// where it can.
+pub fn elements(body: &str, mut value: impl FnMut(&str) -> PatternElement) -> Vec<PatternElement> {
+    let parts = parts(body);
+    let last = parts.len().saturating_sub(1);
+    let mut elements = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        match part {
+            Part::Text(text) => literal_elements(text, i > 0, i < last, &mut elements),
+            Part::Parameter(name) => elements.push(value(name)),
+        }
+    }
+    elements
+}
+
+/// A placeholder before the template is instantiated: any text, captured
+/// as a field named after its parameter.
+pub fn placeholder(name: &str) -> PatternElement {
+    PatternElement::Modified {
+        element: Box::new(PatternElement::Raw { regex: String::from(".+?") }),
+        modifier: ElementModifier {
+            capture: Some(name.to_string()),
+            ..ElementModifier::default()
+        },
+    }
+}
+
+impl Pattern {
+    /// The template's elements with each placeholder left as
+    /// `placeholder`; empty for other patterns.
+    pub fn template_elements(&self) -> Vec<PatternElement> {
+        match self {
+            Pattern::Template { body, .. } => {
+                let mut elements = elements(body, placeholder);
+                // A placeholder at the end takes the rest of the text instead of one character
+                if let Some(PatternElement::Modified { element, .. }) = elements.last_mut() {
+                    **element = PatternElement::Raw { regex: String::from(".+") };
+                }
+                elements
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// A sequence with the template's placeholders filled in from
+    /// `values`, by parameter name. Every parameter needs a value and
+    /// every value a parameter. The sequence is named after the template
+    /// and the values, keeps its options, tags, folder and category, and
+    /// has an empty id.
+    pub fn instantiate(&self, values: &BTreeMap<String, String>) -> Result<Pattern, String> {
+        let Pattern::Template { meta, body, parameters } = self else {
+            return Err(format!("'{}' is not a template", self.get_name()));
+        };
+        if let Some(unknown) = values.keys().find(|key| !parameters.iter().any(|p| &p.name == *key)) {
+            return Err(format!("Template '{}' has no parameter '{}'", meta.name, unknown));
+        }
+        let missing: Vec<&str> = parameters
+            .iter()
+            .filter(|p| !values.contains_key(&p.name))
+            .map(|p| p.name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!("Missing values for parameters: {}", missing.join(", ")));
+        }
+        for parameter in parameters {
+            let value = &values[&parameter.name];
+            if value.trim().is_empty() {
+                return Err(format!("The value for '{}' is empty", parameter.name));
+            }
+            if parameter.kind == ParameterKind::Regex {
+                regex::Regex::new(value).map_err(|e| format!("The value for '{}' is not a valid regex: {}", parameter.name, e))?;
+            }
+        }
+
+        let elements = elements(body, |placeholder| {
+            let parameter = parameters.iter().find(|p| p.name == placeholder);
+            let value = values[placeholder].trim().to_string();
+            match parameter.map(|p| p.kind) {
+                Some(ParameterKind::Regex) => PatternElement::Raw { regex: value },
+                _ => text_element(&value),
+            }
+        });
+        let shown: Vec<&str> = parameters.iter().map(|p| values[&p.name].as_str()).collect();
+        Ok(Pattern::Sequence {
+            meta: PatternMeta {
+                options: meta.options.clone(),
+                tags: meta.tags.clone(),
+                folder: meta.folder.clone(),
+                category: meta.category.clone(),
+                ..PatternMeta::new(&format!("{} ({})", meta.name, shown.join(", ")))
+            },
+            elements,
+        })
+    }
+
+    /// The template's parameters; empty for other patterns.
+    pub fn parameters(&self) -> &[TemplateParameter] {
+        match self {
+            Pattern::Template { parameters, .. } => parameters,
+            _ => &[],
+        }
+    }
+}
+
+/// Text as a word or phrase if it is made of words, and matched exactly
+/// otherwise.
+fn text_element(text: &str) -> PatternElement {
+    if is_wordy(text) {
+        PatternElement::word(text.to_string())
+    } else {
+        PatternElement::Raw { regex: regex::escape(text) }
+    }
+}
+
+fn is_wordy(text: &str) -> bool {
+    text.chars().all(|c| c.is_alphanumeric() || c == '_' || c == ' ')
+}
+
+/// Literal text between other elements as elements of its own: a word or
+/// phrase where it is made of words and ends in spaces or at the edge of
+/// the pattern, since words only match at word boundaries, and the exact
+/// text otherwise. Surrounding spaces become separators.
+pub fn literal_elements(text: &str, has_before: bool, has_after: bool, elements: &mut Vec<PatternElement>) {
+    let core = text.trim();
+    if core.is_empty() {
+        elements.push(PatternElement::gap(0, Some(0)));
+        return;
+    }
+    let spaced_before = text.starts_with(char::is_whitespace);
+    let spaced_after = text.ends_with(char::is_whitespace);
+    if has_before && spaced_before {
+        elements.push(PatternElement::gap(0, Some(0)));
+    }
+    if is_wordy(core) && (spaced_before || !has_before) && (spaced_after || !has_after) {
+        elements.push(PatternElement::word(core.to_string()));
+    } else {
+        elements.push(PatternElement::Raw { regex: regex::escape(core) });
+    }
+    if has_after && spaced_after {
+        elements.push(PatternElement::gap(0, Some(0)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parts, validate, ParameterKind, Part, TemplateParameter};
+    use crate::Pattern;
+    use std::collections::BTreeMap;
+
+    fn parameter(name: &str, kind: ParameterKind) -> TemplateParameter {
+        TemplateParameter { name: name.to_string(), kind }
+    }
+
+    fn template() -> Pattern {
+        Pattern::template(
+            "failure",
+            "«service» failed with code «code»",
+            vec![parameter("service", ParameterKind::Text), parameter("code", ParameterKind::Regex)],
+        )
+    }
+
+    fn values(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn bodies_split_into_text_and_placeholders() {
+        assert_eq!(
+            parts("«service» failed with code « code »"),
+            [Part::Parameter("service"), Part::Text(" failed with code "), Part::Parameter("code")]
+        );
+        assert_eq!(parts("a «b"), [Part::Text("a «b")]);
+    }
+
+    #[test]
+    fn placeholders_and_parameters_must_agree() {
+        let service = [parameter("service", ParameterKind::Text)];
+        assert!(validate("«service» failed", &service).is_ok());
+        assert!(validate("«host» failed", &service).is_err());
+        assert!(validate("«service» failed", &[]).is_err());
+        assert!(validate("failed", &service).is_err());
+        assert!(validate("«service» failed", &[service[0].clone(), service[0].clone()]).is_err());
+    }
+
+    #[test]
+    fn instances_fill_in_text_and_regex_values() {
+        let instance = template().instantiate(&values(&[("service", "auth"), ("code", "50[0-9]")])).unwrap();
+        assert_eq!(instance.get_name(), "failure (auth, 50[0-9])");
+        assert_eq!(instance.get_id(), "");
+        let re = regex::Regex::new(&instance.to_regex().unwrap()).unwrap();
+        assert!(re.is_match("auth failed with code 503"));
+        assert!(!re.is_match("auth failed with code 404"));
+        assert!(!re.is_match("billing failed with code 503"));
+    }
+
+    #[test]
+    fn text_values_are_matched_literally() {
+        let instance = template().instantiate(&values(&[("service", "a.b (v2)"), ("code", "5..")])).unwrap();
+        let re = regex::Regex::new(&instance.to_regex().unwrap()).unwrap();
+        assert!(re.is_match("a.b (v2) failed with code 500"));
+        assert!(!re.is_match("axb (v2) failed with code 500"));
+    }
+
+    #[test]
+    fn values_must_match_the_parameters() {
+        let template = template();
+        assert!(template.instantiate(&values(&[("service", "auth")])).unwrap_err().contains("code"));
+        assert!(template.instantiate(&values(&[("service", "auth"), ("code", "5"), ("host", "x")])).is_err());
+        assert!(template.instantiate(&values(&[("service", " "), ("code", "5")])).is_err());
+        assert!(template.instantiate(&values(&[("service", "auth"), ("code", "5(")])).is_err());
+        let sequence = Pattern::sequence("plain", Vec::new());
+        assert!(sequence.instantiate(&BTreeMap::new()).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use serde::{Serialize, Deserialize};
+
+/// A sample text saved with a pattern, and whether the pattern should
+/// match somewhere in it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TestCase {
+    pub text: String,
+    pub should_match: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TestCaseResult {
+    pub text: String,
+    pub should_match: bool,
+    pub matched: bool,
+    pub passed: bool,
+}
+
+pub fn run(regex: &regex::Regex, cases: &[TestCase]) -> Vec<TestCaseResult> {
+    cases
+        .iter()
+        .map(|case| {
+            let matched = regex.is_match(&case.text);
+            TestCaseResult {
+                text: case.text.clone(),
+                should_match: case.should_match,
+                matched,
+                passed: matched == case.should_match,
+            }
+        })
+        .collect()
+}
+
+/// How well a pattern separates its should-match cases from the rest.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Evaluation {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub true_negatives: usize,
+    pub false_negatives: usize,
+    /// Share of matched cases that should match; missing when nothing
+    /// matched.
+    pub precision: Option<f64>,
+    /// Share of should-match cases that matched; missing when there are
+    /// none.
+    pub recall: Option<f64>,
+    pub f1: Option<f64>,
+    /// The cases the pattern got wrong, to guide the next refinement.
+    pub failures: Vec<TestCaseResult>,
+}
+
+fn ratio(part: usize, whole: usize) -> Option<f64> {
+    (whole > 0).then(|| part as f64 / whole as f64)
+}
+
+pub fn evaluate(results: Vec<TestCaseResult>) -> Evaluation {
+    let mut evaluation = Evaluation::default();
+    for result in results {
+        match (result.should_match, result.matched) {
+            (true, true) => evaluation.true_positives += 1,
+            (false, true) => evaluation.false_positives += 1,
+            (false, false) => evaluation.true_negatives += 1,
+            (true, false) => evaluation.false_negatives += 1,
+        }
+        if !result.passed {
+            evaluation.failures.push(result);
+        }
+    }
+    let tp = evaluation.true_positives;
+    evaluation.precision = ratio(tp, tp + evaluation.false_positives);
+    evaluation.recall = ratio(tp, tp + evaluation.false_negatives);
+    evaluation.f1 = match (evaluation.precision, evaluation.recall) {
+        (Some(p), Some(r)) if p + r > 0.0 => Some(2.0 * p * r / (p + r)),
+        (Some(_), Some(_)) => Some(0.0),
+        _ => None,
+    };
+    evaluation
+}
@@ -0,0 +1,81 @@
+use serde::{Serialize, Deserialize};
+use std::str::FromStr;
+
+use crate::dialect::{DialectError, RegexDialect};
+
+/// Kinds of token matched by a curated regex instead of literal text.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum TokenClass {
+    Number,
+    Date,
+    Email,
+    Url,
+    IpAddress,
+    Custom { name: String, regex: String },
+}
+
+impl FromStr for TokenClass {
+    type Err = String;
+
+    /// Parses the built-in class names; custom classes carry their own
+    /// regex and are built directly.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace(['_', '-'], "").as_str() {
+            "number" => Ok(TokenClass::Number),
+            "date" => Ok(TokenClass::Date),
+            "email" => Ok(TokenClass::Email),
+            "url" => Ok(TokenClass::Url),
+            "ip" | "ipaddress" => Ok(TokenClass::IpAddress),
+            _ => Err(format!("Unknown token class: {}", s)),
+        }
+    }
+}
+
+/// One IPv4 octet, 0-255.
+const OCTET: &str = "(?:25[0-5]|2[0-4][0-9]|1[0-9][0-9]|[1-9]?[0-9])";
+
+impl TokenClass {
+    pub fn name(&self) -> &str {
+        match self {
+            TokenClass::Number => "number",
+            TokenClass::Date => "date",
+            TokenClass::Email => "email",
+            TokenClass::Url => "url",
+            TokenClass::IpAddress => "IP address",
+            TokenClass::Custom { name, .. } => name,
+        }
+    }
+
+    /// The regex for this class, including whatever boundaries it needs.
+    /// Built-in expansions only use syntax common to every dialect, apart
+    /// from non-capturing groups.
+    pub fn to_regex(&self, dialect: RegexDialect) -> Result<String, DialectError> {
+        let (start, end) = (dialect.word_start(), dialect.word_end());
+        let regex = match self {
+            TokenClass::Number => format!("[+-]?{}[0-9]+(?:[.,][0-9]+)*{}", start, end),
+            TokenClass::Date => format!(
+                "{}(?:[0-9]{{4}}-[0-9]{{1,2}}-[0-9]{{1,2}}|[0-9]{{1,2}}/[0-9]{{1,2}}/[0-9]{{2,4}}){}",
+                start, end
+            ),
+            TokenClass::Email => format!(
+                "{}[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\\.[A-Za-z0-9-]+)*\\.[A-Za-z]{{2,}}{}",
+                start, end
+            ),
+            TokenClass::Url => format!(
+                "{}https?://[A-Za-z0-9.-]+(?::[0-9]+)?(?:/[^ \\t\\r\\n\"'<>]*)?",
+                start
+            ),
+            TokenClass::IpAddress => format!("{}(?:{}\\.){{3}}{}{}", start, OCTET, OCTET, end),
+            TokenClass::Custom { regex, .. } => return Ok(dialect.group(regex)),
+        };
+        if dialect == RegexDialect::PosixEre {
+            // Only our own "(?:" groups and escapes need translating
+            return Ok(regex
+                .replace("(?:", "(")
+                .replace("\\t", "\t")
+                .replace("\\r", "\r")
+                .replace("\\n", "\n"));
+        }
+        Ok(regex)
+    }
+}
@@ -0,0 +1,251 @@
+use serde::{Serialize, Deserialize};
+use std::str::FromStr;
+
+/// How text is split into selectable units.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentationMode {
+    /// Runs of word characters.
+    #[default]
+    Simple,
+    /// Like `Simple`, but Chinese characters and hiragana are a word each
+    /// and katakana runs are split from the text around them, roughly as
+    /// Unicode word segmentation does without a dictionary. Combining
+    /// marks stay with their base letter.
+    Unicode,
+    /// Every character as the user perceives it (base plus combining marks,
+    /// emoji sequences), whitespace excepted.
+    Graphemes,
+}
+
+/// Which characters make up a word when splitting sample text. By default
+/// only letters and digits do, so "don't", "re-try" and "user_id" are each
+/// split in two.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct TokenizerConfig {
+    /// A hyphen between two word characters joins them ("re-try").
+    pub join_hyphens: bool,
+    /// An apostrophe between two word characters joins them ("don't").
+    pub join_apostrophes: bool,
+    /// `_` is a word character ("user_id").
+    pub underscore: bool,
+    /// Further characters that always count as word characters, e.g. "#@".
+    pub extra_word_chars: String,
+    pub mode: SegmentationMode,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WordInfo {
+    pub text: String,
+    pub start_index: usize,
+    pub end_index: usize,
+    pub word_index: usize,
+}
+
+/// How much of each document `tokenize_documents` returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenDetail {
+    /// Every word with its text and position.
+    Words,
+    /// Only word boundaries, as a flat array.
+    Offsets,
+    /// Only the number of words.
+    Counts,
+}
+
+impl FromStr for TokenDetail {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "words" | "full" => Ok(TokenDetail::Words),
+            "offsets" => Ok(TokenDetail::Offsets),
+            "counts" | "count" => Ok(TokenDetail::Counts),
+            _ => Err(format!("Unknown tokenization detail: {}", s)),
+        }
+    }
+}
+
+/// One document of a batch, by its position in the batch.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TokenizedDocument {
+    pub index: usize,
+    pub word_count: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub words: Option<Vec<WordInfo>>,
+    /// Start and end of every word, flattened: `[start0, end0, start1, ...]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offsets: Option<Vec<usize>>,
+}
+
+impl TokenizerConfig {
+    pub fn tokenize_document(&self, index: usize, text: &str, detail: TokenDetail) -> TokenizedDocument {
+        let words = self.tokenize(text);
+        let word_count = words.len();
+        let (words, offsets) = match detail {
+            TokenDetail::Words => (Some(words), None),
+            TokenDetail::Offsets => (None, Some(words.iter().flat_map(|w| [w.start_index, w.end_index]).collect())),
+            TokenDetail::Counts => (None, None),
+        };
+        TokenizedDocument { index, word_count, words, offsets }
+    }
+
+    pub fn is_word_char(&self, c: char) -> bool {
+        c.is_alphanumeric()
+            || (self.underscore && c == '_')
+            || self.extra_word_chars.contains(c)
+    }
+
+    /// Word characters, plus the marks that attach to them in Unicode mode.
+    fn continues_word(&self, c: char) -> bool {
+        self.is_word_char(c) || (self.mode == SegmentationMode::Unicode && is_combining_mark(c))
+    }
+
+    /// Whether `next` starts a new word even though both are word
+    /// characters.
+    fn breaks_between(&self, prev: char, next: char) -> bool {
+        if self.mode != SegmentationMode::Unicode || is_combining_mark(next) {
+            return false;
+        }
+        let (prev, next) = (script_class(prev), script_class(next));
+        prev == ScriptClass::Ideographic || next == ScriptClass::Ideographic || prev != next
+    }
+
+    /// Characters that only belong to a word when they sit between two
+    /// word characters.
+    fn is_joiner(&self, c: char) -> bool {
+        (self.join_hyphens && c == '-') || (self.join_apostrophes && matches!(c, '\'' | '\u{2019}'))
+    }
+
+    /// Splits `text` into words, with byte offsets.
+    pub fn tokenize(&self, text: &str) -> Vec<WordInfo> {
+        if self.mode == SegmentationMode::Graphemes {
+            return graphemes(text);
+        }
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let mut words = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            if !self.is_word_char(chars[i].1) {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            i += 1;
+            while i < chars.len() {
+                if self.breaks_between(chars[i - 1].1, chars[i].1) {
+                    break;
+                } else if self.continues_word(chars[i].1) {
+                    i += 1;
+                } else if self.is_joiner(chars[i].1)
+                    && chars.get(i + 1).is_some_and(|&(_, next)| self.is_word_char(next))
+                {
+                    i += 2;
+                } else {
+                    break;
+                }
+            }
+            let start_index = chars[start].0;
+            let end_index = chars.get(i).map_or(text.len(), |&(offset, _)| offset);
+            words.push(WordInfo {
+                text: text[start_index..end_index].to_string(),
+                start_index,
+                end_index,
+                word_index: words.len(),
+            });
+        }
+        words
+    }
+
+    /// The word containing the character at `position` (counted in
+    /// characters, not bytes).
+    pub fn word_at(&self, text: &str, position: usize) -> Option<String> {
+        let (offset, _) = text.char_indices().nth(position)?;
+        self.tokenize(text)
+            .into_iter()
+            .find(|word| word.start_index <= offset && offset < word.end_index)
+            .map(|word| word.text)
+    }
+}
+
+/// Scripts written without spaces between words. `\b` finds no boundary
+/// between two of their letters, since all of them are word characters.
+pub fn is_scriptio_continua(c: char) -> bool {
+    matches!(script_class(c), ScriptClass::Ideographic | ScriptClass::Katakana | ScriptClass::Continua)
+}
+
+/// Whether a `\b` next to `c` means something: `c` is a word character of
+/// a script that separates words with spaces.
+pub fn takes_word_boundary(c: char) -> bool {
+    (c.is_alphanumeric() || c == '_') && !is_scriptio_continua(c)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ScriptClass {
+    /// Chinese characters and hiragana, a word per character.
+    Ideographic,
+    Katakana,
+    /// Thai, Lao, Khmer and Myanmar, kept as runs (splitting them needs a
+    /// dictionary).
+    Continua,
+    Other,
+}
+
+fn script_class(c: char) -> ScriptClass {
+    match c as u32 {
+        0x2E80..=0x2FDF | 0x3005..=0x3007 | 0x3021..=0x3029 | 0x3038..=0x303B | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x323AF | 0x3040..=0x309F => ScriptClass::Ideographic,
+        0x30A0..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => ScriptClass::Katakana,
+        0x0E00..=0x0EFF | 0x1000..=0x109F | 0x1780..=0x17FF => ScriptClass::Continua,
+        _ => ScriptClass::Other,
+    }
+}
+
+/// Diacritics, variation selectors and the zero-width joiner, which attach
+/// to the character before them.
+fn is_combining_mark(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x200D | 0x20D0..=0x20FF | 0x3099..=0x309A
+            | 0xFE00..=0xFE0F | 0xFE20..=0xFE2F | 0x1F3FB..=0x1F3FF | 0xE0020..=0xE007F | 0xE0100..=0xE01EF
+    )
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c as u32, 0x1F1E6..=0x1F1FF)
+}
+
+/// Approximate grapheme clusters: a base character with its combining
+/// marks, joined emoji sequences and flag pairs.
+fn graphemes(text: &str) -> Vec<WordInfo> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut words = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let start = i;
+        let base = chars[i].1;
+        i += 1;
+        if is_regional_indicator(base) && chars.get(i).is_some_and(|&(_, c)| is_regional_indicator(c)) {
+            i += 1;
+        }
+        while i < chars.len() && is_combining_mark(chars[i].1) {
+            // A joiner pulls in the next character as well
+            i += if chars[i].1 == '\u{200D}' && i + 1 < chars.len() { 2 } else { 1 };
+        }
+        if base.is_whitespace() {
+            continue;
+        }
+        let start_index = chars[start].0;
+        let end_index = chars.get(i).map_or(text.len(), |&(offset, _)| offset);
+        words.push(WordInfo {
+            text: text[start_index..end_index].to_string(),
+            start_index,
+            end_index,
+            word_index: words.len(),
+        });
+    }
+    words
+}
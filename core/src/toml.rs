@@ -0,0 +1,428 @@
+use serde_json::{Map, Value};
+
+use crate::library_file::ordered_entries;
+
+/// Writes an object as TOML, keys in `ordered_entries` order: scalars
+/// and nested values as `key = value` with inline tables, then each array
+/// of objects as `[[key]]` tables. `comment` names each of those tables
+/// in a `#` line above it, e.g. after a pattern's name. TOML has no null,
+/// so null fields are left out; a null in an array is an error.
+pub fn to_toml(value: &Value, comment: &dyn Fn(&Value) -> Option<String>) -> Result<String, String> {
+    let Value::Object(map) = value else {
+        return Err(String::from("Only objects can be written as TOML"));
+    };
+    let mut out = String::new();
+    let mut tables = Vec::new();
+    for (key, value) in ordered_entries(map) {
+        match value {
+            Value::Null => {}
+            Value::Array(items) if !items.is_empty() && items.iter().all(Value::is_object) => tables.push((key, items)),
+            value => out.push_str(&format!("{} = {}\n", key_text(key), inline(value, 0)?)),
+        }
+    }
+    for (key, items) in tables {
+        for item in items {
+            out.push('\n');
+            if let Some(text) = comment(item) {
+                out.push_str(&format!("# {}\n", text.replace('\n', " ")));
+            }
+            out.push_str(&format!("[[{}]]\n", key_text(key)));
+            if let Value::Object(fields) = item {
+                for (field, value) in ordered_entries(fields) {
+                    if !value.is_null() {
+                        out.push_str(&format!("{} = {}\n", key_text(field), inline(value, 0)?));
+                    }
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// A value on the right of `=`. Arrays of tables span a line per table,
+/// indented by `depth`; everything else stays on one line.
+fn inline(value: &Value, depth: usize) -> Result<String, String> {
+    Ok(match value {
+        Value::Null => return Err(String::from("TOML can't hold a null in a list")),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        // JSON string escapes are valid TOML basic string ones
+        Value::String(s) => serde_json::to_string(s).unwrap_or_default(),
+        Value::Array(items) if items.iter().any(Value::is_object) => {
+            let indent = "  ".repeat(depth + 1);
+            let mut text = String::from("[\n");
+            for item in items {
+                text.push_str(&format!("{}{},\n", indent, inline(item, depth + 1)?));
+            }
+            text.push_str(&"  ".repeat(depth));
+            text.push(']');
+            text
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(|item| inline(item, depth)).collect::<Result<_, _>>()?;
+            format!("[{}]", items.join(", "))
+        }
+        Value::Object(map) => {
+            let mut fields = Vec::new();
+            for (key, value) in ordered_entries(map) {
+                if !value.is_null() {
+                    fields.push(format!("{} = {}", key_text(key), inline(value, depth)?));
+                }
+            }
+            match fields.is_empty() {
+                true => String::from("{}"),
+                false => format!("{{ {} }}", fields.join(", ")),
+            }
+        }
+    })
+}
+
+fn key_text(key: &str) -> String {
+    let bare = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        key.to_string()
+    } else {
+        serde_json::to_string(key).unwrap_or_default()
+    }
+}
+
+/// Reads the TOML `to_toml` writes, and hand edits of it: comments,
+/// `[table]` and `[[array]]` headers, literal strings, and arrays over
+/// several lines. Dotted keys, multi-line strings and dates aren't
+/// supported.
+pub fn from_toml(text: &str) -> Result<Value, String> {
+    let mut parser = Parser { chars: text.chars().collect(), pos: 0, line: 1 };
+    let mut root = Map::new();
+    // Where `key = value` lines go: the root, a `[table]`, or the last
+    // table of an `[[array]]`
+    let mut section: Option<(String, bool)> = None;
+    loop {
+        parser.skip_blank_lines();
+        let Some(c) = parser.peek() else { break };
+        if c == '[' {
+            let array = parser.chars.get(parser.pos + 1) == Some(&'[');
+            parser.pos += if array { 2 } else { 1 };
+            parser.skip_spaces();
+            let name = parser.key()?;
+            parser.skip_spaces();
+            parser.expect(']')?;
+            if array {
+                parser.expect(']')?;
+            }
+            parser.end_of_line()?;
+            let slot = root.entry(name.clone()).or_insert_with(|| if array { Value::Array(Vec::new()) } else { Value::Object(Map::new()) });
+            match (array, slot) {
+                (true, Value::Array(tables)) => tables.push(Value::Object(Map::new())),
+                (false, Value::Object(_)) => {}
+                _ => return Err(parser.error(&format!("'{}' is defined twice", name))),
+            }
+            section = Some((name, array));
+            continue;
+        }
+        let key = parser.key()?;
+        parser.skip_spaces();
+        parser.expect('=')?;
+        parser.skip_spaces();
+        let value = parser.value()?;
+        parser.end_of_line()?;
+        let table = match &section {
+            None => &mut root,
+            Some((name, array)) => match root.get_mut(name) {
+                Some(Value::Array(tables)) if *array => match tables.last_mut() {
+                    Some(Value::Object(table)) => table,
+                    _ => return Err(parser.error("Expected a table")),
+                },
+                Some(Value::Object(table)) => table,
+                _ => return Err(parser.error("Expected a table")),
+            },
+        };
+        if table.insert(key.clone(), value).is_some() {
+            return Err(parser.error(&format!("'{}' is defined twice", key)));
+        }
+    }
+    Ok(Value::Object(root))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> String {
+        format!("Line {}: {}", self.line, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() != Some(c) {
+            return Err(self.error(&format!("Expected '{}'", c)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Skips spaces, comments and line breaks.
+    fn skip_blank_lines(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n') => {
+                    self.pos += 1;
+                    self.line += 1;
+                }
+                Some('\r') => self.pos += 1,
+                _ => return,
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_spaces();
+        self.skip_comment();
+        match self.peek() {
+            None | Some('\n' | '\r') => Ok(()),
+            Some(c) => Err(self.error(&format!("Unexpected '{}' after the value", c))),
+        }
+    }
+
+    fn key(&mut self) -> Result<String, String> {
+        let key = match self.peek() {
+            Some('"') => self.basic_string()?,
+            Some('\'') => self.literal_string()?,
+            _ => {
+                let start = self.pos;
+                while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                    self.pos += 1;
+                }
+                if start == self.pos {
+                    return Err(self.error("Expected a key"));
+                }
+                self.chars[start..self.pos].iter().collect()
+            }
+        };
+        if self.peek() == Some('.') {
+            return Err(self.error("Dotted keys aren't supported"));
+        }
+        Ok(key)
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') if self.chars.get(self.pos + 1..self.pos + 3) == Some(&['"', '"']) => {
+                Err(self.error("Multi-line strings aren't supported"))
+            }
+            Some('"') => Ok(Value::String(self.basic_string()?)),
+            Some('\'') => Ok(Value::String(self.literal_string()?)),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            Some(_) => self.bare_value(),
+            None => Err(self.error("Expected a value")),
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n') => return Err(self.error("Unterminated string")),
+                Some('"') => {
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                Some('\\') => {
+                    let escape = self.chars.get(self.pos + 1).copied();
+                    self.pos += 2;
+                    match escape {
+                        Some('n') => text.push('\n'),
+                        Some('t') => text.push('\t'),
+                        Some('r') => text.push('\r'),
+                        Some('b') => text.push('\u{8}'),
+                        Some('f') => text.push('\u{c}'),
+                        Some('"') => text.push('"'),
+                        Some('\\') => text.push('\\'),
+                        Some(u @ ('u' | 'U')) => {
+                            let len = if u == 'u' { 4 } else { 8 };
+                            let hex: String = self.chars.iter().skip(self.pos).take(len).collect();
+                            let c = u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .filter(|_| hex.len() == len)
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error(&format!("Invalid escape \\{}{}", u, hex)))?;
+                            text.push(c);
+                            self.pos += len;
+                        }
+                        other => return Err(self.error(&format!("Invalid escape \\{}", other.unwrap_or(' ')))),
+                    }
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        self.expect('\'')?;
+        let start = self.pos;
+        while !matches!(self.peek(), None | Some('\'' | '\n')) {
+            self.pos += 1;
+        }
+        let text = self.chars[start..self.pos].iter().collect();
+        self.expect('\'')?;
+        Ok(text)
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank_lines();
+            if self.peek() == Some(']') {
+                self.pos += 1;
+                return Ok(Value::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_blank_lines();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {}
+                _ => return Err(self.error("Expected ',' or ']' in the array")),
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Value, String> {
+        self.expect('{')?;
+        let mut map = Map::new();
+        self.skip_spaces();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Value::Object(map));
+        }
+        loop {
+            self.skip_spaces();
+            let key = self.key()?;
+            self.skip_spaces();
+            self.expect('=')?;
+            self.skip_spaces();
+            let value = self.value()?;
+            if map.insert(key.clone(), value).is_some() {
+                return Err(self.error(&format!("'{}' is defined twice", key)));
+            }
+            self.skip_spaces();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(Value::Object(map));
+                }
+                _ => return Err(self.error("Expected ',' or '}' in the inline table")),
+            }
+        }
+    }
+
+    /// A boolean or number.
+    fn bare_value(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.' | '_')) {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().filter(|&&c| c != '_').collect();
+        match text.as_str() {
+            "true" => return Ok(Value::Bool(true)),
+            "false" => return Ok(Value::Bool(false)),
+            _ => {}
+        }
+        if let Ok(n) = text.parse::<i64>() {
+            return Ok(Value::from(n));
+        }
+        match text.parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(Value::from(n)),
+            _ => Err(self.error(&format!("Unsupported value: {}", text))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use std::collections::BTreeMap;
+
+    use super::{from_toml, to_toml};
+    use crate::library_file::{export_library_toml, parse_library_toml};
+    use crate::{CompositeOperator, ParameterKind, Pattern, PatternElement, TemplateParameter, WordMatch};
+
+    /// Strings that need quoting or escapes as TOML keys and values.
+    const AWKWARD: [&str; 14] = [
+        "true", "123", "1.5", "", " padded ", "a # not a comment", "#start", "key = value", "[[table]]",
+        "quote \" and \\ backslash", "it's", "tab\there\nnewline", "bell\u{7}", "héllo ✓ 日本語 🎉",
+    ];
+
+    fn no_comment(_: &Value) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn awkward_strings_round_trip() {
+        let map: serde_json::Map<String, Value> = AWKWARD.iter().map(|s| (s.to_string(), json!(s))).collect();
+        let value = json!({
+            "values": AWKWARD,
+            "keys": map,
+            "tables": [{ "inner": AWKWARD[5] }, { "list": [{ "deep": AWKWARD[9] }] }],
+        });
+        assert_eq!(from_toml(&to_toml(&value, &no_comment).unwrap()).unwrap(), value);
+    }
+
+    #[test]
+    fn hand_edits_read_back() {
+        let text = "# a comment\nname = 'C:\\path' # trailing\nflags = [\n  1,\n  2,\n]\n\n[settings]\nmode = \"a\\u00e9\"\n";
+        assert_eq!(from_toml(text).unwrap(), json!({ "name": "C:\\path", "flags": [1, 2], "settings": { "mode": "aé" } }));
+    }
+
+    #[test]
+    fn nested_composites_and_templates_round_trip() {
+        let word = |text: &str| PatternElement::Word { text: text.to_string(), mode: WordMatch::Exact };
+        let mut inner = Pattern::composite("inner = \"quoted\"", CompositeOperator::Not, vec![Pattern::sequence("true", vec![word("#hash")])]);
+        inner.meta_mut().tags = vec!["[x]".to_string(), "ünïcode".to_string()];
+        let mut outer = Pattern::composite("outer", CompositeOperator::Or, vec![Pattern::sequence("plain", vec![word("a = b")]), inner]);
+        outer.set_id("outer".to_string());
+        outer.meta_mut().description = "line one\nline two # not a comment".to_string();
+        let mut template = Pattern::template(
+            "«service» failed",
+            "«service» failed with code «code»",
+            vec![
+                TemplateParameter { name: "service".to_string(), kind: ParameterKind::Text },
+                TemplateParameter { name: "code".to_string(), kind: ParameterKind::Regex },
+            ],
+        );
+        template.set_id("template".to_string());
+        let patterns = vec![outer, template];
+        let documents = BTreeMap::from([("log = 1".to_string(), "[[not a table]]\n# not a comment".to_string())]);
+
+        let toml = export_library_toml(&patterns, &documents).unwrap();
+        let read = parse_library_toml(&toml).unwrap();
+        assert_eq!(serde_json::to_value(&read).unwrap(), serde_json::to_value(&patterns).unwrap());
+    }
+}
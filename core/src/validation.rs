@@ -0,0 +1,253 @@
+use regex_syntax::hir::{Class, ClassUnicode, ClassUnicodeRange, Hir, HirKind};
+use serde::{Serialize, Deserialize};
+
+use crate::portability::portability_warnings;
+use crate::{Pattern, RegexContext, RegexDialect};
+
+/// Patterns whose estimated size exceeds this get a warning; the regex
+/// crate's default compile limit is reached somewhere beyond it.
+pub const LARGE_PATTERN_SIZE: usize = 10_000;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl std::str::FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Ok(Severity::Error),
+            "warning" => Ok(Severity::Warning),
+            "info" => Ok(Severity::Info),
+            _ => Err(format!("Unknown severity: {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: String,
+    pub message: String,
+    /// The dialect the diagnostic applies to, if only one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dialect: Option<RegexDialect>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ValidationReport {
+    /// The regex the builder tests with.
+    pub regex: String,
+    /// Whether the builder can compile and test `regex`.
+    pub compiles: bool,
+    pub regex_length: usize,
+    /// Rough number of regex program states, with bounded repetitions
+    /// spelled out. Missing when no Rust regex could be produced.
+    pub estimated_size: Option<usize>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+fn diagnostic(severity: Severity, code: &str, message: String, dialect: Option<RegexDialect>) -> Diagnostic {
+    Diagnostic {
+        severity,
+        code: code.to_string(),
+        message,
+        dialect,
+    }
+}
+
+/// Checks everything that can go wrong with `pattern` before it is put
+/// to use: compiling, size, backtracking risk and dialect support.
+pub fn validate(pattern: &Pattern, context: &RegexContext) -> ValidationReport {
+    let mut diagnostics = Vec::new();
+    let regex = match pattern.to_regex_with(context) {
+        Ok(regex) => Some(regex),
+        Err(e) => {
+            diagnostics.push(diagnostic(Severity::Error, "no_regex", format!("The pattern has no regex: {}", e), None));
+            None
+        }
+    };
+
+    let compiles = match regex.as_deref().map(regex::Regex::new) {
+        None => false,
+        Some(Ok(_)) => true,
+        Some(Err(e)) => {
+            diagnostics.push(diagnostic(
+                Severity::Error,
+                "compile_error",
+                format!("The regex does not compile, so the pattern can't be tested here: {}", e),
+                None,
+            ));
+            false
+        }
+    };
+
+    // Lookaround can't be analysed, so look at the lookahead-free form
+    let hir = pattern
+        .to_regex_for_with(RegexDialect::Rust, context)
+        .ok()
+        .and_then(|rust| regex_syntax::Parser::new().parse(&rust).ok());
+    let estimated_size = hir.as_ref().map(estimate_size);
+    if let Some(size) = estimated_size.filter(|&size| size > LARGE_PATTERN_SIZE) {
+        diagnostics.push(diagnostic(
+            Severity::Warning,
+            "large_pattern",
+            format!(
+                "The regex expands to about {} states; large option lists or repetition counts make it slow to compile and match",
+                size
+            ),
+            None,
+        ));
+    }
+    if let Some(hir) = &hir {
+        let mut nested = Vec::new();
+        find_nested_quantifiers(hir, &ClassUnicode::empty(), false, &mut nested);
+        for sub in nested {
+            diagnostics.push(diagnostic(
+                Severity::Warning,
+                "nested_quantifier",
+                format!(
+                    "`{}` is repeated inside another unbounded repetition and can match the same text in many ways; backtracking engines such as JavaScript and PCRE may take exponential time on text that almost matches",
+                    sub
+                ),
+                None,
+            ));
+        }
+    }
+
+    for dialect in RegexDialect::ALL {
+        match pattern.to_regex_for_with(dialect, context) {
+            Ok(_) => {
+                for warning in portability_warnings(pattern, dialect) {
+                    diagnostics.push(diagnostic(Severity::Info, &warning.code, warning.message, Some(dialect)));
+                }
+            }
+            Err(e) => diagnostics.push(diagnostic(Severity::Warning, "dialect_unsupported", e.to_string(), Some(dialect))),
+        }
+    }
+
+    let regex = regex.unwrap_or_default();
+    ValidationReport {
+        regex_length: regex.chars().count(),
+        regex,
+        compiles,
+        estimated_size,
+        diagnostics,
+    }
+}
+
+/// Node count of `hir` with every bounded repetition repeated out, which
+/// is roughly how an automaton for it grows.
+fn estimate_size(hir: &Hir) -> usize {
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => 1,
+        HirKind::Literal(literal) => literal.0.len(),
+        HirKind::Class(Class::Unicode(class)) => class.ranges().len(),
+        HirKind::Class(Class::Bytes(class)) => class.ranges().len(),
+        HirKind::Repetition(repetition) => {
+            let copies = repetition.max.unwrap_or(repetition.min).max(1) as usize;
+            estimate_size(&repetition.sub).saturating_mul(copies).saturating_add(1)
+        }
+        HirKind::Capture(capture) => estimate_size(&capture.sub),
+        HirKind::Concat(subs) | HirKind::Alternation(subs) => {
+            subs.iter().map(estimate_size).fold(1, usize::saturating_add)
+        }
+    }
+}
+
+/// Characters `hir` can start with.
+fn first_chars(hir: &Hir) -> ClassUnicode {
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => ClassUnicode::empty(),
+        HirKind::Literal(literal) => {
+            let first = std::str::from_utf8(&literal.0)
+                .ok()
+                .and_then(|s| s.chars().next())
+                .or_else(|| literal.0.first().map(|&b| b as char));
+            match first {
+                Some(c) => ClassUnicode::new([ClassUnicodeRange::new(c, c)]),
+                None => ClassUnicode::empty(),
+            }
+        }
+        HirKind::Class(Class::Unicode(class)) => class.clone(),
+        HirKind::Class(Class::Bytes(class)) => ClassUnicode::new(
+            class
+                .ranges()
+                .iter()
+                .map(|r| ClassUnicodeRange::new(r.start() as char, r.end() as char)),
+        ),
+        HirKind::Repetition(repetition) => first_chars(&repetition.sub),
+        HirKind::Capture(capture) => first_chars(&capture.sub),
+        HirKind::Concat(subs) => first_chars_of_seq(subs, &ClassUnicode::empty()),
+        HirKind::Alternation(subs) => {
+            let mut class = ClassUnicode::empty();
+            for sub in subs {
+                class.union(&first_chars(sub));
+            }
+            class
+        }
+    }
+}
+
+/// Characters a sequence can start with, or `follow` where it can be empty.
+fn first_chars_of_seq(subs: &[Hir], follow: &ClassUnicode) -> ClassUnicode {
+    let mut class = ClassUnicode::empty();
+    for sub in subs {
+        class.union(&first_chars(sub));
+        if !can_be_empty(sub) {
+            return class;
+        }
+    }
+    class.union(follow);
+    class
+}
+
+fn can_be_empty(hir: &Hir) -> bool {
+    hir.properties().minimum_len() == Some(0)
+}
+
+fn overlaps(a: &ClassUnicode, b: &ClassUnicode) -> bool {
+    let mut both = a.clone();
+    both.intersect(b);
+    !both.ranges().is_empty()
+}
+
+/// Collects unbounded repetitions inside another unbounded repetition
+/// whose characters overlap with what may follow them, e.g. `(a+)+` or
+/// `(?:.*x)*`. Where they don't overlap, as in `(?:\w+\W+)*`, there is
+/// only one way to split the text and backtracking stays linear.
+fn find_nested_quantifiers(hir: &Hir, follow: &ClassUnicode, in_loop: bool, found: &mut Vec<String>) {
+    match hir.kind() {
+        HirKind::Repetition(repetition) => {
+            let first = first_chars(&repetition.sub);
+            let unbounded = repetition.max.is_none();
+            if unbounded && in_loop && overlaps(&first, follow) && !found.contains(&hir.to_string()) {
+                found.push(hir.to_string());
+                return;
+            }
+            let mut inner_follow = follow.clone();
+            if repetition.max != Some(1) {
+                inner_follow.union(&first);
+            }
+            find_nested_quantifiers(&repetition.sub, &inner_follow, in_loop || unbounded, found);
+        }
+        HirKind::Capture(capture) => find_nested_quantifiers(&capture.sub, follow, in_loop, found),
+        HirKind::Alternation(subs) => {
+            for sub in subs {
+                find_nested_quantifiers(sub, follow, in_loop, found);
+            }
+        }
+        HirKind::Concat(subs) => {
+            for (i, sub) in subs.iter().enumerate() {
+                let after = first_chars_of_seq(&subs[i + 1..], follow);
+                find_nested_quantifiers(sub, &after, in_loop, found);
+            }
+        }
+        HirKind::Empty | HirKind::Literal(_) | HirKind::Class(_) | HirKind::Look(_) => {}
+    }
+}
@@ -0,0 +1,90 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{elements_from_selections, matching, CompositeOperator, GapStrategy, MatchOptions, Pattern, PatternElement, RegexContext, RegexDialect, SelectionSpan};
+
+/// How closely a variant sticks to the selected text, strictest first.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Strictness {
+    /// Exactly as many words between the selections as in the sample,
+    /// case-sensitive.
+    Exact,
+    /// Up to as many words between the selections, case-sensitive.
+    Near,
+    /// The selections in order with anything between, ignoring case.
+    Ordered,
+    /// All the selections anywhere, in any order, ignoring case.
+    Unordered,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Variant {
+    pub strictness: Strictness,
+    pub pattern: Pattern,
+    pub regex: String,
+    /// Matches in the source text; missing if the variant can't be
+    /// tested here (an AND of too many keywords).
+    pub match_count: Option<usize>,
+}
+
+fn with_case(mut pattern: Pattern, base: &MatchOptions, case_insensitive: bool) -> Pattern {
+    pattern.meta_mut().options = MatchOptions {
+        case_insensitive,
+        ..base.clone()
+    };
+    pattern
+}
+
+/// One pattern per strictness level for `selections` (sorted by word
+/// index), each tested against `text`. Levels that come out the same as
+/// the stricter one before them are left out.
+pub fn variants(
+    selections: &[SelectionSpan],
+    base: &MatchOptions,
+    context: &RegexContext,
+    text: &str,
+) -> Vec<Variant> {
+    let sequence = |strategy| Pattern::sequence("Variant", elements_from_selections(selections, strategy));
+    let ordered = sequence(GapStrategy::Open);
+    let keywords: Vec<Pattern> = match &ordered {
+        Pattern::Sequence { elements, .. } => elements
+            .iter()
+            .filter(|e| !matches!(e, PatternElement::Gap { .. }))
+            .map(|e| Pattern::sequence("Keyword", vec![e.clone()]))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let unordered = match keywords.len() {
+        1 => ordered.clone(),
+        _ => Pattern::composite("Variant", CompositeOperator::And, keywords),
+    };
+
+    let candidates = [
+        (Strictness::Exact, with_case(sequence(GapStrategy::Exact), base, false)),
+        (Strictness::Near, with_case(sequence(GapStrategy::UpTo), base, false)),
+        (Strictness::Ordered, with_case(ordered, base, true)),
+        (Strictness::Unordered, with_case(unordered, base, true)),
+    ];
+    let mut variants: Vec<Variant> = Vec::new();
+    for (strictness, pattern) in candidates {
+        let Ok(regex) = pattern.to_regex_with(context) else {
+            continue;
+        };
+        if variants.last().is_some_and(|v| v.regex == regex) {
+            continue;
+        }
+        // AND needs lookahead in `to_regex`; count with the ordered alternatives instead
+        let match_count = pattern
+            .to_regex_for_with(RegexDialect::Rust, context)
+            .ok()
+            .and_then(|rust| regex::Regex::new(&rust).ok())
+            .map(|re| matching::find_scoped(&re, text, pattern.meta().options.scope).len());
+        variants.push(Variant {
+            strictness,
+            pattern,
+            regex,
+            match_count,
+        });
+    }
+    variants
+}
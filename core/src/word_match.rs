@@ -0,0 +1,172 @@
+use serde::{Serialize, Deserialize};
+use std::str::FromStr;
+
+use crate::dialect::RegexDialect;
+use crate::tokenizer::takes_word_boundary;
+
+/// How a `Word` element matches its text.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WordMatch {
+    #[default]
+    Exact,
+    /// In any mix of upper and lower case.
+    CaseInsensitive,
+    /// As the start of a longer word: "connect" also matches "connected"
+    /// and "connection".
+    Prefix,
+    /// Within one edit: a character added, dropped or changed.
+    Approximate,
+    /// Anywhere, even inside a longer word: "2024-01" also matches in
+    /// "2024-01-15".
+    Substring,
+}
+
+impl FromStr for WordMatch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "_").as_str() {
+            "exact" => Ok(WordMatch::Exact),
+            "case_insensitive" | "ignore_case" => Ok(WordMatch::CaseInsensitive),
+            "prefix" | "stem" => Ok(WordMatch::Prefix),
+            "approximate" | "fuzzy" => Ok(WordMatch::Approximate),
+            "substring" | "anywhere" => Ok(WordMatch::Substring),
+            _ => Err(format!("Unknown word match mode: {}", s)),
+        }
+    }
+}
+
+impl WordMatch {
+    pub fn is_exact(&self) -> bool {
+        *self == WordMatch::Exact
+    }
+
+    /// The regex for `text` matched this way, with word boundaries on each
+    /// side where one means something ("C++", "#tag", "東京").
+    pub fn to_regex(self, text: &str, dialect: RegexDialect) -> String {
+        let (start, end) = match self {
+            WordMatch::Substring => ("", ""),
+            _ => edge_boundaries(text, dialect),
+        };
+        let body = match self {
+            WordMatch::Exact | WordMatch::Substring => dialect.escape(text),
+            WordMatch::CaseInsensitive => case_insensitive(text, dialect),
+            // The end boundary still applies after the rest of the word
+            WordMatch::Prefix => format!("{}{}*", dialect.escape(text), dialect.word_char()),
+            WordMatch::Approximate => dialect.group(&one_edit(text, dialect).join("|")),
+        };
+        format!("{}{}{}", start, body, end)
+    }
+}
+
+/// The word boundaries to put before and after literal `text`, each only
+/// where its first or last character is a word character: `\b` beside
+/// "(" in "malloc(" would demand a word character after the parenthesis.
+pub fn edge_boundaries(text: &str, dialect: RegexDialect) -> (&'static str, &'static str) {
+    let start = if text.starts_with(takes_word_boundary) { dialect.word_start() } else { "" };
+    let end = if text.ends_with(takes_word_boundary) { dialect.word_end() } else { "" };
+    (start, end)
+}
+
+/// An inline flag where the dialect has one; POSIX gets a bracket per letter.
+fn case_insensitive(text: &str, dialect: RegexDialect) -> String {
+    if dialect != RegexDialect::PosixEre {
+        return format!("(?i:{})", dialect.escape(text));
+    }
+    text.chars()
+        .map(|c| {
+            let (upper, lower): (String, String) = (c.to_uppercase().collect(), c.to_lowercase().collect());
+            if upper != lower && upper.chars().count() == 1 && lower.chars().count() == 1 {
+                format!("[{}{}]", upper, lower)
+            } else {
+                dialect.escape(&c.to_string())
+            }
+        })
+        .collect()
+}
+
+/// Every spelling of `text` one edit away, as alternation branches: each
+/// character replaced by any word character (which includes itself),
+/// each dropped, and one inserted at each position.
+fn one_edit(text: &str, dialect: RegexDialect) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let literal = |part: &[char]| dialect.escape(&part.iter().collect::<String>());
+    let any = dialect.word_char();
+    let substitutions = (0..chars.len()).map(|i| format!("{}{}{}", literal(&chars[..i]), any, literal(&chars[i + 1..])));
+    let deletions = (0..chars.len()).map(|i| format!("{}{}", literal(&chars[..i]), literal(&chars[i + 1..])));
+    let insertions = (0..=chars.len()).map(|i| format!("{}{}{}", literal(&chars[..i]), any, literal(&chars[i..])));
+
+    let mut branches: Vec<String> = Vec::new();
+    for branch in substitutions.chain(deletions).chain(insertions) {
+        // Dropping either of a doubled letter spells the same thing
+        if !branch.is_empty() && !branches.contains(&branch) {
+            branches.push(branch);
+        }
+    }
+    branches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edge_boundaries, one_edit, WordMatch};
+    use crate::dialect::RegexDialect;
+
+    fn found(mode: WordMatch, word: &str, text: &str) -> Option<String> {
+        let re = regex::Regex::new(&mode.to_regex(word, RegexDialect::Rust)).unwrap();
+        re.find(text).map(|m| m.as_str().to_string())
+    }
+
+    #[test]
+    fn prefixes_match_the_whole_longer_word() {
+        assert_eq!(found(WordMatch::Prefix, "connect", "connection lost").as_deref(), Some("connection"));
+        assert_eq!(found(WordMatch::Prefix, "connect", "connect").as_deref(), Some("connect"));
+        assert_eq!(found(WordMatch::Prefix, "connect", "reconnected"), None);
+    }
+
+    #[test]
+    fn substrings_match_inside_words() {
+        assert_eq!(found(WordMatch::Substring, "connect", "reconnected").as_deref(), Some("connect"));
+        assert_eq!(found(WordMatch::Exact, "connect", "reconnected"), None);
+    }
+
+    #[test]
+    fn approximate_words_are_within_one_edit() {
+        for near in ["cat", "bat", "cab", "at", "ca", "scat", "cats", "cast"] {
+            assert_eq!(found(WordMatch::Approximate, "cat", near).as_deref(), Some(near), "cat ~ {}", near);
+        }
+        for far in ["dog", "coast", "bats", "c"] {
+            assert_eq!(found(WordMatch::Approximate, "cat", far), None, "cat !~ {}", far);
+        }
+    }
+
+    #[test]
+    fn one_edit_covers_both_ends() {
+        let branches = one_edit("ab", RegexDialect::Rust);
+        // Substitutions, deletions, then insertions, first to last
+        assert_eq!(branches, vec![r"\wb", r"a\w", "b", "a", r"\wab", r"a\wb", r"ab\w"]);
+        // Dropping either "o" spells "fod" once
+        assert_eq!(one_edit("food", RegexDialect::Rust).iter().filter(|branch| *branch == "fod").count(), 1);
+        // No empty branch for a single character
+        assert!(!one_edit("a", RegexDialect::Rust).contains(&String::new()));
+    }
+
+    #[test]
+    fn boundaries_only_beside_word_characters() {
+        let rust = RegexDialect::Rust;
+        assert_eq!(edge_boundaries("free", rust), (r"\b", r"\b"));
+        assert_eq!(edge_boundaries("malloc(", rust), (r"\b", ""));
+        assert_eq!(edge_boundaries("#tag", rust), ("", r"\b"));
+        assert_eq!(edge_boundaries("C++", rust), (r"\b", ""));
+        assert_eq!(edge_boundaries("(x)", rust), ("", ""));
+        assert_eq!(edge_boundaries("東京", rust), ("", ""));
+        assert_eq!(edge_boundaries("free", RegexDialect::PosixEre), (r"\<", r"\>"));
+    }
+
+    #[test]
+    fn modes_parse_by_name_and_alias() {
+        assert_eq!("fuzzy".parse(), Ok(WordMatch::Approximate));
+        assert_eq!("ignore-case".parse(), Ok(WordMatch::CaseInsensitive));
+        assert!("loose".parse::<WordMatch>().is_err());
+    }
+}
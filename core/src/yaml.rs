@@ -0,0 +1,375 @@
+use serde_json::{Map, Value};
+
+use crate::library_file::ordered_entries;
+
+/// Indentation per nesting level.
+const INDENT: usize = 2;
+
+/// Writes `value` as block-style YAML, keys in `ordered_entries` order.
+/// Strings are quoted unless they read back unchanged without quotes.
+pub fn to_yaml(value: &Value) -> String {
+    let mut out = String::new();
+    match value {
+        Value::Object(map) if !map.is_empty() => write_map(map, 0, &mut out),
+        Value::Array(items) if !items.is_empty() => write_seq(items, 0, &mut out),
+        scalar => {
+            out.push_str(&scalar_text(scalar));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn write_map(map: &Map<String, Value>, indent: usize, out: &mut String) {
+    for (key, value) in ordered_entries(map) {
+        out.push_str(&" ".repeat(indent));
+        out.push_str(&string_text(key));
+        out.push(':');
+        write_child(value, indent, out);
+    }
+}
+
+fn write_seq(items: &[Value], indent: usize, out: &mut String) {
+    for item in items {
+        out.push_str(&" ".repeat(indent));
+        out.push('-');
+        match item {
+            // The first entry goes on the dash's line, the rest under it
+            Value::Object(map) if !map.is_empty() => {
+                let mut nested = String::new();
+                write_map(map, indent + INDENT, &mut nested);
+                out.push(' ');
+                out.push_str(&nested[indent + INDENT..]);
+            }
+            Value::Array(inner) if !inner.is_empty() => {
+                let mut nested = String::new();
+                write_seq(inner, indent + INDENT, &mut nested);
+                out.push(' ');
+                out.push_str(&nested[indent + INDENT..]);
+            }
+            scalar => {
+                out.push(' ');
+                out.push_str(&scalar_text(scalar));
+                out.push('\n');
+            }
+        }
+    }
+}
+
+/// What follows `key:` for `value`: a scalar on the same line, or a
+/// nested block on the lines after it.
+fn write_child(value: &Value, indent: usize, out: &mut String) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            out.push('\n');
+            write_map(map, indent + INDENT, out);
+        }
+        Value::Array(items) if !items.is_empty() => {
+            out.push('\n');
+            write_seq(items, indent + INDENT, out);
+        }
+        scalar => {
+            out.push(' ');
+            out.push_str(&scalar_text(scalar));
+            out.push('\n');
+        }
+    }
+}
+
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::from("null"),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => string_text(s),
+        Value::Array(_) => String::from("[]"),
+        Value::Object(_) => String::from("{}"),
+    }
+}
+
+/// A string plain where that is unambiguous, otherwise double-quoted.
+/// JSON string escapes are valid YAML ones.
+fn string_text(s: &str) -> String {
+    if is_plain(s) {
+        s.to_string()
+    } else {
+        serde_json::to_string(s).unwrap_or_default()
+    }
+}
+
+fn is_plain(s: &str) -> bool {
+    let Some(first) = s.chars().next() else { return false };
+    (first.is_alphabetic() || first == '_')
+        && !s.ends_with(' ')
+        && s.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ' ' | '/' | '@'))
+        && matches!(plain_scalar(s), Value::String(_))
+        // Booleans to YAML 1.1 readers
+        && !["yes", "no", "on", "off", "y", "n"].contains(&s.to_ascii_lowercase().as_str())
+}
+
+/// A line of YAML with its indentation, comments and trailing space
+/// removed.
+#[derive(Clone, Debug)]
+struct Line {
+    number: usize,
+    indent: usize,
+    text: String,
+}
+
+/// Reads the block-style YAML `to_yaml` writes, as well as hand edits of
+/// it: comments, single-quoted strings and JSON-style flow collections.
+/// Anchors, tags and multi-line strings aren't supported.
+pub fn from_yaml(text: &str) -> Result<Value, String> {
+    let mut lines: Vec<Line> = Vec::new();
+    for (i, raw) in text.lines().enumerate() {
+        let content = strip_comment(raw.trim_end());
+        if content.trim().is_empty() || content.trim() == "---" {
+            continue;
+        }
+        if content.starts_with('\t') {
+            return Err(format!("Line {}: tabs can't indent YAML", i + 1));
+        }
+        let indent = content.len() - content.trim_start().len();
+        lines.push(Line { number: i + 1, indent, text: content.trim().to_string() });
+    }
+    if lines.is_empty() {
+        return Ok(Value::Null);
+    }
+    let mut parser = Parser { lines, next: 0 };
+    let indent = parser.lines[0].indent;
+    let value = parser.block(indent)?;
+    if let Some(line) = parser.lines.get(parser.next) {
+        return Err(format!("Line {}: unexpected indentation", line.number));
+    }
+    Ok(value)
+}
+
+struct Parser {
+    lines: Vec<Line>,
+    next: usize,
+}
+
+impl Parser {
+    fn block(&mut self, indent: usize) -> Result<Value, String> {
+        match self.lines.get(self.next) {
+            Some(line) if is_seq_item(&line.text) => self.seq(indent),
+            Some(_) => self.map(indent),
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn seq(&mut self, indent: usize) -> Result<Value, String> {
+        let mut items = Vec::new();
+        while let Some(line) = self.lines.get(self.next).cloned() {
+            if line.indent != indent || !is_seq_item(&line.text) {
+                break;
+            }
+            let content = line.text[1..].trim_start();
+            if content.is_empty() {
+                self.next += 1;
+                items.push(self.nested(indent)?);
+            } else if is_seq_item(content) || split_key(content).is_some() {
+                // "- key: value" opens a mapping whose entries line up
+                // with "key"
+                let inner = indent + (line.text.len() - content.len());
+                self.lines[self.next] = Line { number: line.number, indent: inner, text: content.to_string() };
+                items.push(self.block(inner)?);
+            } else {
+                self.next += 1;
+                items.push(scalar(content).map_err(|e| format!("Line {}: {}", line.number, e))?);
+            }
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn map(&mut self, indent: usize) -> Result<Value, String> {
+        let mut map = Map::new();
+        while let Some(line) = self.lines.get(self.next).cloned() {
+            if line.indent != indent || is_seq_item(&line.text) {
+                break;
+            }
+            let (key, rest) = split_key(&line.text).ok_or_else(|| format!("Line {}: expected 'key: value'", line.number))?;
+            self.next += 1;
+            let value = if rest.is_empty() {
+                match self.lines.get(self.next) {
+                    // A sequence may sit at its key's indentation
+                    Some(next) if next.indent == indent && is_seq_item(&next.text) => self.seq(indent)?,
+                    _ => self.nested(indent)?,
+                }
+            } else {
+                scalar(rest).map_err(|e| format!("Line {}: {}", line.number, e))?
+            };
+            if map.insert(key.clone(), value).is_some() {
+                return Err(format!("Line {}: '{}' appears twice", line.number, key));
+            }
+        }
+        Ok(Value::Object(map))
+    }
+
+    /// The block indented under the line before, or null without one.
+    fn nested(&mut self, indent: usize) -> Result<Value, String> {
+        match self.lines.get(self.next) {
+            Some(line) if line.indent > indent => {
+                let inner = line.indent;
+                self.block(inner)
+            }
+            _ => Ok(Value::Null),
+        }
+    }
+}
+
+fn is_seq_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Splits "key: value" (or "key:") into the key and the value text.
+fn split_key(text: &str) -> Option<(String, &str)> {
+    if text.starts_with('"') || text.starts_with('\'') {
+        let end = quoted_end(text)?;
+        let key = match scalar(&text[..end]).ok()? {
+            Value::String(key) => key,
+            _ => return None,
+        };
+        let rest = text[end..].strip_prefix(':')?;
+        return (rest.is_empty() || rest.starts_with(' ')).then(|| (key, rest.trim()));
+    }
+    let colon = text.find(": ").or_else(|| text.ends_with(':').then(|| text.len() - 1))?;
+    let key = &text[..colon];
+    if key.is_empty() || key.starts_with(['[', '{', '-']) {
+        return None;
+    }
+    Some((key.to_string(), text[colon + 1..].trim()))
+}
+
+/// The byte just past the closing quote of the string `text` starts with.
+fn quoted_end(text: &str) -> Option<usize> {
+    let quote = text.chars().next()?;
+    let mut chars = text.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                chars.next();
+            }
+            '\'' if quote == '\'' && chars.peek().map(|(_, c)| *c) == Some('\'') => {
+                chars.next();
+            }
+            c if c == quote => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `line` without a trailing `# comment`, which needs a space before it
+/// and can't be inside quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    let mut previous = ' ';
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '#' && previous.is_whitespace() => return &line[..i],
+            None if (c == '"' || c == '\'') && (previous.is_whitespace() || previous == '-' || previous == ':') => quote = Some(c),
+            None => {}
+        }
+        previous = c;
+    }
+    line
+}
+
+fn scalar(text: &str) -> Result<Value, String> {
+    if text.starts_with('"') {
+        if quoted_end(text) != Some(text.len()) {
+            return Err(format!("Unterminated or trailing text after {}", text));
+        }
+        return serde_json::from_str(text).map_err(|e| format!("Invalid quoted string: {}", e));
+    }
+    if text.starts_with('\'') {
+        if quoted_end(text) != Some(text.len()) {
+            return Err(format!("Unterminated or trailing text after {}", text));
+        }
+        return Ok(Value::String(text[1..text.len() - 1].replace("''", "'")));
+    }
+    if text.starts_with('[') || text.starts_with('{') {
+        return serde_json::from_str(text).map_err(|_| format!("Only JSON-style flow collections are supported: {}", text));
+    }
+    Ok(plain_scalar(text))
+}
+
+fn plain_scalar(text: &str) -> Value {
+    match text {
+        "null" | "~" | "Null" | "NULL" => Value::Null,
+        "true" | "True" | "TRUE" => Value::Bool(true),
+        "false" | "False" | "FALSE" => Value::Bool(false),
+        _ => {
+            if let Ok(n) = text.parse::<i64>() {
+                return Value::from(n);
+            }
+            if let Ok(n) = text.parse::<u64>() {
+                return Value::from(n);
+            }
+            match text.parse::<f64>() {
+                Ok(n) if n.is_finite() && text.chars().any(|c| c.is_ascii_digit()) => Value::from(n),
+                _ => Value::String(text.to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use std::collections::BTreeMap;
+
+    use super::{from_yaml, to_yaml};
+    use crate::library_file::{export_library_yaml, parse_library_yaml};
+    use crate::{CompositeOperator, ParameterKind, Pattern, PatternElement, TemplateParameter, WordMatch};
+
+    /// Strings YAML would read as something else, or that need escapes.
+    const AWKWARD: [&str; 16] = [
+        "true", "no", "null", "~", "123", "1.5", "", " padded ", "a # not a comment", "#start", "key: value", "- dash",
+        "quote \" and \\ backslash", "it's", "tab\there\nnewline", "héllo ✓ 日本語 🎉",
+    ];
+
+    #[test]
+    fn awkward_strings_round_trip() {
+        let map: serde_json::Map<String, Value> = AWKWARD.iter().map(|s| (s.to_string(), json!(s))).collect();
+        let value = json!({ "values": AWKWARD, "keys": map, "nested": [[AWKWARD[0], AWKWARD[8]], { "inner": AWKWARD[10] }] });
+        assert_eq!(from_yaml(&to_yaml(&value)).unwrap(), value);
+    }
+
+    #[test]
+    fn hand_edits_read_back() {
+        let text = "# a comment\nname: 'it''s' # trailing\nflags: [1, 2]\nlist:\n- a\n- \"b: c\"\n";
+        assert_eq!(from_yaml(text).unwrap(), json!({ "name": "it's", "flags": [1, 2], "list": ["a", "b: c"] }));
+    }
+
+    #[test]
+    fn nested_composites_and_templates_round_trip() {
+        let word = |text: &str| PatternElement::Word { text: text.to_string(), mode: WordMatch::Exact };
+        let mut inner = Pattern::composite("inner: \"quoted\"", CompositeOperator::Not, vec![Pattern::sequence("yes", vec![word("#hash")])]);
+        inner.meta_mut().tags = vec!["null".to_string(), "ünïcode".to_string()];
+        let mut outer = Pattern::composite("outer", CompositeOperator::Or, vec![Pattern::sequence("plain", vec![word("a: b")]), inner]);
+        outer.set_id("outer".to_string());
+        outer.meta_mut().description = "line one\nline two # not a comment".to_string();
+        let mut template = Pattern::template(
+            "«service» failed",
+            "«service» failed with code «code»",
+            vec![
+                TemplateParameter { name: "service".to_string(), kind: ParameterKind::Text },
+                TemplateParameter { name: "code".to_string(), kind: ParameterKind::Regex },
+            ],
+        );
+        template.set_id("template".to_string());
+        let patterns = vec![outer, template];
+        let documents = BTreeMap::from([("log: 1".to_string(), "true\n# not a comment".to_string())]);
+
+        let yaml = export_library_yaml(&patterns, &documents).unwrap();
+        let read = parse_library_yaml(&yaml).unwrap();
+        assert_eq!(serde_json::to_value(&read).unwrap(), serde_json::to_value(&patterns).unwrap());
+    }
+}
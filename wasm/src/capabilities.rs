@@ -0,0 +1,84 @@
+use serde::{Serialize, Deserialize};
+use wasm_bindgen::prelude::*;
+
+use crate::{web_clipboard, web_storage};
+
+/// What the current build and host environment can do, so host UIs can
+/// hide features instead of running into errors.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Capabilities {
+    pub version: String,
+    /// Storage backends available to the page, e.g. `["local_storage"]`.
+    pub storage_backends: Vec<String>,
+    /// Whether the module was built with shared memory and atomics.
+    pub threads: bool,
+    /// Regex engines compiled into this build. Only `regex` so far; there
+    /// is no backtracking (fancy-regex) backend for lookaround yet.
+    pub regex_backends: Vec<String>,
+    /// Whether `crypto.getRandomValues` is available.
+    pub crypto: bool,
+    /// Whether the page may use the async clipboard, for
+    /// `copy_pattern_to_clipboard` and `paste_pattern_from_clipboard`.
+    pub clipboard: bool,
+    /// Whether library files can be opened and saved on disk: built with
+    /// "file-access" and running in a browser with the File System Access
+    /// API.
+    pub file_access: bool,
+    /// Dialects `export_pattern_regex` accepts.
+    pub dialects: Vec<String>,
+    /// Optional parts compiled into this build: "storage", "corpus",
+    /// "export-flavors", "inference" and "file-access". Methods of a missing part don't
+    /// exist on `PatternBuilder`.
+    pub features: Vec<String>,
+}
+
+fn has_global(name: &str) -> bool {
+    js_sys::Reflect::has(&js_sys::global(), &JsValue::from_str(name)).unwrap_or(false)
+}
+
+#[cfg(feature = "file-access")]
+fn file_access_available() -> bool {
+    crate::file_access::is_available()
+}
+
+#[cfg(not(feature = "file-access"))]
+fn file_access_available() -> bool {
+    false
+}
+
+pub fn probe() -> Capabilities {
+    let mut storage_backends = Vec::new();
+    if web_storage::is_durable() {
+        storage_backends.push(String::from("local_storage"));
+    }
+    if has_global("indexedDB") {
+        storage_backends.push(String::from("indexed_db"));
+    }
+
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        storage_backends,
+        threads: cfg!(target_feature = "atomics"),
+        regex_backends: vec![String::from("regex")],
+        crypto: has_global("crypto"),
+        clipboard: web_clipboard::is_available(),
+        file_access: file_access_available(),
+        dialects: ["rust", "js", "pcre", "re2", "posix"].iter().map(|d| d.to_string()).collect(),
+        features: [
+            ("storage", cfg!(feature = "storage")),
+            ("corpus", cfg!(feature = "corpus")),
+            ("export-flavors", cfg!(feature = "export-flavors")),
+            ("inference", cfg!(feature = "inference")),
+            ("file-access", cfg!(feature = "file-access")),
+        ]
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name.to_string())
+        .collect(),
+    }
+}
+
+#[wasm_bindgen]
+pub fn get_capabilities() -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&probe())?)
+}
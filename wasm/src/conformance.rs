@@ -0,0 +1,240 @@
+use serde::{Serialize, Deserialize};
+use wasm_bindgen::prelude::*;
+
+use crate::PatternBuilder;
+#[cfg(feature = "corpus")]
+use crate::CorpusReport;
+
+/// Outcome of one conformance check.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CheckResult {
+    pub name: String,
+    pub passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConformanceReport {
+    pub version: String,
+    pub passed: usize,
+    pub failed: usize,
+    pub checks: Vec<CheckResult>,
+}
+
+type Check = fn(&mut PatternBuilder) -> Result<(), String>;
+
+/// Every check, run in order, each against a fresh in-memory builder.
+const CHECKS: &[(&str, Check)] = &[
+    ("phrase_from_adjacent_selections", phrase_from_adjacent_selections),
+    ("gap_between_separate_selections", gap_between_separate_selections),
+    ("token_class_selection", token_class_selection),
+    ("composite_operators", composite_operators),
+    ("element_editing", element_editing),
+    ("dialect_export", dialect_export),
+    ("regex_import", regex_import),
+    ("library_round_trip", library_round_trip),
+    ("transaction_rollback", transaction_rollback),
+    #[cfg(feature = "corpus")]
+    ("corpus_testing", corpus_testing),
+    ("recording_replay", recording_replay),
+    ("explanation_and_validation", explanation_and_validation),
+];
+
+/// The message of an error thrown to JavaScript.
+fn js(e: JsValue) -> String {
+    js_sys::Reflect::get(&e, &"message".into())
+        .ok()
+        .and_then(|message| message.as_string())
+        .or_else(|| e.as_string())
+        .unwrap_or_else(|| format!("{:?}", e))
+}
+
+fn ensure(condition: bool, message: &str) -> Result<(), String> {
+    if condition {
+        Ok(())
+    } else {
+        Err(message.to_string())
+    }
+}
+
+/// Selects `words` (text and word index) and saves them as a sequence.
+fn build(builder: &mut PatternBuilder, name: &str, words: &[(&str, usize)]) -> Result<String, String> {
+    builder.clear_selections();
+    let mut offset = 0;
+    for &(text, word_index) in words {
+        builder.add_selection(text.to_string(), offset, offset + text.len(), word_index).map_err(js)?;
+        offset += text.len() + 1;
+    }
+    builder.build_sequence_pattern(name.to_string(), None).map_err(js)
+}
+
+fn match_count(builder: &mut PatternBuilder, pattern_index: usize, text: &str) -> Result<usize, String> {
+    let matches: Vec<(usize, usize)> =
+        serde_wasm_bindgen::from_value(builder.test_pattern(pattern_index, text, None).map_err(js)?).map_err(|e| e.to_string())?;
+    Ok(matches.len())
+}
+
+fn last_id(builder: &PatternBuilder) -> Result<String, String> {
+    builder
+        .patterns
+        .last()
+        .map(|p| p.get_id().to_string())
+        .ok_or_else(|| String::from("no pattern was saved"))
+}
+
+fn phrase_from_adjacent_selections(builder: &mut PatternBuilder) -> Result<(), String> {
+    build(builder, "refused", &[("connection", 0), ("refused", 1)])?;
+    ensure(match_count(builder, 0, "the connection refused twice")? == 1, "phrase did not match its own text")?;
+    ensure(match_count(builder, 0, "the connection was refused")? == 0, "phrase matched with a word in between")
+}
+
+fn gap_between_separate_selections(builder: &mut PatternBuilder) -> Result<(), String> {
+    build(builder, "timeout", &[("error", 0), ("timeout", 4)])?;
+    ensure(match_count(builder, 0, "error after a long timeout")? == 1, "gap did not span the words between")?;
+    ensure(match_count(builder, 0, "timeout before error")? == 0, "sequence matched out of order")
+}
+
+fn token_class_selection(builder: &mut PatternBuilder) -> Result<(), String> {
+    builder.add_selection(String::from("port"), 0, 4, 0).map_err(js)?;
+    builder
+        .add_token_class_selection(String::from("number"), None, String::from("8080"), 5, 9, 1)
+        .map_err(js)?;
+    builder.build_sequence_pattern(String::from("port"), None).map_err(js)?;
+    ensure(match_count(builder, 0, "listening on port 443")? == 1, "number class did not match another number")
+}
+
+fn composite_operators(builder: &mut PatternBuilder) -> Result<(), String> {
+    build(builder, "disk", &[("disk", 0)])?;
+    let disk = last_id(builder)?;
+    build(builder, "full", &[("full", 0)])?;
+    let full = last_id(builder)?;
+
+    builder
+        .build_composite_pattern(String::from("either"), String::from("OR"), vec![disk.clone(), full.clone()])
+        .map_err(js)?;
+    ensure(match_count(builder, 2, "disk almost empty")? > 0, "OR did not match one operand")?;
+    builder
+        .build_composite_pattern(String::from("both"), String::from("AND"), vec![disk.clone(), full.clone()])
+        .map_err(js)?;
+    ensure(match_count(builder, 3, "full disk")? > 0, "AND did not match both operands in any order")?;
+    ensure(match_count(builder, 3, "disk almost empty")? == 0, "AND matched one operand")?;
+    builder
+        .build_composite_pattern(String::from("but not"), String::from("NOT"), vec![disk, full])
+        .map_err(js)?;
+    ensure(match_count(builder, 4, "disk almost empty")? > 0, "NOT did not match the first operand alone")?;
+    ensure(match_count(builder, 4, "disk is full")? == 0, "NOT matched despite an excluded operand")
+}
+
+fn element_editing(builder: &mut PatternBuilder) -> Result<(), String> {
+    build(builder, "retry", &[("retry", 0), ("failed", 3)])?;
+    let id = last_id(builder)?;
+    builder.rename_pattern(&id, String::from("retries")).map_err(js)?;
+    ensure(builder.patterns[0].get_name() == "retries", "rename did not change the name")?;
+    builder.set_gap_bounds(&id, 1, 0, Some(1)).map_err(js)?;
+    ensure(match_count(builder, 0, "retry has failed")? == 1, "gap bounds rejected an allowed gap")?;
+    ensure(match_count(builder, 0, "retry it has failed")? == 0, "gap bounds allowed too many words")?;
+    builder.delete_pattern(0, None).map_err(js)?;
+    ensure(builder.patterns.is_empty(), "delete_pattern left the pattern in place")
+}
+
+fn dialect_export(builder: &mut PatternBuilder) -> Result<(), String> {
+    build(builder, "warn", &[("warning", 0)])?;
+    for dialect in ["rust", "js", "pcre", "re2", "posix"] {
+        builder
+            .export_pattern_regex(0, dialect)
+            .map_err(|e| format!("{}: {}", dialect, js(e)))?;
+    }
+    ensure(builder.export_pattern_regex(0, "cobol").is_err(), "an unknown dialect was accepted")
+}
+
+fn regex_import(builder: &mut PatternBuilder) -> Result<(), String> {
+    builder.import_regex("imported", r"\bdisk\b.*?\bfull\b").map_err(js)?;
+    ensure(match_count(builder, 0, "disk is full")? == 1, "imported regex did not match")?;
+    ensure(builder.import_regex("broken", "(unclosed").is_err(), "an invalid regex was imported")
+}
+
+fn library_round_trip(builder: &mut PatternBuilder) -> Result<(), String> {
+    build(builder, "refused", &[("connection", 0), ("refused", 1)])?;
+    let exported = builder.export_patterns().map_err(js)?;
+    let mut other = PatternBuilder::in_memory();
+    other.import_patterns(&exported, "skip").map_err(js)?;
+    ensure(other.patterns.len() == 1, "import did not restore the exported pattern")?;
+    ensure(
+        match_count(&mut other, 0, "connection refused")? == 1,
+        "imported pattern matches differently",
+    )
+}
+
+fn transaction_rollback(builder: &mut PatternBuilder) -> Result<(), String> {
+    build(builder, "kept", &[("kept", 0)])?;
+    builder.begin_transaction().map_err(js)?;
+    build(builder, "dropped", &[("dropped", 0)])?;
+    builder.rollback().map_err(js)?;
+    ensure(builder.patterns.len() == 1, "rollback kept a pattern built in the transaction")?;
+    ensure(!builder.in_transaction(), "transaction still open after rollback")
+}
+
+#[cfg(feature = "corpus")]
+fn corpus_testing(builder: &mut PatternBuilder) -> Result<(), String> {
+    build(builder, "error", &[("error", 0)])?;
+    let docs = serde_wasm_bindgen::to_value(&["error and error", "all good", "one error"]).map_err(|e| e.to_string())?;
+    let report: CorpusReport =
+        serde_wasm_bindgen::from_value(builder.test_pattern_on_corpus(0, docs).map_err(js)?).map_err(|e| e.to_string())?;
+    ensure(report.total_matches == 3, "wrong total match count")?;
+    ensure(report.matched_documents == 2 && report.unmatched_documents == 1, "wrong document counts")
+}
+
+fn recording_replay(builder: &mut PatternBuilder) -> Result<(), String> {
+    builder.start_recording();
+    let regex = build(builder, "refused", &[("connection", 0), ("refused", 1)])?;
+    let id = last_id(builder)?;
+    builder.rename_pattern(&id, String::from("renamed")).map_err(js)?;
+    builder.stop_recording();
+
+    let script = builder.export_recording().map_err(js)?;
+    let mut other = PatternBuilder::in_memory();
+    other.replay(&script).map_err(js)?;
+    ensure(other.patterns.len() == 1, "replay did not rebuild the pattern")?;
+    ensure(other.patterns[0].get_name() == "renamed", "replay did not apply the later edit")?;
+    ensure(
+        other.patterns[0].to_regex_with(&other.regex_context()).as_ref() == Ok(&regex),
+        "replayed pattern has a different regex",
+    )
+}
+
+fn explanation_and_validation(builder: &mut PatternBuilder) -> Result<(), String> {
+    build(builder, "refused", &[("connection", 0), ("refused", 1)])?;
+    builder.explain_pattern(0, None).map_err(js)?;
+    builder.validate_pattern(0).map_err(js)?;
+    ensure(builder.explain_pattern(1, None).is_err(), "explained a pattern that does not exist")
+}
+
+pub fn run() -> ConformanceReport {
+    let checks: Vec<CheckResult> = CHECKS
+        .iter()
+        .map(|(name, check)| {
+            let error = check(&mut PatternBuilder::in_memory()).err();
+            CheckResult {
+                name: name.to_string(),
+                passed: error.is_none(),
+                error,
+            }
+        })
+        .collect();
+    let passed = checks.iter().filter(|c| c.passed).count();
+    ConformanceReport {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        passed,
+        failed: checks.len() - passed,
+        checks,
+    }
+}
+
+/// Exercises the builder API end to end against in-memory storage, so a
+/// host can check that a new version of the crate still behaves the way
+/// it relies on. Nothing is read from or written to local storage.
+#[wasm_bindgen]
+pub fn run_conformance_suite() -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&run())?)
+}
@@ -0,0 +1,177 @@
+use serde::{Serialize, Deserialize};
+use std::cell::Cell;
+use wasm_bindgen::JsValue;
+
+use crate::journal::PatternChange;
+use crate::RegexGenError;
+use crate::telemetry::UsageEvent;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeType {
+    Created,
+    Updated,
+    Deleted,
+    /// Only its position in the library changed.
+    Moved,
+}
+
+/// What a `on_patterns_changed` callback is called with, once per
+/// pattern a save touched.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LibraryEvent {
+    #[serde(rename = "type")]
+    pub change_type: ChangeType,
+    pub id: String,
+    pub name: String,
+}
+
+impl LibraryEvent {
+    pub fn from_change(change: &PatternChange) -> Option<LibraryEvent> {
+        let (change_type, pattern) = match (&change.before, &change.after) {
+            (None, Some(after)) => (ChangeType::Created, after),
+            (Some(_), Some(after)) if change.is_move() => (ChangeType::Moved, after),
+            (Some(_), Some(after)) => (ChangeType::Updated, after),
+            (Some(before), None) => (ChangeType::Deleted, before),
+            (None, None) => return None,
+        };
+        Some(LibraryEvent {
+            change_type,
+            id: pattern.get_id().to_string(),
+            name: pattern.get_name().to_string(),
+        })
+    }
+}
+
+/// Callbacks registered with `on_patterns_changed`, by subscription id.
+#[derive(Clone, Default)]
+pub struct Listeners {
+    next_id: u32,
+    callbacks: Vec<(u32, js_sys::Function)>,
+}
+
+impl Listeners {
+    pub fn subscribe(&mut self, callback: js_sys::Function) -> u32 {
+        self.next_id += 1;
+        self.callbacks.push((self.next_id, callback));
+        self.next_id
+    }
+
+    pub fn unsubscribe(&mut self, id: u32) -> bool {
+        let before = self.callbacks.len();
+        self.callbacks.retain(|(subscription, _)| *subscription != id);
+        self.callbacks.len() < before
+    }
+
+    pub fn clear(&mut self) {
+        self.callbacks.clear();
+    }
+
+    /// Calls every callback with every change. A callback that throws is
+    /// logged and doesn't stop the others; the library is already saved.
+    pub fn notify(&self, changes: &[PatternChange]) {
+        if self.callbacks.is_empty() {
+            return;
+        }
+        for event in changes.iter().filter_map(LibraryEvent::from_change) {
+            let Ok(payload) = serde_wasm_bindgen::to_value(&event) else { continue };
+            for (_, callback) in &self.callbacks {
+                if let Err(e) = callback.call1(&JsValue::NULL, &payload) {
+                    web_sys::console::error_2(&"on_patterns_changed callback failed:".into(), &e);
+                }
+            }
+        }
+    }
+}
+
+/// The callback registered with `set_telemetry_hook`, if any. Nothing is
+/// measured or sent while there is none.
+#[derive(Clone, Default)]
+pub struct Telemetry {
+    hook: Option<js_sys::Function>,
+}
+
+impl Telemetry {
+    pub fn set_hook(&mut self, hook: Option<js_sys::Function>) {
+        self.hook = hook;
+    }
+
+    /// The time an evaluation starts, when it will be reported.
+    pub fn start(&self) -> Option<f64> {
+        self.hook.as_ref().map(|_| js_sys::Date::now())
+    }
+
+    /// Reports an evaluation started at `started` as finished now.
+    pub fn evaluated(&self, operation: &str, started: Option<f64>) {
+        if let Some(started) = started {
+            self.emit(&UsageEvent::evaluated(operation, js_sys::Date::now() - started));
+        }
+    }
+
+    /// Calls the hook with `event`. A hook that throws is logged; the
+    /// operation being reported has already succeeded.
+    pub fn emit(&self, event: &UsageEvent) {
+        let Some(hook) = &self.hook else { return };
+        let Ok(payload) = serde_wasm_bindgen::to_value(event) else { return };
+        if let Err(e) = hook.call1(&JsValue::NULL, &payload) {
+            web_sys::console::error_2(&"telemetry hook failed:".into(), &e);
+        }
+    }
+}
+
+/// Milliseconds a long call runs between heartbeats unless
+/// `set_heartbeat` says otherwise.
+pub const DEFAULT_HEARTBEAT_MS: f64 = 50.0;
+
+/// The callback registered with `set_heartbeat`, called from inside long
+/// synchronous calls whenever they have run for another `budget_ms`, so
+/// hosts without workers can report progress and cancel.
+#[derive(Clone, Default)]
+pub struct Heartbeat {
+    callback: Option<js_sys::Function>,
+    budget_ms: f64,
+    started: Cell<f64>,
+    next: Cell<f64>,
+}
+
+impl Heartbeat {
+    pub fn set(&mut self, callback: Option<js_sys::Function>, budget_ms: f64) {
+        self.callback = callback;
+        self.budget_ms = budget_ms;
+    }
+
+    /// Starts timing a long call.
+    pub fn start(&self) {
+        if self.callback.is_some() {
+            let now = js_sys::Date::now();
+            self.started.set(now);
+            self.next.set(now + self.budget_ms);
+        }
+    }
+
+    /// Called between units of work. Once the budget is spent, calls the
+    /// callback with the operation and the milliseconds since `start`; if
+    /// it returns `false` the call stops with a Cancelled error. A
+    /// callback that throws is logged and the call goes on.
+    pub fn beat(&self, operation: &str) -> Result<(), RegexGenError> {
+        let Some(callback) = &self.callback else { return Ok(()) };
+        let now = js_sys::Date::now();
+        if now < self.next.get() {
+            return Ok(());
+        }
+        let elapsed = JsValue::from(now - self.started.get());
+        let carry_on = match callback.call2(&JsValue::NULL, &JsValue::from_str(operation), &elapsed) {
+            Ok(result) => result.as_bool() != Some(false),
+            Err(e) => {
+                web_sys::console::error_2(&"heartbeat callback failed:".into(), &e);
+                true
+            }
+        };
+        // The callback's own time doesn't count against the next slice
+        self.next.set(js_sys::Date::now() + self.budget_ms);
+        match carry_on {
+            true => Ok(()),
+            false => Err(RegexGenError::Cancelled { operation: operation.to_string() }),
+        }
+    }
+}
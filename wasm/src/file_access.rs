@@ -0,0 +1,99 @@
+use js_sys::{Array, Function, Object, Promise, Reflect};
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{FileSystemFileHandle, FileSystemWritableFileStream};
+
+use regexgen_core::RegexGenError;
+
+use crate::promises;
+
+/// File name offered the first time a library is saved.
+pub const SUGGESTED_NAME: &str = "patterns.json";
+
+thread_local! {
+    /// The library file last opened or saved, which `save` writes back to.
+    static CURRENT: RefCell<Option<FileSystemFileHandle>> = const { RefCell::new(None) };
+}
+
+/// Whether the browser lets pages open and save files on disk; so far
+/// only Chromium-based ones do.
+pub fn is_available() -> bool {
+    picker("showOpenFilePicker").is_ok()
+}
+
+/// Name of the library file last opened or saved, if any.
+pub fn current_name() -> Option<String> {
+    CURRENT.with(|current| current.borrow().as_ref().map(|handle| handle.name()))
+}
+
+fn remember(handle: &FileSystemFileHandle) {
+    CURRENT.with(|current| *current.borrow_mut() = Some(handle.clone()));
+}
+
+/// `window.showOpenFilePicker` or `showSaveFilePicker`, which web-sys
+/// only binds behind its unstable APIs.
+fn picker(name: &str) -> Result<Function, JsValue> {
+    let window = web_sys::window().ok_or_else(|| unavailable("there is no window"))?;
+    Reflect::get(&window, &JsValue::from_str(name))?
+        .dyn_into()
+        .map_err(|_| unavailable("this browser doesn't support the File System Access API"))
+}
+
+fn unavailable(reason: &str) -> JsValue {
+    RegexGenError::InvalidState(format!("Library files can't be opened or saved: {}", reason)).into()
+}
+
+/// Picker options limiting the choice to JSON files.
+fn picker_options(suggested_name: Option<&str>) -> Result<Object, JsValue> {
+    let accept = Object::new();
+    Reflect::set(&accept, &JsValue::from_str("application/json"), &Array::of1(&JsValue::from_str(".json")))?;
+    let file_type = Object::new();
+    Reflect::set(&file_type, &JsValue::from_str("description"), &JsValue::from_str("Pattern library"))?;
+    Reflect::set(&file_type, &JsValue::from_str("accept"), &accept)?;
+    let options = Object::new();
+    Reflect::set(&options, &JsValue::from_str("types"), &Array::of1(&file_type))?;
+    if let Some(name) = suggested_name {
+        Reflect::set(&options, &JsValue::from_str("suggestedName"), &JsValue::from_str(name))?;
+    }
+    Ok(options)
+}
+
+/// Asks the user for a library file and returns a promise for its text.
+/// The file becomes the one `save` writes to.
+pub fn open() -> Result<Promise, JsValue> {
+    let chosen: Promise = picker("showOpenFilePicker")?
+        .call1(&js_sys::global(), &picker_options(None)?.into())?
+        .unchecked_into();
+    let file = promises::and_then(&chosen, |handles| {
+        let handle: FileSystemFileHandle = Array::from(&handles).get(0).unchecked_into();
+        remember(&handle);
+        Ok(handle.get_file().into())
+    })?;
+    promises::and_then(&file, |file| Ok(file.unchecked_into::<web_sys::Blob>().text().into()))
+}
+
+/// Writes `text` to the current library file, or with `save_as` or
+/// without one to a file the user picks, which becomes the current one.
+/// Returns a promise for the file's name.
+pub fn save(text: String, save_as: bool) -> Result<Promise, JsValue> {
+    let current = CURRENT.with(|current| current.borrow().clone()).filter(|_| !save_as);
+    let chosen = match current {
+        Some(handle) => Promise::resolve(&handle),
+        None => picker("showSaveFilePicker")?
+            .call1(&js_sys::global(), &picker_options(Some(SUGGESTED_NAME))?.into())?
+            .unchecked_into(),
+    };
+    promises::and_then(&chosen, move |handle| {
+        let handle: FileSystemFileHandle = handle.unchecked_into();
+        remember(&handle);
+        let name = JsValue::from_str(&handle.name());
+        let writable = promises::and_then(&handle.create_writable(), move |stream| {
+            let stream: FileSystemWritableFileStream = stream.unchecked_into();
+            let written = stream.write_with_str(&text)?;
+            // Nothing reaches the file until the stream is closed
+            promises::and_then(&written, move |_| Ok(stream.close().into())).map(JsValue::from)
+        })?;
+        promises::and_then(&writable, move |_| Ok(name)).map(JsValue::from)
+    })
+}
@@ -0,0 +1,3538 @@
+//! WebAssembly bindings for regexgen: `PatternBuilder` and the free
+//! functions the page calls, with the library kept in the browser's local
+//! storage. The pattern model itself lives in `regexgen-core`, re-exported
+//! here.
+
+use js_sys::Promise;
+use wasm_bindgen::prelude::*;
+use web_sys::console;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::borrow::Cow;
+use std::ops::Range;
+use std::rc::Rc;
+
+mod capabilities;
+mod conformance;
+mod events;
+#[cfg(feature = "file-access")]
+mod file_access;
+mod promises;
+mod quick;
+mod recording;
+mod sandbox;
+mod seed;
+#[cfg(any(feature = "corpus", feature = "inference"))]
+mod tasks;
+mod web_clipboard;
+mod web_storage;
+
+pub use regexgen_core::*;
+pub use capabilities::{get_capabilities, Capabilities};
+pub use conformance::{run_conformance_suite, CheckResult, ConformanceReport};
+pub use events::{ChangeType, LibraryEvent};
+pub use quick::{quick_pattern, QuickMode};
+pub use sandbox::Sandbox;
+pub use seed::{clear_seed, set_seed};
+use seed::generate_id;
+use events::{Heartbeat, Listeners, Telemetry};
+use journal::Journal;
+use matcher::RegexCache;
+use matching::CompiledComposite;
+use recording::{Call, RecordedCall, Recording};
+use synonyms::SynonymSets;
+
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct PatternBuilder {
+    patterns: Vec<Pattern>,
+    current_selections: Vec<SelectionSpan>,
+    alternation: AlternationConfig,
+    lists: HashMap<String, Vec<String>>,
+    settings: Settings,
+    option_hits: option_stats::OptionHits,
+    /// Whether library changes are written to storage: local storage, or
+    /// memory when that is unavailable.
+    persistent: bool,
+    /// Library snapshot taken by `begin_transaction`; saves are deferred
+    /// while it is set.
+    transaction: Option<Vec<Pattern>>,
+    /// Library revision this builder last loaded or saved. Saving fails if
+    /// storage has moved past it (another tab saved in between).
+    revision: u64,
+    /// Compiled form of the whole library, built on first use and dropped
+    /// whenever patterns or compile settings change.
+    matcher: Option<Rc<PatternMatcher>>,
+    /// Compiled regex of each pattern tested so far.
+    regex_cache: RegexCache,
+    /// Word index of `documents` with the `index_documents` setting,
+    /// built on first search and dropped when a document changes.
+    document_index: Option<Rc<DocumentIndex>>,
+    /// Calls recorded since `start_recording`, kept after it stops.
+    recording: Option<Recording>,
+    recording_active: bool,
+    /// Store whose keys the library is read from and saved to; empty for
+    /// the original one.
+    store: String,
+    /// Whether other builders may have the store open: saves merge into
+    /// their changes, and undo history isn't stored.
+    shared: bool,
+    /// Workspace whose storage keys the library is read from and saved to.
+    workspace: String,
+    /// Named sample texts kept with the workspace.
+    documents: BTreeMap<String, String>,
+    /// Synonym sets kept with the workspace.
+    synonyms: SynonymSets,
+    /// Explanation messages registered by the host, by locale.
+    catalogs: BTreeMap<String, MessageCatalog>,
+    /// Library changes that can be undone and redone.
+    journal: Journal,
+    /// The library as of the last save, to diff the next against.
+    journal_base: Vec<Pattern>,
+    /// Callbacks registered with `on_patterns_changed`.
+    listeners: Listeners,
+    /// The host's opt-in usage hook.
+    telemetry: Telemetry,
+    heartbeat: Heartbeat,
+}
+
+impl Default for PatternBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl PatternBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> PatternBuilder {
+        console::log_1(&"PatternBuilder initialized".into());
+        PatternBuilder::load("", false)
+    }
+
+    /// A builder whose library lives in the named store, apart from the
+    /// one `new` uses, so panes editing different libraries don't write
+    /// over each other. An empty name opens the store `new` uses.
+    ///
+    /// With `shared`, several builders can have the same store open: a
+    /// save merges the builder's own changes into what the others saved
+    /// instead of failing with a Conflict, and each keeps its own undo
+    /// history rather than storing it. Call `reload` to pick up the
+    /// others' changes.
+    pub fn open(store: &str, shared: bool) -> Result<PatternBuilder, JsValue> {
+        if !store.is_empty() {
+            validate_key_name("store", store)?;
+        }
+        Ok(PatternBuilder::load(store, shared))
+    }
+
+    /// A builder rebuilt from a `to_snapshot` snapshot that never touches
+    /// storage, so it works where there is no `window`, such as a Web
+    /// Worker. Its edits go back to a storing builder through
+    /// `apply_snapshot`.
+    pub fn from_snapshot(snapshot: &str) -> Result<PatternBuilder, JsValue> {
+        let snapshot = BuilderSnapshot::parse(snapshot).map_err(RegexGenError::InvalidArgument)?;
+        let mut builder = PatternBuilder::with_library(
+            snapshot.workspace,
+            snapshot.patterns,
+            snapshot.revision,
+            snapshot.documents,
+            snapshot.settings,
+            false,
+        );
+        builder.synonyms = snapshot.synonyms;
+        builder.lists = snapshot.lists;
+        Ok(builder)
+    }
+
+    /// The library, documents, synonym sets, loaded lists and settings as
+    /// JSON, to post to a worker and open there with `from_snapshot`.
+    pub fn to_snapshot(&self) -> Result<String, JsValue> {
+        let snapshot = BuilderSnapshot {
+            format: snapshot::SNAPSHOT_FORMAT.to_string(),
+            workspace: self.workspace.clone(),
+            revision: self.revision,
+            patterns: self.patterns.clone(),
+            documents: self.documents.clone(),
+            synonyms: self.synonyms.clone(),
+            lists: self.lists.clone(),
+            settings: self.settings.clone(),
+        };
+        Ok(snapshot.to_json().map_err(RegexGenError::from)?)
+    }
+
+    /// Takes over the patterns, documents and synonym sets of a snapshot
+    /// edited elsewhere and saves them. Refused with a Conflict if this
+    /// builder has saved since the snapshot was taken, or the snapshot is
+    /// of another workspace; nothing changes if the save fails.
+    pub fn apply_snapshot(&mut self, snapshot: &str) -> Result<(), JsValue> {
+        let snapshot = BuilderSnapshot::parse(snapshot).map_err(RegexGenError::InvalidArgument)?;
+        if snapshot.workspace != self.workspace {
+            return Err(RegexGenError::InvalidArgument(format!(
+                "Snapshot is of workspace {}, not {}",
+                snapshot.workspace, self.workspace
+            ))
+            .into());
+        }
+        if snapshot.revision != self.revision {
+            return Err(RegexGenError::Conflict { stored: self.revision, expected: snapshot.revision }.into());
+        }
+        let previous = std::mem::replace(&mut self.patterns, snapshot.patterns);
+        if let Err(e) = self.save() {
+            self.patterns = previous;
+            self.invalidate_matcher();
+            return Err(e);
+        }
+        self.documents = snapshot.documents;
+        self.document_index = None;
+        self.synonyms = snapshot.synonyms;
+        self.invalidate_matcher();
+        self.save_documents()?;
+        self.save_synonyms()
+    }
+
+    /// Whether saved patterns survive a reload. False when local storage
+    /// is unavailable (private browsing, sandboxed iframes, or a build
+    /// without the `storage` feature) and the library is kept in memory,
+    /// and for sandboxes, so the UI can warn before work is lost.
+    pub fn is_persistent(&self) -> bool {
+        self.persistent && web_storage::is_durable()
+    }
+
+    /// Name of the store the library is kept in; empty for the one `new`
+    /// uses.
+    pub fn get_store(&self) -> String {
+        self.store.clone()
+    }
+
+    /// Whether the builder was opened to share its store with others.
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+
+    pub fn add_selection(&mut self, text: String, start_index: usize, end_index: usize, word_index: usize) -> Result<(), JsValue> {
+        self.settings.limits.check_selections(self.current_selections.len() + 1)?;
+        self.record(Call::AddSelection { text: text.clone(), start_index, end_index, word_index });
+        let selection = SelectionSpan {
+            text,
+            start_index,
+            end_index,
+            word_index,
+            token_class: None,
+            raw: None,
+            word_match: None,
+            last_word_index: None,
+            alternatives: Vec::new(),
+        };
+        self.current_selections.push(selection);
+        Ok(())
+    }
+
+    /// Selects the characters `start_index..end_index` of `text` (offsets
+    /// in the configured unit), which need not line up with words:
+    /// "2024-01" out of "2024-01-15", or "user:" with its colon. A range
+    /// starting and ending on word edges matches as whole words; anything
+    /// else matches its exact text anywhere, without word boundaries.
+    /// Returns which it is, as `{ kind: "word" | "substring", text,
+    /// word_index, last_word_index }`.
+    pub fn add_range_selection(&mut self, text: &str, start_index: usize, end_index: usize) -> Result<JsValue, JsValue> {
+        self.check_text(text)?;
+        self.record(Call::AddRangeSelection { text: text.to_string(), start_index, end_index });
+        let encoding = self.settings.offset_encoding;
+        let (Some(start), Some(end)) = (encoding.byte_offset(text, start_index), encoding.byte_offset(text, end_index)) else {
+            return Err(RegexGenError::InvalidArgument(String::from("The range is outside the text or splits a character")).into());
+        };
+        if start >= end {
+            return Err(RegexGenError::InvalidArgument(String::from("The range is empty")).into());
+        }
+        self.settings.limits.check_selections(self.current_selections.len() + 1)?;
+        let words = self.settings.tokenizer.tokenize(text);
+        let range = range_selection::classify(text, &words, start, end);
+        let word_match = match range.kind {
+            RangeKind::Word => WordMatch::Exact,
+            RangeKind::Substring => WordMatch::Substring,
+        };
+        self.current_selections.push(SelectionSpan {
+            text: range.text.clone(),
+            start_index: start,
+            end_index: end,
+            word_index: range.word_index,
+            token_class: None,
+            raw: None,
+            word_match: Some(word_match),
+            last_word_index: Some(range.last_word_index),
+            alternatives: Vec::new(),
+        });
+        Ok(serde_wasm_bindgen::to_value(&range)?)
+    }
+
+    /// Selects a token that should match any value of a class ("number",
+    /// "date", "email", "url", "ip") instead of its literal text. Pass a
+    /// `custom_regex` to define an ad-hoc class named by `class`.
+    pub fn add_token_class_selection(&mut self, class: String, custom_regex: Option<String>, text: String, start_index: usize, end_index: usize, word_index: usize) -> Result<(), JsValue> {
+        self.record(Call::AddTokenClassSelection {
+            class: class.clone(),
+            custom_regex: custom_regex.clone(),
+            text: text.clone(),
+            start_index,
+            end_index,
+            word_index,
+        });
+        let token_class = match custom_regex {
+            Some(regex) => {
+                regex::Regex::new(&regex).map_err(RegexGenError::from)?;
+                TokenClass::Custom { name: class, regex }
+            }
+            None => class.parse().map_err(RegexGenError::InvalidArgument)?,
+        };
+        self.settings.limits.check_selections(self.current_selections.len() + 1)?;
+        self.current_selections.push(SelectionSpan {
+            text,
+            start_index,
+            end_index,
+            word_index,
+            token_class: Some(token_class),
+            raw: None,
+            word_match: None,
+            last_word_index: None,
+            alternatives: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Adds a hand-written regex fragment at `word_index` among the
+    /// selections. The fragment is compile-checked now and emitted as-is.
+    pub fn add_raw_selection(&mut self, regex: String, word_index: usize) -> Result<(), JsValue> {
+        validate_raw_fragment(&regex)?;
+        self.record(Call::AddRawSelection { regex: regex.clone(), word_index });
+        self.settings.limits.check_selections(self.current_selections.len() + 1)?;
+        self.current_selections.push(SelectionSpan {
+            text: regex.clone(),
+            start_index: 0,
+            end_index: 0,
+            word_index,
+            token_class: None,
+            raw: Some(regex),
+            word_match: None,
+            last_word_index: None,
+            alternatives: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Marks `text` as interchangeable with the selected word, e.g.
+    /// "refused" and "reset" for a selected "timeout", so the pattern
+    /// matches any of them there. Word selections made at the same
+    /// position, say in different example lines, are grouped the same way.
+    pub fn add_alternative_to_selection(&mut self, selection_index: usize, text: String) -> Result<(), JsValue> {
+        self.record(Call::AddAlternativeToSelection { selection_index, text: text.clone() });
+        let text = text.trim().to_string();
+        if text.is_empty() {
+            return Err(RegexGenError::InvalidArgument(String::from("An alternative needs some text")).into());
+        }
+        let selection = self
+            .current_selections
+            .get_mut(selection_index)
+            .ok_or_else(|| RegexGenError::NotFound(String::from("Selection index out of range")))?;
+        if !selection.is_word() {
+            return Err(RegexGenError::InvalidArgument(String::from("Only word selections can have alternatives")).into());
+        }
+        if selection.text != text && !selection.alternatives.contains(&text) {
+            selection.alternatives.push(text);
+        }
+        Ok(())
+    }
+
+    pub fn remove_alternative_from_selection(&mut self, selection_index: usize, text: &str) -> Result<(), JsValue> {
+        self.record(Call::RemoveAlternativeFromSelection { selection_index, text: text.to_string() });
+        let selection = self
+            .current_selections
+            .get_mut(selection_index)
+            .ok_or_else(|| RegexGenError::NotFound(String::from("Selection index out of range")))?;
+        selection.alternatives.retain(|alternative| alternative != text);
+        Ok(())
+    }
+
+    /// Sets how many `OneOf` options it takes before they are compacted into
+    /// a trie-shaped alternation. Pass 0 to always compact.
+    pub fn set_alternation_threshold(&mut self, min_options: usize) -> Result<(), JsValue> {
+        self.settings.alternation_threshold = min_options;
+        self.alternation.min_options = min_options;
+        self.invalidate_matcher();
+        self.save_settings()
+    }
+
+    /// Match options below apply to every pattern built afterwards until
+    /// they are changed again.
+    pub fn set_case_insensitive(&mut self, enabled: bool) -> Result<(), JsValue> {
+        self.settings.match_options.case_insensitive = enabled;
+        self.save_settings()
+    }
+
+    pub fn set_multi_line(&mut self, enabled: bool) -> Result<(), JsValue> {
+        self.settings.match_options.multi_line = enabled;
+        self.save_settings()
+    }
+
+    pub fn set_dot_all(&mut self, enabled: bool) -> Result<(), JsValue> {
+        self.settings.match_options.dot_all = enabled;
+        self.save_settings()
+    }
+
+    /// Anchors built patterns to the start and/or end of the text, or of
+    /// each line when multi-line mode is on.
+    pub fn set_anchors(&mut self, start: bool, end: bool) -> Result<(), JsValue> {
+        self.settings.match_options.anchor_start = start;
+        self.settings.match_options.anchor_end = end;
+        self.save_settings()
+    }
+
+    pub fn get_match_options(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.settings.match_options)?)
+    }
+
+    pub fn get_settings(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.settings)?)
+    }
+
+    /// Splits sample text into words the way the tokenizer settings say,
+    /// e.g. keeping "don't" or "user_id" together.
+    pub fn tokenize(&self, text: &str) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.settings.tokenizer.tokenize(text))?)
+    }
+
+    /// Tokenizes many documents in one call, e.g. a whole corpus. `detail`
+    /// is "words" (the default, as `tokenize` returns them), "offsets"
+    /// (only a flat `[start, end, ...]` array per document) or "counts"
+    /// (only the number of words), to keep large batches cheap to pass back.
+    pub fn tokenize_documents(&self, texts: Vec<String>, detail: Option<String>) -> Result<JsValue, JsValue> {
+        let detail = match detail {
+            Some(detail) => detail.parse().map_err(RegexGenError::InvalidArgument)?,
+            None => TokenDetail::Words,
+        };
+        for text in &texts {
+            self.check_text(text)?;
+        }
+        let documents: Vec<TokenizedDocument> = texts
+            .iter()
+            .enumerate()
+            .map(|(index, text)| self.settings.tokenizer.tokenize_document(index, text, detail))
+            .collect();
+        Ok(serde_wasm_bindgen::to_value(&documents)?)
+    }
+
+    pub fn word_at_position(&self, text: &str, position: usize) -> Option<String> {
+        self.settings.tokenizer.word_at(text, position)
+    }
+
+    /// Replaces all settings at once. Fields left out of the object keep
+    /// their default value.
+    pub fn set_settings(&mut self, settings: JsValue) -> Result<(), JsValue> {
+        let settings: Settings = serde_wasm_bindgen::from_value(settings).map_err(RegexGenError::from)?;
+        self.check_taxonomy(&settings.categories)?;
+        self.apply_settings(settings);
+        self.save_settings()
+    }
+
+    /// The size limits calls are checked against; set them with
+    /// `set_limits` or as `limits` in `set_settings`.
+    pub fn get_limits(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.settings.limits)?)
+    }
+
+    /// Replaces the size limits. Fields left out keep their defaults.
+    pub fn set_limits(&mut self, limits: JsValue) -> Result<(), JsValue> {
+        self.settings.limits = serde_wasm_bindgen::from_value(limits).map_err(RegexGenError::from)?;
+        self.save_settings()
+    }
+
+    /// Supplies the entries of a named list referenced by `OneOf` elements.
+    /// Lists are kept out of the pattern JSON; the host loads them lazily
+    /// (from IndexedDB, a URL, ...) once `get_missing_lists` asks for them.
+    pub fn load_list(&mut self, name: String, entries: Vec<String>) {
+        self.lists.insert(name, entries);
+        self.invalidate_matcher();
+    }
+
+    pub fn unload_list(&mut self, name: &str) {
+        self.lists.remove(name);
+        self.invalidate_matcher();
+    }
+
+    /// Names of lists referenced by saved patterns that have not been loaded.
+    pub fn get_missing_lists(&self) -> Vec<String> {
+        let mut missing: Vec<String> = Vec::new();
+        for pattern in &self.patterns {
+            for name in pattern.referenced_lists() {
+                if !self.lists.contains_key(name) && !missing.iter().any(|m| m == name) {
+                    missing.push(name.to_string());
+                }
+            }
+        }
+        missing
+    }
+
+    /// Registers or replaces a named set of interchangeable words, e.g.
+    /// "error-words": error, failure, fault, exception. Patterns use it
+    /// through `SynonymSet` elements and pick up edits on their next
+    /// compile. Saved with the workspace.
+    pub fn set_synonym_set(&mut self, set_id: &str, words: Vec<String>) -> Result<(), JsValue> {
+        synonyms::validate_set_id(set_id).map_err(RegexGenError::InvalidArgument)?;
+        self.record(Call::SetSynonymSet { set_id: set_id.to_string(), words: words.clone() });
+        let words = synonyms::normalize(words).map_err(RegexGenError::InvalidArgument)?;
+        self.synonyms.insert(set_id.to_string(), words);
+        self.invalidate_matcher();
+        self.save_synonyms()
+    }
+
+    /// Every synonym set, by id.
+    pub fn get_synonym_sets(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.synonyms)?)
+    }
+
+    /// Removes a synonym set. Refused while patterns still use it.
+    pub fn delete_synonym_set(&mut self, set_id: &str) -> Result<(), JsValue> {
+        self.record(Call::DeleteSynonymSet { set_id: set_id.to_string() });
+        let users = self.patterns_using_synonym_set(set_id);
+        if !users.is_empty() {
+            return Err(RegexGenError::InvalidState(format!(
+                "Synonym set '{}' is used by {} pattern(s); remove it from them first",
+                set_id,
+                users.len()
+            ))
+            .into());
+        }
+        if self.synonyms.remove(set_id).is_none() {
+            return Err(RegexGenError::NotFound(format!("Synonym set not found: {}", set_id)).into());
+        }
+        self.invalidate_matcher();
+        self.save_synonyms()
+    }
+
+    /// Ids of the patterns whose elements use a synonym set.
+    pub fn patterns_using_synonym_set(&self, set_id: &str) -> Vec<String> {
+        self.patterns
+            .iter()
+            .filter(|p| p.referenced_synonym_sets().contains(&set_id))
+            .map(|p| p.get_id().to_string())
+            .collect()
+    }
+
+    pub fn clear_selections(&mut self) {
+        self.record(Call::ClearSelections);
+        self.current_selections.clear();
+    }
+
+    /// Saves the current selections as a sequence pattern and returns its
+    /// regex. Refuses to save a pattern identical to a saved one; see
+    /// `build_sequence_pattern_checked` to save it anyway.
+    ///
+    /// `gap_mode` sets how the gaps between selections that weren't next
+    /// to each other follow the number of words between them in the text:
+    /// "strict" (exactly that many), "loose" (up to twice as many) or
+    /// "unbounded" (anything). Without it the `gap_strategy` setting
+    /// applies.
+    pub fn build_sequence_pattern(&mut self, name: String, gap_mode: Option<String>) -> Result<String, JsValue> {
+        self.record(Call::BuildSequencePattern { name: name.clone(), gap_mode: gap_mode.clone() });
+        let outcome = self.build_sequence(name, false, gap_mode.as_deref())?;
+        Ok(created_regex(outcome)?)
+    }
+
+    /// Like `build_sequence_pattern`, but returns whether the pattern was
+    /// `created` (with its id and regex) or is a `duplicate` of a saved
+    /// one (with that one's id and name) instead of failing. With
+    /// `allow_duplicate` it is saved either way. `gap_mode` is as for
+    /// `build_sequence_pattern`.
+    pub fn build_sequence_pattern_checked(&mut self, name: String, allow_duplicate: bool, gap_mode: Option<String>) -> Result<JsValue, JsValue> {
+        self.record(Call::BuildSequencePatternChecked { name: name.clone(), allow_duplicate, gap_mode: gap_mode.clone() });
+        let outcome = self.build_sequence(name, allow_duplicate, gap_mode.as_deref())?;
+        Ok(serde_wasm_bindgen::to_value(&outcome)?)
+    }
+
+    /// Saved patterns identical or equivalent to the one the current
+    /// selections would build, or sharing most of its elements, each with
+    /// a score from 0.6 to 1, most similar first. Lets the UI offer to
+    /// reuse one before `build_sequence_pattern`. `gap_mode` is as for
+    /// `build_sequence_pattern`.
+    pub fn find_similar_to_selections(&mut self, gap_mode: Option<String>) -> Result<JsValue, JsValue> {
+        let pattern = self.selection_sequence("", gap_mode.as_deref())?;
+        let similar = duplicates::find_similar(&self.patterns, &pattern, &self.regex_context());
+        Ok(serde_wasm_bindgen::to_value(&similar)?)
+    }
+
+    /// `find_similar_to_selections` for a pattern given in the JSON shape
+    /// `get_patterns` returns. A saved pattern isn't reported as similar
+    /// to itself.
+    pub fn find_similar_patterns(&self, pattern: JsValue) -> Result<JsValue, JsValue> {
+        let pattern: Pattern = serde_wasm_bindgen::from_value(pattern).map_err(RegexGenError::from)?;
+        let similar = duplicates::find_similar(&self.patterns, &pattern, &self.regex_context());
+        Ok(serde_wasm_bindgen::to_value(&similar)?)
+    }
+
+    fn build_sequence(&mut self, name: String, allow_duplicate: bool, gap_mode: Option<&str>) -> Result<SaveOutcome, JsValue> {
+        let mut pattern = self.selection_sequence(&name, gap_mode)?;
+        pattern.set_id(generate_id());
+
+        let outcome = self.save_new(pattern, allow_duplicate)?;
+        if let SaveOutcome::Created { .. } = outcome {
+            self.clear_selections();
+        }
+        Ok(outcome)
+    }
+
+    /// The unsaved sequence the current selections make.
+    fn selection_sequence(&mut self, name: &str, gap_mode: Option<&str>) -> Result<Pattern, JsValue> {
+        if self.current_selections.is_empty() {
+            return Err(RegexGenError::InvalidState(String::from("No selections to build pattern from")).into());
+        }
+        self.settings.limits.check_selections(self.current_selections.len())?;
+        let gap_strategy = match gap_mode {
+            Some(mode) => mode.parse().map_err(RegexGenError::InvalidArgument)?,
+            None => self.settings.gap_strategy,
+        };
+
+        // Sort selections by their position in the text
+        self.current_selections.sort_by_key(SelectionSpan::position);
+        let elements = elements_from_selections(&self.current_selections, gap_strategy);
+
+        let mut pattern = Pattern::sequence(name, elements);
+        pattern.meta_mut().options = self.settings.match_options.clone();
+        Ok(pattern)
+    }
+
+    /// Patterns for the current selections at several levels of
+    /// strictness, from exact gaps and case to unordered keywords, each
+    /// with its number of matches in `text` (usually the text the
+    /// selections were made in). Nothing is saved; save the chosen one
+    /// with `add_pattern`.
+    #[cfg(feature = "inference")]
+    pub fn generate_variants(&self, text: &str) -> Result<JsValue, JsValue> {
+        self.check_text(text)?;
+        if self.current_selections.is_empty() {
+            return Err(RegexGenError::InvalidState(String::from("No selections to build pattern from")).into());
+        }
+        let mut selections = self.current_selections.clone();
+        selections.sort_by_key(SelectionSpan::position);
+        let variants = variants::variants(&selections, &self.settings.match_options, &self.regex_context(), text);
+        Ok(serde_wasm_bindgen::to_value(&variants)?)
+    }
+
+    /// How often the pattern the current selections would build matches
+    /// `text`, the text they were made in. A draft matching more than
+    /// `max_match_density` times per word, as a lone "the" does, is
+    /// reported `degenerate` with a `warning` to show before saving.
+    pub fn check_draft(&self, text: &str) -> Result<JsValue, JsValue> {
+        self.check_text(text)?;
+        if self.current_selections.is_empty() {
+            return Err(RegexGenError::InvalidState(String::from("No selections to build pattern from")).into());
+        }
+        let mut selections = self.current_selections.clone();
+        selections.sort_by_key(SelectionSpan::position);
+        let mut draft = Pattern::sequence("Draft", elements_from_selections(&selections, self.settings.gap_strategy));
+        draft.meta_mut().options = self.settings.match_options.clone();
+
+        let regex = draft.to_regex_with(&self.regex_context())?;
+        let compiled = regex::Regex::new(&regex).map_err(RegexGenError::from)?;
+        let match_count = matching::find_scoped(&compiled, text, draft.meta().options.scope).len();
+        let word_count = self.settings.tokenizer.tokenize(text).len();
+        let report = DensityReport::new(regex, match_count, word_count, self.settings.max_match_density);
+        Ok(serde_wasm_bindgen::to_value(&report)?)
+    }
+
+    /// Combines saved patterns with AND, OR or NOT into a new saved
+    /// Composite pattern and returns its regex.
+    /// Refuses to save a combination identical to a saved one, as
+    /// `build_sequence_pattern` does.
+    pub fn build_composite_pattern(&mut self, name: String, operator: String, pattern_ids: Vec<String>) -> Result<String, JsValue> {
+        self.record(Call::BuildCompositePattern { name: name.clone(), operator: operator.clone(), pattern_ids: pattern_ids.clone() });
+        let outcome = self.build_composite(name, operator, pattern_ids, false)?;
+        Ok(created_regex(outcome)?)
+    }
+
+    /// `build_composite_pattern` reporting duplicates as
+    /// `build_sequence_pattern_checked` does.
+    pub fn build_composite_pattern_checked(
+        &mut self,
+        name: String,
+        operator: String,
+        pattern_ids: Vec<String>,
+        allow_duplicate: bool,
+    ) -> Result<JsValue, JsValue> {
+        self.record(Call::BuildCompositePatternChecked {
+            name: name.clone(),
+            operator: operator.clone(),
+            pattern_ids: pattern_ids.clone(),
+            allow_duplicate,
+        });
+        let outcome = self.build_composite(name, operator, pattern_ids, allow_duplicate)?;
+        Ok(serde_wasm_bindgen::to_value(&outcome)?)
+    }
+
+    fn build_composite(
+        &mut self,
+        name: String,
+        operator: String,
+        pattern_ids: Vec<String>,
+        allow_duplicate: bool,
+    ) -> Result<SaveOutcome, JsValue> {
+        let composite = self.composite_of(name, &operator, &pattern_ids)?;
+        self.save_new(composite, allow_duplicate)
+    }
+
+    /// An unsaved composite combining copies of the saved patterns
+    /// `pattern_ids` with `operator`.
+    fn composite_of(&self, name: String, operator: &str, pattern_ids: &[String]) -> Result<Pattern, JsValue> {
+        let operator: CompositeOperator = operator.parse().map_err(RegexGenError::InvalidArgument)?;
+
+        if pattern_ids.is_empty() {
+            return Err(RegexGenError::InvalidArgument(String::from("No patterns selected")).into());
+        }
+        if matches!(operator, CompositeOperator::Not) && pattern_ids.len() < 2 {
+            return Err(RegexGenError::InvalidArgument(String::from("NOT takes the pattern to require followed by at least one pattern to exclude")).into());
+        }
+
+        let mut sub_patterns = Vec::new();
+        for id in pattern_ids {
+            match self.patterns.iter().find(|p| p.get_id() == id) {
+                Some(pattern) => sub_patterns.push(pattern.clone()),
+                None => return Err(RegexGenError::PatternNotFound(id.to_string()).into()),
+            }
+        }
+
+        let composite = Pattern::Composite {
+            meta: PatternMeta { id: generate_id(), name, options: self.settings.match_options.clone(), ..PatternMeta::default() },
+            operator,
+            patterns: sub_patterns,
+        };
+        Ok(composite)
+    }
+
+    /// Gives a pattern from outside the builder, and the parts of a
+    /// composite, new ids and saves it. Returns its id.
+    fn save_imported(&mut self, mut pattern: Pattern) -> Result<String, JsValue> {
+        let id = generate_id();
+        pattern.set_id(id.clone());
+        if let Pattern::Composite { patterns, .. } = &mut pattern {
+            for sub_pattern in patterns {
+                sub_pattern.set_id(generate_id());
+            }
+        }
+        self.patterns.push(pattern);
+        self.created_pattern();
+        self.save()?;
+        Ok(id)
+    }
+
+    /// Saves a newly built pattern unless an identical one is saved
+    /// already and `allow_duplicate` is false.
+    fn save_new(&mut self, pattern: Pattern, allow_duplicate: bool) -> Result<SaveOutcome, JsValue> {
+        if !allow_duplicate {
+            if let Some(existing) = duplicates::find_duplicate(&self.patterns, &pattern) {
+                return Ok(SaveOutcome::Duplicate {
+                    existing_id: existing.get_id().to_string(),
+                    existing_name: existing.get_name().to_string(),
+                });
+            }
+        }
+        self.check_room_for(1)?;
+        let id = pattern.get_id().to_string();
+        let regex = pattern.to_regex_with(&self.regex_context())?;
+        self.patterns.push(pattern);
+        self.created_pattern();
+        self.save()?;
+        Ok(SaveOutcome::Created { id, regex })
+    }
+
+    /// Every saved pattern. One that can't be serialized comes back as
+    /// `{ id, name, serialization_error }` in its place.
+    pub fn get_patterns(&self) -> Result<JsValue, JsValue> {
+        Ok(patterns_to_js(&self.patterns)?)
+    }
+
+    /// The elements the current selections would build, as `{ type, text,
+    /// spans }` chips. `spans` are the `start`/`end` offsets of the
+    /// selections each came from, for drawing connectors to the text.
+    /// "and" chips also give `words_apart`, the number of words between
+    /// the selections, and the `element_index` of the gap in the pattern
+    /// `build_sequence_pattern` saves, so it can be bounded to that
+    /// distance with `set_gap_bounds`. Empty when nothing is selected.
+    pub fn get_pattern_preview(&self) -> Result<JsValue, JsValue> {
+        let mut sorted_selections = self.current_selections.clone();
+        sorted_selections.sort_by_key(SelectionSpan::position);
+
+        // Where each element came from in the text; a gap's are the
+        // selections on either side of it
+        let spans = |sources: &Range<usize>| -> Vec<serde_json::Value> {
+            sorted_selections[sources.clone()]
+                .iter()
+                .map(|s| serde_json::json!({ "start": s.start_index, "end": s.end_index }))
+                .collect()
+        };
+        let preview_elements: Vec<serde_json::Value> = sourced_elements(&sorted_selections, self.settings.gap_strategy)
+            .iter()
+            .enumerate()
+            .filter_map(|(element_index, (element, sources))| match element {
+                PatternElement::Word { text, .. } if text.contains(' ') => Some(serde_json::json!({
+                    "type": "phrase",
+                    "text": text,
+                    "spans": spans(sources)
+                })),
+                PatternElement::Word { text, .. } => Some(serde_json::json!({
+                    "type": "word",
+                    "text": text,
+                    "spans": spans(sources)
+                })),
+                PatternElement::TokenClass { class } => Some(serde_json::json!({
+                    "type": "token_class",
+                    "text": format!("<{}>", class.name()),
+                    "spans": spans(sources)
+                })),
+                PatternElement::OneOf { options, .. } => Some(serde_json::json!({
+                    "type": "one_of",
+                    "text": options.join(" / "),
+                    "spans": spans(sources)
+                })),
+                PatternElement::Raw { regex } => Some(serde_json::json!({
+                    "type": "raw",
+                    "text": regex,
+                    "spans": spans(sources)
+                })),
+                // Adjacent selections that can't form one phrase
+                gap if gap.is_separator() => None,
+                // Non-adjacent selections: show AND relationship, and how far
+                // apart they are for turning it into a bounded gap
+                PatternElement::Gap { .. } => {
+                    let (before, after) = (&sorted_selections[sources.start], &sorted_selections[sources.end - 1]);
+                    Some(serde_json::json!({
+                        "type": "and",
+                        "text": "AND",
+                        "spans": spans(sources),
+                        "words_apart": after.word_index.saturating_sub(before.end_word_index() + 1),
+                        "element_index": element_index
+                    }))
+                }
+                _ => None,
+            })
+            .collect();
+
+        Ok(serde_wasm_bindgen::to_value(&preview_elements).map_err(|e| RegexGenError::Serialization(e.to_string()))?)
+    }
+
+    /// Deprecated: indices shift when patterns are deleted or reordered;
+    /// use `test_pattern_by_id`.
+    pub fn test_pattern(&mut self, pattern_index: usize, text: &str, mode: Option<String>) -> Result<JsValue, JsValue> {
+        self.check_text(text)?;
+        let mode = match_mode(mode)?;
+        Ok(self.test_pattern_encoded(pattern_index, text, self.settings.offset_encoding, mode)?)
+    }
+
+    /// `test_pattern` with offsets in `unit` instead of the configured
+    /// encoding: "bytes", "chars" (code points) or "utf16", the unit
+    /// JavaScript strings are indexed in.
+    pub fn test_pattern_with_offsets(&mut self, pattern_index: usize, text: &str, unit: &str, mode: Option<String>) -> Result<JsValue, JsValue> {
+        self.check_text(text)?;
+        let encoding: OffsetEncoding = unit.parse().map_err(RegexGenError::InvalidArgument)?;
+        let mode = match_mode(mode)?;
+        Ok(self.test_pattern_encoded(pattern_index, text, encoding, mode)?)
+    }
+
+    /// Match offsets of the pattern with the given id in `text`, in the
+    /// configured offset encoding. `mode` says what counts as a match:
+    /// "contains" (the default: every match, none overlapping),
+    /// "full_match" (the whole text, or nothing), "per_line" (the span of
+    /// each line with a match, as a line filter keeps it) or "overlapping"
+    /// (a match from every position one starts at). The pattern runs as
+    /// `to_regex_checked` writes it; an AND or NOT composite that has no
+    /// such regex matches each scope segment all its operands agree on.
+    pub fn test_pattern_by_id(&mut self, id: &str, text: &str, mode: Option<String>) -> Result<JsValue, JsValue> {
+        self.check_text(text)?;
+        let mode = match_mode(mode)?;
+        let index = self.index_of(id)?;
+        Ok(self.test_pattern_encoded(index, text, self.settings.offset_encoding, mode)?)
+    }
+
+    fn test_pattern_encoded(&mut self, pattern_index: usize, text: &str, encoding: OffsetEncoding, mode: MatchMode) -> Result<JsValue, RegexGenError> {
+        let started = self.telemetry.start();
+        let matches = self.find_matches(pattern_index, text, mode)?;
+        self.telemetry.evaluated("test_pattern", started);
+
+        // Other modes report spans that aren't the matches themselves
+        if mode == MatchMode::Contains {
+            self.record_option_hits(pattern_index, text, &matches);
+        }
+        let matches: Vec<(usize, usize)> = matches
+            .into_iter()
+            .map(|(start, end)| (encoding.offset(text, start), encoding.offset(text, end)))
+            .collect();
+        serde_wasm_bindgen::to_value(&matches).map_err(|e| RegexGenError::Serialization(e.to_string()))
+    }
+
+    /// Runs one pattern over an array of documents in a single call and
+    /// returns the match count of each plus totals. Option hits are
+    /// recorded as `test_pattern` does.
+    #[cfg(feature = "corpus")]
+    pub fn test_pattern_on_corpus(&mut self, pattern_index: usize, docs: JsValue) -> Result<JsValue, JsValue> {
+        let docs: Vec<String> = serde_wasm_bindgen::from_value(docs).map_err(RegexGenError::from)?;
+        for doc in &docs {
+            self.check_text(doc)?;
+        }
+        let started = self.telemetry.start();
+        let searcher = self.searcher(pattern_index)?;
+
+        let mut report = CorpusReport::default();
+        self.heartbeat.start();
+        for doc in &docs {
+            self.heartbeat.beat("test_pattern_on_corpus")?;
+            let matches = searcher.find(doc, MatchMode::Contains)?;
+            self.record_option_hits(pattern_index, doc, &matches);
+            report.add(matches.len());
+        }
+        self.telemetry.evaluated("test_pattern_on_corpus", started);
+        Ok(serde_wasm_bindgen::to_value(&report)?)
+    }
+
+    /// `test_pattern_on_corpus` for the pattern with id `pattern_id`,
+    /// returning a Promise and testing documents a few at a time so a large
+    /// corpus doesn't block the page. Option hits aren't recorded.
+    #[cfg(feature = "corpus")]
+    pub fn test_pattern_on_corpus_async(&mut self, pattern_id: &str, docs: JsValue) -> Result<Promise, JsValue> {
+        let docs: Vec<String> = serde_wasm_bindgen::from_value(docs).map_err(RegexGenError::from)?;
+        for doc in &docs {
+            self.check_text(doc)?;
+        }
+        let pattern_index = self.index_of(pattern_id)?;
+        let searcher = self.searcher(pattern_index)?;
+
+        let mut report = CorpusReport::default();
+        let mut docs = docs.into_iter();
+        let telemetry = self.telemetry.clone();
+        let started = telemetry.start();
+        Ok(tasks::run_chunked(move || match docs.next() {
+            Some(doc) => {
+                report.add(searcher.find(&doc, MatchMode::Contains)?.len());
+                Ok(None)
+            }
+            None => {
+                telemetry.evaluated("test_pattern_on_corpus", started);
+                Ok(Some(serde_wasm_bindgen::to_value(&report)?))
+            }
+        }))
+    }
+
+    /// Compiles the saved pattern with id `pattern_id` and runs it over
+    /// `text` `iterations` times (at most 100,000), returning `{ regex,
+    /// iterations, compile_ms, total_match_ms, matches, matches_per_sec }`,
+    /// to compare two formulations of a pattern before deploying one. Timed
+    /// with `performance.now()` where there is one.
+    pub fn benchmark_pattern(&self, pattern_id: &str, text: &str, iterations: u32) -> Result<JsValue, JsValue> {
+        self.check_text(text)?;
+        let pattern = self.pattern_by_id(pattern_id)?;
+        let report = benchmark::benchmark_with_clock(pattern, &self.regex_context(), text, iterations, &now_ms)?;
+        Ok(serde_wasm_bindgen::to_value(&report)?)
+    }
+
+    /// `text` as HTML with every match of a pattern wrapped in a `<span>`
+    /// of class `css_class`, and everything else HTML-escaped.
+    pub fn highlight_matches(&mut self, pattern_index: usize, text: &str, css_class: &str) -> Result<String, JsValue> {
+        self.check_text(text)?;
+        let segments = self.match_segments(pattern_index, text)?;
+        Ok(highlight::to_html(&segments, css_class))
+    }
+
+    /// `text` cut into matched and unmatched segments, in order, for hosts
+    /// that render highlights themselves.
+    pub fn highlight_segments(&mut self, pattern_index: usize, text: &str) -> Result<JsValue, JsValue> {
+        self.check_text(text)?;
+        let segments = self.match_segments(pattern_index, text)?;
+        Ok(serde_wasm_bindgen::to_value(&segments)?)
+    }
+
+    /// Replaces every match of a pattern in `text` with `replacement`, taken
+    /// literally. Returns the new text and the spans that changed.
+    pub fn apply_pattern_replace(&mut self, pattern_index: usize, text: &str, replacement: &str) -> Result<JsValue, JsValue> {
+        self.check_text(text)?;
+        let matches = self.tagged_matches(pattern_index, text)?;
+        let transformed = redact::transform(text, matches, |_| replacement.to_string(), self.settings.offset_encoding);
+        Ok(serde_wasm_bindgen::to_value(&transformed)?)
+    }
+
+    /// Masks the text matched by any of the given patterns with
+    /// `mask_char`, one per character (whitespace is kept), e.g. to scrub
+    /// personal data. Returns the masked text and the spans that changed.
+    pub fn redact(&mut self, text: &str, pattern_ids: Vec<String>, mask_char: char) -> Result<JsValue, JsValue> {
+        self.check_text(text)?;
+        let mut matches = Vec::new();
+        for id in &pattern_ids {
+            let index = self
+                .patterns
+                .iter()
+                .position(|p| p.get_id() == id)
+                .ok_or_else(|| RegexGenError::PatternNotFound(id.to_string()))?;
+            matches.extend(self.tagged_matches(index, text)?);
+        }
+        let mask = |matched: &str| matched.chars().map(|c| if c.is_whitespace() { c } else { mask_char }).collect();
+        let transformed = redact::transform(text, matches, mask, self.settings.offset_encoding);
+        Ok(serde_wasm_bindgen::to_value(&transformed)?)
+    }
+
+    /// Renames a saved pattern and returns its regex.
+    pub fn rename_pattern(&mut self, id: &str, name: String) -> Result<String, JsValue> {
+        self.record(Call::RenamePattern { id: id.to_string(), name: name.clone() });
+        if name.trim().is_empty() {
+            return Err(RegexGenError::InvalidArgument(String::from("Pattern name cannot be empty")).into());
+        }
+        self.pattern_by_id_mut(id)?.set_name(name);
+        self.finish_edit(id)
+    }
+
+    /// Saves a copy of a pattern under a new id, named "<name> (copy)", at
+    /// the end of the library. Returns the copy's id.
+    pub fn duplicate_pattern(&mut self, id: &str) -> Result<String, JsValue> {
+        self.check_room_for(1)?;
+        self.record(Call::DuplicatePattern { id: id.to_string() });
+        let mut copy = self.pattern_by_id(id)?.clone();
+        copy.set_name(format!("{} (copy)", copy.get_name()));
+        copy.meta_mut().history.clear();
+        copy.meta_mut().metadata = PatternMetadata::default();
+        self.save_imported(copy)
+    }
+
+    /// Replaces saved patterns with one composite combining them with
+    /// AND, OR or NOT, in the order of `pattern_ids`, placed where the
+    /// first of them was. Without a `name` it is named after them, e.g.
+    /// "Timeout + Refused". Refused while other patterns reference any of
+    /// them. Returns the composite's id.
+    pub fn merge_patterns(&mut self, pattern_ids: Vec<String>, operator: String, name: Option<String>) -> Result<String, JsValue> {
+        self.record(Call::MergePatterns { pattern_ids: pattern_ids.clone(), operator: operator.clone(), name: name.clone() });
+        if pattern_ids.len() < 2 {
+            return Err(RegexGenError::InvalidArgument(String::from("Select at least two patterns to merge")).into());
+        }
+        let merged: BTreeSet<&str> = pattern_ids.iter().map(String::as_str).collect();
+        for id in &pattern_ids {
+            let outside: Vec<String> = dependencies::dependents(&self.patterns, id)
+                .into_iter()
+                .filter(|d| !merged.contains(d.id.as_str()))
+                .filter_map(|d| d.name)
+                .collect();
+            if !outside.is_empty() {
+                return Err(RegexGenError::InvalidState(format!(
+                    "'{}' is referenced by other patterns ({}); it can't be merged away",
+                    self.pattern_by_id(id)?.get_name(),
+                    outside.join(", ")
+                ))
+                .into());
+            }
+        }
+        let name = match name {
+            Some(name) if name.trim().is_empty() => {
+                return Err(RegexGenError::InvalidArgument(String::from("Pattern name cannot be empty")).into());
+            }
+            Some(name) => name,
+            None => {
+                let names: Vec<&str> = pattern_ids
+                    .iter()
+                    .map(|id| self.pattern_by_id(id).map(Pattern::get_name))
+                    .collect::<Result<_, _>>()?;
+                names.join(" + ")
+            }
+        };
+        let composite = self.composite_of(name, &operator, &pattern_ids)?;
+        let position = self
+            .patterns
+            .iter()
+            .position(|p| merged.contains(p.get_id()))
+            .unwrap_or(self.patterns.len());
+        let id = composite.get_id().to_string();
+
+        self.patterns.push(composite);
+        self.created_pattern();
+        for removed in self.patterns.iter().filter(|p| merged.contains(p.get_id())) {
+            self.regex_cache.remove(removed.get_id());
+        }
+        self.patterns.retain(|p| !merged.contains(p.get_id()));
+        if let Some(composite) = self.patterns.pop() {
+            self.patterns.insert(position, composite);
+        }
+        self.save()?;
+        Ok(id)
+    }
+
+    /// Moves a pattern to `new_position` in the library (0 is first),
+    /// shifting the ones in between. The order is saved, and the move can
+    /// be undone like any other change.
+    pub fn move_pattern(&mut self, id: &str, new_position: usize) -> Result<(), JsValue> {
+        self.record(Call::MovePattern { id: id.to_string(), new_position });
+        let index = self.index_of(id)?;
+        if new_position >= self.patterns.len() {
+            return Err(self.invalid_index(new_position).into());
+        }
+        if index == new_position {
+            return Ok(());
+        }
+        let pattern = self.patterns.remove(index);
+        self.patterns.insert(new_position, pattern);
+        self.save()
+    }
+
+    /// Renames several patterns in one go from a template such as
+    /// "auth-{index:02} {name}", e.g. to bring an imported pack in line
+    /// with a naming convention. See `naming::render` for the
+    /// placeholders. Nothing is renamed if any name can't be rendered.
+    /// Returns the new names, in the order of `ids`.
+    pub fn rename_patterns(&mut self, ids: Vec<String>, template: &str) -> Result<Vec<String>, JsValue> {
+        let mut names = Vec::new();
+        for (i, id) in ids.iter().enumerate() {
+            let fields = naming::NameFields { index: i + 1, pattern: self.pattern_by_id(id)? };
+            let name = naming::render(template, &fields).map_err(RegexGenError::InvalidArgument)?;
+            if name.is_empty() {
+                return Err(RegexGenError::InvalidArgument(format!("The template gives pattern {} an empty name", id)).into());
+            }
+            names.push(name);
+        }
+        for (id, name) in ids.iter().zip(&names) {
+            self.record(Call::RenamePattern { id: id.clone(), name: name.clone() });
+            self.pattern_by_id_mut(id)?.set_name(name.clone());
+            self.add_version(id, None)?;
+        }
+        self.save()?;
+        Ok(names)
+    }
+
+    /// Replaces one element of a sequence pattern with a new element given
+    /// in the same JSON shape `get_patterns` returns.
+    pub fn replace_element(&mut self, id: &str, element_index: usize, element: JsValue) -> Result<String, JsValue> {
+        let element: PatternElement = serde_wasm_bindgen::from_value(element).map_err(RegexGenError::from)?;
+        element.validate().map_err(RegexGenError::InvalidArgument)?;
+        self.record(Call::ReplaceElement { id: id.to_string(), element_index, element: element.clone() });
+        *self.element_mut(id, element_index)? = element;
+        self.finish_edit(id)
+    }
+
+    /// Inserts a hand-written regex fragment into a saved sequence pattern
+    /// before `element_index` (or at the end if it is past the last one).
+    pub fn insert_raw_element(&mut self, id: &str, element_index: usize, regex: String) -> Result<String, JsValue> {
+        validate_raw_fragment(&regex)?;
+        self.record(Call::InsertRawElement { id: id.to_string(), element_index, regex: regex.clone() });
+        match self.pattern_by_id_mut(id)? {
+            Pattern::Sequence { elements, .. } => {
+                let index = element_index.min(elements.len());
+                elements.insert(index, PatternElement::Raw { regex });
+            }
+            Pattern::Composite { .. } => return Err(RegexGenError::InvalidArgument(String::from("Composite patterns have no elements")).into()),
+            Pattern::Template { .. } => return Err(template_elements_error().into()),
+        }
+        self.finish_edit(id)
+    }
+
+    pub fn set_gap_bounds(&mut self, id: &str, element_index: usize, min_words: u32, max_words: Option<u32>) -> Result<String, JsValue> {
+        self.record(Call::SetGapBounds { id: id.to_string(), element_index, min_words, max_words });
+        if max_words.is_some_and(|max| max < min_words) {
+            return Err(RegexGenError::InvalidArgument(String::from("max_words must not be less than min_words")).into());
+        }
+        match self.element_mut(id, element_index)? {
+            PatternElement::Gap { min_words: min, max_words: max, max_chars, .. } => {
+                *min = min_words;
+                *max = max_words;
+                *max_chars = None;
+            }
+            _ => return Err(RegexGenError::InvalidArgument(String::from("Element is not a Gap")).into()),
+        }
+        self.finish_edit(id)
+    }
+
+    /// Limits a gap to `max_chars` characters instead of a number of words,
+    /// or goes back to its word bounds if `max_chars` is undefined.
+    pub fn set_gap_char_limit(&mut self, id: &str, element_index: usize, max_chars: Option<u32>) -> Result<String, JsValue> {
+        self.record(Call::SetGapCharLimit { id: id.to_string(), element_index, max_chars });
+        match self.element_mut(id, element_index)? {
+            PatternElement::Gap { max_chars: limit, .. } => *limit = max_chars,
+            _ => return Err(RegexGenError::InvalidArgument(String::from("Element is not a Gap")).into()),
+        }
+        self.finish_edit(id)
+    }
+
+    /// Words the gap must not contain, e.g. "error ... timeout" but not
+    /// across "recovered". An empty list removes the constraint.
+    pub fn set_gap_excluded_words(&mut self, id: &str, element_index: usize, words: Vec<String>) -> Result<String, JsValue> {
+        self.record(Call::SetGapExcludedWords { id: id.to_string(), element_index, words: words.clone() });
+        if words.iter().any(|w| w.is_empty() || !w.chars().all(tokenizer::takes_word_boundary)) {
+            return Err(RegexGenError::InvalidArgument(String::from("Excluded words must be single words of letters, digits and '_'")).into());
+        }
+        match self.element_mut(id, element_index)? {
+            PatternElement::Gap { excluded, .. } => *excluded = words,
+            _ => return Err(RegexGenError::InvalidArgument(String::from("Element is not a Gap")).into()),
+        }
+        self.finish_edit(id)
+    }
+
+    /// How a word or phrase matches: "exact", "case_insensitive",
+    /// "prefix" (also longer words it starts, "connect" in "connection")
+    /// or "approximate" (within one added, dropped or changed character).
+    /// Returns the new regex.
+    pub fn set_word_match(&mut self, id: &str, element_index: usize, mode: &str) -> Result<String, JsValue> {
+        self.record(Call::SetWordMatch { id: id.to_string(), element_index, mode: mode.to_string() });
+        let mode: WordMatch = mode.parse().map_err(RegexGenError::InvalidArgument)?;
+        match self.element_mut(id, element_index)?.base_mut() {
+            PatternElement::Word { mode: current, .. } => *current = mode,
+            _ => return Err(RegexGenError::InvalidArgument(String::from("Element is not a Word")).into()),
+        }
+        self.finish_edit(id)
+    }
+
+    /// Makes an element optional and/or repeated `min_repeat` to
+    /// `max_repeat` times (no upper limit if `max_repeat` is undefined).
+    /// Passing `false, 1, 1` removes the modifier again. Gaps can't be
+    /// modified; change their bounds instead.
+    pub fn set_element_modifier(&mut self, id: &str, element_index: usize, optional: bool, min_repeat: u32, max_repeat: Option<u32>) -> Result<String, JsValue> {
+        self.record(Call::SetElementModifier { id: id.to_string(), element_index, optional, min_repeat, max_repeat });
+        let element = self.element_mut(id, element_index)?;
+        let modifier = ElementModifier {
+            optional,
+            min_repeat,
+            max_repeat,
+            capture: element.modifier().capture,
+            note: element.modifier().note,
+        };
+        modifier.validate().map_err(RegexGenError::InvalidArgument)?;
+        if matches!(element.base(), PatternElement::Gap { .. }) && (optional || modifier.is_repeated()) {
+            return Err(RegexGenError::InvalidArgument(String::from("Gaps can't be modified; set their bounds instead")).into());
+        }
+        element.set_modifier(modifier);
+        self.finish_edit(id)
+    }
+
+    /// Names an element as a field `extract` returns, e.g. the gap in
+    /// "user _ failed login" as "username". Any element can be a field,
+    /// gaps included; `None` stops extracting it.
+    pub fn set_element_capture(&mut self, id: &str, element_index: usize, name: Option<String>) -> Result<String, JsValue> {
+        self.record(Call::SetElementCapture { id: id.to_string(), element_index, name: name.clone() });
+        if let Some(name) = &name {
+            ElementModifier::validate_capture(name).map_err(RegexGenError::InvalidArgument)?;
+            let pattern = self.pattern_by_id(id)?;
+            let taken = pattern
+                .fields()
+                .iter()
+                .any(|&(index, field)| index != element_index && field == name);
+            if taken {
+                return Err(RegexGenError::InvalidArgument(format!("Another element is already the field '{}'", name)).into());
+            }
+        }
+        let element = self.element_mut(id, element_index)?;
+        let modifier = ElementModifier {
+            capture: name,
+            ..element.modifier()
+        };
+        element.set_modifier(modifier);
+        self.finish_edit(id)
+    }
+
+    /// Attaches a short note to an element, e.g. "this gap covers the
+    /// optional thread-id field", shown with its explanation, its part of
+    /// detailed matches and in generated docs. `None` or a blank note
+    /// removes it.
+    pub fn set_element_note(&mut self, id: &str, element_index: usize, note: Option<String>) -> Result<(), JsValue> {
+        self.record(Call::SetElementNote { id: id.to_string(), element_index, note: note.clone() });
+        let note = ElementModifier::normalize_note(note).map_err(RegexGenError::InvalidArgument)?;
+        let element = self.element_mut(id, element_index)?;
+        let modifier = ElementModifier {
+            note,
+            ..element.modifier()
+        };
+        element.set_modifier(modifier);
+        self.save()
+    }
+
+    /// Runs the pattern over `text` and returns, per match, an object of
+    /// the fields named with `set_element_capture`, e.g.
+    /// `[{ username: "bob", ip: "10.0.0.7" }]`. A field whose element took
+    /// no part in a match, such as an optional one left out, is absent.
+    pub fn extract(&self, pattern_id: &str, text: &str) -> Result<JsValue, JsValue> {
+        self.check_text(text)?;
+        let pattern = self.pattern_by_id(pattern_id)?;
+        match pattern {
+            // A template's placeholders are its fields
+            Pattern::Sequence { .. } | Pattern::Template { .. } => {}
+            Pattern::Composite { .. } => {
+                return Err(RegexGenError::InvalidArgument(String::from("Composite patterns have no fields")).into());
+            }
+        }
+        if pattern.fields().is_empty() {
+            return Err(RegexGenError::InvalidState(String::from(
+                "The pattern has no fields; name elements with set_element_capture first",
+            ))
+            .into());
+        }
+        let records = matching::extract_fields(pattern, &self.regex_context(), text)?;
+        Ok(serde_wasm_bindgen::to_value(&records)?)
+    }
+
+    pub fn add_one_of_option(&mut self, id: &str, element_index: usize, option: String) -> Result<String, JsValue> {
+        self.record(Call::AddOneOfOption { id: id.to_string(), element_index, option: option.clone() });
+        match self.element_mut(id, element_index)?.base_mut() {
+            PatternElement::OneOf { options, .. } => {
+                if !options.contains(&option) {
+                    options.push(option);
+                }
+            }
+            _ => return Err(RegexGenError::InvalidArgument(String::from("Element is not a OneOf")).into()),
+        }
+        self.finish_edit(id)
+    }
+
+    pub fn remove_one_of_option(&mut self, id: &str, element_index: usize, option: &str) -> Result<String, JsValue> {
+        self.record(Call::RemoveOneOfOption { id: id.to_string(), element_index, option: option.to_string() });
+        match self.element_mut(id, element_index)?.base_mut() {
+            PatternElement::OneOf { options, list, weights, option_flags } => {
+                if options.len() == 1 && list.is_none() && options[0] == option {
+                    return Err(RegexGenError::InvalidArgument(String::from("A OneOf needs at least one option")).into());
+                }
+                options.retain(|opt| opt != option);
+                weights.remove(option);
+                option_flags.remove(option);
+            }
+            _ => return Err(RegexGenError::InvalidArgument(String::from("Element is not a OneOf")).into()),
+        }
+        self.finish_edit(id)
+    }
+
+    /// Like `test_pattern`, but each match also lists the span every
+    /// element of the pattern covered (anchor words vs gap filler vs the
+    /// chosen `OneOf` option).
+    pub fn test_pattern_detailed(&self, pattern_index: usize, text: &str) -> Result<JsValue, JsValue> {
+        self.check_text(text)?;
+        let pattern = self
+            .patterns
+            .get(pattern_index)
+            .ok_or_else(|| self.invalid_index(pattern_index))?;
+        let mut results = matching::find_detailed(pattern, &self.regex_context(), text)?;
+        matching::encode_offsets(&mut results, text, self.settings.offset_encoding);
+        Ok(serde_wasm_bindgen::to_value(&results)?)
+    }
+
+    /// The whole library as a versioned JSON document for backup or sharing.
+    pub fn export_patterns(&self) -> Result<String, JsValue> {
+        Ok(library_file::export_library(&self.patterns, &BTreeMap::new()).map_err(RegexGenError::from)?)
+    }
+
+    /// Asks the user for a library file on disk and returns a Promise for
+    /// its text, to pass to `import_patterns`. `save_library_file` writes
+    /// back to it. Only in browsers with the File System Access API; see
+    /// `file_access` in `get_capabilities`.
+    #[cfg(feature = "file-access")]
+    pub fn open_library_file(&self) -> Result<Promise, JsValue> {
+        file_access::open()
+    }
+
+    /// Writes the library, as `export_patterns` does, to the file last
+    /// opened or saved, asking the user for one the first time. Returns a
+    /// Promise for the file's name.
+    #[cfg(feature = "file-access")]
+    pub fn save_library_file(&self) -> Result<Promise, JsValue> {
+        file_access::save(self.export_patterns()?, false)
+    }
+
+    /// `save_library_file` to a new file the user picks, which later saves
+    /// write to.
+    #[cfg(feature = "file-access")]
+    pub fn save_library_file_as(&self) -> Result<Promise, JsValue> {
+        file_access::save(self.export_patterns()?, true)
+    }
+
+    /// Name of the library file last opened or saved, if any.
+    #[cfg(feature = "file-access")]
+    pub fn library_file_name(&self) -> Option<String> {
+        file_access::current_name()
+    }
+
+    /// Merges a file written by `export_patterns` (any schema version) into
+    /// the library. `merge_strategy` decides what happens to patterns whose
+    /// id already exists: "skip", "overwrite" or "duplicate" (new id).
+    /// There is no Promise-returning variant: the merge changes this
+    /// builder, which a task resuming between yields can't hold on to. To
+    /// keep a large import off the page, run it in a worker on a
+    /// `from_snapshot` builder and bring the result back with
+    /// `apply_snapshot`.
+    pub fn import_patterns(&mut self, json: &str, merge_strategy: &str) -> Result<JsValue, JsValue> {
+        let incoming = library_file::parse_library(json).map_err(RegexGenError::InvalidArgument)?;
+        self.merge_library(incoming, merge_strategy)
+    }
+
+    /// The library as YAML with stable key order and a `# name (id)`
+    /// comment above each pattern, for reviewing in version control.
+    pub fn export_patterns_yaml(&self) -> Result<String, JsValue> {
+        Ok(library_file::export_library_yaml(&self.patterns, &BTreeMap::new()).map_err(RegexGenError::from)?)
+    }
+
+    /// The library as TOML, laid out like `export_patterns_yaml`.
+    pub fn export_patterns_toml(&self) -> Result<String, JsValue> {
+        Ok(library_file::export_library_toml(&self.patterns, &BTreeMap::new()).map_err(RegexGenError::InvalidState)?)
+    }
+
+    /// `import_patterns` for a file written by `export_patterns_yaml`.
+    pub fn import_patterns_yaml(&mut self, yaml: &str, merge_strategy: &str) -> Result<JsValue, JsValue> {
+        self.check_text(yaml)?;
+        let incoming = library_file::parse_library_yaml(yaml).map_err(RegexGenError::InvalidArgument)?;
+        self.merge_library(incoming, merge_strategy)
+    }
+
+    /// `import_patterns` for a file written by `export_patterns_toml`.
+    pub fn import_patterns_toml(&mut self, toml: &str, merge_strategy: &str) -> Result<JsValue, JsValue> {
+        self.check_text(toml)?;
+        let incoming = library_file::parse_library_toml(toml).map_err(RegexGenError::InvalidArgument)?;
+        self.merge_library(incoming, merge_strategy)
+    }
+    /// A pattern pack of the patterns `ids` and those they reference, as
+    /// JSON to share. `manifest` is `{ name, version, author?, license?,
+    /// description? }`; the schema version is filled in.
+    pub fn publish_pack(&self, ids: Vec<String>, manifest: JsValue) -> Result<String, JsValue> {
+        let manifest: PackManifest = serde_wasm_bindgen::from_value(manifest).map_err(RegexGenError::from)?;
+        let json = pack::publish(&self.patterns, &ids, manifest).map_err(RegexGenError::InvalidArgument)?;
+        self.telemetry.emit(&UsageEvent::Exported { flavor: String::from("pack") });
+        Ok(json)
+    }
+
+    /// Installs a pack written by `publish_pack`, or upgrades the version
+    /// installed, with its pattern ids prefixed by the pack's name. Returns
+    /// the `InstallReport`.
+    pub fn install_pack(&mut self, json: &str) -> Result<JsValue, JsValue> {
+        self.check_text(json)?;
+        self.record(Call::InstallPack { json: json.to_string() });
+        let incoming = pack::parse(json).map_err(RegexGenError::InvalidArgument)?;
+        let mut patterns = self.patterns.clone();
+        let report = pack::install(&mut patterns, incoming).map_err(RegexGenError::InvalidState)?;
+        self.check_room_for(patterns.len().saturating_sub(self.patterns.len()))?;
+        self.patterns = patterns;
+        self.save()?;
+        Ok(serde_wasm_bindgen::to_value(&report)?)
+    }
+
+    /// Name, version and pattern ids of every installed pack.
+    pub fn get_installed_packs(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&pack::installed(&self.patterns))?)
+    }
+
+    /// Imports a cheat-sheet style list with one regex per line, either
+    /// as `name<TAB>regex` or as a bare regex named by the `#` comment above
+    /// it. Valid lines become patterns with a single Raw element; the report
+    /// lists them along with per-line errors.
+    pub fn import_regex_list(&mut self, text: &str) -> Result<JsValue, JsValue> {
+        self.check_text(text)?;
+        self.record(Call::ImportRegexList { text: text.to_string() });
+        let (entries, errors) = import::parse_regex_list(text);
+        self.import_entries(entries, errors, MatchOptions::default(), Vec::new())
+    }
+
+    /// Imports the path rules of a config file a repository already
+    /// keeps, given its `dialect`: "gitignore", "gitattributes",
+    /// "codeowners" or "editorconfig". Each glob becomes a pattern with a
+    /// single Raw element matching the paths, one per line, it applies to,
+    /// named after its rule (e.g. "/docs/ @docs-team") and tagged with the
+    /// dialect. The report is as for `import_regex_list`.
+    pub fn import_config(&mut self, text: &str, dialect: &str) -> Result<JsValue, JsValue> {
+        self.check_text(text)?;
+        self.record(Call::ImportConfig { text: text.to_string(), dialect: dialect.to_string() });
+        let dialect: ConfigDialect = dialect.parse().map_err(RegexGenError::InvalidArgument)?;
+        let (entries, errors) = config_import::parse_config(text, dialect);
+        let options = MatchOptions {
+            multi_line: true,
+            ..MatchOptions::default()
+        };
+        self.import_entries(entries, errors, options, vec![dialect.name().to_string()])
+    }
+
+    /// Saves each imported regex as a pattern with a single Raw element,
+    /// created as `save_imported` creates one, in a single save so one
+    /// `undo` takes the whole import back.
+    fn import_entries(
+        &mut self,
+        entries: Vec<import::RegexListEntry>,
+        errors: Vec<LineError>,
+        options: MatchOptions,
+        tags: Vec<String>,
+    ) -> Result<JsValue, JsValue> {
+        self.check_room_for(entries.len())?;
+        let mut imported = Vec::new();
+        for entry in entries {
+            let id = generate_id();
+            self.patterns.push(Pattern::Sequence {
+                meta: PatternMeta {
+                    id: id.clone(),
+                    name: entry.name.clone(),
+                    options: options.clone(),
+                    tags: tags.clone(),
+                    ..PatternMeta::default()
+                },
+                elements: vec![PatternElement::Raw { regex: entry.regex }],
+            });
+            self.created_pattern();
+            imported.push(ImportedPattern {
+                line: entry.line,
+                id,
+                name: entry.name,
+            });
+        }
+        if !imported.is_empty() {
+            self.save()?;
+        }
+        Ok(serde_wasm_bindgen::to_value(&ImportReport { imported, errors })?)
+    }
+
+    /// Copies a saved pattern to the clipboard as JSON that
+    /// `paste_pattern_from_clipboard` reads back, with its regex alongside
+    /// for pasting anywhere else. Returns a Promise that resolves once it
+    /// is written. Needs a secure page; see `clipboard` in
+    /// `get_capabilities`.
+    pub fn copy_pattern_to_clipboard(&self, id: &str) -> Result<Promise, JsValue> {
+        let text = clipboard::clipboard_text(self.pattern_by_id(id)?, &self.regex_context())?;
+        let written = web_clipboard::write_text(&text)?;
+        self.telemetry.emit(&UsageEvent::Exported { flavor: String::from("clipboard") });
+        Ok(written)
+    }
+
+    /// A Promise for the pattern on the clipboard, in the JSON shape
+    /// `get_patterns` returns with an empty id: one copied with
+    /// `copy_pattern_to_clipboard`, in this tab or another, or else a
+    /// plain regex rebuilt as `import_regex` does. Nothing is saved; save
+    /// it with `add_pattern`.
+    pub fn paste_pattern_from_clipboard(&self) -> Result<Promise, JsValue> {
+        web_clipboard::read_text_with(|text| {
+            let pattern = clipboard::pattern_from_clipboard(&text).map_err(RegexGenError::InvalidArgument)?;
+            Ok(serde_wasm_bindgen::to_value(&pattern)?)
+        })
+    }
+
+    /// Saves a pattern given in the JSON shape `get_patterns` returns, such
+    /// as a draft from `infer_from_pair`, under a new id. Returns the id.
+    pub fn add_pattern(&mut self, pattern: JsValue) -> Result<String, JsValue> {
+        let pattern: Pattern = serde_wasm_bindgen::from_value(pattern).map_err(RegexGenError::from)?;
+        self.check_room_for(1)?;
+        self.record(Call::AddPattern { pattern: pattern.clone() });
+        if let Pattern::Sequence { elements, .. } = &pattern {
+            for element in elements {
+                element.validate().map_err(RegexGenError::InvalidArgument)?;
+            }
+        }
+        self.save_imported(pattern)
+    }
+
+    /// Replaces the definition of a saved pattern with one given in the
+    /// JSON shape `get_patterns` returns, keeping its id, history and
+    /// metadata.
+    /// `note` says why, and is kept with the new version. Returns the regex.
+    pub fn update_pattern(&mut self, id: &str, pattern: JsValue, note: Option<String>) -> Result<String, JsValue> {
+        let mut pattern: Pattern = serde_wasm_bindgen::from_value(pattern).map_err(RegexGenError::from)?;
+        self.record(Call::UpdatePattern { id: id.to_string(), pattern: pattern.clone(), note: note.clone() });
+        if let Pattern::Sequence { elements, .. } = &pattern {
+            for element in elements {
+                element.validate().map_err(RegexGenError::InvalidArgument)?;
+            }
+        }
+        pattern.set_id(id.to_string());
+        let existing = self.pattern_by_id_mut(id)?;
+        // The stored history is authoritative, not the copy sent back
+        pattern.meta_mut().history = std::mem::take(&mut existing.meta_mut().history);
+        pattern.meta_mut().metadata = std::mem::take(&mut existing.meta_mut().metadata);
+        *existing = pattern;
+        self.finish_edit_with_note(id, note)
+    }
+
+    /// Every saved version of a pattern, oldest first, with its regex and
+    /// the note explaining the change.
+    pub fn get_pattern_history(&self, id: &str) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.pattern_by_id(id)?.meta().history)?)
+    }
+
+    /// Attaches `note` to the latest version of a pattern, for edits made
+    /// through methods that don't take one.
+    pub fn set_change_note(&mut self, id: &str, note: String) -> Result<(), JsValue> {
+        let entry = self
+            .pattern_by_id_mut(id)?
+            .meta_mut().history
+            .last_mut()
+            .ok_or_else(|| RegexGenError::InvalidState(String::from("Pattern has no saved versions")))?;
+        entry.note = Some(note).filter(|n| !n.trim().is_empty());
+        self.save()
+    }
+
+    /// Proposes the smallest relaxation of a saved sequence that makes it
+    /// match `example` too: widening gaps, adding `OneOf` options or
+    /// turning a word into its token class. Nothing is saved; the result
+    /// lists each change so the user can approve it with `update_pattern`.
+    #[cfg(feature = "inference")]
+    pub fn generalize_pattern(&self, id: &str, example: &str) -> Result<JsValue, JsValue> {
+        self.check_text(example)?;
+        let pattern = self.pattern_by_id(id)?;
+        let generalization = generalize::generalize(pattern, example, &self.settings.tokenizer, &self.regex_context())
+            .map_err(RegexGenError::InvalidArgument)?;
+        Ok(serde_wasm_bindgen::to_value(&generalization)?)
+    }
+
+    /// Drafts a sequence from two examples of the same event: words both
+    /// share are kept, differing tokens of the same class (numbers, dates,
+    /// IP addresses, ...) become that class and other differences become
+    /// gaps. Returns the unsaved pattern and its regex; save it with
+    /// `add_pattern`.
+    #[cfg(feature = "inference")]
+    pub fn infer_from_pair(&self, a: &str, b: &str) -> Result<JsValue, JsValue> {
+        self.check_text(a)?;
+        self.check_text(b)?;
+        let pattern = infer::infer_pair(a, b, &self.settings.tokenizer).map_err(RegexGenError::InvalidArgument)?;
+        let regex = pattern.to_regex_with(&self.regex_context())?;
+        Ok(serde_wasm_bindgen::to_value(&Draft { pattern, regex, evaluation: None })?)
+    }
+
+    /// Suggests a sequence from texts it should match and texts it
+    /// shouldn't: common anchor words, gaps where the positives differ in
+    /// length, and token classes or `OneOf` groups for single varying
+    /// tokens. The examples are attached as test cases, and the returned
+    /// draft says how the suggestion does on them. Save it with
+    /// `add_pattern`.
+    #[cfg(feature = "inference")]
+    pub fn suggest_pattern(&self, positives: Vec<String>, negatives: Vec<String>) -> Result<JsValue, JsValue> {
+        for example in positives.iter().chain(&negatives) {
+            self.check_text(example)?;
+        }
+        let pattern = infer::induce(&positives, &negatives, &self.settings.tokenizer).map_err(RegexGenError::InvalidArgument)?;
+        let draft = labeled_draft(pattern, positives, negatives, &self.regex_context())?;
+        Ok(serde_wasm_bindgen::to_value(&draft)?)
+    }
+
+    /// `suggest_pattern` returning a Promise, aligning one positive example
+    /// per step so long example lists don't block the page.
+    #[cfg(feature = "inference")]
+    pub fn suggest_pattern_async(&self, positives: Vec<String>, negatives: Vec<String>) -> Result<Promise, JsValue> {
+        for example in positives.iter().chain(&negatives) {
+            self.check_text(example)?;
+        }
+        let tokenizer = self.settings.tokenizer.clone();
+        let (alternation, lists, synonyms) = (self.alternation.clone(), self.lists.clone(), self.synonyms.clone());
+        let mut examples = positives.clone().into_iter();
+        let mut template: Option<infer::Template> = None;
+        Ok(tasks::run_chunked(move || {
+            if let Some(example) = examples.next() {
+                match template.as_mut() {
+                    None => template = Some(infer::Template::new(&example, &tokenizer)),
+                    Some(template) => template.align(&example, &tokenizer, true).map_err(RegexGenError::InvalidArgument)?,
+                }
+                return Ok(None);
+            }
+            let template = template
+                .take()
+                .ok_or_else(|| RegexGenError::InvalidArgument(String::from("At least one example that should match is needed")))?;
+            let context = RegexContext {
+                alternation: alternation.clone(),
+                lists: Some(&lists),
+                synonyms: Some(&synonyms),
+                ..RegexContext::default()
+            };
+            let draft = labeled_draft(template.suggest(&negatives), positives.clone(), negatives.clone(), &context)?;
+            Ok(Some(serde_wasm_bindgen::to_value(&draft)?))
+        }))
+    }
+
+    /// Saves a pattern read from a KQL `parse` operator, e.g. `parse
+    /// RawData with * "user " user:string " logged in" *`, or from an
+    /// expression with `matches regex`, `extract` or `extract_all`. Parse
+    /// columns become fields. Returns the new pattern's id.
+    pub fn import_kql(&mut self, name: &str, expression: &str) -> Result<String, JsValue> {
+        self.check_room_for(1)?;
+        self.record(Call::ImportKql { name: name.to_string(), expression: expression.to_string() });
+        let pattern = kql::pattern_from_kql(name, expression).map_err(RegexGenError::InvalidArgument)?;
+        self.save_imported(pattern)
+    }
+
+    /// Saves a template like "«service» failed with code «code»", whose
+    /// `parameters` (`[{ name, kind }]`, kind "text" or "regex") name every
+    /// placeholder in `body`. Returns the new template's id.
+    pub fn build_template(&mut self, name: &str, body: &str, parameters: JsValue) -> Result<String, JsValue> {
+        let parameters: Vec<TemplateParameter> = serde_wasm_bindgen::from_value(parameters).map_err(RegexGenError::from)?;
+        template::validate(body, &parameters).map_err(RegexGenError::InvalidArgument)?;
+        self.record(Call::BuildTemplate { name: name.to_string(), body: body.to_string(), parameters: parameters.clone() });
+        self.check_room_for(1)?;
+        self.save_imported(Pattern::template(name, body, parameters))
+    }
+
+    /// Saves a sequence made from the template `id` with its placeholders
+    /// filled in from `values`, an object from parameter name to value.
+    /// Every parameter needs a value. Returns the new pattern's id.
+    pub fn instantiate_template(&mut self, id: &str, values: JsValue) -> Result<String, JsValue> {
+        let values: BTreeMap<String, String> = serde_wasm_bindgen::from_value(values).map_err(RegexGenError::from)?;
+        self.record(Call::InstantiateTemplate { id: id.to_string(), values: values.clone() });
+        let pattern = self.pattern_by_id(id)?.instantiate(&values).map_err(RegexGenError::InvalidArgument)?;
+        self.check_room_for(1)?;
+        self.save_imported(pattern)
+    }
+
+    /// Saves a pattern rebuilt from an existing regex, with whatever parts
+    /// could be recognised turned into editable elements. Returns its id.
+    pub fn import_regex(&mut self, name: &str, regex: &str) -> Result<String, JsValue> {
+        self.check_room_for(1)?;
+        self.record(Call::ImportRegex { name: name.to_string(), regex: regex.to_string() });
+        let pattern = Pattern::from_regex(name, regex).map_err(RegexGenError::InvalidArgument)?;
+        self.save_imported(pattern)
+    }
+
+    /// Saves a pattern matching the paths, one per line, that a
+    /// shell-style glob matches, named after the glob. Returns its id.
+    pub fn from_glob(&mut self, glob: &str) -> Result<String, JsValue> {
+        self.check_room_for(1)?;
+        self.record(Call::FromGlob { glob: glob.to_string() });
+        let pattern = Pattern::from_glob(glob, glob).map_err(RegexGenError::InvalidArgument)?;
+        self.save_imported(pattern)
+    }
+
+    /// The glob closest to a pattern, with notes on anything it had to
+    /// approximate. See `Pattern::to_glob`.
+    pub fn to_glob(&self, pattern_id: &str) -> Result<JsValue, JsValue> {
+        let conversion = self.pattern_by_id(pattern_id)?.to_glob().map_err(RegexGenError::InvalidArgument)?;
+        Ok(serde_wasm_bindgen::to_value(&conversion)?)
+    }
+
+    /// IDs of every saved pattern that matches somewhere in `text`, found
+    /// in one pass over the text. The compiled library is cached until the
+    /// patterns change. `order` is "pattern_order" (the default: library
+    /// order), "name", "match_count" (most matches first) or "severity"
+    /// (the most severe `set_pattern_severity` first); ties keep library
+    /// order.
+    pub fn match_all(&mut self, text: &str, order: Option<String>) -> Result<Vec<String>, JsValue> {
+        let order = match_order(order)?;
+        Ok(self
+            .matcher()
+            .match_all(text, order)
+            .into_iter()
+            .map(String::from)
+            .collect())
+    }
+
+    /// Labels `text` with the saved patterns matching it, or only those in
+    /// `pattern_ids`, in that order: `{ start, end, pattern_id,
+    /// pattern_name }` spans in text order, offsets in the configured
+    /// encoding. Where matches overlap one is kept after `priority`:
+    /// "pattern_order" (the default: the earlier pattern), "longest_match",
+    /// "priority" (the pattern with the highest `set_pattern_priority`),
+    /// "severity" (the most severe `set_pattern_severity`) or
+    /// "specificity" (the pattern requiring the most literal text).
+    pub fn annotate(&mut self, text: &str, pattern_ids: Option<Vec<String>>, priority: Option<String>) -> Result<JsValue, JsValue> {
+        self.check_text(text)?;
+        let priority = annotation_priority(priority)?;
+        let encoding = self.settings.offset_encoding;
+        let telemetry = self.telemetry.clone();
+        let started = telemetry.start();
+        let matches = self.matches_of_each(text, pattern_ids, "annotate")?;
+        let annotations = annotate::annotate(text, &matches, priority, encoding);
+        telemetry.evaluated("annotate", started);
+        Ok(serde_wasm_bindgen::to_value(&annotations)?)
+    }
+
+    /// `text` cut into segments each owned by at most one pattern, for
+    /// highlighting every pattern (or those in `pattern_ids`) at once:
+    /// `{ text, start, end, pattern? }`, `pattern` being its legend entry.
+    /// Where patterns overlap the span goes to one after `precedence`,
+    /// as `annotate`'s `priority` chooses.
+    pub fn highlight_all(&mut self, text: &str, pattern_ids: Option<Vec<String>>, precedence: Option<String>) -> Result<JsValue, JsValue> {
+        self.check_text(text)?;
+        let precedence = annotation_priority(precedence)?;
+        let encoding = self.settings.offset_encoding;
+        let matches = self.matches_of_each(text, pattern_ids, "highlight_all")?;
+        let spans: Vec<(usize, usize, &Pattern)> = annotate::resolve(&matches, precedence)
+            .into_iter()
+            .map(|(start, end, rank)| (start, end, matches[rank].0))
+            .collect();
+        let segments = highlight::owned_segments(text, &spans, encoding);
+        Ok(serde_wasm_bindgen::to_value(&segments)?)
+    }
+
+    /// How much the compiled library shares between patterns: `{ patterns,
+    /// distinct_regexes, shared_fragments }`. Compiles it if needed.
+    pub fn get_matcher_stats(&mut self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(self.matcher().stats())?)
+    }
+
+    /// Compiles every saved pattern ahead of time so the first
+    /// `test_pattern` or `match_all` after loading doesn't pay for it.
+    /// Returns the IDs of patterns that failed to compile.
+    pub fn precompile_all(&mut self) -> Vec<String> {
+        let mut failed = Vec::new();
+        for index in 0..self.patterns.len() {
+            if let Some(Err(_)) = self.compiled(index) {
+                failed.push(self.patterns[index].get_id().to_string());
+            }
+        }
+        self.matcher();
+        failed
+    }
+
+    /// Everything worth knowing before a pattern goes into production
+    /// tooling: whether it compiles, its estimated size, nested quantifiers
+    /// that risk catastrophic backtracking in JavaScript and PCRE, and
+    /// which dialects can't express it or behave differently.
+    pub fn validate_pattern(&self, pattern_index: usize) -> Result<JsValue, JsValue> {
+        let pattern = self
+            .patterns
+            .get(pattern_index)
+            .ok_or_else(|| self.invalid_index(pattern_index))?;
+        let report = validation::validate(pattern, &self.regex_context());
+        Ok(serde_wasm_bindgen::to_value(&report)?)
+    }
+
+    /// Starts recording the selection, build and edit calls made on this
+    /// builder, discarding any earlier recording.
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Recording {
+            version: recording::RECORDING_VERSION,
+            settings: self.settings.clone(),
+            calls: Vec::new(),
+        });
+        self.recording_active = true;
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.recording_active = false;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording_active
+    }
+
+    /// The current or last recording as a JSON script for `replay`.
+    pub fn export_recording(&self) -> Result<String, JsValue> {
+        let recording = self
+            .recording
+            .as_ref()
+            .ok_or_else(|| RegexGenError::InvalidState(String::from("Nothing has been recorded")))?;
+        Ok(serde_json::to_string_pretty(recording).map_err(RegexGenError::from)?)
+    }
+
+    /// Runs a script from `export_recording` against this builder, best
+    /// done in a sandbox or a fresh workspace. Ids of patterns the script
+    /// created are mapped to the ids the replay creates. Returns each
+    /// call's result, stopping at the first that fails.
+    pub fn replay(&mut self, script: &str) -> Result<JsValue, JsValue> {
+        let recording: Recording = serde_json::from_str(script)
+            .map_err(|e| RegexGenError::InvalidArgument(format!("Invalid recording: {}", e)))?;
+        if recording.version > recording::RECORDING_VERSION {
+            return Err(RegexGenError::InvalidArgument(format!(
+                "Recording version {} is newer than this builder supports ({})",
+                recording.version,
+                recording::RECORDING_VERSION
+            ))
+            .into());
+        }
+        let report = recording::replay(self, recording)?;
+        Ok(serde_wasm_bindgen::to_value(&report)?)
+    }
+
+    /// Plain-language description of a saved pattern, as structured JSON
+    /// with message keys for translation. The text is in `locale`, or the
+    /// settings' locale if none is given; messages without a registered
+    /// translation stay in English.
+    pub fn explain_pattern(&self, pattern_index: usize, locale: Option<String>) -> Result<JsValue, JsValue> {
+        let pattern = self
+            .patterns
+            .get(pattern_index)
+            .ok_or_else(|| self.invalid_index(pattern_index))?;
+        let locale = locale.unwrap_or_else(|| self.settings.locale.clone());
+        Ok(serde_wasm_bindgen::to_value(&pattern.explain_in(&self.catalog(&locale)))?)
+    }
+
+    /// A short sentence per pattern saying how often and on which lines it
+    /// matched `text`, for screen readers and notifications. Rendered in
+    /// the settings' locale.
+    pub fn summarize_matches(&mut self, text: &str, pattern_ids: Vec<String>) -> Result<String, JsValue> {
+        self.check_text(text)?;
+        let mut sentences = Vec::new();
+        for id in &pattern_ids {
+            let index = self
+                .patterns
+                .iter()
+                .position(|p| p.get_id() == id)
+                .ok_or_else(|| RegexGenError::PatternNotFound(id.to_string()))?;
+            let Some(compiled) = self.compiled(index) else {
+                continue;
+            };
+            let catalog = self.catalog(&self.settings.locale);
+            let name = self.patterns[index].get_name().to_string();
+            sentences.push(match compiled {
+                Ok(regex) => {
+                    let scope = self.patterns[index].meta().options.scope;
+                    let starts: Vec<usize> = matching::find_scoped(&regex, text, scope).iter().map(|m| m.0).collect();
+                    summary::summarize(&name, text, &starts, &catalog)
+                }
+                Err(e) => catalog.render("summary_invalid", &[("name", name), ("error", e.to_string())]),
+            });
+        }
+        Ok(sentences.join("\n"))
+    }
+
+    /// Adds or replaces the explanation messages for `locale`, given as an
+    /// object of message key to template, e.g.
+    /// `{ "word": "das Wort '{text}'" }`. Messages left out fall back to
+    /// English.
+    pub fn register_message_catalog(&mut self, locale: String, messages: JsValue) -> Result<(), JsValue> {
+        let messages: BTreeMap<String, String> = serde_wasm_bindgen::from_value(messages).map_err(RegexGenError::from)?;
+        let catalog = MessageCatalog::from_templates(&locale, messages).map_err(RegexGenError::InvalidArgument)?;
+        self.catalogs.insert(locale, catalog);
+        Ok(())
+    }
+
+    /// Every message key with its English template, as a starting point
+    /// for a translation.
+    pub fn get_message_templates(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&locale::english_templates())?)
+    }
+
+    pub fn list_locales(&self) -> Vec<String> {
+        let mut locales: Vec<String> = self.catalogs.keys().cloned().collect();
+        if !self.catalogs.contains_key(locale::DEFAULT_LOCALE) {
+            locales.insert(0, locale::DEFAULT_LOCALE.to_string());
+        }
+        locales
+    }
+
+    /// Synthetic strings the pattern would match, for showing users what
+    /// "text like this" looks like. Different ones on every call unless
+    /// `set_seed` was called.
+    pub fn generate_examples(&self, pattern_index: usize, count: usize) -> Result<Vec<String>, JsValue> {
+        let pattern = self
+            .patterns
+            .get(pattern_index)
+            .ok_or_else(|| self.invalid_index(pattern_index))?;
+        let seed = seed::random_below(u32::MAX as u64);
+        Ok(pattern.generate_examples_seeded(count, seed, &self.regex_context()))
+    }
+
+    /// Sets the ranking weight of one option of a `OneOf` element.
+    pub fn set_option_weight(&mut self, pattern_index: usize, element_index: usize, option: String, weight: u32) -> Result<(), JsValue> {
+        self.record(Call::SetOptionWeight { pattern_index, element_index, option: option.clone(), weight });
+        let elements = self.elements_at_mut(pattern_index)?;
+        match elements.get_mut(element_index).map(PatternElement::base_mut) {
+            Some(PatternElement::OneOf { weights, .. }) => {
+                if weight == 0 {
+                    weights.remove(&option);
+                } else {
+                    weights.insert(option, weight);
+                }
+            }
+            _ => return Err(RegexGenError::InvalidArgument(String::from("Element is not a OneOf")).into()),
+        }
+        let id = self.patterns[pattern_index].get_id().to_string();
+        self.finish_edit(&id)?;
+        Ok(())
+    }
+
+    /// Overrides case sensitivity and whole-word matching for one option of
+    /// a `OneOf` element, independently of the other options. Returns the
+    /// pattern's regex.
+    pub fn set_option_flags(&mut self, pattern_index: usize, element_index: usize, option: String, case_insensitive: bool, whole_word: bool) -> Result<String, JsValue> {
+        self.record(Call::SetOptionFlags {
+            pattern_index,
+            element_index,
+            option: option.clone(),
+            case_insensitive,
+            whole_word,
+        });
+        let elements = self.elements_at_mut(pattern_index)?;
+        match elements.get_mut(element_index).map(PatternElement::base_mut) {
+            Some(PatternElement::OneOf { options, option_flags, .. }) => {
+                if !options.contains(&option) {
+                    return Err(RegexGenError::InvalidArgument(format!("Unknown option: {}", option)).into());
+                }
+                let flags = OptionFlags { case_insensitive, whole_word };
+                if flags == OptionFlags::default() {
+                    option_flags.remove(&option);
+                } else {
+                    option_flags.insert(option, flags);
+                }
+            }
+            _ => return Err(RegexGenError::InvalidArgument(String::from("Element is not a OneOf")).into()),
+        }
+        let id = self.patterns[pattern_index].get_id().to_string();
+        self.finish_edit(&id)?;
+        // The regex `test_pattern` compiles
+        Ok(self.patterns[pattern_index].to_regex_for_with(RegexDialect::Rust, &self.regex_context())?)
+    }
+
+    /// Ranked options with weights and hit counts for every `OneOf` element
+    /// of a pattern. Hits accumulate across `test_pattern` calls.
+    pub fn get_option_stats(&self, pattern_index: usize) -> Result<JsValue, JsValue> {
+        let (id, elements) = match self.patterns.get(pattern_index) {
+            Some(Pattern::Sequence { meta, elements }) => (&meta.id, elements.as_slice()),
+            // Composites and templates have no options to rank
+            Some(Pattern::Composite { meta, .. } | Pattern::Template { meta, .. }) => (&meta.id, &[][..]),
+            None => return Err(self.invalid_index(pattern_index).into()),
+        };
+        let context = self.regex_context();
+        let stats: Vec<OneOfStats> = elements
+            .iter()
+            .enumerate()
+            .filter_map(|(element_index, element)| match element.base() {
+                PatternElement::OneOf { options, list, weights, .. } => Some(OneOfStats {
+                    element_index,
+                    options: option_stats::rank(
+                        context
+                            .resolve_options(options, list.as_deref())
+                            .into_iter()
+                            .map(|option| OptionStat {
+                                weight: weights.get(&option).copied().unwrap_or(0),
+                                hits: self.option_hits.get(id, element_index, &option),
+                                option,
+                            })
+                            .collect(),
+                    ),
+                }),
+                _ => None,
+            })
+            .collect();
+        Ok(serde_wasm_bindgen::to_value(&stats)?)
+    }
+
+    pub fn reset_option_hits(&mut self, pattern_index: usize) {
+        if let Some(pattern) = self.patterns.get(pattern_index) {
+            self.option_hits.clear_pattern(pattern.get_id());
+        }
+    }
+
+    /// Revision of the library as last loaded or saved by this builder.
+    pub fn get_revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Whether another tab has saved the library since this builder last
+    /// loaded or saved it.
+    pub fn has_conflict(&self) -> bool {
+        self.persistent && web_storage::load_revision_from_storage(&self.store, &self.workspace) != self.revision
+    }
+
+    /// Replaces the in-memory library with what is in storage, discarding
+    /// unsaved edits. Use after a Conflict error.
+    pub fn reload(&mut self) {
+        if self.persistent {
+            self.patterns = web_storage::load_patterns_from_storage(&self.store, &self.workspace);
+            display::assign_missing(&mut self.patterns);
+            self.invalidate_matcher();
+            self.revision = web_storage::load_revision_from_storage(&self.store, &self.workspace);
+            self.documents = web_storage::load_documents_from_storage(&self.store, &self.workspace);
+            self.synonyms = web_storage::load_synonyms_from_storage(&self.store, &self.workspace);
+            self.apply_settings(web_storage::load_settings_from_storage(&self.store, &self.workspace));
+            self.transaction = None;
+            self.load_journal();
+        }
+    }
+
+    /// Groups the following library edits so they are saved together by
+    /// `commit` or undone together by `rollback`.
+    pub fn begin_transaction(&mut self) -> Result<(), JsValue> {
+        if self.transaction.is_some() {
+            return Err(RegexGenError::InvalidState(String::from("A transaction is already in progress")).into());
+        }
+        self.transaction = Some(self.patterns.clone());
+        Ok(())
+    }
+
+    /// Saves every edit made since `begin_transaction`. If saving fails the
+    /// library is rolled back and storage is left untouched.
+    pub fn commit(&mut self) -> Result<(), JsValue> {
+        let snapshot = self
+            .transaction
+            .take()
+            .ok_or_else(|| RegexGenError::InvalidState(String::from("No transaction in progress")))?;
+        if let Err(e) = self.save() {
+            self.patterns = snapshot;
+            self.invalidate_matcher();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Discards every edit made since `begin_transaction`.
+    pub fn rollback(&mut self) -> Result<(), JsValue> {
+        let snapshot = self
+            .transaction
+            .take()
+            .ok_or_else(|| RegexGenError::InvalidState(String::from("No transaction in progress")))?;
+        self.patterns = snapshot;
+        self.invalidate_matcher();
+        Ok(())
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    /// Calls `callback` with `{ type, id, name }` for every pattern
+    /// created, updated or deleted from now on, where `type` is "created",
+    /// "updated" or "deleted", including changes picked up by `reload` or
+    /// `switch_workspace`. Edits grouped in a transaction are reported
+    /// when it commits. Returns an id for `off_patterns_changed`.
+    pub fn on_patterns_changed(&mut self, callback: js_sys::Function) -> u32 {
+        self.listeners.subscribe(callback)
+    }
+
+    /// Stops calling a callback registered with `on_patterns_changed`.
+    /// Returns false if the id was unknown.
+    pub fn off_patterns_changed(&mut self, subscription: u32) -> bool {
+        self.listeners.unsubscribe(subscription)
+    }
+
+    /// Opts in to usage telemetry: `callback` is called with an anonymized
+    /// event whenever a pattern is built (`{ type: "pattern_built",
+    /// composite, elements }`), exported (`{ type: "exported", flavor }`,
+    /// e.g. "regex_javascript" or "tool_grep") or tested (`{ type:
+    /// "evaluated", operation, duration }`, with the duration as a bucket
+    /// such as "under_10ms"). Events never include patterns, names or
+    /// text, and the builder sends nothing anywhere itself. Undefined
+    /// turns telemetry off again.
+    pub fn set_telemetry_hook(&mut self, callback: Option<js_sys::Function>) {
+        self.telemetry.set_hook(callback);
+    }
+
+    /// For hosts that can't move work to a worker: long calls
+    /// (`test_pattern_on_corpus`, `search_documents`, `annotate`,
+    /// `export_sarif`) call `callback(operation, elapsed_ms)` each time
+    /// they have run another `budget_ms` (50 by default), so the host can
+    /// update a progress indicator. Returning `false` stops the call with
+    /// a Cancelled error. Undefined removes the callback.
+    pub fn set_heartbeat(&mut self, callback: Option<js_sys::Function>, budget_ms: Option<f64>) -> Result<(), JsValue> {
+        let budget_ms = budget_ms.unwrap_or(events::DEFAULT_HEARTBEAT_MS);
+        if budget_ms.is_nan() || budget_ms <= 0.0 {
+            return Err(RegexGenError::InvalidArgument(format!("The heartbeat budget must be positive, got {}", budget_ms)).into());
+        }
+        self.heartbeat.set(callback, budget_ms);
+        Ok(())
+    }
+
+    /// Reverts the latest library change (a create, edit, delete or a
+    /// whole transaction). Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> Result<bool, JsValue> {
+        self.record(Call::Undo);
+        self.step_journal(true)
+    }
+
+    /// Reapplies the latest undone change. Returns false if there was
+    /// nothing to redo; any new change clears what can be redone.
+    pub fn redo(&mut self) -> Result<bool, JsValue> {
+        self.record(Call::Redo);
+        self.step_journal(false)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.journal.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.journal.redo.is_empty()
+    }
+
+    /// What `undo` would revert, e.g. "Delete refused", for a menu item.
+    pub fn undo_label(&self) -> Option<String> {
+        self.journal.undo.last().map(JournalEntry::label)
+    }
+
+    pub fn redo_label(&self) -> Option<String> {
+        self.journal.redo.last().map(JournalEntry::label)
+    }
+
+    /// Starts a sandbox holding a copy of the current library. Nothing done
+    /// in the sandbox touches storage until it is committed back.
+    pub fn create_sandbox(&self) -> Sandbox {
+        let mut builder = self.clone();
+        builder.persistent = false;
+        builder.transaction = None;
+        builder.current_selections.clear();
+        builder.listeners.clear();
+        Sandbox::new(builder)
+    }
+
+    /// Name of the workspace the library is loaded from.
+    pub fn get_current_workspace(&self) -> String {
+        self.workspace.clone()
+    }
+
+    pub fn list_workspaces(&self) -> Vec<String> {
+        web_storage::load_workspace_names(&self.store)
+    }
+
+    /// Registers an empty workspace; switch to it to start using it.
+    pub fn create_workspace(&self, name: &str) -> Result<(), JsValue> {
+        validate_key_name("workspace", name)?;
+        let mut names = web_storage::load_workspace_names(&self.store);
+        if names.iter().any(|n| n == name) {
+            return Err(RegexGenError::InvalidArgument(format!("Workspace already exists: {}", name)).into());
+        }
+        names.push(name.to_string());
+        Ok(web_storage::save_workspace_names(&self.store, &names)?)
+    }
+
+    /// Makes `name` the current workspace, creating it if needed, and loads
+    /// its library and documents. Selections and any open transaction are
+    /// dropped.
+    pub fn switch_workspace(&mut self, name: &str) -> Result<(), JsValue> {
+        if self.transaction.is_some() {
+            return Err(RegexGenError::InvalidState(String::from("Commit or roll back the transaction before switching workspaces")).into());
+        }
+        validate_key_name("workspace", name)?;
+        let mut names = web_storage::load_workspace_names(&self.store);
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+            web_storage::save_workspace_names(&self.store, &names)?;
+        }
+        web_storage::save_current_workspace(&self.store, name)?;
+
+        self.workspace = name.to_string();
+        self.patterns = web_storage::load_patterns_from_storage(&self.store, name);
+        display::assign_missing(&mut self.patterns);
+        self.revision = web_storage::load_revision_from_storage(&self.store, name);
+        self.documents = web_storage::load_documents_from_storage(&self.store, name);
+        self.synonyms = web_storage::load_synonyms_from_storage(&self.store, name);
+        self.apply_settings(web_storage::load_settings_from_storage(&self.store, name));
+        self.current_selections.clear();
+        self.invalidate_matcher();
+        self.journal = Journal::default();
+        self.load_journal();
+        Ok(())
+    }
+
+    /// Removes a workspace and everything stored under it. The default and
+    /// the current workspace can't be deleted.
+    pub fn delete_workspace(&self, name: &str) -> Result<(), JsValue> {
+        if name == web_storage::DEFAULT_WORKSPACE {
+            return Err(RegexGenError::InvalidArgument(String::from("The default workspace can't be deleted")).into());
+        }
+        if name == self.workspace {
+            return Err(RegexGenError::InvalidState(String::from("Switch to another workspace before deleting this one")).into());
+        }
+        let mut names = web_storage::load_workspace_names(&self.store);
+        let before = names.len();
+        names.retain(|n| n != name);
+        if names.len() == before {
+            return Err(RegexGenError::NotFound(format!("Workspace not found: {}", name)).into());
+        }
+        web_storage::remove_workspace_data(&self.store, name)?;
+        Ok(web_storage::save_workspace_names(&self.store, &names)?)
+    }
+
+    /// A library file with a workspace's patterns and documents, readable
+    /// by `import_patterns`.
+    pub fn export_workspace(&self, name: &str) -> Result<String, JsValue> {
+        let (patterns, documents) = if name == self.workspace {
+            (self.patterns.clone(), self.documents.clone())
+        } else if web_storage::load_workspace_names(&self.store).iter().any(|n| n == name) {
+            (web_storage::load_patterns_from_storage(&self.store, name), web_storage::load_documents_from_storage(&self.store, name))
+        } else {
+            return Err(RegexGenError::NotFound(format!("Workspace not found: {}", name)).into());
+        };
+        Ok(library_file::export_library(&patterns, &documents).map_err(RegexGenError::from)?)
+    }
+
+    /// Stores a sample text under `name` in the current workspace.
+    pub fn save_document(&mut self, name: String, text: String) -> Result<(), JsValue> {
+        self.check_text(&text)?;
+        self.documents.insert(name, text);
+        self.document_index = None;
+        self.save_documents()
+    }
+
+    pub fn get_document(&self, name: &str) -> Option<String> {
+        self.documents.get(name).cloned()
+    }
+
+    pub fn list_documents(&self) -> Vec<String> {
+        self.documents.keys().cloned().collect()
+    }
+
+    pub fn delete_document(&mut self, name: &str) -> Result<(), JsValue> {
+        if self.documents.remove(name).is_none() {
+            return Err(RegexGenError::NotFound(format!("Document not found: {}", name)).into());
+        }
+        self.document_index = None;
+        self.save_documents()
+    }
+
+    /// Matches of a pattern in each saved document that has any, as
+    /// `{ document, matches }` with offsets in the configured encoding.
+    /// With the `index_documents` setting, only the documents, or for a
+    /// pattern scoped to lines or sentences only the lines, holding the
+    /// words the pattern needs are searched.
+    pub fn search_documents(&mut self, pattern_id: &str) -> Result<JsValue, JsValue> {
+        let pattern_index = self.index_of(pattern_id)?;
+        let started = self.telemetry.start();
+        let documents: Vec<(String, String)> = self.documents.iter().map(|(name, text)| (name.clone(), text.clone())).collect();
+        let candidates = match self.settings.index_documents {
+            true => {
+                let index = self.document_index.get_or_insert_with(|| Rc::new(DocumentIndex::new(&self.documents))).clone();
+                Some((index.candidates(&self.patterns[pattern_index]), index))
+            }
+            false => None,
+        };
+
+        let mut results = Vec::new();
+        self.heartbeat.start();
+        for (document, (name, text)) in documents.iter().enumerate() {
+            self.heartbeat.beat("search_documents")?;
+            let matches = match &candidates {
+                Some((Candidates::Documents(wanted), _)) if !wanted.contains(&document) => Vec::new(),
+                Some((Candidates::Lines(lines), index)) => {
+                    let mut matches = Vec::new();
+                    for &(_, line) in lines.range((document, 0)..(document + 1, 0)) {
+                        self.heartbeat.beat("search_documents")?;
+                        let (start, end) = index.line_range(document, line, text);
+                        let found = self.find_matches(pattern_index, &text[start..end], MatchMode::Contains)?;
+                        matches.extend(found.into_iter().map(|(s, e)| (start + s, start + e)));
+                    }
+                    matches
+                }
+                _ => self.find_matches(pattern_index, text, MatchMode::Contains)?,
+            };
+            if !matches.is_empty() {
+                let encoding = self.settings.offset_encoding;
+                let matches = matches.into_iter().map(|(s, e)| (encoding.offset(text, s), encoding.offset(text, e))).collect();
+                results.push(DocumentMatches { document: name.clone(), matches });
+            }
+        }
+        self.telemetry.evaluated("search_documents", started);
+        Ok(serde_wasm_bindgen::to_value(&results)?)
+    }
+
+    /// The query and toggle states to reproduce a pattern in VS Code's
+    /// search box.
+    #[cfg(feature = "export-flavors")]
+    pub fn export_vscode_search(&self, pattern_id: &str) -> Result<JsValue, JsValue> {
+        let pattern = self.pattern_by_id(pattern_id)?;
+        let search = export::vscode_search(pattern, &self.regex_context())
+            .map_err(RegexGenError::from)?;
+        self.telemetry.emit(&UsageEvent::Exported { flavor: String::from("vscode_search") });
+        Ok(serde_wasm_bindgen::to_value(&search)?)
+    }
+
+    /// A Vector (vector.dev) config snippet that routes and tags log events
+    /// by the given patterns.
+    #[cfg(feature = "export-flavors")]
+    pub fn export_vector_transform(&self, pattern_ids: Vec<String>) -> Result<String, JsValue> {
+        let mut patterns = Vec::new();
+        for id in &pattern_ids {
+            patterns.push(self.pattern_by_id(id)?);
+        }
+        let config = export::vector_transform(&patterns, &self.regex_context())?;
+        self.telemetry.emit(&UsageEvent::Exported { flavor: String::from("vector_transform") });
+        Ok(config)
+    }
+
+    /// A ready-to-paste snippet running the pattern in another tool:
+    /// "grep" (a `grep -E` command line), "splunk" (a `regex`/`rex`
+    /// search), "elasticsearch" (a `regexp` query) or "loki" (a LogQL line
+    /// filter). Comes with the regex used and portability warnings.
+    #[cfg(feature = "export-flavors")]
+    pub fn export_for_tool(&self, pattern_id: &str, target: &str) -> Result<JsValue, JsValue> {
+        let target: ToolTarget = target.parse().map_err(RegexGenError::InvalidArgument)?;
+        let pattern = self.pattern_by_id(pattern_id)?;
+        let export = export::tool_snippet(pattern, &self.regex_context(), target)?;
+        self.telemetry.emit(&UsageEvent::exported("tool", target));
+        Ok(serde_wasm_bindgen::to_value(&export)?)
+    }
+
+    /// The pattern as KQL for Microsoft Sentinel: `{ regex, query, parse,
+    /// warnings }`, where `query` filters with `matches regex` and
+    /// extracts the pattern's fields, and `parse` is an equivalent `parse`
+    /// operator when the pattern is simple enough to have one.
+    #[cfg(feature = "export-flavors")]
+    pub fn export_kql(&self, pattern_id: &str) -> Result<JsValue, JsValue> {
+        let pattern = self.pattern_by_id(pattern_id)?;
+        let export = kql::kql_export(pattern, &self.regex_context())?;
+        self.telemetry.emit(&UsageEvent::Exported { flavor: String::from("kql") });
+        Ok(serde_wasm_bindgen::to_value(&export)?)
+    }
+
+    /// A SARIF 2.1.0 log of where the given patterns match, for tools
+    /// that take code-scanning results: each pattern is a rule and each
+    /// match a result. `input` is a text (reported as "text"), an array of
+    /// documents (as "document-1", "document-2", ...) or an object of
+    /// documents by name.
+    #[cfg(feature = "export-flavors")]
+    pub fn export_sarif(&mut self, input: JsValue, pattern_ids: Vec<String>) -> Result<String, JsValue> {
+        let documents: Vec<(String, String)> = if let Some(text) = input.as_string() {
+            vec![(String::from("text"), text)]
+        } else if js_sys::Array::is_array(&input) {
+            let docs: Vec<String> = serde_wasm_bindgen::from_value(input).map_err(RegexGenError::from)?;
+            docs.into_iter().enumerate().map(|(i, doc)| (format!("document-{}", i + 1), doc)).collect()
+        } else {
+            let docs: BTreeMap<String, String> = serde_wasm_bindgen::from_value(input).map_err(RegexGenError::from)?;
+            docs.into_iter().collect()
+        };
+        for (_, doc) in &documents {
+            self.check_text(doc)?;
+        }
+
+        let indices: Vec<usize> = pattern_ids.iter().map(|id| self.index_of(id)).collect::<Result<_, _>>()?;
+
+        let mut matches = vec![Vec::new(); indices.len()];
+        self.heartbeat.start();
+        for (_, doc) in &documents {
+            for (rule, &index) in indices.iter().enumerate() {
+                self.heartbeat.beat("export_sarif")?;
+                matches[rule].push(self.find_matches(index, doc, MatchMode::Contains)?);
+            }
+        }
+        let documents: Vec<(String, &str)> = documents.iter().map(|(uri, doc)| (uri.clone(), doc.as_str())).collect();
+        let rules: Vec<sarif::RuleMatches> = indices.iter().map(|&index| &self.patterns[index]).zip(matches).collect();
+        let log = sarif::sarif_log(&documents, &rules, &self.regex_context());
+        self.telemetry.emit(&UsageEvent::Exported { flavor: String::from("sarif") });
+        Ok(log)
+    }
+
+    /// Returns the regex for a saved pattern in the given dialect
+    /// ("rust", "javascript", "pcre", "re2" or "posix"), or in the default
+    /// dialect from the settings when `dialect` is empty.
+    pub fn export_pattern_regex(&self, pattern_index: usize, dialect: &str) -> Result<String, JsValue> {
+        let dialect = self.export_dialect(dialect)?;
+        let regex = self.exported_regex(pattern_index, dialect)?;
+        self.telemetry.emit(&UsageEvent::exported("regex", dialect));
+        Ok(regex)
+    }
+
+    /// The Rust regex for a saved pattern with redundant groups, repeated
+    /// word boundaries and shared alternative prefixes simplified away. It
+    /// matches exactly what the plain Rust export does.
+    pub fn export_pattern_regex_optimized(&self, pattern_index: usize) -> Result<String, JsValue> {
+        let regex = self.exported_regex(pattern_index, RegexDialect::Rust)?;
+        self.telemetry.emit(&UsageEvent::Exported { flavor: String::from("regex_rust_optimized") });
+        Ok(optimize::optimize(&regex).unwrap_or(regex))
+    }
+
+    /// Like `export_pattern_regex`, but also returns warnings about
+    /// constructs that behave differently in the target engine than in
+    /// `test_pattern`.
+    pub fn export_pattern(&self, pattern_index: usize, dialect: &str) -> Result<JsValue, JsValue> {
+        let dialect = self.export_dialect(dialect)?;
+        let regex = self.exported_regex(pattern_index, dialect)?;
+        let warnings = portability::portability_warnings(&self.patterns[pattern_index], dialect);
+        self.telemetry.emit(&UsageEvent::exported("regex", dialect));
+        Ok(serde_wasm_bindgen::to_value(&ExportResult {
+            regex,
+            dialect,
+            warnings,
+        })?)
+    }
+
+    /// The regex of a pattern with its test cases embedded, as "comments",
+    /// a "pytest" module or a "jest" test file. An empty `dialect` picks the
+    /// one the test harness runs (PCRE for pytest, JavaScript for Jest).
+    #[cfg(feature = "export-flavors")]
+    pub fn export_pattern_with_tests(&self, pattern_index: usize, dialect: &str, format: &str) -> Result<String, JsValue> {
+        let format: FixtureFormat = format.parse().map_err(RegexGenError::InvalidArgument)?;
+        let dialect = match format.natural_dialect() {
+            Some(natural) if dialect.is_empty() => natural,
+            _ => self.export_dialect(dialect)?,
+        };
+        let regex = self.exported_regex(pattern_index, dialect)?;
+        self.telemetry.emit(&UsageEvent::exported("tests", format));
+        Ok(export::with_test_fixture(&self.patterns[pattern_index], &regex, format))
+    }
+
+    /// Overrides how a pattern is shown: its palette color index, a short
+    /// label and a host-defined icon. Undefined label or icon clear them.
+    pub fn set_pattern_display(&mut self, id: &str, color_index: usize, label: Option<String>, icon: Option<String>) -> Result<(), JsValue> {
+        if color_index >= display::PALETTE.len() {
+            return Err(RegexGenError::InvalidArgument(format!("color_index must be below {}", display::PALETTE.len())).into());
+        }
+        let pattern = self.pattern_by_id_mut(id)?;
+        let severity = pattern.meta().display.as_ref().and_then(|display| display.severity);
+        pattern.meta_mut().display = Some(DisplayMeta { color_index, label, icon, severity });
+        self.save()
+    }
+
+    /// How serious a pattern's matches are: "error", "warning" or "info",
+    /// or `None` for none. Decides which pattern owns overlapping text in
+    /// `highlight_all` and `annotate` with the "severity" precedence.
+    pub fn set_pattern_severity(&mut self, id: &str, severity: Option<String>) -> Result<(), JsValue> {
+        let severity: Option<Severity> = severity.map(|s| s.parse()).transpose().map_err(RegexGenError::InvalidArgument)?;
+        display::assign_missing(&mut self.patterns);
+        let pattern = self.pattern_by_id_mut(id)?;
+        let mut display = pattern.meta().display.clone().unwrap_or(DisplayMeta { color_index: 0, label: None, icon: None, severity: None });
+        display.severity = severity;
+        pattern.meta_mut().display = Some(display);
+        self.save()
+    }
+
+    /// Color, label and icon of each given pattern, or of every pattern if
+    /// `ids` is empty, so all views render a pattern the same way.
+    pub fn get_legend(&self, ids: Vec<String>) -> Result<JsValue, JsValue> {
+        let entries: Vec<LegendEntry> = if ids.is_empty() {
+            self.patterns.iter().map(LegendEntry::new).collect()
+        } else {
+            ids.iter()
+                .map(|id| self.pattern_by_id(id).map(LegendEntry::new))
+                .collect::<Result<_, _>>()?
+        };
+        Ok(serde_wasm_bindgen::to_value(&entries)?)
+    }
+
+    /// Keeps a pattern's gaps and AND parts within one "line" or
+    /// "sentence", or lets them span the whole "document". Returns the
+    /// new regex.
+    pub fn set_pattern_scope(&mut self, id: &str, scope: &str) -> Result<String, JsValue> {
+        self.record(Call::SetPatternScope { id: id.to_string(), scope: scope.to_string() });
+        let scope: Scope = scope.parse().map_err(RegexGenError::InvalidArgument)?;
+        self.pattern_by_id_mut(id)?.meta_mut().options.scope = scope;
+        self.finish_edit(id)
+    }
+
+    /// Sets the free-text description shown in generated documentation.
+    pub fn set_description(&mut self, id: &str, description: String) -> Result<(), JsValue> {
+        self.record(Call::SetDescription { id: id.to_string(), description: description.clone() });
+        self.pattern_by_id_mut(id)?.meta_mut().description = description;
+        self.save()
+    }
+
+    /// Sets or clears who a pattern is credited to. New patterns get the
+    /// `author` setting.
+    pub fn set_pattern_author(&mut self, id: &str, author: Option<String>) -> Result<(), JsValue> {
+        self.record(Call::SetPatternAuthor { id: id.to_string(), author: author.clone() });
+        self.pattern_by_id_mut(id)?.meta_mut().metadata.author = author.filter(|a| !a.trim().is_empty());
+        self.save()
+    }
+
+    /// Replaces a pattern's tags. Tags are trimmed and lowercased; blank
+    /// and repeated ones are dropped.
+    pub fn set_pattern_tags(&mut self, id: &str, tags: Vec<String>) -> Result<(), JsValue> {
+        self.record(Call::SetPatternTags { id: id.to_string(), tags: tags.clone() });
+        let mut normalized: Vec<String> = Vec::new();
+        for tag in tags.iter().map(|t| search::normalize_tag(t)) {
+            if !tag.is_empty() && !normalized.contains(&tag) {
+                normalized.push(tag);
+            }
+        }
+        self.pattern_by_id_mut(id)?.meta_mut().tags = normalized;
+        self.save()
+    }
+
+    /// Moves a pattern into a folder given as a slash-separated path, e.g.
+    /// "auth/ssh". `None` or an empty path moves it to the top level.
+    pub fn set_pattern_folder(&mut self, id: &str, folder: Option<String>) -> Result<(), JsValue> {
+        self.record(Call::SetPatternFolder { id: id.to_string(), folder: folder.clone() });
+        let folder = folder.as_deref().and_then(search::normalize_folder);
+        self.pattern_by_id_mut(id)?.meta_mut().folder = folder;
+        self.save()
+    }
+
+    /// Sets how a pattern ranks against others matching the same text when
+    /// `annotate` resolves overlaps by priority; higher wins, 0 by default.
+    pub fn set_pattern_priority(&mut self, id: &str, priority: i32) -> Result<(), JsValue> {
+        self.record(Call::SetPatternPriority { id: id.to_string(), priority });
+        self.pattern_by_id_mut(id)?.meta_mut().priority = priority;
+        self.save()
+    }
+
+    /// The categories patterns can be filed under, in display order.
+    pub fn get_categories(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.settings.categories)?)
+    }
+
+    /// Replaces the category taxonomy, a list of `{ id, name, icon }`.
+    /// Refused while a pattern is filed under a category it leaves out;
+    /// move those with `recategorize` first.
+    pub fn set_categories(&mut self, categories: JsValue) -> Result<(), JsValue> {
+        let categories: Vec<Category> = serde_wasm_bindgen::from_value(categories).map_err(RegexGenError::from)?;
+        self.check_taxonomy(&categories)?;
+        self.settings.categories = categories;
+        self.save_settings()
+    }
+
+    /// Files a pattern under a category of the taxonomy, or under none.
+    pub fn set_pattern_category(&mut self, id: &str, category: Option<String>) -> Result<(), JsValue> {
+        self.check_category(category.as_deref())?;
+        self.record(Call::SetPatternCategory { id: id.to_string(), category: category.clone() });
+        self.pattern_by_id_mut(id)?.meta_mut().category = category;
+        self.save()
+    }
+
+    /// Files every pattern in `pattern_ids` under `category`. Nothing
+    /// changes if any id is unknown. Returns how many patterns changed.
+    pub fn set_patterns_category(&mut self, pattern_ids: Vec<String>, category: Option<String>) -> Result<usize, JsValue> {
+        self.check_category(category.as_deref())?;
+        self.record(Call::SetPatternsCategory { pattern_ids: pattern_ids.clone(), category: category.clone() });
+        for id in &pattern_ids {
+            self.index_of(id)?;
+        }
+        let mut changed = 0;
+        for pattern in self.patterns.iter_mut().filter(|p| pattern_ids.contains(&p.get_id().to_string())) {
+            if pattern.meta().category.as_deref() != category.as_deref() {
+                pattern.meta_mut().category = category.clone();
+                changed += 1;
+            }
+        }
+        self.save()?;
+        Ok(changed)
+    }
+
+    /// Moves every pattern filed under `from` to `to` (or to no category),
+    /// e.g. before dropping `from` from the taxonomy. Returns how many
+    /// patterns moved.
+    pub fn recategorize(&mut self, from: &str, to: Option<String>) -> Result<usize, JsValue> {
+        self.check_category(to.as_deref())?;
+        self.record(Call::Recategorize { from: from.to_string(), to: to.clone() });
+        let mut moved = 0;
+        for pattern in self.patterns.iter_mut().filter(|p| p.meta().category.as_deref() == Some(from)) {
+            pattern.meta_mut().category = to.clone();
+            moved += 1;
+        }
+        self.save()?;
+        Ok(moved)
+    }
+
+    /// Patterns matching every term of `query`, in library order. Plain
+    /// terms are looked for in names, descriptions, ids, folders and tags;
+    /// `tag:x`, `folder:x` and `category:x` terms filter by tag, folder
+    /// and category.
+    pub fn find_patterns(&self, query: &str) -> Result<JsValue, JsValue> {
+        let found: Vec<&Pattern> = self.patterns.iter().filter(|p| search::matches(p, query)).collect();
+        Ok(serde_wasm_bindgen::to_value(&found)?)
+    }
+
+    pub fn get_patterns_by_tag(&self, tag: &str) -> Result<JsValue, JsValue> {
+        let found: Vec<&Pattern> = self.patterns.iter().filter(|p| search::has_tag(p, tag)).collect();
+        Ok(serde_wasm_bindgen::to_value(&found)?)
+    }
+
+    /// Every tag in use, sorted.
+    pub fn get_tags(&self) -> Vec<String> {
+        let tags: BTreeSet<&String> = self.patterns.iter().flat_map(|p| &p.meta().tags).collect();
+        tags.into_iter().cloned().collect()
+    }
+
+    /// Every folder in use, including the parents of nested ones, sorted.
+    pub fn get_folders(&self) -> Vec<String> {
+        let mut folders = BTreeSet::new();
+        for folder in self.patterns.iter().filter_map(|p| p.meta().folder.as_deref()) {
+            for (end, _) in folder.match_indices('/') {
+                folders.insert(folder[..end].to_string());
+            }
+            folders.insert(folder.to_string());
+        }
+        folders.into_iter().collect()
+    }
+
+    /// A rule catalog for the given patterns as "markdown" or "html": name,
+    /// description, plain-language explanation, the regex in every dialect
+    /// and up to three matches from the workspace's documents.
+    pub fn generate_docs(&mut self, pattern_ids: Vec<String>, format: &str) -> Result<String, JsValue> {
+        let format: docs::DocsFormat = format.parse().map_err(RegexGenError::InvalidArgument)?;
+        let mut pattern_docs = Vec::new();
+        for id in &pattern_ids {
+            let index = self
+                .patterns
+                .iter()
+                .position(|p| p.get_id() == id)
+                .ok_or_else(|| RegexGenError::PatternNotFound(id.to_string()))?;
+            let scope = self.patterns[index].meta().options.scope;
+            let examples = match self.compiled(index) {
+                Some(Ok(regex)) => self
+                    .documents
+                    .values()
+                    .flat_map(|text| matching::find_scoped(&regex, text, scope).into_iter().map(|(start, end)| text[start..end].to_string()))
+                    .take(docs::MAX_DOC_EXAMPLES)
+                    .collect(),
+                _ => Vec::new(),
+            };
+            let pattern = &self.patterns[index];
+            let context = self.regex_context();
+            let explanation = pattern.explain_in(&self.catalog(&self.settings.locale));
+            pattern_docs.push(docs::PatternDoc {
+                name: pattern.get_name().to_string(),
+                description: pattern.meta().description.clone(),
+                notes: explanation
+                    .parts
+                    .iter()
+                    .filter_map(|part| part.params.get("note").map(|note| (part.text.clone(), note.clone())))
+                    .collect(),
+                explanation: explanation.text,
+                regexes: docs::DOC_DIALECTS
+                    .iter()
+                    .map(|&dialect| (dialect, pattern.to_regex_for_with(dialect, &context).map_err(|e| e.to_string())))
+                    .collect(),
+                examples,
+            });
+        }
+        Ok(docs::render(&pattern_docs, format))
+    }
+
+    /// A "markdown" or "html" report of where the given patterns match
+    /// `text`, for pasting into tickets and pull requests: per pattern its
+    /// regex, a summary and each matching line with its neighbours, the
+    /// matches marked. Lines are labelled `L12` or `L11-L13` as in GitHub
+    /// permalinks.
+    #[cfg(feature = "corpus")]
+    pub fn export_report(&mut self, text: &str, pattern_ids: Vec<String>, format: &str) -> Result<String, JsValue> {
+        self.check_text(text)?;
+        let format: docs::DocsFormat = format.parse().map_err(RegexGenError::InvalidArgument)?;
+        let catalog = self.catalog(&self.settings.locale).into_owned();
+        let mut results = Vec::new();
+        for id in &pattern_ids {
+            let (index, regex) = self.compiled_by_id(id)?;
+            let pattern = &self.patterns[index];
+            let matches = matching::find_scoped(&regex, text, pattern.meta().options.scope);
+            let starts: Vec<usize> = matches.iter().map(|&(start, _)| start).collect();
+            results.push(report::PatternMatches {
+                name: pattern.get_name().to_string(),
+                regex: pattern.to_regex_with(&self.regex_context())?,
+                summary: summary::summarize(pattern.get_name(), text, &starts, &catalog),
+                matches,
+            });
+        }
+        self.telemetry.emit(&UsageEvent::exported("report", format));
+        Ok(report::render(text, &results, format))
+    }
+
+    /// Saves a sample text the pattern should (or should not) match.
+    pub fn add_test_case(&mut self, id: &str, text: String, should_match: bool) -> Result<(), JsValue> {
+        self.pattern_by_id_mut(id)?
+            .meta_mut().test_cases
+            .push(TestCase { text, should_match });
+        self.save()
+    }
+
+    /// Adds labeled examples in bulk: texts the pattern should match and
+    /// texts it shouldn't.
+    pub fn add_labeled_examples(&mut self, id: &str, positives: Vec<String>, negatives: Vec<String>) -> Result<(), JsValue> {
+        let cases = &mut self.pattern_by_id_mut(id)?.meta_mut().test_cases;
+        cases.extend(positives.into_iter().map(|text| TestCase { text, should_match: true }));
+        cases.extend(negatives.into_iter().map(|text| TestCase { text, should_match: false }));
+        self.save()
+    }
+
+    pub fn remove_test_case(&mut self, id: &str, case_index: usize) -> Result<(), JsValue> {
+        let cases = &mut self.pattern_by_id_mut(id)?.meta_mut().test_cases;
+        if case_index >= cases.len() {
+            return Err(RegexGenError::NotFound(String::from("Test case not found")).into());
+        }
+        cases.remove(case_index);
+        self.save()
+    }
+
+    /// Checks every saved test case of a pattern against its current regex.
+    pub fn run_test_cases(&mut self, id: &str) -> Result<JsValue, JsValue> {
+        let (index, regex) = self.compiled_by_id(id)?;
+        let results = test_cases::run(&regex, &self.patterns[index].meta().test_cases);
+        Ok(serde_wasm_bindgen::to_value(&results)?)
+    }
+
+    /// True/false positive and negative counts, precision and recall of a
+    /// pattern over its saved test cases.
+    pub fn evaluate_pattern(&mut self, id: &str) -> Result<JsValue, JsValue> {
+        let (index, regex) = self.compiled_by_id(id)?;
+        let results = test_cases::run(&regex, &self.patterns[index].meta().test_cases);
+        Ok(serde_wasm_bindgen::to_value(&test_cases::evaluate(results))?)
+    }
+
+    /// Deprecated: indices shift when patterns are deleted or reordered;
+    /// use `delete_pattern_by_id`.
+    pub fn delete_pattern(&mut self, index: usize, cascade: Option<bool>) -> Result<(), JsValue> {
+        self.record(Call::DeletePattern { index, cascade });
+        if let Some(pattern) = self.patterns.get(index) {
+            let id = pattern.get_id().to_string();
+            self.delete_with_dependents(&id, cascade.unwrap_or(false))?;
+        }
+        Ok(())
+    }
+
+    /// Deletes a pattern. If other patterns reference it, the call is
+    /// refused unless `cascade` is set, which deletes them too.
+    pub fn delete_pattern_by_id(&mut self, id: &str, cascade: Option<bool>) -> Result<(), JsValue> {
+        self.record(Call::DeletePatternById { id: id.to_string(), cascade });
+        self.index_of(id)?;
+        self.delete_with_dependents(id, cascade.unwrap_or(false))
+    }
+
+    /// The patterns a pattern references through `Reference` elements,
+    /// directly or through others, nearest first. Each names the pattern
+    /// that references it as `via`; a `name` of null marks a broken
+    /// reference.
+    pub fn get_pattern_dependencies(&self, id: &str) -> Result<JsValue, JsValue> {
+        self.pattern_by_id(id)?;
+        Ok(serde_wasm_bindgen::to_value(&dependencies::dependencies(&self.patterns, id))?)
+    }
+
+    /// The patterns that reference a pattern, directly or through others,
+    /// nearest first: those deleting it would break. Each names the
+    /// pattern it references as `via`.
+    pub fn get_pattern_dependents(&self, id: &str) -> Result<JsValue, JsValue> {
+        self.pattern_by_id(id)?;
+        Ok(serde_wasm_bindgen::to_value(&dependencies::dependents(&self.patterns, id))?)
+    }
+
+    pub fn get_pattern_by_id(&self, id: &str) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(self.pattern_by_id(id)?)?)
+    }
+
+    /// A pattern as the JSON it is stored as, byte for byte, for hosts
+    /// that back up, diff or sync the library themselves. A pattern with
+    /// nothing stored yet, such as one created in an open transaction or
+    /// in a builder without storage, is serialized as it would be stored.
+    pub fn get_pattern_json(&self, id: &str) -> Result<String, JsValue> {
+        let pattern = self.pattern_by_id(id)?;
+        if let Some(json) = self.stored_library_json() {
+            let stored: Vec<&serde_json::value::RawValue> = serde_json::from_str(&json).map_err(RegexGenError::from)?;
+            if let Some(raw) = stored.into_iter().find(|raw| stored_id(raw).as_deref() == Some(id)) {
+                return Ok(raw.get().to_string());
+            }
+        }
+        Ok(serde_json::to_string(pattern).map_err(RegexGenError::from)?)
+    }
+
+    /// The whole library as the JSON it is stored as (before any
+    /// `compress_storage` compression): an array of patterns in library
+    /// order. Each element is exactly what `get_pattern_json` gives.
+    /// Unsaved edits aren't in it; without anything stored, the patterns
+    /// are serialized as they would be.
+    pub fn get_library_json(&self) -> Result<String, JsValue> {
+        match self.stored_library_json() {
+            Some(json) => Ok(json),
+            None => Ok(serde_json::to_string(&self.patterns).map_err(RegexGenError::from)?),
+        }
+    }
+
+    pub fn remove_selection(&mut self, index: usize) {
+        self.record(Call::RemoveSelection { index });
+        if index < self.current_selections.len() {
+            self.current_selections.remove(index);
+        }
+    }
+
+    pub fn create_composite_pattern(&mut self, name: String, base_operator: String, pattern_indices: Vec<usize>, operators: Vec<String>) -> Result<String, JsValue> {
+        self.record(Call::CreateCompositePattern {
+            name: name.clone(),
+            base_operator: base_operator.clone(),
+            pattern_indices: pattern_indices.clone(),
+            operators: operators.clone(),
+        });
+        if pattern_indices.is_empty() {
+            return Err(RegexGenError::InvalidArgument(String::from("No patterns selected")).into());
+        }
+
+        if pattern_indices.len() != operators.len() {
+            return Err(RegexGenError::InvalidArgument(String::from("Pattern indices and operators must have same length")).into());
+        }
+
+        let mut sub_patterns = Vec::new();
+
+        for (idx, op) in pattern_indices.iter().zip(operators.iter()) {
+            if let Some(pattern) = self.patterns.get(*idx).cloned() {
+                if op == "NOT" {
+                    // Wrap pattern in NOT composite
+                    let mut not = Pattern::composite(&format!("NOT {}", pattern.get_name()), CompositeOperator::Not, vec![pattern]);
+                    not.set_id(generate_id());
+                    sub_patterns.push(not);
+                } else {
+                    // Add pattern as-is for AND
+                    sub_patterns.push(pattern);
+                }
+            }
+        }
+
+        if sub_patterns.is_empty() {
+            return Err(RegexGenError::InvalidArgument(String::from("No valid patterns found")).into());
+        }
+
+        // Create the composite pattern
+        let composite = Pattern::Composite {
+            meta: PatternMeta { id: generate_id(), name: name.clone(), options: self.settings.match_options.clone(), ..PatternMeta::default() },
+            operator: CompositeOperator::And, // Topics use AND as base operator
+            patterns: sub_patterns,
+        };
+
+        let regex = composite.to_regex_with(&self.regex_context())?;
+        
+        // Store topic separately from patterns
+        if self.persistent {
+            web_storage::save_topic_to_storage(&self.store, &self.workspace, &composite)?;
+        }
+
+        Ok(regex)
+    }
+}
+
+impl PatternBuilder {
+    /// A builder with the current workspace of `store` loaded.
+    fn load(store: &str, shared: bool) -> PatternBuilder {
+        let workspace = web_storage::load_current_workspace(store);
+        let mut patterns = web_storage::load_patterns_from_storage(store, &workspace);
+        display::assign_missing(&mut patterns);
+        let revision = web_storage::load_revision_from_storage(store, &workspace);
+        let documents = web_storage::load_documents_from_storage(store, &workspace);
+        let settings = web_storage::load_settings_from_storage(store, &workspace);
+
+        let mut builder = PatternBuilder::with_library(workspace, patterns, revision, documents, settings, true);
+        builder.store = store.to_string();
+        builder.shared = shared;
+        builder.synonyms = web_storage::load_synonyms_from_storage(store, &builder.workspace);
+        builder.load_journal();
+        builder
+    }
+
+    fn with_library(
+        workspace: String,
+        patterns: Vec<Pattern>,
+        revision: u64,
+        documents: BTreeMap<String, String>,
+        settings: Settings,
+        persistent: bool,
+    ) -> PatternBuilder {
+        PatternBuilder {
+            journal_base: patterns.clone(),
+            patterns,
+            current_selections: Vec::new(),
+            alternation: AlternationConfig {
+                min_options: settings.alternation_threshold,
+                ..AlternationConfig::default()
+            },
+            lists: HashMap::new(),
+            settings,
+            option_hits: option_stats::OptionHits::default(),
+            persistent,
+            transaction: None,
+            revision,
+            matcher: None,
+            regex_cache: RegexCache::default(),
+            document_index: None,
+            store: String::new(),
+            shared: false,
+            workspace,
+            documents,
+            synonyms: SynonymSets::new(),
+            catalogs: BTreeMap::new(),
+            recording: None,
+            recording_active: false,
+            journal: Journal::default(),
+            listeners: Listeners::default(),
+            telemetry: Telemetry::default(),
+            heartbeat: Heartbeat::default(),
+        }
+    }
+
+    /// An empty builder that never reads or writes storage.
+    pub(crate) fn in_memory() -> PatternBuilder {
+        PatternBuilder::with_library(
+            web_storage::DEFAULT_WORKSPACE.to_string(),
+            Vec::new(),
+            0,
+            BTreeMap::new(),
+            Settings::default(),
+            false,
+        )
+    }
+
+    fn save(&mut self) -> Result<(), JsValue> {
+        self.save_changes(true)
+    }
+
+    /// Saves the library, tells listeners what changed since the last
+    /// save and, with `journal`, records it for `undo`.
+    fn save_changes(&mut self, journal: bool) -> Result<(), JsValue> {
+        display::assign_missing(&mut self.patterns);
+        metadata::stamp(&self.journal_base, &mut self.patterns, self.settings.author.as_deref(), js_sys::Date::now());
+        self.invalidate_matcher();
+        self.regex_cache.retain_patterns(&self.patterns);
+        if self.persistent && self.transaction.is_none() {
+            let stored = web_storage::load_revision_from_storage(&self.store, &self.workspace);
+            if stored != self.revision {
+                if !self.shared {
+                    return Err(RegexGenError::Conflict { stored, expected: self.revision }.into());
+                }
+                self.rebase(stored);
+            }
+            web_storage::save_patterns_to_storage(&self.store, &self.workspace, &self.patterns, self.settings.compress_storage)?;
+            web_storage::save_revision_to_storage(&self.store, &self.workspace, self.revision + 1)?;
+            self.revision += 1;
+        }
+        if self.transaction.is_none() {
+            let changes = journal::diff(&self.journal_base, &self.patterns);
+            if !changes.is_empty() {
+                self.journal_base = self.patterns.clone();
+                self.listeners.notify(&changes);
+                if journal {
+                    self.journal.push(JournalEntry { changes });
+                    self.save_journal()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes pattern `id`, and with `cascade` every pattern depending on
+    /// it; without, refuses if there are any.
+    fn delete_with_dependents(&mut self, id: &str, cascade: bool) -> Result<(), JsValue> {
+        let dependents = dependencies::dependents(&self.patterns, id);
+        if !dependents.is_empty() && !cascade {
+            let names: Vec<&str> = dependents.iter().filter_map(|d| d.name.as_deref()).collect();
+            return Err(RegexGenError::InvalidState(format!(
+                "Pattern is referenced by {} other pattern(s) ({}); delete those first or cascade",
+                dependents.len(),
+                names.join(", ")
+            ))
+            .into());
+        }
+        let doomed: BTreeSet<&str> = std::iter::once(id).chain(dependents.iter().map(|d| d.id.as_str())).collect();
+        for removed in self.patterns.iter().filter(|p| doomed.contains(p.get_id())) {
+            self.regex_cache.remove(removed.get_id());
+        }
+        self.patterns.retain(|p| !doomed.contains(p.get_id()));
+        self.save()
+    }
+
+    /// Replays this builder's unsaved changes on top of the library other
+    /// builders sharing the store saved at revision `stored`. Listeners
+    /// hear the others' changes; the journal only gets this builder's.
+    fn rebase(&mut self, stored: u64) {
+        let own = journal::diff(&self.journal_base, &self.patterns);
+        let mut patterns = web_storage::load_patterns_from_storage(&self.store, &self.workspace);
+        display::assign_missing(&mut patterns);
+        self.listeners.notify(&journal::diff(&self.journal_base, &patterns));
+        self.journal_base = patterns.clone();
+        journal::apply(&mut patterns, &own, false);
+        self.patterns = patterns;
+        self.revision = stored;
+    }
+
+    /// The library as this builder last stored it, if it keeps one.
+    fn stored_library_json(&self) -> Option<String> {
+        if !self.persistent {
+            return None;
+        }
+        web_storage::load_patterns_json_from_storage(&self.store, &self.workspace)
+    }
+
+    fn save_journal(&self) -> Result<(), JsValue> {
+        if self.persistent && !self.shared && self.settings.persist_undo_history {
+            web_storage::save_journal_to_storage(&self.store, &self.workspace, &self.journal, self.settings.compress_storage)?;
+        }
+        Ok(())
+    }
+
+    /// Starts journaling from the loaded library, picking up the stored
+    /// undo history if it is kept. Listeners hear what loading changed.
+    fn load_journal(&mut self) {
+        self.listeners.notify(&journal::diff(&self.journal_base, &self.patterns));
+        self.journal_base = self.patterns.clone();
+        if self.persistent && !self.shared && self.settings.persist_undo_history {
+            self.journal = web_storage::load_journal_from_storage(&self.store, &self.workspace);
+        }
+    }
+
+    /// Undoes (or redoes) the latest journaled change and saves the result.
+    fn step_journal(&mut self, undo: bool) -> Result<bool, JsValue> {
+        if self.transaction.is_some() {
+            return Err(RegexGenError::InvalidState(String::from("Commit or roll back the transaction before undoing or redoing")).into());
+        }
+        let (from, to) = if undo {
+            (&mut self.journal.undo, &mut self.journal.redo)
+        } else {
+            (&mut self.journal.redo, &mut self.journal.undo)
+        };
+        let Some(entry) = from.pop() else { return Ok(false) };
+        let previous = self.patterns.clone();
+        journal::apply(&mut self.patterns, &entry.changes, undo);
+        to.push(entry);
+        if let Err(e) = self.save_changes(false).and_then(|_| self.save_journal()) {
+            let (from, to) = if undo {
+                (&mut self.journal.undo, &mut self.journal.redo)
+            } else {
+                (&mut self.journal.redo, &mut self.journal.undo)
+            };
+            from.extend(to.pop());
+            self.patterns = previous.clone();
+            self.journal_base = previous;
+            self.invalidate_matcher();
+            return Err(e);
+        }
+        Ok(true)
+    }
+
+    fn export_dialect(&self, dialect: &str) -> Result<RegexDialect, RegexGenError> {
+        if dialect.is_empty() {
+            Ok(self.settings.dialect)
+        } else {
+            dialect.parse().map_err(RegexGenError::InvalidArgument)
+        }
+    }
+
+    fn exported_regex(&self, pattern_index: usize, dialect: RegexDialect) -> Result<String, RegexGenError> {
+        let pattern = self
+            .patterns
+            .get(pattern_index)
+            .ok_or_else(|| self.invalid_index(pattern_index))?;
+        if let Some(name) = pattern.referenced_lists().into_iter().find(|name| !self.lists.contains_key(*name)) {
+            return Err(RegexGenError::NotFound(format!("List '{}' has not been loaded", name)));
+        }
+        pattern
+            .to_regex_for_with(dialect, &self.regex_context())
+            .map_err(RegexGenError::from)
+    }
+
+    fn apply_settings(&mut self, settings: Settings) {
+        self.alternation.min_options = settings.alternation_threshold;
+        self.settings = settings;
+        self.invalidate_matcher();
+        self.document_index = None;
+    }
+
+    fn save_settings(&mut self) -> Result<(), JsValue> {
+        self.record(Call::SetSettings { settings: self.settings.clone() });
+        if self.persistent {
+            web_storage::save_settings_to_storage(&self.store, &self.workspace, &self.settings)?;
+        }
+        Ok(())
+    }
+
+    fn check_category(&self, category: Option<&str>) -> Result<(), RegexGenError> {
+        match category {
+            Some(id) if !self.settings.categories.iter().any(|c| c.id == id) => Err(RegexGenError::InvalidArgument(
+                format!("Unknown category '{}'; add it to the taxonomy first", id),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// A taxonomy may replace the current one if it is well-formed and
+    /// keeps every category a pattern is filed under.
+    fn check_taxonomy(&self, categories: &[Category]) -> Result<(), RegexGenError> {
+        category::validate_taxonomy(categories).map_err(RegexGenError::InvalidArgument)?;
+        let orphaned: BTreeSet<&str> = self
+            .patterns
+            .iter()
+            .filter_map(|p| p.meta().category.as_deref())
+            .filter(|id| !categories.iter().any(|c| c.id == *id))
+            .collect();
+        if !orphaned.is_empty() {
+            return Err(RegexGenError::InvalidState(format!(
+                "Patterns are still filed under {}; recategorize them first",
+                orphaned.into_iter().collect::<Vec<_>>().join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    fn save_synonyms(&self) -> Result<(), JsValue> {
+        if self.persistent {
+            web_storage::save_synonyms_to_storage(&self.store, &self.workspace, &self.synonyms, self.settings.compress_storage)?;
+        }
+        Ok(())
+    }
+
+    fn save_documents(&self) -> Result<(), JsValue> {
+        if self.persistent {
+            web_storage::save_documents_to_storage(&self.store, &self.workspace, &self.documents, self.settings.compress_storage)?;
+        }
+        Ok(())
+    }
+
+    fn matcher(&mut self) -> Rc<PatternMatcher> {
+        if let Some(matcher) = &self.matcher {
+            return matcher.clone();
+        }
+        let matcher = Rc::new(PatternMatcher::new(&self.patterns, &self.regex_context()));
+        self.matcher = Some(matcher.clone());
+        matcher
+    }
+
+    /// Each pattern of `pattern_ids`, or every pattern, with its matches
+    /// in `text`, beating the heartbeat as `operation`.
+    fn matches_of_each(&mut self, text: &str, pattern_ids: Option<Vec<String>>, operation: &str) -> Result<Vec<PatternMatches<'_>>, RegexGenError> {
+        let indices: Vec<usize> = match pattern_ids {
+            Some(ids) => ids.iter().map(|id| self.index_of(id)).collect::<Result<_, _>>()?,
+            None => (0..self.patterns.len()).collect(),
+        };
+        let mut matches = Vec::new();
+        self.heartbeat.start();
+        for &index in &indices {
+            self.heartbeat.beat(operation)?;
+            matches.push(self.find_matches(index, text, MatchMode::Contains)?);
+        }
+        Ok(indices.into_iter().map(|index| &self.patterns[index]).zip(matches).collect())
+    }
+
+    /// Matches of a saved pattern in `text`: with its regex, or for an
+    /// AND or NOT composite that has none by running its operands one by
+    /// one.
+    fn find_matches(&mut self, pattern_index: usize, text: &str, mode: MatchMode) -> Result<Vec<(usize, usize)>, RegexGenError> {
+        self.searcher(pattern_index)?.find(text, mode)
+    }
+
+    /// A saved pattern compiled for `find_matches`, to search many texts
+    /// with.
+    fn searcher(&mut self, pattern_index: usize) -> Result<Searcher, RegexGenError> {
+        match self.compiled(pattern_index).ok_or_else(|| self.invalid_index(pattern_index))? {
+            Ok(re) => Ok(Searcher::Regex(re, self.patterns[pattern_index].meta().options.scope)),
+            Err(RegexGenError::Unsupported(_)) if matches!(self.patterns[pattern_index], Pattern::Composite { .. }) => {
+                Ok(Searcher::Composite(CompiledComposite::new(&self.patterns[pattern_index], &self.regex_context())?))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The compiled regex of a saved pattern, from the cache if its source
+    /// hasn't changed since it was last compiled.
+    fn compiled(&mut self, pattern_index: usize) -> Option<Result<regex::Regex, RegexGenError>> {
+        let mut cache = std::mem::take(&mut self.regex_cache);
+        let compiled = self
+            .patterns
+            .get(pattern_index)
+            .map(|pattern| cache.get_or_compile(pattern, &self.regex_context()));
+        self.regex_cache = cache;
+        compiled
+    }
+
+    fn compiled_by_id(&mut self, id: &str) -> Result<(usize, regex::Regex), RegexGenError> {
+        let index = self
+            .patterns
+            .iter()
+            .position(|p| p.get_id() == id)
+            .ok_or_else(|| RegexGenError::PatternNotFound(id.to_string()))?;
+        match self.compiled(index) {
+            Some(Ok(regex)) => Ok((index, regex)),
+            Some(Err(e)) => Err(e),
+            None => Err(self.invalid_index(index)),
+        }
+    }
+
+    /// Byte ranges of a pattern's matches in `text`, each with the
+    /// pattern's id.
+    fn tagged_matches(&mut self, pattern_index: usize, text: &str) -> Result<Vec<(usize, usize, String)>, JsValue> {
+        let matches = self.find_matches(pattern_index, text, MatchMode::Contains)?;
+        let id = self.patterns[pattern_index].get_id();
+        Ok(matches.into_iter().map(|(start, end)| (start, end, id.to_string())).collect())
+    }
+
+    fn match_segments(&mut self, pattern_index: usize, text: &str) -> Result<Vec<Segment>, JsValue> {
+        let matches = self.find_matches(pattern_index, text, MatchMode::Contains)?;
+        Ok(highlight::segments(text, &matches, self.settings.offset_encoding))
+    }
+
+    fn invalidate_matcher(&mut self) {
+        self.matcher = None;
+    }
+
+    fn invalid_index(&self, index: usize) -> RegexGenError {
+        RegexGenError::InvalidIndex { index, len: self.patterns.len() }
+    }
+
+    /// Adds `incoming` patterns read from a library file, resolving id
+    /// clashes by `merge_strategy`.
+    fn merge_library(&mut self, incoming: Vec<Pattern>, merge_strategy: &str) -> Result<JsValue, JsValue> {
+        let strategy: MergeStrategy = merge_strategy.parse().map_err(RegexGenError::InvalidArgument)?;
+        let new = incoming
+            .iter()
+            .filter(|p| strategy == MergeStrategy::Duplicate || !self.patterns.iter().any(|q| q.get_id() == p.get_id()))
+            .count();
+        self.check_room_for(new)?;
+
+        let mut report = MergeReport::default();
+        for mut pattern in incoming {
+            match self.patterns.iter().position(|p| p.get_id() == pattern.get_id()) {
+                None => {
+                    self.patterns.push(pattern);
+                    report.added += 1;
+                }
+                Some(_) if strategy == MergeStrategy::Skip => report.skipped += 1,
+                Some(existing) if strategy == MergeStrategy::Overwrite => {
+                    self.patterns[existing] = pattern;
+                    report.overwritten += 1;
+                }
+                Some(_) => {
+                    pattern.set_id(generate_id());
+                    self.patterns.push(pattern);
+                    report.duplicated += 1;
+                }
+            }
+        }
+        self.save()?;
+        Ok(serde_wasm_bindgen::to_value(&report)?)
+    }
+
+    fn check_text(&self, text: &str) -> Result<(), JsValue> {
+        Ok(self.settings.limits.check_text(text)?)
+    }
+
+    /// Fails if adding `count` patterns would take the library past its limit.
+    fn check_room_for(&self, count: usize) -> Result<(), JsValue> {
+        Ok(self.settings.limits.check_patterns(self.patterns.len() + count)?)
+    }
+
+    fn index_of(&self, id: &str) -> Result<usize, RegexGenError> {
+        self.patterns
+            .iter()
+            .position(|p| p.get_id() == id)
+            .ok_or_else(|| RegexGenError::PatternNotFound(id.to_string()))
+    }
+
+    fn pattern_by_id(&self, id: &str) -> Result<&Pattern, RegexGenError> {
+        self.patterns
+            .iter()
+            .find(|p| p.get_id() == id)
+            .ok_or_else(|| RegexGenError::PatternNotFound(id.to_string()))
+    }
+
+    fn pattern_by_id_mut(&mut self, id: &str) -> Result<&mut Pattern, RegexGenError> {
+        self.patterns
+            .iter_mut()
+            .find(|p| p.get_id() == id)
+            .ok_or_else(|| RegexGenError::PatternNotFound(id.to_string()))
+    }
+
+    fn elements_at_mut(&mut self, pattern_index: usize) -> Result<&mut Vec<PatternElement>, RegexGenError> {
+        let len = self.patterns.len();
+        match self.patterns.get_mut(pattern_index) {
+            Some(Pattern::Sequence { elements, .. }) => Ok(elements),
+            Some(Pattern::Composite { .. }) => Err(RegexGenError::InvalidArgument(String::from("Composite patterns have no elements"))),
+            Some(Pattern::Template { .. }) => Err(template_elements_error()),
+            None => Err(RegexGenError::InvalidIndex { index: pattern_index, len }),
+        }
+    }
+
+    fn element_mut(&mut self, id: &str, element_index: usize) -> Result<&mut PatternElement, RegexGenError> {
+        match self.pattern_by_id_mut(id)? {
+            Pattern::Sequence { elements, .. } => elements
+                .get_mut(element_index)
+                .ok_or_else(|| RegexGenError::NotFound(String::from("Element index out of range"))),
+            Pattern::Composite { .. } => Err(RegexGenError::InvalidArgument(String::from("Composite patterns have no elements"))),
+            Pattern::Template { .. } => Err(template_elements_error()),
+        }
+    }
+
+    /// Persists the library after an edit and returns the edited pattern's
+    /// regenerated regex.
+    fn finish_edit(&mut self, id: &str) -> Result<String, JsValue> {
+        self.finish_edit_with_note(id, None)
+    }
+
+    /// `finish_edit`, adding a version to the pattern's history with `note`.
+    fn finish_edit_with_note(&mut self, id: &str, note: Option<String>) -> Result<String, JsValue> {
+        let regex = self.add_version(id, note)?;
+        self.save()?;
+        Ok(regex)
+    }
+
+    /// Drops the edited pattern's cached regex and adds its new version to
+    /// its history, without saving. Returns the regex.
+    fn add_version(&mut self, id: &str, note: Option<String>) -> Result<String, JsValue> {
+        self.regex_cache.remove(id);
+        let regex = self.pattern_by_id(id)?.to_regex_with(&self.regex_context())?;
+        history::push(&mut self.pattern_by_id_mut(id)?.meta_mut().history, regex.clone(), note, js_sys::Date::now());
+        Ok(regex)
+    }
+
+    fn record(&mut self, call: Call) {
+        if let (true, Some(recording)) = (self.recording_active, &mut self.recording) {
+            recording.calls.push(RecordedCall { call, created_id: None });
+        }
+    }
+
+    /// Starts the history of the pattern just added and notes it as created
+    /// by the last recorded call.
+    fn created_pattern(&mut self) {
+        let regex = self.patterns.last().and_then(|p| p.to_regex_with(&self.regex_context()).ok());
+        if let (Some(pattern), Some(regex)) = (self.patterns.last_mut(), regex) {
+            history::push(&mut pattern.meta_mut().history, regex, None, js_sys::Date::now());
+        }
+        if let Some(pattern) = self.patterns.last() {
+            let (composite, elements) = match pattern {
+                Pattern::Sequence { elements, .. } => (false, elements.len()),
+                Pattern::Composite { patterns, .. } => (true, patterns.len()),
+                Pattern::Template { .. } => (false, pattern.template_elements().len()),
+            };
+            self.telemetry.emit(&UsageEvent::PatternBuilt { composite, elements });
+        }
+        let created = self.patterns.last().map(|p| p.get_id().to_string());
+        if let (true, Some(call)) = (self.recording_active, self.recording.as_mut().and_then(|r| r.calls.last_mut())) {
+            call.created_id = created;
+        }
+    }
+
+    /// The registered catalog for `locale`, or the built-in English one.
+    fn catalog(&self, locale: &str) -> Cow<'_, MessageCatalog> {
+        locale::resolve(&self.catalogs, locale)
+            .map(Cow::Borrowed)
+            .unwrap_or_else(|| Cow::Owned(MessageCatalog::english()))
+    }
+
+    fn regex_context(&self) -> RegexContext<'_> {
+        RegexContext {
+            alternation: self.alternation.clone(),
+            lists: Some(&self.lists),
+            synonyms: Some(&self.synonyms),
+            capture_elements: false,
+            capture_fields: false,
+            scope: Scope::Document,
+            fragments: None,
+        }
+    }
+
+    fn record_option_hits(&mut self, pattern_index: usize, text: &str, matches: &[(usize, usize)]) {
+        let Some(Pattern::Sequence { meta, elements }) = self.patterns.get(pattern_index) else {
+            return;
+        };
+        let context = RegexContext {
+            lists: Some(&self.lists),
+            ..RegexContext::default()
+        };
+        let matched: Vec<&str> = matches.iter().map(|&(start, end)| &text[start..end]).collect();
+        for (element_index, element) in elements.iter().enumerate() {
+            if let PatternElement::OneOf { options, list, .. } = element.base() {
+                let all_options = context.resolve_options(options, list.as_deref());
+                self.option_hits.record(&meta.id, element_index, &all_options, meta.options.case_insensitive, &matched);
+            }
+        }
+    }
+}
+
+/// A saved pattern ready to search many texts: its regex, or for an AND
+/// or NOT composite that has none, its operands.
+enum Searcher {
+    Regex(regex::Regex, Scope),
+    Composite(CompiledComposite),
+}
+
+impl Searcher {
+    fn find(&self, text: &str, mode: MatchMode) -> Result<Vec<(usize, usize)>, RegexGenError> {
+        match self {
+            Searcher::Regex(re, scope) => Ok(matching::find_in_mode(re, text, *scope, mode)?),
+            Searcher::Composite(composite) => Ok(composite.find(text, mode)),
+        }
+    }
+}
+
+fn validate_raw_fragment(regex: &str) -> Result<(), RegexGenError> {
+    PatternElement::Raw { regex: regex.to_string() }
+        .validate()
+        .map_err(RegexGenError::InvalidArgument)
+}
+
+/// Store and workspace names become part of storage keys, so keep them
+/// simple.
+fn validate_key_name(kind: &str, name: &str) -> Result<(), RegexGenError> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(RegexGenError::InvalidArgument(format!(
+            "Invalid {} name '{}': use letters, digits, '-' and '_'",
+            kind, name
+        )));
+    }
+    Ok(())
+}
+
+/// A draft of `pattern` with the examples it was suggested from attached
+/// as test cases, and how it does on them.
+#[cfg(feature = "inference")]
+fn labeled_draft(mut pattern: Pattern, positives: Vec<String>, negatives: Vec<String>, context: &RegexContext) -> Result<Draft, RegexGenError> {
+    let cases = &mut pattern.meta_mut().test_cases;
+    cases.extend(positives.into_iter().map(|text| TestCase { text, should_match: true }));
+    cases.extend(negatives.into_iter().map(|text| TestCase { text, should_match: false }));
+
+    let regex = pattern.to_regex_with(context)?;
+    let compiled = regex::Regex::new(&regex)?;
+    let evaluation = test_cases::evaluate(test_cases::run(&compiled, &pattern.meta().test_cases));
+    Ok(Draft { pattern, regex, evaluation: Some(evaluation) })
+}
+
+/// Stands in for a pattern `get_patterns` couldn't serialize.
+#[derive(serde::Serialize)]
+struct UnserializablePattern<'a> {
+    id: &'a str,
+    name: &'a str,
+    serialization_error: String,
+}
+
+/// The `id` of a stored pattern, read without parsing the rest of it.
+fn stored_id(raw: &serde_json::value::RawValue) -> Option<String> {
+    #[derive(serde::Deserialize)]
+    struct Stored {
+        id: String,
+    }
+    serde_json::from_str::<Stored>(raw.get()).ok().map(|stored| stored.id)
+}
+
+/// The patterns as a JavaScript array, each serialized on its own so one
+/// that can't be (an element kind this version can't write, say) shows up
+/// as `{ id, name, serialization_error }` instead of failing the list.
+fn patterns_to_js(patterns: &[Pattern]) -> Result<JsValue, RegexGenError> {
+    let array = js_sys::Array::new();
+    for pattern in patterns {
+        let value = match serde_wasm_bindgen::to_value(pattern) {
+            Ok(value) => value,
+            Err(e) => {
+                let placeholder = UnserializablePattern { id: pattern.get_id(), name: pattern.get_name(), serialization_error: e.to_string() };
+                serde_wasm_bindgen::to_value(&placeholder).map_err(|e| RegexGenError::Serialization(e.to_string()))?
+            }
+        };
+        array.push(&value);
+    }
+    Ok(array.into())
+}
+
+fn annotation_priority(priority: Option<String>) -> Result<AnnotationPriority, RegexGenError> {
+    priority.map_or(Ok(AnnotationPriority::default()), |priority| priority.parse().map_err(RegexGenError::InvalidArgument))
+}
+
+fn match_mode(mode: Option<String>) -> Result<MatchMode, RegexGenError> {
+    mode.map_or(Ok(MatchMode::default()), |mode| mode.parse().map_err(RegexGenError::InvalidArgument))
+}
+
+/// The regex of a created pattern, or an error naming the saved pattern
+/// a duplicate matches.
+fn created_regex(outcome: SaveOutcome) -> Result<String, RegexGenError> {
+    match outcome {
+        SaveOutcome::Created { regex, .. } => Ok(regex),
+        SaveOutcome::Duplicate { existing_id, existing_name } => Err(RegexGenError::Duplicate { existing_id, existing_name }),
+    }
+}
+
+/// Templates' elements only exist once they are instantiated.
+fn template_elements_error() -> RegexGenError {
+    RegexGenError::InvalidArgument(String::from("Templates have no elements; instantiate the template to edit them"))
+}
+
+/// Milliseconds from `performance.now()`, or from `Date.now()` where the
+/// global has no `performance`.
+fn now_ms() -> f64 {
+    js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("performance"))
+        .ok()
+        .filter(|performance| !performance.is_undefined())
+        .and_then(|performance| {
+            let now = js_sys::Reflect::get(&performance, &JsValue::from_str("now")).ok()?;
+            now.dyn_into::<js_sys::Function>().ok()?.call0(&performance).ok()?.as_f64()
+        })
+        .unwrap_or_else(js_sys::Date::now)
+}
+
+fn match_order(order: Option<String>) -> Result<MatchOrder, RegexGenError> {
+    order.map_or(Ok(MatchOrder::default()), |order| order.parse().map_err(RegexGenError::InvalidArgument))
+}
+
+#[wasm_bindgen]
+pub fn get_word_at_position(text: &str, position: usize) -> Option<String> {
+    TokenizerConfig::default().word_at(text, position)
+}
+
+#[wasm_bindgen]
+pub fn get_words_from_text(text: &str) -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&TokenizerConfig::default().tokenize(text))?)
+}
+
+/// The regex of a pattern given as JSON (as `get_pattern_by_id` returns
+/// it), without a `PatternBuilder` or storage. Without a `flavor` the
+/// regex is the one the builder tests with; with one it is exported for
+/// that engine. Patterns using named lists or synonym sets are refused,
+/// as only a builder has those loaded.
+#[wasm_bindgen]
+pub fn pattern_json_to_regex(json: &str, flavor: Option<String>) -> Result<String, JsValue> {
+    let pattern: Pattern = serde_json::from_str(json).map_err(|e| RegexGenError::InvalidArgument(format!("Invalid pattern JSON: {}", e)))?;
+    if let Some(name) = pattern.referenced_lists().first() {
+        return Err(RegexGenError::NotFound(format!("List '{}' needs a builder to load it", name)).into());
+    }
+    if let Some(set_id) = pattern.referenced_synonym_sets().first() {
+        return Err(RegexGenError::NotFound(format!("Synonym set '{}' needs a builder to load it", set_id)).into());
+    }
+    match flavor {
+        None => Ok(pattern.to_regex_for(RegexDialect::Rust)?),
+        Some(flavor) => {
+            let dialect: RegexDialect = flavor.parse().map_err(RegexGenError::InvalidArgument)?;
+            Ok(pattern.to_regex_for(dialect)?)
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use js_sys::{Function, Promise};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+/// `promise.then(f)`: a promise for what `f` returns once `promise`
+/// resolves, waiting in turn if that is a promise. An error from `f`
+/// rejects it, as does a rejection of `promise`, in which case `f` is
+/// never called.
+pub fn and_then<F>(promise: &Promise, f: F) -> Result<Promise, JsValue>
+where
+    F: FnOnce(JsValue) -> Result<JsValue, JsValue> + 'static,
+{
+    // Called through `then` itself: `Promise::then` only takes a `Closure`
+    // that returns nothing, and would resolve to undefined
+    let then: Function = js_sys::Reflect::get(promise, &JsValue::from_str("then"))?.unchecked_into();
+    Ok(then.call1(promise, &Closure::once_into_js(f))?.unchecked_into())
+}
@@ -0,0 +1,57 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{CompositeOperator, Pattern, PatternElement, RegexDialect, RegexGenError};
+
+/// Whether a quick pattern needs every word or just one of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuickMode {
+    All,
+    Any,
+}
+
+impl std::str::FromStr for QuickMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "all" | "and" => Ok(QuickMode::All),
+            "any" | "or" => Ok(QuickMode::Any),
+            _ => Err(format!("Unknown quick pattern mode: {}", s)),
+        }
+    }
+}
+
+/// An unsaved pattern for a plain word list: a composite AND of one
+/// sequence per word, or a single `OneOf` element.
+pub fn quick(words: &[String], mode: QuickMode) -> Result<Pattern, String> {
+    let mut unique: Vec<String> = Vec::new();
+    for word in words.iter().map(|w| w.trim()).filter(|w| !w.is_empty()) {
+        if !unique.iter().any(|u| u == word) {
+            unique.push(word.to_string());
+        }
+    }
+    let word = |text: String| Pattern::sequence(&text, vec![PatternElement::word(text.clone())]);
+    match (mode, unique.len()) {
+        (_, 0) => Err(String::from("No words given")),
+        (_, 1) => Ok(word(unique.remove(0))),
+        (QuickMode::Any, _) => Ok(Pattern::sequence("any", vec![PatternElement::one_of(unique)])),
+        (QuickMode::All, _) => Ok(Pattern::composite("all", CompositeOperator::And, unique.into_iter().map(word).collect())),
+    }
+}
+
+/// A regex matching text that contains all (`mode` "all") or any ("any")
+/// of `words`, without a `PatternBuilder` or storage. Without a
+/// `dialect` the regex is the one the builder tests with; with one it is
+/// exported for that engine.
+#[wasm_bindgen]
+pub fn quick_pattern(words: Vec<String>, mode: &str, dialect: Option<String>) -> Result<String, JsValue> {
+    let mode: QuickMode = mode.parse().map_err(RegexGenError::InvalidArgument)?;
+    let pattern = quick(&words, mode).map_err(RegexGenError::InvalidArgument)?;
+    match dialect {
+        None => Ok(pattern.to_regex()?),
+        Some(dialect) => {
+            let dialect: RegexDialect = dialect.parse().map_err(RegexGenError::InvalidArgument)?;
+            Ok(pattern.to_regex_for(dialect)?)
+        }
+    }
+}
@@ -0,0 +1,393 @@
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use wasm_bindgen::JsValue;
+
+use crate::{Pattern, PatternBuilder, PatternElement, Settings, TemplateParameter};
+
+/// Recording format version, bumped when calls change incompatibly.
+pub const RECORDING_VERSION: u32 = 1;
+
+/// One builder API call with its arguments, named after the method.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "call", rename_all = "snake_case")]
+pub enum Call {
+    AddSelection { text: String, start_index: usize, end_index: usize, word_index: usize },
+    AddRangeSelection { text: String, start_index: usize, end_index: usize },
+    AddTokenClassSelection {
+        class: String,
+        custom_regex: Option<String>,
+        text: String,
+        start_index: usize,
+        end_index: usize,
+        word_index: usize,
+    },
+    AddRawSelection { regex: String, word_index: usize },
+    AddAlternativeToSelection { selection_index: usize, text: String },
+    RemoveAlternativeFromSelection { selection_index: usize, text: String },
+    RemoveSelection { index: usize },
+    ClearSelections,
+    BuildSequencePattern {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gap_mode: Option<String>,
+    },
+    BuildSequencePatternChecked {
+        name: String,
+        allow_duplicate: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gap_mode: Option<String>,
+    },
+    BuildCompositePattern { name: String, operator: String, pattern_ids: Vec<String> },
+    BuildCompositePatternChecked { name: String, operator: String, pattern_ids: Vec<String>, allow_duplicate: bool },
+    CreateCompositePattern { name: String, base_operator: String, pattern_indices: Vec<usize>, operators: Vec<String> },
+    ImportRegex { name: String, regex: String },
+    ImportRegexList { text: String },
+    ImportConfig { text: String, dialect: String },
+    FromGlob { glob: String },
+    InstallPack { json: String },
+    ImportKql { name: String, expression: String },
+    BuildTemplate { name: String, body: String, parameters: Vec<TemplateParameter> },
+    InstantiateTemplate { id: String, values: BTreeMap<String, String> },
+    AddPattern { pattern: Pattern },
+    UpdatePattern { id: String, pattern: Pattern, note: Option<String> },
+    RenamePattern { id: String, name: String },
+    DuplicatePattern { id: String },
+    MergePatterns { pattern_ids: Vec<String>, operator: String, name: Option<String> },
+    MovePattern { id: String, new_position: usize },
+    ReplaceElement { id: String, element_index: usize, element: PatternElement },
+    InsertRawElement { id: String, element_index: usize, regex: String },
+    SetGapBounds { id: String, element_index: usize, min_words: u32, max_words: Option<u32> },
+    SetGapCharLimit { id: String, element_index: usize, max_chars: Option<u32> },
+    SetGapExcludedWords { id: String, element_index: usize, words: Vec<String> },
+    SetSynonymSet { set_id: String, words: Vec<String> },
+    DeleteSynonymSet { set_id: String },
+    SetWordMatch { id: String, element_index: usize, mode: String },
+    SetElementModifier { id: String, element_index: usize, optional: bool, min_repeat: u32, max_repeat: Option<u32> },
+    SetElementCapture { id: String, element_index: usize, name: Option<String> },
+    SetElementNote { id: String, element_index: usize, note: Option<String> },
+    AddOneOfOption { id: String, element_index: usize, option: String },
+    RemoveOneOfOption { id: String, element_index: usize, option: String },
+    SetOptionWeight { pattern_index: usize, element_index: usize, option: String, weight: u32 },
+    SetOptionFlags { pattern_index: usize, element_index: usize, option: String, case_insensitive: bool, whole_word: bool },
+    SetPatternScope { id: String, scope: String },
+    SetDescription { id: String, description: String },
+    SetPatternTags { id: String, tags: Vec<String> },
+    SetPatternAuthor { id: String, author: Option<String> },
+    SetPatternFolder { id: String, folder: Option<String> },
+    SetPatternCategory { id: String, category: Option<String> },
+    SetPatternPriority { id: String, priority: i32 },
+    SetPatternsCategory { pattern_ids: Vec<String>, category: Option<String> },
+    Recategorize { from: String, to: Option<String> },
+    DeletePattern {
+        index: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cascade: Option<bool>,
+    },
+    DeletePatternById {
+        id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cascade: Option<bool>,
+    },
+    Undo,
+    Redo,
+    /// Any settings change, recorded as the settings it left behind.
+    SetSettings { settings: Settings },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordedCall {
+    #[serde(flatten)]
+    pub call: Call,
+    /// Id of the pattern the call created, so later calls that refer to
+    /// it can be pointed at the pattern the replay creates instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Recording {
+    pub version: u32,
+    /// Settings when recording started; a replay starts from them.
+    pub settings: Settings,
+    pub calls: Vec<RecordedCall>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplayStep {
+    pub call: String,
+    /// The call's return value, if it returns a regex.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// What happened to each call of a replayed recording. A replay stops at
+/// the first call that fails.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplayReport {
+    pub steps: Vec<ReplayStep>,
+    pub completed: bool,
+}
+
+impl Call {
+    pub fn name(&self) -> String {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(fields)) => fields
+                .get("call")
+                .and_then(|name| name.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            _ => String::new(),
+        }
+    }
+
+    /// Points pattern ids at the patterns created during the replay.
+    fn remap_ids(&mut self, ids: &HashMap<String, String>) {
+        let remap = |id: &mut String| {
+            if let Some(new_id) = ids.get(id) {
+                *id = new_id.clone();
+            }
+        };
+        match self {
+            Call::BuildCompositePattern { pattern_ids, .. }
+            | Call::BuildCompositePatternChecked { pattern_ids, .. }
+            | Call::MergePatterns { pattern_ids, .. }
+            | Call::SetPatternsCategory { pattern_ids, .. } => {
+                pattern_ids.iter_mut().for_each(remap)
+            }
+            Call::RenamePattern { id, .. }
+            | Call::DuplicatePattern { id, .. }
+            | Call::MovePattern { id, .. }
+            | Call::UpdatePattern { id, .. }
+            | Call::ReplaceElement { id, .. }
+            | Call::InsertRawElement { id, .. }
+            | Call::SetGapBounds { id, .. }
+            | Call::SetGapCharLimit { id, .. }
+            | Call::SetGapExcludedWords { id, .. }
+            | Call::SetWordMatch { id, .. }
+            | Call::SetElementModifier { id, .. }
+            | Call::SetElementCapture { id, .. }
+            | Call::SetElementNote { id, .. }
+            | Call::AddOneOfOption { id, .. }
+            | Call::RemoveOneOfOption { id, .. }
+            | Call::SetPatternScope { id, .. }
+            | Call::SetDescription { id, .. }
+            | Call::SetPatternTags { id, .. }
+            | Call::SetPatternAuthor { id, .. }
+            | Call::SetPatternFolder { id, .. }
+            | Call::SetPatternCategory { id, .. }
+            | Call::SetPatternPriority { id, .. }
+            | Call::InstantiateTemplate { id, .. }
+            | Call::DeletePatternById { id, .. } => remap(id),
+            _ => {}
+        }
+    }
+}
+
+/// Runs `call` through the builder's public API and returns the regex
+/// the call produced, if any.
+fn apply(builder: &mut PatternBuilder, call: Call) -> Result<Option<String>, JsValue> {
+    let regex = match call {
+        Call::AddSelection { text, start_index, end_index, word_index } => {
+            builder.add_selection(text, start_index, end_index, word_index)?;
+            return Ok(None);
+        }
+        Call::AddTokenClassSelection { class, custom_regex, text, start_index, end_index, word_index } => {
+            builder.add_token_class_selection(class, custom_regex, text, start_index, end_index, word_index)?;
+            return Ok(None);
+        }
+        Call::AddRangeSelection { text, start_index, end_index } => {
+            builder.add_range_selection(&text, start_index, end_index)?;
+            return Ok(None);
+        }
+        Call::AddAlternativeToSelection { selection_index, text } => {
+            builder.add_alternative_to_selection(selection_index, text)?;
+            return Ok(None);
+        }
+        Call::RemoveAlternativeFromSelection { selection_index, text } => {
+            builder.remove_alternative_from_selection(selection_index, &text)?;
+            return Ok(None);
+        }
+        Call::AddRawSelection { regex, word_index } => {
+            builder.add_raw_selection(regex, word_index)?;
+            return Ok(None);
+        }
+        Call::RemoveSelection { index } => {
+            builder.remove_selection(index);
+            return Ok(None);
+        }
+        Call::ClearSelections => {
+            builder.clear_selections();
+            return Ok(None);
+        }
+        Call::BuildSequencePattern { name, gap_mode } => builder.build_sequence_pattern(name, gap_mode)?,
+        Call::BuildSequencePatternChecked { name, allow_duplicate, gap_mode } => {
+            builder.build_sequence_pattern_checked(name, allow_duplicate, gap_mode)?;
+            return Ok(None);
+        }
+        Call::BuildCompositePattern { name, operator, pattern_ids } => {
+            builder.build_composite_pattern(name, operator, pattern_ids)?
+        }
+        Call::BuildCompositePatternChecked { name, operator, pattern_ids, allow_duplicate } => {
+            builder.build_composite_pattern_checked(name, operator, pattern_ids, allow_duplicate)?;
+            return Ok(None);
+        }
+        Call::CreateCompositePattern { name, base_operator, pattern_indices, operators } => {
+            builder.create_composite_pattern(name, base_operator, pattern_indices, operators)?
+        }
+        Call::ImportRegex { name, regex } => builder.import_regex(&name, &regex)?,
+        Call::ImportRegexList { text } => {
+            builder.import_regex_list(&text)?;
+            return Ok(None);
+        }
+        Call::ImportConfig { text, dialect } => {
+            builder.import_config(&text, &dialect)?;
+            return Ok(None);
+        }
+        Call::FromGlob { glob } => builder.from_glob(&glob)?,
+        Call::InstallPack { json } => {
+            builder.install_pack(&json)?;
+            return Ok(None);
+        }
+        Call::ImportKql { name, expression } => builder.import_kql(&name, &expression)?,
+        Call::BuildTemplate { name, body, parameters } => {
+            builder.build_template(&name, &body, serde_wasm_bindgen::to_value(&parameters)?)?
+        }
+        Call::InstantiateTemplate { id, values } => builder.instantiate_template(&id, serde_wasm_bindgen::to_value(&values)?)?,
+        Call::AddPattern { pattern } => builder.add_pattern(serde_wasm_bindgen::to_value(&pattern)?)?,
+        Call::UpdatePattern { id, pattern, note } => {
+            builder.update_pattern(&id, serde_wasm_bindgen::to_value(&pattern)?, note)?
+        }
+        Call::RenamePattern { id, name } => builder.rename_pattern(&id, name)?,
+        Call::DuplicatePattern { id } => builder.duplicate_pattern(&id)?,
+        Call::MergePatterns { pattern_ids, operator, name } => builder.merge_patterns(pattern_ids, operator, name)?,
+        Call::MovePattern { id, new_position } => {
+            builder.move_pattern(&id, new_position)?;
+            return Ok(None);
+        }
+        Call::ReplaceElement { id, element_index, element } => {
+            builder.replace_element(&id, element_index, serde_wasm_bindgen::to_value(&element)?)?
+        }
+        Call::InsertRawElement { id, element_index, regex } => builder.insert_raw_element(&id, element_index, regex)?,
+        Call::SetGapBounds { id, element_index, min_words, max_words } => {
+            builder.set_gap_bounds(&id, element_index, min_words, max_words)?
+        }
+        Call::SetGapCharLimit { id, element_index, max_chars } => builder.set_gap_char_limit(&id, element_index, max_chars)?,
+        Call::SetGapExcludedWords { id, element_index, words } => {
+            builder.set_gap_excluded_words(&id, element_index, words)?
+        }
+        Call::SetSynonymSet { set_id, words } => {
+            builder.set_synonym_set(&set_id, words)?;
+            return Ok(None);
+        }
+        Call::DeleteSynonymSet { set_id } => {
+            builder.delete_synonym_set(&set_id)?;
+            return Ok(None);
+        }
+        Call::SetWordMatch { id, element_index, mode } => builder.set_word_match(&id, element_index, &mode)?,
+        Call::SetElementModifier { id, element_index, optional, min_repeat, max_repeat } => {
+            builder.set_element_modifier(&id, element_index, optional, min_repeat, max_repeat)?
+        }
+        Call::SetElementCapture { id, element_index, name } => builder.set_element_capture(&id, element_index, name)?,
+        Call::SetElementNote { id, element_index, note } => {
+            builder.set_element_note(&id, element_index, note)?;
+            return Ok(None);
+        }
+        Call::AddOneOfOption { id, element_index, option } => builder.add_one_of_option(&id, element_index, option)?,
+        Call::RemoveOneOfOption { id, element_index, option } => builder.remove_one_of_option(&id, element_index, &option)?,
+        Call::SetOptionWeight { pattern_index, element_index, option, weight } => {
+            builder.set_option_weight(pattern_index, element_index, option, weight)?;
+            return Ok(None);
+        }
+        Call::SetOptionFlags { pattern_index, element_index, option, case_insensitive, whole_word } => {
+            builder.set_option_flags(pattern_index, element_index, option, case_insensitive, whole_word)?
+        }
+        Call::SetPatternScope { id, scope } => builder.set_pattern_scope(&id, &scope)?,
+        Call::SetDescription { id, description } => {
+            builder.set_description(&id, description)?;
+            return Ok(None);
+        }
+        Call::SetPatternTags { id, tags } => {
+            builder.set_pattern_tags(&id, tags)?;
+            return Ok(None);
+        }
+        Call::SetPatternAuthor { id, author } => {
+            builder.set_pattern_author(&id, author)?;
+            return Ok(None);
+        }
+        Call::SetPatternPriority { id, priority } => {
+            builder.set_pattern_priority(&id, priority)?;
+            return Ok(None);
+        }
+        Call::SetPatternFolder { id, folder } => {
+            builder.set_pattern_folder(&id, folder)?;
+            return Ok(None);
+        }
+        Call::SetPatternCategory { id, category } => {
+            builder.set_pattern_category(&id, category)?;
+            return Ok(None);
+        }
+        Call::SetPatternsCategory { pattern_ids, category } => {
+            builder.set_patterns_category(pattern_ids, category)?;
+            return Ok(None);
+        }
+        Call::Recategorize { from, to } => {
+            builder.recategorize(&from, to)?;
+            return Ok(None);
+        }
+        Call::DeletePattern { index, cascade } => {
+            builder.delete_pattern(index, cascade)?;
+            return Ok(None);
+        }
+        Call::DeletePatternById { id, cascade } => {
+            builder.delete_pattern_by_id(&id, cascade)?;
+            return Ok(None);
+        }
+        Call::Undo => {
+            builder.undo()?;
+            return Ok(None);
+        }
+        Call::Redo => {
+            builder.redo()?;
+            return Ok(None);
+        }
+        Call::SetSettings { settings } => {
+            builder.set_settings(serde_wasm_bindgen::to_value(&settings)?)?;
+            return Ok(None);
+        }
+    };
+    Ok(Some(regex))
+}
+
+/// Replays `recording` against `builder`, starting from the recorded
+/// settings.
+pub fn replay(builder: &mut PatternBuilder, recording: Recording) -> Result<ReplayReport, JsValue> {
+    let mut steps = Vec::new();
+    let mut ids = HashMap::new();
+    builder.apply_settings(recording.settings);
+    builder.save_settings()?;
+
+    for recorded in recording.calls {
+        let mut call = recorded.call;
+        call.remap_ids(&ids);
+        let name = call.name();
+        let existing: HashSet<String> = builder.patterns.iter().map(|p| p.get_id().to_string()).collect();
+        match apply(builder, call) {
+            Ok(result) => {
+                // Not always the last one: merged patterns take the place of their parts
+                let created = builder.patterns.iter().find(|p| !existing.contains(p.get_id()));
+                if let (Some(old_id), Some(created)) = (recorded.created_id, created) {
+                    ids.insert(old_id, created.get_id().to_string());
+                }
+                steps.push(ReplayStep { call: name, result, error: None });
+            }
+            Err(e) => {
+                let error = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                steps.push(ReplayStep { call: name, result: None, error: Some(error) });
+                return Ok(ReplayReport { steps, completed: false });
+            }
+        }
+    }
+    Ok(ReplayReport { steps, completed: true })
+}
@@ -0,0 +1,110 @@
+use wasm_bindgen::prelude::*;
+
+use crate::PatternBuilder;
+
+/// A scratch copy of the pattern library. Patterns can be built, edited
+/// and tested freely; nothing is persisted until `commit` copies the
+/// sandbox's library back into a builder in one save.
+#[wasm_bindgen]
+pub struct Sandbox {
+    builder: PatternBuilder,
+}
+
+impl Sandbox {
+    pub(crate) fn new(builder: PatternBuilder) -> Sandbox {
+        Sandbox { builder }
+    }
+}
+
+#[wasm_bindgen]
+impl Sandbox {
+    pub fn add_selection(&mut self, text: String, start_index: usize, end_index: usize, word_index: usize) -> Result<(), JsValue> {
+        self.builder.add_selection(text, start_index, end_index, word_index)
+    }
+
+    pub fn clear_selections(&mut self) {
+        self.builder.clear_selections();
+    }
+
+    pub fn build_sequence_pattern(&mut self, name: String, gap_mode: Option<String>) -> Result<String, JsValue> {
+        self.builder.build_sequence_pattern(name, gap_mode)
+    }
+
+    pub fn build_composite_pattern(&mut self, name: String, operator: String, pattern_ids: Vec<String>) -> Result<String, JsValue> {
+        self.builder.build_composite_pattern(name, operator, pattern_ids)
+    }
+
+    pub fn get_patterns(&self) -> Result<JsValue, JsValue> {
+        self.builder.get_patterns()
+    }
+
+    /// Deprecated: use `test_pattern_by_id`.
+    pub fn test_pattern(&mut self, pattern_index: usize, text: &str, mode: Option<String>) -> Result<JsValue, JsValue> {
+        self.builder.test_pattern(pattern_index, text, mode)
+    }
+
+    pub fn test_pattern_by_id(&mut self, id: &str, text: &str, mode: Option<String>) -> Result<JsValue, JsValue> {
+        self.builder.test_pattern_by_id(id, text, mode)
+    }
+
+    pub fn get_pattern_by_id(&self, id: &str) -> Result<JsValue, JsValue> {
+        self.builder.get_pattern_by_id(id)
+    }
+
+    pub fn rename_pattern(&mut self, id: &str, name: String) -> Result<String, JsValue> {
+        self.builder.rename_pattern(id, name)
+    }
+
+    pub fn replace_element(&mut self, id: &str, element_index: usize, element: JsValue) -> Result<String, JsValue> {
+        self.builder.replace_element(id, element_index, element)
+    }
+
+    pub fn set_gap_bounds(&mut self, id: &str, element_index: usize, min_words: u32, max_words: Option<u32>) -> Result<String, JsValue> {
+        self.builder.set_gap_bounds(id, element_index, min_words, max_words)
+    }
+
+    pub fn set_gap_char_limit(&mut self, id: &str, element_index: usize, max_chars: Option<u32>) -> Result<String, JsValue> {
+        self.builder.set_gap_char_limit(id, element_index, max_chars)
+    }
+
+    pub fn set_gap_excluded_words(&mut self, id: &str, element_index: usize, words: Vec<String>) -> Result<String, JsValue> {
+        self.builder.set_gap_excluded_words(id, element_index, words)
+    }
+
+    pub fn add_one_of_option(&mut self, id: &str, element_index: usize, option: String) -> Result<String, JsValue> {
+        self.builder.add_one_of_option(id, element_index, option)
+    }
+
+    pub fn remove_one_of_option(&mut self, id: &str, element_index: usize, option: &str) -> Result<String, JsValue> {
+        self.builder.remove_one_of_option(id, element_index, option)
+    }
+
+    /// Deprecated: use `delete_pattern_by_id`.
+    pub fn delete_pattern(&mut self, index: usize, cascade: Option<bool>) -> Result<(), JsValue> {
+        self.builder.delete_pattern(index, cascade)
+    }
+
+    pub fn delete_pattern_by_id(&mut self, id: &str, cascade: Option<bool>) -> Result<(), JsValue> {
+        self.builder.delete_pattern_by_id(id, cascade)
+    }
+
+    pub fn export_pattern_regex(&self, pattern_index: usize, dialect: &str) -> Result<String, JsValue> {
+        self.builder.export_pattern_regex(pattern_index, dialect)
+    }
+
+    pub fn export_pattern(&self, pattern_index: usize, dialect: &str) -> Result<JsValue, JsValue> {
+        self.builder.export_pattern(pattern_index, dialect)
+    }
+
+    /// Replaces `target`'s library with the sandbox's and saves it. If the
+    /// save fails, `target` keeps its previous patterns.
+    pub fn commit(&self, target: &mut PatternBuilder) -> Result<(), JsValue> {
+        let previous = std::mem::replace(&mut target.patterns, self.builder.patterns.clone());
+        if let Err(e) = target.save() {
+            target.patterns = previous;
+            target.invalidate_matcher();
+            return Err(e);
+        }
+        Ok(())
+    }
+}
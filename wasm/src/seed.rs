@@ -0,0 +1,46 @@
+use std::cell::RefCell;
+
+use wasm_bindgen::prelude::*;
+
+use crate::SeededRng;
+
+thread_local! {
+    /// The generator `set_seed` started and how many ids it has handed
+    /// out; None while randomness comes from `Math.random`.
+    static SEEDED: RefCell<Option<(SeededRng, u64)>> = const { RefCell::new(None) };
+}
+
+/// Makes everything random in every builder on this thread reproducible:
+/// new pattern ids and generated examples follow from `seed` and the
+/// order of calls, so a test, demo or bug report replays exactly.
+#[wasm_bindgen]
+pub fn set_seed(seed: u64) {
+    SEEDED.with(|seeded| *seeded.borrow_mut() = Some((SeededRng::new(seed), 0)));
+}
+
+/// Goes back to `Math.random` and timestamped ids.
+#[wasm_bindgen]
+pub fn clear_seed() {
+    SEEDED.with(|seeded| *seeded.borrow_mut() = None);
+}
+
+/// A random number below `bound`.
+pub fn random_below(bound: u64) -> u64 {
+    SEEDED.with(|seeded| match seeded.borrow_mut().as_mut() {
+        Some((rng, _)) => rng.next_u64() % bound.max(1),
+        None => (js_sys::Math::random() * bound as f64) as u64,
+    })
+}
+
+/// A new pattern id: the time and a random number, or when seeded the
+/// number of ids handed out since instead of the time.
+pub fn generate_id() -> String {
+    let counter = SEEDED.with(|seeded| {
+        seeded.borrow_mut().as_mut().map(|(_, issued)| {
+            *issued += 1;
+            *issued
+        })
+    });
+    let timestamp = counter.unwrap_or_else(|| js_sys::Date::now() as u64);
+    format!("{}-{}", timestamp, random_below(1000))
+}
@@ -0,0 +1,61 @@
+use js_sys::{Function, Promise};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+/// Milliseconds of work done between yields to the event loop.
+pub const SLICE_MS: f64 = 8.0;
+
+/// A promise for the result of a long task split into steps. `step` is
+/// called repeatedly, in slices of about `SLICE_MS` with a `setTimeout`
+/// between them so the page can paint and handle input, until it returns
+/// the value to resolve with. An error rejects the promise.
+pub fn run_chunked<F>(step: F) -> Promise
+where
+    F: FnMut() -> Result<Option<JsValue>, JsValue> + 'static,
+{
+    let mut step = Some(step);
+    Promise::new(&mut |resolve, reject| {
+        if let Some(step) = step.take() {
+            schedule(step, resolve, reject);
+        }
+    })
+}
+
+fn schedule<F>(mut step: F, resolve: Function, reject: Function)
+where
+    F: FnMut() -> Result<Option<JsValue>, JsValue> + 'static,
+{
+    let on_error = reject.clone();
+    let slice = Closure::once_into_js(move || {
+        let deadline = js_sys::Date::now() + SLICE_MS;
+        loop {
+            match step() {
+                Ok(Some(value)) => {
+                    let _ = resolve.call1(&JsValue::NULL, &value);
+                    return;
+                }
+                Err(error) => {
+                    let _ = reject.call1(&JsValue::NULL, &error);
+                    return;
+                }
+                Ok(None) if js_sys::Date::now() >= deadline => break,
+                Ok(None) => {}
+            }
+        }
+        schedule(step, resolve, reject);
+    });
+    if let Err(error) = set_timeout(&slice) {
+        let _ = on_error.call1(&JsValue::NULL, &error);
+    }
+}
+
+/// `setTimeout(callback, 0)` on whatever global there is, so tasks run in
+/// workers as well as pages.
+fn set_timeout(callback: &JsValue) -> Result<(), JsValue> {
+    let global = js_sys::global();
+    let set_timeout: Function = js_sys::Reflect::get(&global, &JsValue::from_str("setTimeout"))?
+        .dyn_into()
+        .map_err(|_| JsValue::from_str("setTimeout is not available"))?;
+    set_timeout.call2(&global, callback, &JsValue::from(0))?;
+    Ok(())
+}
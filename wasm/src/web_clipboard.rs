@@ -0,0 +1,36 @@
+use js_sys::Promise;
+use wasm_bindgen::prelude::*;
+
+use regexgen_core::RegexGenError;
+
+use crate::promises;
+
+/// The page's async clipboard. Browsers only offer it to secure (https or
+/// localhost) pages, and never to workers.
+fn clipboard() -> Option<web_sys::Clipboard> {
+    let clipboard = web_sys::window()?.navigator().clipboard();
+    (!clipboard.is_undefined()).then_some(clipboard)
+}
+
+pub fn is_available() -> bool {
+    clipboard().is_some()
+}
+
+fn unavailable() -> JsValue {
+    RegexGenError::InvalidState(String::from("The clipboard is not available; it needs a secure (https) page")).into()
+}
+
+/// A promise that resolves once `text` is on the clipboard.
+pub fn write_text(text: &str) -> Result<Promise, JsValue> {
+    Ok(clipboard().ok_or_else(unavailable)?.write_text(text))
+}
+
+/// A promise for what `read` makes of the text on the clipboard. It
+/// rejects if the text can't be read, e.g. because the user refused.
+pub fn read_text_with<F>(read: F) -> Result<Promise, JsValue>
+where
+    F: FnOnce(String) -> Result<JsValue, JsValue> + 'static,
+{
+    let pending = clipboard().ok_or_else(unavailable)?.read_text();
+    promises::and_then(&pending, move |text| read(text.as_string().unwrap_or_default()))
+}
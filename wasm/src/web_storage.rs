@@ -0,0 +1,232 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::BTreeMap;
+#[cfg(feature = "storage")]
+use wasm_bindgen::JsValue;
+
+use regexgen_core::journal::Journal;
+use regexgen_core::storage::{key_prefix, load_text, remove_text, save_text, storage_key, Store, StorageError, WORKSPACE_ITEMS};
+use regexgen_core::storage::MemoryStore;
+use regexgen_core::synonyms::SynonymSets;
+use regexgen_core::{Pattern, Settings};
+
+pub use regexgen_core::storage::DEFAULT_WORKSPACE;
+
+/// The browser's local storage as a `Store`.
+#[cfg(feature = "storage")]
+pub struct LocalStorage(web_sys::Storage);
+
+#[cfg(feature = "storage")]
+impl Store for LocalStorage {
+    fn get_item(&self, key: &str) -> Option<String> {
+        self.0.get_item(key).ok().flatten()
+    }
+
+    fn set_item(&self, key: &str, value: &str) -> Result<(), StorageError> {
+        self.0.set_item(key, value).map_err(|e| from_write(key, value.len(), e))
+    }
+
+    fn remove_item(&self, key: &str) -> Result<(), StorageError> {
+        self.0
+            .remove_item(key)
+            .map_err(|e| StorageError::Other(e.as_string().unwrap_or_default()))
+    }
+}
+
+/// Sorts a failed `setItem` into quota errors and the rest.
+#[cfg(feature = "storage")]
+fn from_write(key: &str, bytes: usize, error: JsValue) -> StorageError {
+    let name = js_sys::Reflect::get(&error, &JsValue::from_str("name"))
+        .ok()
+        .and_then(|name| name.as_string())
+        .unwrap_or_default();
+    if name == "QuotaExceededError" || name == "NS_ERROR_DOM_QUOTA_REACHED" {
+        StorageError::QuotaExceeded { key: key.to_string(), bytes }
+    } else {
+        StorageError::Other(error.as_string().unwrap_or(name))
+    }
+}
+
+/// Key written and removed again to check that local storage takes writes.
+#[cfg(feature = "storage")]
+const PROBE_KEY: &str = "regexgen_probe";
+
+thread_local! {
+    /// Whether local storage works, checked once so a page never ends up
+    /// with its library split between local storage and memory.
+    static DURABLE: bool = probe();
+    /// Where everything is kept instead when it doesn't.
+    static MEMORY: MemoryStore = MemoryStore::default();
+}
+
+/// Private browsing and sandboxed iframes can hand out a storage object
+/// that throws on every write, so only a test write tells.
+#[cfg(feature = "storage")]
+fn probe() -> bool {
+    let works = get_local_storage()
+        .and_then(|storage| storage.set_item(PROBE_KEY, "").and_then(|()| storage.remove_item(PROBE_KEY)))
+        .is_ok();
+    if !works {
+        web_sys::console::warn_1(&"Local storage is unavailable; patterns are kept in memory and lost on reload".into());
+    }
+    works
+}
+
+#[cfg(not(feature = "storage"))]
+fn probe() -> bool {
+    false
+}
+
+/// Whether what is saved survives a reload: false when local storage
+/// can't be used and everything is kept in memory instead.
+pub fn is_durable() -> bool {
+    DURABLE.with(|durable| *durable)
+}
+
+/// Runs `f` on local storage, or on the in-memory store if local storage
+/// doesn't work.
+fn with_store<R>(f: impl FnOnce(&dyn Store) -> Result<R, StorageError>) -> Result<R, StorageError> {
+    if is_durable() {
+        f(&get_local_storage()?)
+    } else {
+        MEMORY.with(|memory| f(memory))
+    }
+}
+
+#[cfg(feature = "storage")]
+fn get_local_storage() -> Result<impl Store, StorageError> {
+    let window = web_sys::window().ok_or_else(|| StorageError::Unavailable(String::from("no window")))?;
+    window
+        .local_storage()
+        .map_err(|e| StorageError::Unavailable(e.as_string().unwrap_or_else(|| String::from("access denied"))))?
+        .map(LocalStorage)
+        .ok_or_else(|| StorageError::Unavailable(String::from("no local storage")))
+}
+
+/// Without the `storage` feature everything is kept in memory.
+#[cfg(not(feature = "storage"))]
+fn get_local_storage() -> Result<impl Store, StorageError> {
+    Err::<MemoryStore, _>(StorageError::Unavailable(String::from("built without the storage feature")))
+}
+
+fn load_json<T: DeserializeOwned + Default>(key: &str) -> T {
+    with_store(|storage| Ok(load_text(storage, key)))
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_json<T: Serialize + ?Sized>(key: &str, value: &T) -> Result<(), StorageError> {
+    save_json_with(key, value, false)
+}
+
+/// `save_json`, compressing the JSON if `compress` is set. Loading
+/// recognises either form.
+fn save_json_with<T: Serialize + ?Sized>(key: &str, value: &T, compress: bool) -> Result<(), StorageError> {
+    let json = serde_json::to_string(value).map_err(|e| StorageError::Serialization(e.to_string()))?;
+    with_store(|storage| save_text(storage, key, &json, compress))
+}
+
+pub fn save_patterns_to_storage(store: &str, workspace: &str, patterns: &[Pattern], compress: bool) -> Result<(), StorageError> {
+    save_json_with(&storage_key(store, workspace, "patterns"), patterns, compress)
+}
+
+pub fn load_patterns_from_storage(store: &str, workspace: &str) -> Vec<Pattern> {
+    load_json(&storage_key(store, workspace, "patterns"))
+}
+
+/// The library exactly as stored, decompressed; `None` if nothing is.
+pub fn load_patterns_json_from_storage(store: &str, workspace: &str) -> Option<String> {
+    with_store(|storage| Ok(load_text(storage, &storage_key(store, workspace, "patterns"))))
+        .ok()
+        .flatten()
+}
+
+pub fn load_revision_from_storage(store: &str, workspace: &str) -> u64 {
+    load_json(&storage_key(store, workspace, "revision"))
+}
+
+pub fn save_revision_to_storage(store: &str, workspace: &str, revision: u64) -> Result<(), StorageError> {
+    save_json(&storage_key(store, workspace, "revision"), &revision)
+}
+
+pub fn save_topic_to_storage(store: &str, workspace: &str, topic: &Pattern) -> Result<(), StorageError> {
+    let key = storage_key(store, workspace, "topics");
+
+    // Load existing topics, add the new one and save back
+    let mut topics: Vec<Pattern> = load_json(&key);
+    topics.push(topic.clone());
+    save_json(&key, &topics)
+}
+
+pub fn load_documents_from_storage(store: &str, workspace: &str) -> BTreeMap<String, String> {
+    load_json(&storage_key(store, workspace, "documents"))
+}
+
+pub fn save_documents_to_storage(
+    store: &str,
+    workspace: &str,
+    documents: &BTreeMap<String, String>,
+    compress: bool,
+) -> Result<(), StorageError> {
+    save_json_with(&storage_key(store, workspace, "documents"), documents, compress)
+}
+
+pub fn load_synonyms_from_storage(store: &str, workspace: &str) -> SynonymSets {
+    load_json(&storage_key(store, workspace, "synonyms"))
+}
+
+pub fn save_synonyms_to_storage(store: &str, workspace: &str, synonyms: &SynonymSets, compress: bool) -> Result<(), StorageError> {
+    save_json_with(&storage_key(store, workspace, "synonyms"), synonyms, compress)
+}
+
+pub fn load_settings_from_storage(store: &str, workspace: &str) -> Settings {
+    load_json(&storage_key(store, workspace, "settings"))
+}
+
+pub fn save_settings_to_storage(store: &str, workspace: &str, settings: &Settings) -> Result<(), StorageError> {
+    save_json(&storage_key(store, workspace, "settings"), settings)
+}
+
+pub fn load_journal_from_storage(store: &str, workspace: &str) -> Journal {
+    load_json(&storage_key(store, workspace, "journal"))
+}
+
+pub fn save_journal_to_storage(store: &str, workspace: &str, journal: &Journal, compress: bool) -> Result<(), StorageError> {
+    save_json_with(&storage_key(store, workspace, "journal"), journal, compress)
+}
+
+/// Names of all workspaces; the default one always exists.
+pub fn load_workspace_names(store: &str) -> Vec<String> {
+    let mut names: Vec<String> = load_json(&format!("{}workspaces", key_prefix(store)));
+    if !names.iter().any(|n| n == DEFAULT_WORKSPACE) {
+        names.insert(0, DEFAULT_WORKSPACE.to_string());
+    }
+    names
+}
+
+pub fn save_workspace_names(store: &str, names: &[String]) -> Result<(), StorageError> {
+    save_json(&format!("{}workspaces", key_prefix(store)), names)
+}
+
+pub fn load_current_workspace(store: &str) -> String {
+    let name: String = load_json(&format!("{}current_workspace", key_prefix(store)));
+    if name.is_empty() {
+        DEFAULT_WORKSPACE.to_string()
+    } else {
+        name
+    }
+}
+
+pub fn save_current_workspace(store: &str, name: &str) -> Result<(), StorageError> {
+    save_json(&format!("{}current_workspace", key_prefix(store)), name)
+}
+
+pub fn remove_workspace_data(store: &str, workspace: &str) -> Result<(), StorageError> {
+    with_store(|storage| {
+        for item in WORKSPACE_ITEMS {
+            remove_text(storage, &storage_key(store, workspace, item))?;
+        }
+        Ok(())
+    })
+}